@@ -1,49 +1,50 @@
 use std::process::Command;
 
+/// Shared IOReport declarations and sampler, compiled into the bridges that use it
+const IOREPORT_SWIFT: &str = "src/collectors/ioreport.swift";
+
+/// Swift bridges compiled into the combined static library: (object and module
+/// name, source paths)
+const SWIFT_BRIDGES: &[(&str, &[&str])] = &[
+    ("gpu_bridge", &["src/collectors/gpu/apple_silicon_bridge.swift"]),
+    ("cpu_bridge", &["src/collectors/cpu/apple_silicon_bridge.swift", IOREPORT_SWIFT]),
+    ("disk_bridge", &["src/collectors/disk/macos_bridge.swift"]),
+    ("ane_bridge", &["src/collectors/ane/apple_silicon_bridge.swift", IOREPORT_SWIFT]),
+    ("power_bridge", &["src/collectors/power/macos_bridge.swift"]),
+    ("thermal_bridge", &["src/collectors/thermal/macos_bridge.swift"]),
+    ("sensors_bridge", &["src/collectors/sensors/macos_bridge.swift"]),
+];
+
 fn main() {
     // Only compile Swift bridge on macOS
     if cfg!(target_os = "macos") {
-        println!("cargo:rerun-if-changed=src/collectors/gpu/apple_silicon_bridge.swift");
-        println!("cargo:rerun-if-changed=src/collectors/cpu/apple_silicon_bridge.swift");
-        
-        // Compile GPU Swift bridge to object file
-        let gpu_output = Command::new("swiftc")
-            .args(&[
-                "-c",
-                "-emit-object",
-                "-o", "target/gpu_bridge.o",
-                "src/collectors/gpu/apple_silicon_bridge.swift",
-            ])
-            .output()
-            .expect("Failed to compile GPU Swift bridge");
+        let mut objects = Vec::new();
 
-        if !gpu_output.status.success() {
-            panic!("GPU Swift compilation failed: {}", String::from_utf8_lossy(&gpu_output.stderr));
-        }
+        for (name, sources) in SWIFT_BRIDGES {
+            for source in *sources {
+                println!("cargo:rerun-if-changed={}", source);
+            }
 
-        // Compile CPU Swift bridge to object file
-        let cpu_output = Command::new("swiftc")
-            .args(&[
-                "-c",
-                "-emit-object",
-                "-o", "target/cpu_bridge.o",
-                "src/collectors/cpu/apple_silicon_bridge.swift",
-            ])
-            .output()
-            .expect("Failed to compile CPU Swift bridge");
+            // Compile Swift bridge to object file; each bridge is its own module so
+            // the shared sources don't clash between objects
+            let object = format!("target/{}.o", name);
+            let output = Command::new("swiftc")
+                .args(["-c", "-emit-object", "-parse-as-library", "-wmo", "-module-name", name, "-o", &object])
+                .args(*sources)
+                .output()
+                .unwrap_or_else(|_| panic!("Failed to compile {} Swift bridge", name));
+
+            if !output.status.success() {
+                panic!("{} Swift compilation failed: {}", name, String::from_utf8_lossy(&output.stderr));
+            }
 
-        if !cpu_output.status.success() {
-            panic!("CPU Swift compilation failed: {}", String::from_utf8_lossy(&cpu_output.stderr));
+            objects.push(object);
         }
 
         // Create combined static library
         let ar_output = Command::new("ar")
-            .args(&[
-                "rcs",
-                "target/libbridge.a",
-                "target/gpu_bridge.o",
-                "target/cpu_bridge.o",
-            ])
+            .args(["rcs", "target/libbridge.a"])
+            .args(&objects)
             .output()
             .expect("Failed to create static library");
 
@@ -55,5 +56,7 @@ fn main() {
         println!("cargo:rustc-link-lib=static=bridge");
         println!("cargo:rustc-link-lib=framework=IOKit");
         println!("cargo:rustc-link-lib=framework=Foundation");
+        // Private IOReport library used for energy and DVFS residency counters
+        println!("cargo:rustc-link-lib=dylib=IOReport");
     }
-}
\ No newline at end of file
+}
@@ -3,7 +3,14 @@
 # Thrud Utilization Metrics Script
 # Shows CPU and GPU utilization for the last N collection rounds
 
-DB_PATH="$HOME/.thrud/thrud.db"
+# Same resolution order as the collector's default database path
+if [ -n "$THRUD_DB" ]; then
+    DB_PATH="$THRUD_DB"
+elif [[ "$XDG_DATA_HOME" == /* ]]; then
+    DB_PATH="$XDG_DATA_HOME/thrud/thrud.db"
+else
+    DB_PATH="$HOME/.thrud/thrud.db"
+fi
 DEFAULT_ROUNDS=5
 
 # Get number of rounds from command line argument or use default
@@ -80,7 +87,7 @@ while IFS='|' read -r round_id timestamp; do
     
     # Get GPU utilization (this is instantaneous, not cumulative)
     gpu_util=$(sqlite3 "$DB_PATH" "
-    SELECT value 
+    SELECT value_float 
     FROM metrics 
     WHERE collection_round_id = '$round_id' 
       AND name LIKE 'gpu.%.utilization'
@@ -102,16 +109,16 @@ while IFS='|' read -r round_id timestamp; do
     perf_delta=$(sqlite3 "$DB_PATH" "
     WITH current AS (
         SELECT 
-            SUM(CASE WHEN name = 'cpu.performance.total_ticks' THEN CAST(value AS INTEGER) ELSE 0 END) as total,
-            SUM(CASE WHEN name = 'cpu.performance.idle_ticks' THEN CAST(value AS INTEGER) ELSE 0 END) as idle
+            SUM(CASE WHEN name = 'cpu.performance.total_ticks' THEN value_int ELSE 0 END) as total,
+            SUM(CASE WHEN name = 'cpu.performance.idle_ticks' THEN value_int ELSE 0 END) as idle
         FROM metrics 
         WHERE collection_round_id = '$round_id' 
           AND name IN ('cpu.performance.total_ticks', 'cpu.performance.idle_ticks')
     ),
     previous AS (
         SELECT 
-            SUM(CASE WHEN name = 'cpu.performance.total_ticks' THEN CAST(value AS INTEGER) ELSE 0 END) as total,
-            SUM(CASE WHEN name = 'cpu.performance.idle_ticks' THEN CAST(value AS INTEGER) ELSE 0 END) as idle
+            SUM(CASE WHEN name = 'cpu.performance.total_ticks' THEN value_int ELSE 0 END) as total,
+            SUM(CASE WHEN name = 'cpu.performance.idle_ticks' THEN value_int ELSE 0 END) as idle
         FROM metrics 
         WHERE collection_round_id = '$prev_round_id' 
           AND name IN ('cpu.performance.total_ticks', 'cpu.performance.idle_ticks')
@@ -141,16 +148,16 @@ while IFS='|' read -r round_id timestamp; do
     eff_delta=$(sqlite3 "$DB_PATH" "
     WITH current AS (
         SELECT 
-            SUM(CASE WHEN name = 'cpu.efficiency.total_ticks' THEN CAST(value AS INTEGER) ELSE 0 END) as total,
-            SUM(CASE WHEN name = 'cpu.efficiency.idle_ticks' THEN CAST(value AS INTEGER) ELSE 0 END) as idle
+            SUM(CASE WHEN name = 'cpu.efficiency.total_ticks' THEN value_int ELSE 0 END) as total,
+            SUM(CASE WHEN name = 'cpu.efficiency.idle_ticks' THEN value_int ELSE 0 END) as idle
         FROM metrics 
         WHERE collection_round_id = '$round_id' 
           AND name IN ('cpu.efficiency.total_ticks', 'cpu.efficiency.idle_ticks')
     ),
     previous AS (
         SELECT 
-            SUM(CASE WHEN name = 'cpu.efficiency.total_ticks' THEN CAST(value AS INTEGER) ELSE 0 END) as total,
-            SUM(CASE WHEN name = 'cpu.efficiency.idle_ticks' THEN CAST(value AS INTEGER) ELSE 0 END) as idle
+            SUM(CASE WHEN name = 'cpu.efficiency.total_ticks' THEN value_int ELSE 0 END) as total,
+            SUM(CASE WHEN name = 'cpu.efficiency.idle_ticks' THEN value_int ELSE 0 END) as idle
         FROM metrics 
         WHERE collection_round_id = '$prev_round_id' 
           AND name IN ('cpu.efficiency.total_ticks', 'cpu.efficiency.idle_ticks')
@@ -0,0 +1,126 @@
+use clap::{Parser, Subcommand};
+use std::collections::HashMap;
+use std::fmt::Write;
+use thrud::storage::{Aggregation, AggregationRegistry, SqliteStorage};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Run SQL aggregations over the Thrud database", long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the available aggregations
+    List,
+    /// Print an aggregation's description, parameters and the JSON Schema of its
+    /// result data as JSON
+    Describe {
+        /// Aggregation name, e.g. apple_silicon_cpu
+        name: String,
+    },
+    /// Run an aggregation and print its result as JSON
+    Run {
+        /// Aggregation name, e.g. apple_silicon_cpu
+        name: String,
+
+        /// Aggregation parameter, e.g. window_seconds=300 (repeatable)
+        #[arg(short, long = "param", value_parser = parse_param)]
+        params: Vec<(String, String)>,
+    },
+}
+
+/// Parse a `key=value` aggregation parameter
+fn parse_param(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected key=value, got '{}'", s))?;
+    Ok((key.trim().to_string(), value.trim().to_string()))
+}
+
+/// Look up an aggregation, exiting with the available names if there's none called `name`
+fn find<'a>(registry: &'a AggregationRegistry, name: &str) -> &'a dyn Aggregation {
+    match registry.get(name) {
+        Some(aggregation) => aggregation,
+        None => {
+            let names: Vec<&str> = registry.list().into_iter().map(|(name, _)| name).collect();
+            eprintln!("Error: Unknown aggregation '{}'. Available: {}", name, names.join(", "));
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Every aggregation, sorted by name, with its description and an indented line
+/// per parameter
+fn list(registry: &AggregationRegistry) -> String {
+    let mut output = String::new();
+    for (name, description) in registry.list() {
+        let _ = writeln!(output, "{:<24} {}", name, description);
+        for param in registry.get(name).map(|a| a.param_schema()).unwrap_or_default() {
+            let required = if param.required { ", required" } else { "" };
+            let _ = writeln!(output, "    {:<20} {} [{}{}]", param.name, param.description, param.param_type.describe(), required);
+        }
+    }
+    output
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let registry = AggregationRegistry::new();
+
+    match args.command {
+        Command::List => print!("{}", list(&registry)),
+        Command::Describe { name } => {
+            let aggregation = find(&registry, &name);
+            let description = serde_json::json!({
+                "name": aggregation.name(),
+                "description": aggregation.description(),
+                "params": aggregation.param_schema(),
+                "result_schema": aggregation.result_schema(),
+            });
+            println!("{}", serde_json::to_string_pretty(&description)?);
+        }
+        Command::Run { name, params } => {
+            find(&registry, &name);
+
+            let params: HashMap<String, String> = params.into_iter().collect();
+            let storage = match SqliteStorage::open_read_only(None) {
+                Ok(storage) => storage,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            match storage.run_aggregation(&registry, &name, &params) {
+                Ok(result) => println!("{}", serde_json::to_string_pretty(&result)?),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_shows_every_aggregation_with_its_params() {
+        let registry = AggregationRegistry::new();
+        let output = list(&registry);
+
+        let names: Vec<&str> = output.lines().filter(|line| !line.starts_with(' ')).map(|line| line.split_whitespace().next().unwrap()).collect();
+        let expected: Vec<&str> = registry.list().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, expected);
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", names);
+
+        // Parameters are indented under their aggregation
+        let cpu = output.lines().position(|line| line.starts_with("apple_silicon_cpu ")).unwrap();
+        assert!(output.lines().nth(cpu + 1).unwrap().trim_start().starts_with("window_seconds"), "{}", output);
+    }
+}
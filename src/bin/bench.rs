@@ -0,0 +1,176 @@
+use clap::Parser;
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use thrud::collectors::{Metric, MetricValue};
+use thrud::storage::{SqliteStorage, Storage};
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Benchmark storing synthetic collection rounds",
+    long_about = "Benchmark storing synthetic collection rounds.\n\n\
+                  Writes --rounds rounds of --metrics-per-round fake metrics through the same \
+                  buffered path as thrud-collector and reports throughput and the latency of \
+                  each write transaction."
+)]
+struct Args {
+    /// Number of collection rounds to store
+    #[arg(long, default_value = "1000")]
+    rounds: usize,
+
+    /// Metrics in each round
+    #[arg(long, default_value = "100")]
+    metrics_per_round: usize,
+
+    /// Rounds written per transaction, as with thrud-collector --batch-size
+    #[arg(long, default_value = "1")]
+    batch: usize,
+
+    /// Database path (default: a temporary file, removed afterwards)
+    #[arg(long)]
+    database: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    if args.rounds == 0 || args.metrics_per_round == 0 || args.batch == 0 {
+        eprintln!("Error: Rounds, metrics per round and batch must be positive");
+        std::process::exit(1);
+    }
+
+    let temporary = args.database.is_none();
+    let db_path = args.database.clone().unwrap_or_else(|| {
+        std::env::temp_dir()
+            .join(format!("thrud-bench-{}.db", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned()
+    });
+
+    let result = run(&args, &db_path, &mut std::io::stdout());
+    if temporary {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", db_path, suffix));
+        }
+    }
+    result
+}
+
+/// Run the benchmark against `db_path`, reporting to `out`
+fn run(args: &Args, db_path: &str, out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    // Age never triggers a flush, so every transaction holds exactly --batch rounds
+    // (the last one possibly fewer)
+    let storage = SqliteStorage::new(Some(db_path.to_string()))?.with_write_batch(args.batch, Duration::MAX);
+    storage.initialize()?;
+
+    writeln!(
+        out,
+        "⏱️  Storing {} rounds of {} metrics ({} per transaction) in {}",
+        args.rounds,
+        args.metrics_per_round,
+        args.batch,
+        storage.db_path()
+    )?;
+
+    let mut latencies = Vec::with_capacity(args.rounds / args.batch + 1);
+    let started = Instant::now();
+    for round in 0..args.rounds {
+        let metrics = fake_round(round, args.metrics_per_round);
+        let write_started = Instant::now();
+        if !storage.store_metrics_buffered(metrics)?.is_empty() {
+            latencies.push(write_started.elapsed());
+        }
+    }
+    let write_started = Instant::now();
+    if !storage.flush()?.is_empty() {
+        latencies.push(write_started.elapsed());
+    }
+    let elapsed = started.elapsed();
+
+    let total = args.rounds * args.metrics_per_round;
+    latencies.sort();
+    writeln!(out, "📦 Stored {} metrics in {:.3}s", total, elapsed.as_secs_f64())?;
+    writeln!(out, "🚀 Throughput: {:.0} metrics/sec, {:.0} rounds/sec", total as f64 / elapsed.as_secs_f64(), args.rounds as f64 / elapsed.as_secs_f64())?;
+    writeln!(
+        out,
+        "📊 Write latency over {} transactions: p50 {:.3} ms, p99 {:.3} ms, max {:.3} ms",
+        latencies.len(),
+        millis(percentile(&latencies, 50.0)),
+        millis(percentile(&latencies, 99.0)),
+        millis(latencies.last().copied().unwrap_or_default())
+    )?;
+    Ok(())
+}
+
+/// A round shaped like real collector output: mostly plain gauges plus a few
+/// series distinguished by metadata, with values changing every round
+fn fake_round(round: usize, count: usize) -> Vec<Metric> {
+    (0..count)
+        .map(|i| {
+            let mut metadata = HashMap::new();
+            if i % 10 == 0 {
+                metadata.insert("core_type".to_string(), if i % 20 == 0 { "performance" } else { "efficiency" }.to_string());
+            }
+            let value = if i % 2 == 0 {
+                MetricValue::Integer((round * count + i) as i64)
+            } else {
+                MetricValue::Float(((round + i) % 100) as f64 / 100.0)
+            };
+            Metric::new(format!("bench.metric_{}", i), value, metadata)
+        })
+        .collect()
+}
+
+/// Nearest-rank percentile of sorted durations
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0 * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1]
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiny_benchmark_reports_timings() {
+        let path = std::env::temp_dir()
+            .join(format!("thrud-bench-test-{}.db", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned();
+        let args = Args::try_parse_from(["thrud-bench", "--rounds", "5", "--metrics-per-round", "3", "--batch", "2"]).unwrap();
+
+        let mut out = Vec::new();
+        run(&args, &path, &mut out).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Stored 15 metrics in"), "{}", out);
+        assert!(out.contains("metrics/sec"), "{}", out);
+        // Two full batches and the flushed last round
+        assert!(out.contains("Write latency over 3 transactions: p50 "), "{}", out);
+        let stats = SqliteStorage::new(Some(path.clone())).unwrap().get_stats().unwrap();
+        assert_eq!((stats.total_collection_rounds, stats.total_metrics), (5, 15));
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let sorted: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+
+        assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&sorted, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&sorted[..1], 99.0), Duration::from_millis(1));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+}
@@ -1,6 +1,15 @@
-use thrud::storage::{SqliteStorage, ChartType};
+use chrono::{DateTime, Utc};
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::time::Duration;
+use thrud::storage::{core_chart_index, gpu_chart_index, is_valid_bar_ramp, SqliteStorage, Chart, ChartType, DEFAULT_BAR_RAMP, DEFAULT_CHART_METRICS};
 use clap::Parser;
 
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Query pre-computed charts from Thrud database", long_about = None)]
 struct Args {
@@ -12,82 +21,538 @@ struct Args {
     #[arg(short, long, default_value = "1")]
     limit: usize,
     
-    /// Output format: compact (charts only) or verbose (with metadata)
+    /// Output format: compact (charts only), verbose (with metadata) or json (array of chart objects)
     #[arg(short, long, default_value = "compact")]
     format: String,
+
+    /// Colorize charts by utilization (ignored when stdout is not a terminal)
+    #[arg(long)]
+    color: bool,
+
+    /// Utilization percentage at which charts turn yellow
+    #[arg(long, default_value = "60")]
+    warn_threshold: f64,
+
+    /// Utilization percentage at which charts turn red
+    #[arg(long, default_value = "85")]
+    crit_threshold: f64,
+
+    /// Show charts generated at or after this time (RFC3339, e.g. 2024-01-01T00:00:00Z) instead of the latest ones
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Show charts generated at or before this time (RFC3339) instead of the latest ones
+    #[arg(long)]
+    until: Option<String>,
+
+    /// Re-query and redraw the latest charts in place every this many seconds until Ctrl+C
+    #[arg(long, conflicts_with_all = ["since", "until"])]
+    watch: Option<f64>,
+
+    /// Stop watching after this many refreshes
+    #[arg(long, requires = "watch")]
+    count: Option<u64>,
+
+    /// Also show per-core charts (stored by `thrud-collector --core-charts`)
+    #[arg(long)]
+    cores: bool,
+
+    /// Show bar and braille charts as bare sparklines, without the ..NN%| suffix
+    /// (compact and verbose formats)
+    #[arg(long)]
+    no_suffix: bool,
+
+    /// The 8 bar characters the collector was started with (its --bar-ramp),
+    /// lowest to highest, so --color can tell their levels apart
+    #[arg(long, default_value = DEFAULT_BAR_RAMP)]
+    bar_ramp: String,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
-    
-    // Parse chart type
-    let chart_type = match args.chart_type.as_str() {
-        "bar" => ChartType::Bar,
-        "braille" => ChartType::Braille,
-        _ => {
-            eprintln!("Error: Invalid chart type '{}'. Use 'bar' or 'braille'", args.chart_type);
+/// Clear the terminal and move the cursor to the top left
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Parse an RFC3339 time flag, exiting with an error message if it's invalid
+fn parse_time_arg(flag: &str, value: Option<&String>) -> Option<DateTime<Utc>> {
+    value.map(|s| match DateTime::parse_from_rfc3339(s) {
+        Ok(t) => t.with_timezone(&Utc),
+        Err(e) => {
+            eprintln!("Error: Invalid --{} '{}': {}", flag, s, e);
             std::process::exit(1);
         }
+    })
+}
+
+/// One round's charts in the compact format expected by the shell scripts
+fn compact_round(round_charts: &[&Chart], metrics: &[&str], render: &dyn Fn(&str) -> String) -> String {
+    let mut output = String::new();
+
+    // Find charts for each metric in order
+    for metric in metrics {
+        if let Some(chart) = round_charts.iter().find(|c| &c.metric_name == metric) {
+            let prefix = match *metric {
+                "performance_cores_utilization" => "P:".to_string(),
+                "efficiency_cores_utilization" => "E:".to_string(),
+                "gpu_utilization" => "G:".to_string(),
+                _ => match (gpu_chart_index(metric), core_chart_index(metric)) {
+                    (Some(index), _) => format!("G{}:", index),
+                    (_, Some(core_id)) => format!("C{}:", core_id),
+                    _ => String::new(),
+                },
+            };
+            let rendered = render(&chart.chart_data);
+            if chart.chart_data.contains('\n') {
+                // Multi-row charts can't share a line, so print each metric as its own block
+                output.push_str(&format!("{}\n{}\n", prefix, rendered));
+            } else {
+                // Charts without a suffix have no pipe to separate them
+                if !output.is_empty() && !output.ends_with(['|', '\n']) {
+                    output.push(' ');
+                }
+                output.push_str(&format!("{}{}", prefix, rendered));
+            }
+        }
+    }
+
+    output.trim_end_matches(['|', '\n']).to_string()
+}
+
+/// Utilization thresholds used to pick chart colors
+struct Thresholds {
+    warn: f64,
+    crit: f64,
+}
+
+impl Thresholds {
+    fn color_for(&self, percentage: f64) -> &'static str {
+        if percentage >= self.crit {
+            RED
+        } else if percentage >= self.warn {
+            YELLOW
+        } else {
+            GREEN
+        }
+    }
+}
+
+/// Average percentage embedded in chart data as `..NN%`
+fn embedded_percentage(chart_data: &str) -> Option<f64> {
+    let start = chart_data.rfind("..")? + 2;
+    let end = start + chart_data[start..].find('%')?;
+    chart_data[start..end].trim().parse().ok()
+}
+
+/// Bar or braille chart data without its `..NN%` average and trailing `|`
+fn strip_suffix(chart_data: &str) -> &str {
+    let body = chart_data.trim_end_matches('|');
+    match body.rfind("..") {
+        Some(start) if body.ends_with('%') => &body[..start],
+        _ => body,
+    }
+}
+
+/// Lower bound of the percentage drawn by a single-row bar character of `ramp`
+fn bar_char_percentage(c: char, ramp: &str) -> Option<f64> {
+    let level = std::iter::once(' ').chain(ramp.chars()).position(|b| b == c)?;
+    Some(level.saturating_sub(1) as f64 / 8.0 * 100.0)
+}
+
+/// Color a line chart row by row, using the value each row sits at on the
+/// y-axis between the min and max labels
+fn colorize_line_chart(chart_data: &str, thresholds: &Thresholds) -> String {
+    let rows: Vec<&str> = chart_data.lines().collect();
+    let label = |row: &str| row.split('|').next().and_then(|l| l.trim().parse::<f64>().ok());
+    let max = rows.first().and_then(|r| label(r)).unwrap_or(0.0);
+    let min = rows.last().and_then(|r| label(r)).unwrap_or(0.0);
+    let steps = rows.len().saturating_sub(1).max(1) as f64;
+
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let value = max - (max - min) * i as f64 / steps;
+            format!("{}{}{}", thresholds.color_for(value), row, RESET)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Wrap chart data in ANSI colors. Single-row bar charts are colored per
+/// character, line charts per row, and everything else by `average`, the
+/// percentage embedded in the chart before any suffix was stripped. `ramp` is
+/// the bar characters the chart was drawn with.
+fn colorize(chart_data: &str, average: Option<f64>, chart_type: &ChartType, thresholds: &Thresholds, ramp: &str) -> String {
+    if *chart_type == ChartType::Line {
+        return colorize_line_chart(chart_data, thresholds);
+    }
+
+    let average = average.unwrap_or(0.0);
+    let body = chart_data.trim_end_matches('|');
+    let suffix = &chart_data[body.len()..];
+
+    if *chart_type == ChartType::Bar && !body.contains('\n') {
+        let split = strip_suffix(body).len();
+        let mut output = String::new();
+        for c in body[..split].chars() {
+            let percentage = bar_char_percentage(c, ramp).unwrap_or(average);
+            output.push_str(&format!("{}{}{}", thresholds.color_for(percentage), c, RESET));
+        }
+        output.push_str(&format!("{}{}{}", thresholds.color_for(average), &body[split..], RESET));
+        return format!("{}{}", output, suffix);
+    }
+
+    format!("{}{}{}{}", thresholds.color_for(average), body, RESET, suffix)
+}
+
+/// Chart data as printed: without its suffix if `no_suffix` is set, and colored
+/// by `thresholds` if given, reading bar levels from `ramp`
+fn render_chart(chart_data: &str, chart_type: &ChartType, no_suffix: bool, thresholds: Option<&Thresholds>, ramp: &str) -> String {
+    let average = embedded_percentage(chart_data);
+    let chart_data = if no_suffix && *chart_type != ChartType::Line {
+        strip_suffix(chart_data)
+    } else {
+        chart_data
     };
-    
-    // Initialize storage
-    let storage = SqliteStorage::new(None);
-    
-    // Get charts
-    let metrics = ["performance_cores_utilization", "efficiency_cores_utilization", "gpu_utilization"];
-    let charts = storage.get_latest_charts(&metrics, &chart_type, args.limit)?;
-    
-    if charts.is_empty() {
-        eprintln!("No charts found. Make sure the collector is running and has generated data.");
-        std::process::exit(1);
+    match thresholds {
+        Some(thresholds) => colorize(chart_data, average, chart_type, thresholds, ramp),
+        None => chart_data.to_string(),
     }
-    
-    // Output based on format
-    match args.format.as_str() {
+}
+
+/// Format charts for output: compact groups them by collection round, verbose
+/// lists each chart with its metadata and json emits them as an array
+fn format_charts(charts: &[Chart], format: &str, in_range: bool, metrics: &[&str], render: &dyn Fn(&str) -> String) -> String {
+    let mut output = String::new();
+    match format {
+        "json" => {
+            // Raw chart data, without terminal colors
+            let json = serde_json::to_string_pretty(charts).unwrap_or_else(|_| "[]".to_string());
+            let _ = writeln!(output, "{}", json);
+        },
+        "compact" if in_range => {
+            // One line (or block) per round, oldest first, prefixed by its time
+            let mut rounds: Vec<(&str, Vec<&Chart>)> = Vec::new();
+            for chart in charts {
+                match rounds.iter_mut().find(|(id, _)| *id == chart.collection_round_id) {
+                    Some((_, round_charts)) => round_charts.push(chart),
+                    None => rounds.push((&chart.collection_round_id, vec![chart])),
+                }
+            }
+
+            for (_, round_charts) in &rounds {
+                let time = round_charts[0].timestamp.format("%Y-%m-%d %H:%M:%S");
+                let round = compact_round(round_charts, metrics, render);
+                if round.contains('\n') {
+                    let _ = writeln!(output, "{}\n{}", time, round);
+                } else {
+                    let _ = writeln!(output, "{} {}", time, round);
+                }
+            }
+        },
         "compact" => {
             // Group charts by collection round and output in the format expected by shell scripts
-            let mut charts_by_round: std::collections::HashMap<String, Vec<_>> = std::collections::HashMap::new();
+            let mut charts_by_round: std::collections::HashMap<String, Vec<&Chart>> = std::collections::HashMap::new();
             
             for chart in charts {
-                charts_by_round.entry(chart.collection_round_id.clone()).or_insert_with(Vec::new).push(chart);
+                charts_by_round.entry(chart.collection_round_id.clone()).or_default().push(chart);
             }
             
             // Get the most recent round
             if let Some((_, round_charts)) = charts_by_round.iter().next() {
-                let mut output = String::new();
-                
-                // Find charts for each metric in order
-                for metric in &["performance_cores_utilization", "efficiency_cores_utilization", "gpu_utilization"] {
-                    if let Some(chart) = round_charts.iter().find(|c| &c.metric_name == metric) {
-                        let prefix = match *metric {
-                            "performance_cores_utilization" => "P:",
-                            "efficiency_cores_utilization" => "E:",
-                            "gpu_utilization" => "G:",
-                            _ => "",
-                        };
-                        output.push_str(&format!("{}{}", prefix, chart.chart_data));
-                    }
-                }
-                
-                println!("{}", output.trim_end_matches('|'));
+                let _ = writeln!(output, "{}", compact_round(round_charts, metrics, render));
             }
         },
-        "verbose" => {
-            for chart in &charts {
-                println!("Collection Round: {}", chart.collection_round_id);
-                println!("Metric: {}", chart.metric_name);
-                println!("Chart Type: {:?}", chart.chart_type);
-                println!("Data Points: {}", chart.data_points);
-                println!("Timestamp: {}", chart.timestamp);
-                println!("Chart: {}", chart.chart_data);
-                println!("---");
+        _ => {
+            for chart in charts {
+                let _ = writeln!(output, "Collection Round: {}", chart.collection_round_id);
+                let _ = writeln!(output, "Metric: {}", chart.metric_name);
+                let _ = writeln!(output, "Chart Type: {:?}", chart.chart_type);
+                let _ = writeln!(output, "Data Points: {}", chart.data_points);
+                let _ = writeln!(output, "Timestamp: {}", chart.timestamp);
+                let _ = writeln!(output, "Chart: {}", render(&chart.chart_data));
+                let _ = writeln!(output, "---");
             }
-        },
+        }
+    }
+    output
+}
+
+/// Chart metrics to query: the defaults plus, on machines with several GPUs, a
+/// chart per GPU and, if `cores` is set, a chart per CPU core
+fn chart_metrics(storage: &SqliteStorage, cores: bool) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut metrics: Vec<String> = DEFAULT_CHART_METRICS.iter().map(|m| m.to_string()).collect();
+    metrics.extend(storage.gpu_chart_metrics()?);
+    if cores {
+        metrics.extend(storage.core_chart_metrics()?);
+    }
+    Ok(metrics)
+}
+
+/// Redraw the latest charts to `out` every `interval` until Ctrl+C or `count`
+/// refreshes, clearing the screen first if `clear` is set
+async fn watch(
+    storage: &SqliteStorage,
+    args: &Args,
+    chart_type: &ChartType,
+    interval: Duration,
+    render: &dyn Fn(&str) -> String,
+    out: &mut dyn std::io::Write,
+    clear: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut refreshes = 0u64;
+    loop {
+        // Re-read the metric list each time, as per-GPU and per-core charts may appear later
+        let metrics = chart_metrics(storage, args.cores)?;
+        let metrics: Vec<&str> = metrics.iter().map(String::as_str).collect();
+        let charts = storage.get_latest_charts(&metrics, chart_type, args.limit)?;
+
+        if clear {
+            write!(out, "{}", CLEAR_SCREEN)?;
+        }
+        if charts.is_empty() && args.format != "json" {
+            writeln!(out, "Waiting for charts... (refreshing every {}s)", interval.as_secs_f64())?;
+        } else {
+            write!(out, "{}", format_charts(&charts, &args.format, false, &metrics, render))?;
+        }
+        out.flush()?;
+
+        refreshes += 1;
+        if args.count.is_some_and(|count| refreshes >= count) {
+            return Ok(());
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    
+    // Parse chart type
+    let chart_type = match args.chart_type.as_str() {
+        "bar" => ChartType::Bar,
+        "braille" => ChartType::Braille,
+        "line" => ChartType::Line,
         _ => {
-            eprintln!("Error: Invalid format '{}'. Use 'compact' or 'verbose'", args.format);
+            eprintln!("Error: Invalid chart type '{}'. Use 'bar', 'braille' or 'line'", args.chart_type);
             std::process::exit(1);
         }
+    };
+
+    if !["compact", "verbose", "json"].contains(&args.format.as_str()) {
+        eprintln!("Error: Invalid format '{}'. Use 'compact', 'verbose' or 'json'", args.format);
+        std::process::exit(1);
+    }
+
+    if args.watch.is_some_and(|seconds| !seconds.is_finite() || seconds <= 0.0) {
+        eprintln!("Error: --watch interval must be positive");
+        std::process::exit(1);
+    }
+    
+    if args.warn_threshold > args.crit_threshold {
+        eprintln!("Error: --warn-threshold must not exceed --crit-threshold");
+        std::process::exit(1);
     }
+    let thresholds = (args.color && std::io::stdout().is_terminal())
+        .then_some(Thresholds { warn: args.warn_threshold, crit: args.crit_threshold });
+    if !is_valid_bar_ramp(&args.bar_ramp) {
+        eprintln!("Error: --bar-ramp must have 8 characters");
+        std::process::exit(1);
+    }
+    let render = |chart_data: &str| render_chart(chart_data, &chart_type, args.no_suffix, thresholds.as_ref(), &args.bar_ramp);
+    
+    let since = parse_time_arg("since", args.since.as_ref());
+    let until = parse_time_arg("until", args.until.as_ref());
+    let in_range = since.is_some() || until.is_some();
+    if let (Some(since), Some(until)) = (since, until) {
+        if since > until {
+            eprintln!("Error: --since must not be later than --until");
+            std::process::exit(1);
+        }
+    }
+
+    let storage = match SqliteStorage::open_read_only(None) {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(seconds) = args.watch {
+        let clear = std::io::stdout().is_terminal();
+        return watch(&storage, &args, &chart_type, Duration::from_secs_f64(seconds), &render, &mut std::io::stdout(), clear).await;
+    }
+    
+    let metrics = chart_metrics(&storage, args.cores)?;
+    let metrics: Vec<&str> = metrics.iter().map(String::as_str).collect();
+
+    // Get charts: everything in the requested window, or the latest ones
+    let charts = if in_range {
+        storage.get_charts_in_range(&metrics, &chart_type, since, until)?
+    } else {
+        storage.get_latest_charts(&metrics, &chart_type, args.limit)?
+    };
+    
+    if charts.is_empty() && args.format != "json" {
+        if in_range {
+            eprintln!(
+                "No {} charts found between {} and {}.",
+                chart_type.as_str(),
+                since.map_or("the first collection".to_string(), |t| t.to_rfc3339()),
+                until.map_or("now".to_string(), |t| t.to_rfc3339()),
+            );
+        } else {
+            eprintln!("No charts found. Make sure the collector is running and has generated data.");
+        }
+        std::process::exit(1);
+    }
+    
+    print!("{}", format_charts(&charts, &args.format, in_range, &metrics, &render));
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use thrud::storage::ASCII_BAR_RAMP;
+
+    const THRESHOLDS: Thresholds = Thresholds { warn: 60.0, crit: 85.0 };
+
+    #[test]
+    fn charts_are_plain_without_color() {
+        for chart_type in [ChartType::Bar, ChartType::Braille, ChartType::Line] {
+            let chart = render_chart("▂▅█..50%|", &chart_type, false, None, DEFAULT_BAR_RAMP);
+            assert_eq!(chart, "▂▅█..50%|");
+            assert!(!chart.contains('\x1b'));
+        }
+    }
+
+    #[test]
+    fn no_suffix_leaves_the_bare_sparkline() {
+        assert_eq!(render_chart("▂▅█..50%|", &ChartType::Bar, true, None, DEFAULT_BAR_RAMP), "▂▅█");
+        assert_eq!(render_chart("⣀⣤..50%|", &ChartType::Braille, true, None, DEFAULT_BAR_RAMP), "⣀⣤");
+        // Charts stored without a suffix are left as they are
+        assert_eq!(render_chart("▂▅█", &ChartType::Bar, true, None, DEFAULT_BAR_RAMP), "▂▅█");
+    }
+
+    #[test]
+    fn color_wraps_charts_in_ansi_codes() {
+        for chart_type in [ChartType::Bar, ChartType::Braille, ChartType::Line] {
+            let chart = render_chart("▂▅█..50%|", &chart_type, false, Some(&THRESHOLDS), DEFAULT_BAR_RAMP);
+            assert!(chart.contains(RESET), "{:?}: {:?}", chart_type, chart);
+        }
+    }
+
+    #[test]
+    fn bar_characters_are_colored_by_their_own_level() {
+        let chart = render_chart("▂▆█..70%|", &ChartType::Bar, false, Some(&THRESHOLDS), DEFAULT_BAR_RAMP);
+
+        assert!(chart.starts_with(&format!("{}▂{}{}▆{}{}█{}", GREEN, RESET, YELLOW, RESET, RED, RESET)));
+        // The average suffix takes the color of the average
+        assert!(chart.ends_with(&format!("{}..70%{}|", YELLOW, RESET)));
+    }
+
+    #[test]
+    fn braille_charts_are_colored_by_their_average() {
+        let chart = render_chart("⣀⣤..90%|", &ChartType::Braille, false, Some(&THRESHOLDS), DEFAULT_BAR_RAMP);
+        assert_eq!(chart, format!("{}⣀⣤..90%{}|", RED, RESET));
+
+        let chart = render_chart("⣀⣤..90%|", &ChartType::Braille, true, Some(&THRESHOLDS), DEFAULT_BAR_RAMP);
+        assert_eq!(chart, format!("{}⣀⣤{}", RED, RESET));
+    }
+
+    #[test]
+    fn bar_levels_are_read_from_the_configured_ramp() {
+        let unicode = render_chart("▂▆█..70%|", &ChartType::Bar, false, Some(&THRESHOLDS), DEFAULT_BAR_RAMP);
+        let ascii = render_chart(":*@..70%|", &ChartType::Bar, false, Some(&THRESHOLDS), ASCII_BAR_RAMP);
+
+        assert!(ascii.starts_with(&format!("{}:{}{}*{}{}@{}", GREEN, RESET, YELLOW, RESET, RED, RESET)), "{:?}", ascii);
+        // Both ramps draw the same levels, so only the characters differ
+        assert_eq!(ascii.replace(':', "▂").replace('*', "▆").replace('@', "█"), unicode);
+    }
+
+    #[test]
+    fn json_output_parses_back_into_charts() {
+        let chart = Chart {
+            id: Some(7),
+            collection_round_id: "round-1".to_string(),
+            metric_name: "gpu_utilization".to_string(),
+            chart_type: ChartType::Braille,
+            chart_data: "⣀⣤..90%|".to_string(),
+            data_points: 4,
+            timestamp: chrono::Utc::now(),
+        };
+        let render = |chart_data: &str| chart_data.to_string();
+
+        let json = format_charts(std::slice::from_ref(&chart), "json", false, &[], &render);
+        let parsed: Vec<Chart> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].collection_round_id, chart.collection_round_id);
+        assert_eq!(parsed[0].metric_name, chart.metric_name);
+        assert_eq!(parsed[0].chart_type, chart.chart_type);
+        assert_eq!(parsed[0].chart_data, chart.chart_data);
+        assert_eq!(parsed[0].data_points, chart.data_points);
+        assert_eq!(parsed[0].timestamp, chart.timestamp);
+    }
+
+    #[test]
+    fn json_output_of_no_charts_is_an_empty_array() {
+        let render = |chart_data: &str| chart_data.to_string();
+
+        let json = format_charts(&[], "json", false, &[], &render);
+
+        assert_eq!(json.trim(), "[]");
+        assert!(serde_json::from_str::<Vec<Chart>>(&json).unwrap().is_empty());
+    }
+
+    fn store_bar_chart(storage: &SqliteStorage, chart_data: &str) {
+        use thrud::collectors::Metric;
+        use thrud::storage::Storage;
+
+        let round = storage.store_metrics(vec![Metric::gauge("gpu.0.utilization", 0.5)]).unwrap();
+        storage
+            .store_chart(&Chart {
+                id: None,
+                collection_round_id: round.id,
+                metric_name: "gpu_utilization".to_string(),
+                chart_type: ChartType::Bar,
+                chart_data: chart_data.to_string(),
+                data_points: 2,
+                timestamp: chrono::Utc::now(),
+            })
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn watch_redraws_until_the_refresh_count() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        store_bar_chart(&storage, "▅▅..50%|");
+        let args = Args::try_parse_from(["thrud-chart-query", "--watch", "0.01", "--count", "3"]).unwrap();
+        let render = |chart_data: &str| chart_data.to_string();
+
+        let mut out = Vec::new();
+        watch(&storage, &args, &ChartType::Bar, Duration::from_millis(10), &render, &mut out, true).await.unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches(CLEAR_SCREEN).count(), 3);
+        assert_eq!(out.matches("G:▅▅..50%\n").count(), 3, "{:?}", out);
+    }
+
+    #[tokio::test]
+    async fn watch_waits_for_charts_on_an_empty_store() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let args = Args::try_parse_from(["thrud-chart-query", "--watch", "0.01", "--count", "2"]).unwrap();
+        let render = |chart_data: &str| chart_data.to_string();
+
+        let mut out = Vec::new();
+        watch(&storage, &args, &ChartType::Bar, Duration::from_millis(10), &render, &mut out, false).await.unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("Waiting for charts...").count(), 2);
+        assert!(!out.contains(CLEAR_SCREEN));
+    }
+}
@@ -0,0 +1,88 @@
+use clap::Parser;
+use thrud::storage::{HealthReport, SqliteStorage};
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Check the Thrud database for corruption, missing tables or indexes and orphaned rows",
+    long_about = "Check the Thrud database for corruption, missing tables or indexes and orphaned rows.\n\n\
+                  Exits with 1 if the integrity check fails, 2 if it passes but other problems \
+                  were found, and 0 if the database is healthy."
+)]
+struct Args {
+    /// Database path (default: $THRUD_DB, $XDG_DATA_HOME/thrud/thrud.db or ~/.thrud/thrud.db)
+    #[arg(long)]
+    database: Option<String>,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+fn print_report(report: &HealthReport) {
+    let status = |ok: bool| if ok { "✅" } else { "❌" };
+
+    if !report.integrity_ok() {
+        println!("{} Integrity check failed:", status(false));
+        for message in &report.integrity {
+            println!("   {}", message);
+        }
+        println!("   Skipped the remaining checks");
+        return;
+    }
+
+    println!("{} Integrity: ok", status(true));
+    println!("   Schema version: {}", report.schema_version);
+    println!(
+        "{} Tables: {}",
+        status(report.missing_tables.is_empty()),
+        if report.missing_tables.is_empty() { "all present".to_string() } else { format!("missing {}", report.missing_tables.join(", ")) }
+    );
+    println!(
+        "{} Indexes: {}",
+        status(report.missing_indexes.is_empty()),
+        if report.missing_indexes.is_empty() { "all present".to_string() } else { format!("missing {}", report.missing_indexes.join(", ")) }
+    );
+    for (table, count) in &report.row_counts {
+        println!("   {:<18} {} rows", table, count);
+    }
+    println!("{} Orphaned metrics: {}", status(report.orphaned_metrics == 0), report.orphaned_metrics);
+    println!("{} Orphaned charts: {}", status(report.orphaned_charts == 0), report.orphaned_charts);
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let storage = match SqliteStorage::open_read_only(args.database) {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let db_path = storage.db_path().to_string();
+    let report = match storage.health_check() {
+        Ok(report) => report,
+        Err(e) => {
+            // A file that isn't a database at all fails before the integrity check can run
+            eprintln!("Error: Cannot check {}: {}", db_path, e);
+            std::process::exit(1);
+        }
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("Database: {}", db_path);
+        print_report(&report);
+    }
+
+    if !report.integrity_ok() {
+        std::process::exit(1);
+    }
+    if !report.is_healthy() {
+        std::process::exit(2);
+    }
+    Ok(())
+}
@@ -1,146 +1,1148 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::time;
-use thrud::collectors::{GPUCollector, CPUCollector, Collector};
-use thrud::storage::{SqliteStorage, Storage};
-use clap::Parser;
+use tokio::time::{self, Instant};
+use thrud::collectors::process::MAX_TOP_N;
+use thrud::collectors::{names, CollectorRegistry, ErrorThrottle, Metric, MetricFilter, MetricTransform, ProcessCollector, Schedule};
+use thrud::config::{CollectorConfig, Config};
+use thrud::export::{OtlpExporter, StatsdSink};
+use thrud::storage::{is_valid_bar_ramp, local_hostname, RecentCache, SqliteStorage, Storage, StorageStats, DEFAULT_BAR_RAMP, DEFAULT_CHART_METRICS, PER_CORE_CHARTS};
+use thrud::ThrudError;
+use clap::{Parser, ValueEnum};
+use log::{debug, error, info, warn, LevelFilter};
+
+/// A collection error repeated within this long is counted rather than printed
+const ERROR_SUMMARY_EVERY: Duration = Duration::from_secs(10);
+
+/// Verbosity of the collector's own output, from least to most verbose
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum LogLevel {
+    /// Only errors
+    Error,
+    /// Errors and warnings, e.g. a busy database
+    Warn,
+    /// Startup banner and periodic statistics as well
+    Info,
+    /// Development details such as per-cycle metric counts
+    Debug,
+}
+
+impl LogLevel {
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+        }
+    }
+}
+
+/// Log to stderr, keeping stdout for metrics. `RUST_LOG` filters as usual; an
+/// explicit `level` (from `--log-level` or `--quiet`) takes precedence over it.
+fn init_logging(level: Option<LogLevel>) {
+    // Development mode is a shorthand for debug output
+    let default = if std::env::var("THRUD_DEV_MODE").is_ok() { LogLevel::Debug } else { LogLevel::Info };
+    let mut builder = env_logger::Builder::new();
+    builder
+        .filter_level(default.filter())
+        .parse_default_env()
+        .format(|buf, record| writeln!(buf, "{}", record.args()));
+    if let Some(level) = level {
+        builder.filter_level(level.filter());
+    }
+    builder.init();
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    /// Store metrics in the SQLite database only
+    Sqlite,
+    /// Additionally print each metric as a JSON object per line on stdout
+    Jsonl,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum StatsFormat {
+    /// Human-readable summary
+    Text,
+    /// A single JSON object per report
+    Json,
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Thrud System Metrics Collector", long_about = None)]
 struct Args {
-    /// Collection interval in seconds (supports fractional values, e.g., 0.1 for 100ms)
-    #[arg(short, long, default_value = "5.0")]
-    interval: f64,
+    /// Config file (TOML); command-line flags override its values
+    #[arg(short, long)]
+    config: Option<String>,
+
+    /// Default collection interval in seconds (supports fractional values, e.g., 0.1 for 100ms) [default: 5.0]
+    #[arg(short, long)]
+    interval: Option<f64>,
+
+    /// Per-collector interval override, e.g. gpu=0.5 (repeatable)
+    #[arg(long = "collector-interval", value_parser = parse_collector_interval)]
+    collector_intervals: Vec<(String, f64)>,
+
+    /// Database path [default: $THRUD_DB, $XDG_DATA_HOME/thrud/thrud.db or ~/.thrud/thrud.db]
+    #[arg(long)]
+    database: Option<String>,
+
+    /// Delete stored data older than this many hours (kept forever if not set)
+    #[arg(long)]
+    retention_hours: Option<f64>,
+
+    /// Prune the oldest rounds once the database's live data exceeds this many MB
+    #[arg(long)]
+    max_db_mb: Option<f64>,
+
+    /// Store metrics in one table per UTC day so retention pruning drops whole
+    /// days; converts an existing database (one-way)
+    #[arg(long)]
+    partition_by_day: bool,
+
+    /// Host name recorded with each collection round [default: this machine's]
+    #[arg(long)]
+    hostname: Option<String>,
+
+    /// Output mode for collected metrics
+    #[arg(short, long, value_enum, default_value = "sqlite")]
+    output: OutputFormat,
+
+    /// Format of the periodic database statistics
+    #[arg(long, value_enum, default_value = "text")]
+    stats_format: StatsFormat,
+
+    /// Print metrics as JSON lines without touching the database
+    #[arg(long)]
+    stdout_only: bool,
+
+    /// Run the collection loop and print each round's metrics without touching the database
+    #[arg(long, conflicts_with = "stdout_only")]
+    dry_run: bool,
+
+    /// Comma-separated collectors to run (default: all, e.g. cpu,gpu)
+    #[arg(long, value_delimiter = ',')]
+    collectors: Option<Vec<String>>,
+
+    /// Only keep metrics whose names match one of these globs, e.g. 'cpu.*_cluster.*' (repeatable or comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    include: Vec<String>,
+
+    /// Drop metrics whose names match one of these globs, e.g. 'cpu.*_core.*'; wins over --include
+    #[arg(long, value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// Also send metrics as StatsD gauges to this host:port
+    #[arg(long)]
+    statsd: Option<String>,
+
+    /// Also push metrics to this OTLP/HTTP endpoint, e.g. http://localhost:4318
+    #[arg(long)]
+    otlp_endpoint: Option<String>,
+
+    /// Maximum time to wait for each collector per round, in milliseconds
+    #[arg(long, default_value = "2000")]
+    collector_timeout_ms: u64,
+
+    /// Write this many collection rounds per database transaction
+    #[arg(long, default_value = "1")]
+    batch_size: usize,
+
+    /// Flush a partial batch once its oldest round is this many milliseconds old
+    #[arg(long, default_value = "5000")]
+    batch_max_ms: u64,
+
+    /// Number of recent rounds shown in each stored chart; charts appear once
+    /// this many rounds plus one have been collected (braille charts round an
+    /// odd number up, so they appear one round later)
+    #[arg(long, default_value = "10")]
+    chart_points: usize,
+
+    /// Number of stacked rows used for each stored bar chart
+    #[arg(long, default_value = "1")]
+    bar_rows: usize,
+
+    /// Number of stacked rows used for each stored braille chart; each row adds 4
+    /// levels of vertical resolution
+    #[arg(long, default_value = "1")]
+    braille_rows: usize,
+
+    /// Also store a utilization chart for each individual CPU core
+    #[arg(long)]
+    core_charts: bool,
+
+    /// Gzip stored chart data (thrud-chart-query reads both forms)
+    #[arg(long)]
+    compress_charts: bool,
+
+    /// Store bar and braille charts as bare sparklines, without the ..NN%| suffix
+    #[arg(long)]
+    no_chart_suffix: bool,
+
+    /// 8 characters drawing bar chart levels from lowest to full, e.g. ".:-=+*#@"
+    /// for terminals without Unicode blocks [default: ▁▂▃▄▅▆▇█]
+    #[arg(long)]
+    bar_ramp: Option<String>,
+
+    /// Draw intervals where a core had no tick activity at all (parked, as
+    /// efficiency cores often are) with this character in bar charts, e.g. '·',
+    /// instead of charting them as 0% busy
+    #[arg(long)]
+    parked_gap: Option<char>,
+
+    /// Delete all stored charts, regenerate them from the stored metrics of every
+    /// collection round using the chart options above, then exit
+    #[arg(long, conflicts_with_all = ["stdout_only", "dry_run"])]
+    regenerate_charts: bool,
+
+    /// Don't store a round whose metrics are identical to the previous round's
+    #[arg(long)]
+    skip_duplicate_rounds: bool,
+
+    /// Store an empty "heartbeat" round when a round yields no metrics, so idle
+    /// periods show up as distinct from downtime
+    #[arg(long)]
+    heartbeat: bool,
+
+    /// Number of processes the process collector reports, busiest first (at most 100)
+    #[arg(long, default_value = "10")]
+    top_n: usize,
+
+    /// Number of recent collection rounds kept in memory
+    #[arg(long, default_value = "60")]
+    cache_rounds: usize,
+
+    /// Most verbose messages to print, overriding RUST_LOG [default: info, or debug
+    /// when THRUD_DEV_MODE is set]
+    #[arg(long, value_enum)]
+    log_level: Option<LogLevel>,
+
+    /// Only print errors (same as --log-level error); metrics requested with
+    /// --output jsonl, --stdout-only or --dry-run are still printed
+    #[arg(short, long, conflicts_with = "log_level")]
+    quiet: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
+
+    // --quiet is a shorthand for --log-level error
+    init_logging(if args.quiet { Some(LogLevel::Error) } else { args.log_level });
+
+    let file_config = match &args.config {
+        Some(path) => match Config::from_file(path) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => Config::default(),
+    };
+    let config = file_config.merge(Config {
+        interval: args.interval,
+        database: args.database.clone(),
+        retention_hours: args.retention_hours,
+        max_db_mb: args.max_db_mb,
+        collectors: args.collectors.clone(),
+        collector: args
+            .collector_intervals
+            .iter()
+            .map(|(name, interval)| (name.clone(), CollectorConfig { interval: Some(*interval) }))
+            .collect(),
+        ..Config::default()
+    });
     
-    // Validate interval
-    if args.interval <= 0.0 {
-        eprintln!("Error: Interval must be positive");
+    // Validate intervals
+    if config.interval() <= 0.0 || config.collector.values().any(|c| c.interval.is_some_and(|i| i <= 0.0)) {
+        error!("Error: Interval must be positive");
+        std::process::exit(1);
+    }
+
+    let database_path = match config.database_path() {
+        Ok(path) => path,
+        Err(e) => {
+            error!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if config.transform.iter().any(|(_, rule)| !rule.scale.is_finite() || !rule.offset.is_finite()) {
+        error!("Error: Transform scale and offset must be finite numbers");
+        std::process::exit(1);
+    }
+
+    if args.collector_timeout_ms == 0 {
+        error!("Error: Collector timeout must be positive");
+        std::process::exit(1);
+    }
+
+    if args.batch_size == 0 {
+        error!("Error: Batch size must be at least 1");
+        std::process::exit(1);
+    }
+
+    if args.bar_rows == 0 {
+        error!("Error: Bar rows must be at least 1");
+        std::process::exit(1);
+    }
+
+    if args.braille_rows == 0 {
+        error!("Error: Braille rows must be at least 1");
+        std::process::exit(1);
+    }
+
+    if args.chart_points == 0 {
+        error!("Error: Chart points must be at least 1");
+        std::process::exit(1);
+    }
+
+    let mut chart_metrics = DEFAULT_CHART_METRICS.to_vec();
+    if args.core_charts {
+        chart_metrics.push(PER_CORE_CHARTS);
+    }
+
+    let bar_ramp = match args.bar_ramp.as_deref() {
+        Some(ramp) if !is_valid_bar_ramp(ramp) => {
+            warn!("⚠️  Bar ramp '{}' must have 8 characters, using {}", ramp, DEFAULT_BAR_RAMP);
+            DEFAULT_BAR_RAMP
+        }
+        Some(ramp) => ramp,
+        None => DEFAULT_BAR_RAMP,
+    };
+
+    if args.regenerate_charts {
+        let storage = SqliteStorage::new(database_path.clone())?
+            .with_chart_compression(args.compress_charts)
+            .with_chart_suffix(!args.no_chart_suffix)
+            .with_bar_ramp(bar_ramp)
+            .with_parked_gap(args.parked_gap);
+        storage.initialize()?;
+        info!("📁 Regenerating charts in {}", storage.db_path());
+        let rounds = storage.regenerate_charts(&chart_metrics, args.chart_points, args.bar_rows, args.braille_rows)?;
+        info!("📊 Replayed {} collection rounds through the chart generator", rounds);
+        return Ok(());
+    }
+
+    if config.retention_hours.is_some_and(|hours| hours <= 0.0) {
+        error!("Error: Retention hours must be positive");
+        std::process::exit(1);
+    }
+
+    if config.max_db_mb.is_some_and(|mb| mb <= 0.0) {
+        error!("Error: Max database size must be positive");
         std::process::exit(1);
     }
     
-    println!("Thrud System Metrics Collector");
-    println!("==============================");
-    println!("Collection interval: {}s", args.interval);
-    println!("Collecting metrics and storing to database...");
-    println!("Press Ctrl+C to stop\n");
-
-    // Initialize storage
-    let storage = SqliteStorage::new(None); // Uses default ~/.thrud/thrud.db
-    storage.initialize()?;
-    
-    println!("📁 Database initialized at ~/.thrud/thrud.db");
+    if args.top_n == 0 || args.top_n > MAX_TOP_N {
+        error!("Error: Top N must be between 1 and {}", MAX_TOP_N);
+        std::process::exit(1);
+    }
 
-    let gpu_collector = GPUCollector::new();
-    let cpu_collector = CPUCollector::new();
-    let mut interval = time::interval(Duration::from_secs_f64(args.interval));
+    let mut registry = CollectorRegistry::new();
+    registry.register(Box::new(ProcessCollector::new().with_top_n(args.top_n)));
+    let enabled: Vec<String> = match &config.collectors {
+        Some(names) => names.iter().map(|n| n.trim().to_string()).collect(),
+        None => registry.names().into_iter().map(String::from).collect(),
+    };
+    let enabled: Vec<&str> = enabled.iter().map(String::as_str).collect();
+    let configured = config.collector.keys().map(String::as_str);
+    if let Some(unknown) = enabled.iter().copied().chain(configured).find(|name| registry.get(name).is_none()) {
+        error!("Error: Unknown collector '{}'. Available: {}", unknown, registry.names().join(", "));
+        std::process::exit(1);
+    }
+
+    // Each collector runs on its own interval; the fastest one sets the pace for logging
+    let intervals: Vec<(String, Duration)> = enabled
+        .iter()
+        .map(|name| (name.to_string(), Duration::from_secs_f64(config.collector_interval(name))))
+        .collect();
+    let base_interval = enabled
+        .iter()
+        .map(|name| config.collector_interval(name))
+        .fold(f64::INFINITY, f64::min)
+        .min(config.interval());
 
-    // Show initial stats
-    show_stats(&storage)?;
+    let jsonl = args.output == OutputFormat::Jsonl || args.stdout_only;
+
+    info!("Thrud System Metrics Collector");
+    info!("==============================");
+    info!("Collection interval: {}s", base_interval);
+    info!("Collectors: {}", intervals
+        .iter()
+        .map(|(name, interval)| format!("{} ({}s)", name, interval.as_secs_f64()))
+        .collect::<Vec<_>>()
+        .join(", "));
+    let filter = MetricFilter::new(args.include.clone(), args.exclude.clone());
+    let transform = MetricTransform::new(&config.transform);
+    if !transform.is_empty() {
+        let patterns: Vec<&str> = config.transform.iter().map(|(pattern, _)| pattern.as_str()).collect();
+        info!("Rescaling metrics: {}", patterns.join(", "));
+    }
+    if !args.include.is_empty() {
+        info!("Including metrics: {}", args.include.join(", "));
+    }
+    if !args.exclude.is_empty() {
+        info!("Excluding metrics: {}", args.exclude.join(", "));
+    }
+    if args.stdout_only {
+        info!("Collecting metrics and printing JSON lines to stdout...");
+    } else if args.dry_run {
+        info!("Dry run: printing metrics without storing them...");
+    } else {
+        info!("Collecting metrics and storing to database...");
+    }
+    info!("Press Ctrl+C to stop\n");
+
+    let statsd = match &args.statsd {
+        Some(addr) => match StatsdSink::new(addr) {
+            Ok(sink) => {
+                info!("📡 Sending StatsD gauges to {}", addr);
+                Some(sink)
+            }
+            Err(e) => {
+                error!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let otlp = match &args.otlp_endpoint {
+        Some(endpoint) => match OtlpExporter::new(endpoint) {
+            Ok(exporter) => {
+                info!("📡 Exporting OTLP metrics to {}", endpoint);
+                Some(OtlpSender::new(exporter))
+            }
+            Err(e) => {
+                error!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let storage = open_storage(&args, database_path.clone(), bar_ramp)?;
+    if let Some(storage) = &storage {
+        info!("📁 Database initialized at {}", storage.db_path());
+        show_stats(storage, args.stats_format)?;
+    }
+
+    let recent = RecentCache::new(args.cache_rounds);
     
-    let dev_mode = std::env::var("THRUD_DEV_MODE").is_ok();
+    debug!("🔧 Debug logging enabled");
     
-    if dev_mode {
-        println!("🔧 Development mode active");
+    if base_interval < 1.0 {
+        info!("⚡ High-frequency mode: Reduced logging for subsecond intervals");
     }
-    
-    if args.interval < 1.0 {
-        println!("⚡ High-frequency mode: Reduced logging for subsecond intervals");
+
+    if let (Some(storage), Some(hours)) = (&storage, config.retention_hours) {
+        info!("🧹 Retention: keeping the last {} hours of data", hours);
+        prune(storage, hours);
+    }
+
+    if let (Some(storage), Some(max_mb)) = (&storage, config.max_db_mb) {
+        info!("🧹 Size cap: keeping the database under {} MB", max_mb);
+        enforce_size_cap(storage, max_mb);
     }
 
+    // Prune once a minute, compact once an hour and show stats every ~10 seconds
+    // for subsecond intervals or every ~30 seconds otherwise
+    let prune_every = Duration::from_secs(60);
+    let compact_every = Duration::from_secs(3600);
+    let stats_every = Duration::from_secs(if base_interval < 1.0 { 10 } else { 30 });
+
+    let start = Instant::now();
+    let mut last_prune = start;
+    let mut last_compact = start;
+    let mut last_stats = start;
+    let mut schedule = Schedule::new(intervals);
     let mut collection_count = 0u64;
+    let mut error_throttle = ErrorThrottle::new(ERROR_SUMMARY_EVERY);
+    let mut otlp_skipped = 0;
+    // Set when the loop stops on an error rather than a shutdown signal
+    let mut exit_code = 0;
     
+    if let Err(e) = registry.init(&enabled) {
+        error!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    // Rounds run to completion; a shutdown signal is only acted on between rounds
+    let (shutdown_tx, mut shutdown_rx) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        wait_for_shutdown_signal().await;
+        let _ = shutdown_tx.send(true);
+    });
+
     loop {
-        interval.tick().await;
+        let Some(offset) = schedule.next_offset() else {
+            warn!("⚠️  No collectors enabled");
+            return Ok(());
+        };
+        if *shutdown_rx.borrow() {
+            break;
+        }
+        tokio::select! {
+            _ = time::sleep_until(start + offset) => {}
+            _ = shutdown_rx.changed() => break,
+        }
+
+        // Waking up a whole interval late (e.g. after sleep) skips the missed
+        // collections rather than running them back to back
+        let elapsed = start.elapsed();
+        let missed = schedule.skip_missed(elapsed);
+        if missed > 0 {
+            warn!("⏭️  Fell behind schedule, skipped {} missed collector runs", missed);
+        }
+        let due = schedule.take_due(elapsed);
+        if due.is_empty() {
+            continue;
+        }
         collection_count += 1;
         
-        let mut all_metrics = Vec::new();
-        let mut collection_errors = Vec::new();
+        let (metrics, durations, collection_errors) =
+            collect_round(&registry, &due, Duration::from_millis(args.collector_timeout_ms)).await;
+        let metrics = transform.apply(filter.apply(metrics));
+        let durations = transform.apply(filter.apply(durations));
+        // Whether the round is empty is decided by what the collectors returned;
+        // the timing metrics are only merged in for output and storage
+        let collected_any = !metrics.is_empty();
+        let all_metrics = merge_round(metrics, durations);
 
-        // Collect GPU metrics
-        match gpu_collector.collect() {
-            Ok(mut metrics) => {
-                all_metrics.append(&mut metrics);
+        // Report collection errors, summarizing repeats of the same error
+        let now = Instant::now().into_std();
+        for error in &collection_errors {
+            if error_throttle.should_report(&error.to_string(), now) {
+                error!("❌ Collection error: {}", error);
             }
-            Err(e) => {
-                collection_errors.push(format!("GPU: {}", e));
+        }
+        for summary in error_throttle.summaries(now) {
+            error!("❌ Collection error: {}", summary);
+        }
+
+        if jsonl {
+            if let Err(e) = write_jsonl(&mut std::io::stdout().lock(), &all_metrics) {
+                error!("❌ Output error: {}", e);
             }
         }
-        
-        // Collect CPU metrics
-        match cpu_collector.collect() {
-            Ok(mut metrics) => {
-                all_metrics.append(&mut metrics);
+
+        if args.dry_run {
+            print_round(&all_metrics);
+        }
+
+        if let Some(sink) = &statsd {
+            if let Err(e) = sink.send(&all_metrics) {
+                error!("❌ StatsD error: {}", e);
             }
-            Err(e) => {
-                collection_errors.push(format!("CPU: {}", e));
+        }
+
+        if let Some(sender) = &otlp {
+            if !sender.try_send(all_metrics.clone()) {
+                otlp_skipped += 1;
+            } else if otlp_skipped > 0 {
+                warn!("⏭️  Skipped OTLP export of {} rounds while the previous export was still running", otlp_skipped);
+                otlp_skipped = 0;
             }
         }
 
-        // Report collection errors (always show errors)
-        for error in &collection_errors {
-            println!("❌ Collection error: {}", error);
+        if recent.capacity() > 0 && collected_any {
+            recent.push(chrono::Utc::now(), all_metrics.clone());
         }
 
         // Store metrics to database
         let metrics_count = all_metrics.len();
-        if !all_metrics.is_empty() {
-            match storage.store_metrics(all_metrics) {
-                Ok(collection_round) => {
-                    // Generate and store charts after successful metrics storage
-                    if let Err(e) = storage.generate_and_store_charts(&collection_round.id, 10) {
-                        if dev_mode {
-                            println!("⚠️  Chart generation error: {}", e);
+        let to_store = storage
+            .as_ref()
+            .and_then(|storage| round_to_store(collected_any, all_metrics, args.heartbeat).map(|round| (storage, round)));
+        if let Some((storage, round)) = to_store {
+            match storage.store_metrics_buffered(round) {
+                Ok(rounds) => {
+                    // Generate and store charts once the latest round is written;
+                    // heartbeat rounds have nothing to chart
+                    if let Some(collection_round) = rounds.last().filter(|_| collected_any) {
+                        if let Err(e) = storage.generate_and_store_charts(&collection_round.id, &chart_metrics, args.chart_points, args.bar_rows, args.braille_rows) {
+                            debug!("⚠️  Chart generation error: {}", e);
                         }
                     }
                 }
+                // Failed rounds stay buffered, so a locked database is retried with
+                // the next round; any other SQLite error (e.g. a schema mismatch) won't
+                // go away by itself, so stop and let the shutdown below flush what it can
+                Err(ThrudError::DuplicateRound) => {
+                    debug!("⏭️  Skipped round identical to the previous one");
+                }
+                Err(e) if e.is_busy() => {
+                    warn!("⏳ Database busy, retrying next round ({} rounds pending)", storage.pending_rounds());
+                }
+                Err(e @ ThrudError::Sqlite(_)) => {
+                    error!("Error: {}", e);
+                    exit_code = 1;
+                    break;
+                }
                 Err(e) => {
-                    println!("❌ Storage error: {}", e);
+                    error!("❌ Storage error: {}", e);
                 }
             }
-        } else if !collection_errors.is_empty() {
-            println!("⚠️  No metrics to store");
+        } else if storage.is_some() && !collected_any && !collection_errors.is_empty() {
+            warn!("⚠️  No metrics to store");
         }
 
-        // Show periodic stats based on interval
-        let stats_interval = if args.interval < 1.0 {
-            // For subsecond intervals, show stats every ~10 seconds
-            (10.0 / args.interval) as u64
-        } else {
-            // For >= 1s intervals, show stats every ~30 seconds  
-            std::cmp::max(1, (30.0 / args.interval) as u64)
-        };
-        
-        if collection_count % stats_interval == 0 {
-            println!("📊 Collection #{} at {}", collection_count, chrono::Utc::now().format("%H:%M:%S"));
-            if dev_mode {
-                println!("🔧 Dev info: {} total metrics in this cycle", metrics_count);
+        // The size cap is checked every round since a burst of metrics can
+        // outgrow it well within the retention pruning interval
+        if let (Some(storage), Some(max_mb)) = (&storage, config.max_db_mb) {
+            enforce_size_cap(storage, max_mb);
+        }
+
+        let now = Instant::now();
+        if let Some(storage) = storage.as_ref().filter(|_| config.retention_hours.is_some() || config.max_db_mb.is_some()) {
+            if let Some(hours) = config.retention_hours.filter(|_| now.duration_since(last_prune) >= prune_every) {
+                last_prune = now;
+                prune(storage, hours);
+            }
+            if now.duration_since(last_compact) >= compact_every {
+                last_compact = now;
+                match storage.compact() {
+                    Ok(reclaimed) => info!("🗜️  Compacted database, reclaimed {:.2} KB", reclaimed as f64 / 1024.0),
+                    Err(e) => error!("❌ Compaction error: {}", e),
+                }
+            }
+        }
+
+        if now.duration_since(last_stats) >= stats_every {
+            last_stats = now;
+            info!("📊 Collection #{} at {}", collection_count, chrono::Utc::now().format("%H:%M:%S"));
+            debug!("🔧 Dev info: {} total metrics in this cycle", metrics_count);
+            debug!("🔧 Dev info: {} rounds cached in memory", recent.len());
+            if let Some(storage) = &storage {
+                show_stats(storage, args.stats_format)?;
             }
-            show_stats(&storage)?;
         }
     }
+
+    info!("\n🛑 Shutting down after {} collections", collection_count);
+    if let Some(storage) = &storage {
+        let pending = storage.pending_rounds();
+        match storage.flush() {
+            Ok(rounds) if !rounds.is_empty() => info!("💾 Flushed {} buffered rounds", rounds.len()),
+            Ok(_) => {}
+            Err(e) => error!("❌ Failed to flush {} buffered rounds: {}", pending, e),
+        }
+        if let Err(e) = show_stats(storage, args.stats_format) {
+            error!("❌ Cannot read database statistics: {}", e);
+        }
+    }
+
+    let busy = registry.shutdown(&enabled);
+    if !busy.is_empty() {
+        warn!("⚠️  Skipped shutting down collectors still running: {}", busy.join(", "));
+    }
+
+    // Exit without waiting on collector threads that may be stuck past their timeout
+    std::process::exit(exit_code);
 }
 
-fn show_stats(storage: &SqliteStorage) -> Result<(), Box<dyn std::error::Error>> {
+/// Resolve on Ctrl+C or, on Unix, SIGTERM (as sent by launchd/systemd)
+async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Parse a `name=seconds` collector interval override
+fn parse_collector_interval(s: &str) -> Result<(String, f64), String> {
+    let (name, interval) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=seconds, got '{}'", s))?;
+    let interval: f64 = interval
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid interval '{}'", interval))?;
+    Ok((name.trim().to_string(), interval))
+}
+
+/// Run the enabled collectors concurrently on the blocking pool, waiting at most
+/// `timeout` for all of them. A collector that times out is reported as an error
+/// and its thread is left to finish in the background; the others' metrics are kept.
+/// Each collector that returns adds a `collector.duration_ms` metric timing its
+/// `collect()` call, whether it succeeded or failed; these come back separately
+/// from the collected metrics.
+async fn collect_round(registry: &CollectorRegistry, enabled: &[&str], timeout: Duration) -> (Vec<Metric>, Vec<Metric>, Vec<ThrudError>) {
+    let deadline = Instant::now() + timeout;
+    let tasks: Vec<_> = enabled
+        .iter()
+        .filter_map(|name| registry.get_shared(name).map(|collector| (*name, collector)))
+        .map(|(name, collector)| (name, tokio::task::spawn_blocking(move || timed(|| collector.collect()))))
+        .collect();
+
+    let mut all_metrics = Vec::new();
+    let mut durations = Vec::new();
+    let mut collection_errors = Vec::new();
+
+    for (name, handle) in tasks {
+        match time::timeout_at(deadline, handle).await {
+            Ok(Ok((result, elapsed))) => {
+                match result {
+                    Ok(mut metrics) => all_metrics.append(&mut metrics),
+                    Err(e) => collection_errors.push(ThrudError::collector_failed(name, e)),
+                }
+                durations.push(duration_metric(name, elapsed));
+            }
+            Ok(Err(e)) => collection_errors.push(ThrudError::collector_failed(name, format!("collector panicked: {}", e))),
+            Err(_) => collection_errors.push(ThrudError::collector_failed(name, format!("timed out after {}ms", timeout.as_millis()))),
+        }
+    }
+
+    (all_metrics, durations, collection_errors)
+}
+
+/// Append a round's timing metrics to the metrics its collectors returned
+fn merge_round(mut metrics: Vec<Metric>, mut durations: Vec<Metric>) -> Vec<Metric> {
+    metrics.append(&mut durations);
+    metrics
+}
+
+/// The metrics to store for a round, or `None` to store nothing. A round whose
+/// collectors returned nothing is stored as an empty heartbeat round when
+/// `heartbeat` is set, without its timing metrics, and skipped otherwise.
+fn round_to_store(collected_any: bool, metrics: Vec<Metric>, heartbeat: bool) -> Option<Vec<Metric>> {
+    if collected_any {
+        Some(metrics)
+    } else if heartbeat {
+        Some(Vec::new())
+    } else {
+        None
+    }
+}
+
+/// Runs OTLP exports on the blocking pool, one at a time. HTTP export can block
+/// on the network for up to its timeout, so it is kept off the collection loop;
+/// a round arriving while the previous export still runs is dropped rather than
+/// queueing another blocked export behind it.
+struct OtlpSender {
+    exporter: OtlpExporter,
+    in_flight: Arc<AtomicBool>,
+}
+
+impl OtlpSender {
+    fn new(exporter: OtlpExporter) -> Self {
+        Self { exporter, in_flight: Arc::new(AtomicBool::new(false)) }
+    }
+
+    /// Start exporting `metrics` unless an export is already running; returns
+    /// whether it started
+    fn try_send(&self, metrics: Vec<Metric>) -> bool {
+        if self.in_flight.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        let exporter = self.exporter.clone();
+        let in_flight = Arc::clone(&self.in_flight);
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = exporter.export(&metrics) {
+                error!("❌ OTLP export error: {}", e);
+            }
+            in_flight.store(false, Ordering::Release);
+        });
+        true
+    }
+}
+
+/// Run `f`, returning its result and how long it took
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let started = std::time::Instant::now();
+    let result = f();
+    (result, started.elapsed())
+}
+
+/// The `collector.duration_ms` metric for one collector's `collect()` call
+fn duration_metric(collector: &str, elapsed: Duration) -> Metric {
+    Metric::gauge(names::COLLECTOR_DURATION_MS, elapsed.as_secs_f64() * 1000.0)
+        .with_tag("collector", collector)
+        .with_unit("milliseconds")
+}
+
+/// Write one JSON object per metric, one per line
+fn write_jsonl<W: Write>(writer: &mut W, metrics: &[Metric]) -> Result<(), Box<dyn std::error::Error>> {
+    for metric in metrics {
+        serde_json::to_writer(&mut *writer, metric)?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Print a round's metrics in a human-readable form for dry runs
+fn print_round(metrics: &[Metric]) {
+    println!("--- {} metrics at {} ---", metrics.len(), chrono::Utc::now().format("%H:%M:%S"));
+    for metric in metrics {
+        let mut line = format!("  {} = {}", metric.name, metric.value);
+        if let Some(unit) = &metric.unit {
+            line.push(' ');
+            line.push_str(unit);
+        }
+        if !metric.metadata.is_empty() {
+            let mut metadata: Vec<_> = metric.metadata.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            metadata.sort();
+            line.push_str(&format!(" [{}]", metadata.join(", ")));
+        }
+        println!("{}", line);
+    }
+    println!();
+}
+
+fn prune(storage: &SqliteStorage, retention_hours: f64) {
+    let cutoff = chrono::Utc::now() - chrono::Duration::milliseconds((retention_hours * 3_600_000.0) as i64);
+    match storage.prune_older_than(cutoff) {
+        Ok(0) => {}
+        Ok(removed) => info!("🧹 Pruned {} metrics older than {}", removed, cutoff.format("%Y-%m-%d %H:%M:%S UTC")),
+        Err(e) => error!("❌ Pruning error: {}", e),
+    }
+}
+
+/// Delete the oldest rounds once the live data exceeds `max_mb`, pruning down
+/// to 90% of the cap so the next few rounds don't trigger another pass
+fn enforce_size_cap(storage: &SqliteStorage, max_mb: f64) {
+    let max_bytes = (max_mb * 1024.0 * 1024.0) as u64;
+    let result = storage.live_size_bytes().and_then(|size| {
+        if size > max_bytes { storage.prune_to_size(max_bytes / 10 * 9) } else { Ok(0) }
+    });
+    match result {
+        Ok(0) => {}
+        Ok(removed) => info!("🧹 Pruned {} oldest rounds to stay under {} MB", removed, max_mb),
+        Err(e) => error!("❌ Pruning error: {}", e),
+    }
+}
+
+/// `stats` as one JSON object, with `per_metric_counts` when counts are given
+fn stats_json(stats: &StorageStats, metric_counts: Option<&[(String, i64)]>) -> Result<serde_json::Value, serde_json::Error> {
+    let mut json = serde_json::to_value(stats)?;
+    if let Some(counts) = metric_counts {
+        json["per_metric_counts"] = serde_json::to_value(counts)?;
+    }
+    Ok(json)
+}
+
+/// Number of metric names listed in the debug per-metric breakdown
+const TOP_METRIC_COUNTS: usize = 10;
+
+fn show_stats(storage: &SqliteStorage, format: StatsFormat) -> Result<(), Box<dyn std::error::Error>> {
+    if !log::log_enabled!(log::Level::Info) {
+        return Ok(());
+    }
     let stats = storage.get_stats()?;
+    // Per-name sample counts help spot a collector emitting far more than expected
+    let metric_counts = if log::log_enabled!(log::Level::Debug) { Some(storage.get_metric_counts()?) } else { None };
+
+    if format == StatsFormat::Json {
+        info!("{}", stats_json(&stats, metric_counts.as_deref())?);
+        return Ok(());
+    }
     
-    println!("📊 Database Statistics:");
-    println!("  Total metrics: {}", stats.total_metrics);
-    println!("  Collection rounds: {}", stats.total_collection_rounds);
+    info!("📊 Database Statistics:");
+    info!("  Total metrics: {}", stats.total_metrics);
+    info!("  Collection rounds: {}", stats.total_collection_rounds);
     
     if let Some(size) = stats.database_size_bytes {
-        println!("  Database size: {:.2} KB", size as f64 / 1024.0);
+        info!("  Database size: {:.2} KB", size as f64 / 1024.0);
     }
     
     if let Some(latest) = &stats.latest_collection {
-        println!("  Latest collection: {} ({} metrics)", 
+        info!("  Latest collection: {} ({} metrics{})", 
             latest.timestamp.format("%Y-%m-%d %H:%M:%S UTC"), 
-            latest.metrics_count);
+            latest.metrics_count,
+            latest.hostname.as_ref().map(|host| format!(" from {}", host)).unwrap_or_default());
+    }
+
+    if let Some(counts) = &metric_counts {
+        debug!("  🔧 Samples per metric ({} names):", counts.len());
+        for (name, count) in counts.iter().take(TOP_METRIC_COUNTS) {
+            debug!("    {:>8}  {}", count, name);
+        }
+        if counts.len() > TOP_METRIC_COUNTS {
+            debug!("    ... and {} more", counts.len() - TOP_METRIC_COUNTS);
+        }
     }
     
-    println!();
+    info!("");
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Initialized storage at `database_path` (defaulting to $THRUD_DB or
+/// ~/.thrud/thrud.db), or `None` in stdout-only and dry-run modes, which never
+/// open the database
+fn open_storage(args: &Args, database_path: Option<String>, bar_ramp: &str) -> Result<Option<SqliteStorage>, Box<dyn std::error::Error>> {
+    if args.stdout_only || args.dry_run {
+        return Ok(None);
+    }
+
+    let storage = SqliteStorage::new(database_path)?
+        .with_write_batch(args.batch_size, Duration::from_millis(args.batch_max_ms))
+        .with_chart_compression(args.compress_charts)
+        .with_chart_suffix(!args.no_chart_suffix)
+        .with_bar_ramp(bar_ramp)
+        .with_parked_gap(args.parked_gap)
+        .with_round_dedup(args.skip_duplicate_rounds)
+        .with_heartbeat_rounds(args.heartbeat)
+        .with_day_partitions(args.partition_by_day)
+        .with_hostname(args.hostname.clone().or_else(local_hostname));
+    storage.initialize()?;
+    Ok(Some(storage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use thrud::collectors::{Collector, MetricValue};
+
+    /// Sleeps before returning a single `<name>.value` metric
+    struct SlowCollector {
+        name: &'static str,
+        delay: Duration,
+    }
+
+    impl SlowCollector {
+        fn boxed(name: &'static str, delay_ms: u64) -> Box<Self> {
+            Box::new(Self { name, delay: Duration::from_millis(delay_ms) })
+        }
+    }
+
+    impl Collector for SlowCollector {
+        fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+            std::thread::sleep(self.delay);
+            Ok(vec![Metric::gauge(format!("{}.value", self.name), 1.0)])
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    #[tokio::test]
+    async fn collect_round_times_each_collector() {
+        let mut registry = CollectorRegistry::empty();
+        registry.register(SlowCollector::boxed("slow", 50));
+
+        let (metrics, durations, errors) = collect_round(&registry, &["slow"], Duration::from_secs(5)).await;
+
+        assert!(errors.is_empty());
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(durations.len(), 1);
+        let duration = &durations[0];
+        assert_eq!(duration.name, names::COLLECTOR_DURATION_MS);
+        assert_eq!(duration.metadata.get("collector").map(String::as_str), Some("slow"));
+        match duration.value {
+            MetricValue::Float(ms) => assert!((50.0..5000.0).contains(&ms), "implausible duration {}ms", ms),
+            ref other => panic!("expected a float duration, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn timed_out_collector_does_not_drop_the_round() {
+        let mut registry = CollectorRegistry::empty();
+        registry.register(SlowCollector::boxed("fast", 0));
+        registry.register(SlowCollector::boxed("hung", 1000));
+
+        let started = Instant::now();
+        let (metrics, _, errors) = collect_round(&registry, &["fast", "hung"], Duration::from_millis(100)).await;
+
+        assert!(started.elapsed() < Duration::from_millis(500), "waited {:?}", started.elapsed());
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].name, "fast.value");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], ThrudError::CollectorFailed { name, message } if name == "hung" && message.contains("timed out")));
+    }
+
+    #[tokio::test]
+    async fn collectors_run_in_parallel() {
+        let mut registry = CollectorRegistry::empty();
+        for name in ["a", "b", "c"] {
+            registry.register(SlowCollector::boxed(name, 200));
+        }
+
+        let started = Instant::now();
+        let (metrics, _, errors) = collect_round(&registry, &["a", "b", "c"], Duration::from_millis(500)).await;
+
+        // Run one after another they would need 600ms and miss the deadline
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(metrics.len(), 3);
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn collect_round_reports_timeouts_without_a_duration() {
+        let mut registry = CollectorRegistry::empty();
+        registry.register(SlowCollector::boxed("slow", 500));
+
+        let (metrics, durations, errors) = collect_round(&registry, &["slow"], Duration::from_millis(20)).await;
+
+        assert!(metrics.is_empty());
+        assert!(durations.is_empty());
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn empty_round_is_stored_only_as_a_heartbeat() {
+        let durations = vec![duration_metric("cpu", Duration::from_millis(3))];
+
+        assert!(round_to_store(false, durations.clone(), false).is_none());
+        assert_eq!(round_to_store(false, durations, true).map(|round| round.len()), Some(0));
+    }
+
+    #[test]
+    fn round_with_metrics_is_stored_with_its_durations() {
+        let round = merge_round(vec![Metric::gauge("a", 1.0)], vec![duration_metric("cpu", Duration::from_millis(3))]);
+
+        assert_eq!(round_to_store(true, round.clone(), false).map(|round| round.len()), Some(2));
+        assert_eq!(round_to_store(true, round, true).map(|round| round.len()), Some(2));
+    }
+
+    #[tokio::test]
+    async fn otlp_sender_skips_rounds_while_an_export_is_in_flight() {
+        use std::io::Read;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let sender = OtlpSender::new(OtlpExporter::new(&endpoint).unwrap());
+        let round = || vec![Metric::gauge("cpu.utilization", 0.5)];
+
+        assert!(sender.try_send(round()));
+        // The endpoint hasn't answered yet, so the next round is dropped
+        let (mut stream, _) = listener.accept().unwrap();
+        assert!(!sender.try_send(round()));
+
+        let mut request = [0; 1024];
+        let _ = stream.read(&mut request).unwrap();
+        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        drop(stream);
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while sender.in_flight.load(Ordering::Acquire) {
+            assert!(std::time::Instant::now() < deadline, "export never finished");
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert!(sender.try_send(round()));
+        let _ = listener.accept().unwrap();
+    }
+
+    #[test]
+    fn merge_round_appends_durations() {
+        let merged = merge_round(vec![Metric::gauge("a", 1.0)], vec![duration_metric("cpu", Duration::from_millis(3))]);
+
+        let names: Vec<_> = merged.iter().map(|metric| metric.name.as_str()).collect();
+        assert_eq!(names, ["a", names::COLLECTOR_DURATION_MS]);
+    }
+
+    #[test]
+    fn jsonl_lines_parse_back_into_metrics() {
+        let metrics = vec![
+            Metric::gauge("cpu.utilization", 0.5).with_tag("core_id", "3"),
+            Metric::counter("gpu.0.busy_ticks", 42).with_unit("ticks"),
+            Metric::new("thermal.state".to_string(), MetricValue::String("nominal".to_string()), Default::default()),
+        ];
+        let mut out = Vec::new();
+        write_jsonl(&mut out, &metrics).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let parsed: Vec<Metric> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(parsed.len(), metrics.len());
+        for (parsed, metric) in parsed.iter().zip(&metrics) {
+            assert_eq!(parsed.name, metric.name);
+            assert_eq!(parsed.value, metric.value);
+            assert_eq!(parsed.metadata, metric.metadata);
+            assert_eq!(parsed.timestamp, metric.timestamp);
+            assert_eq!(parsed.unit, metric.unit);
+        }
+        // Timestamps are written as RFC3339
+        let line: serde_json::Value = serde_json::from_str(text.lines().next().unwrap()).unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(line["timestamp"].as_str().unwrap()).is_ok());
+    }
+
+    fn temp_db_path() -> String {
+        std::env::temp_dir()
+            .join(format!("thrud-collector-{}.db", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn dry_run_creates_no_database_file() {
+        let path = temp_db_path();
+        let args = Args::try_parse_from(["thrud-collector", "--dry-run", "--database", &path]).unwrap();
+
+        assert!(open_storage(&args, Some(path.clone()), DEFAULT_BAR_RAMP).unwrap().is_none());
+        assert!(!std::path::Path::new(&path).exists());
+    }
+
+    #[test]
+    fn storing_run_creates_the_database_file() {
+        let path = temp_db_path();
+        let args = Args::try_parse_from(["thrud-collector", "--database", &path]).unwrap();
+
+        let storage = open_storage(&args, Some(path.clone()), DEFAULT_BAR_RAMP).unwrap();
+        assert!(storage.is_some());
+        assert!(std::path::Path::new(&path).exists());
+
+        drop(storage);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn stats_json_deserializes_back() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let empty = stats_json(&storage.get_stats().unwrap(), None).unwrap();
+        // Nothing stored yet: optional fields are present as null
+        assert!(empty["latest_collection"].is_null());
+        assert!(empty.as_object().unwrap().contains_key("database_size_bytes"));
+        assert!(empty.get("per_metric_counts").is_none());
+
+        storage.store_metrics(vec![Metric::gauge("gpu_utilization", 0.5)]).unwrap();
+        let counts = storage.get_metric_counts().unwrap();
+        let json = stats_json(&storage.get_stats().unwrap(), Some(&counts)).unwrap();
+
+        let stats: StorageStats = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(stats.total_metrics, 1);
+        assert_eq!(stats.total_collection_rounds, 1);
+        assert_eq!(stats.latest_collection.map(|round| round.metrics_count), Some(1));
+        assert_eq!(json["per_metric_counts"], serde_json::json!([["gpu_utilization", 1]]));
+    }
+
+    #[test]
+    fn size_cap_prunes_below_the_cap_only_once_exceeded() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        for round in 0..100 {
+            let metrics = (0..20).map(|core| Metric::gauge(format!("cpu.core.{}.utilization", core), round as f64)).collect();
+            storage.store_metrics(metrics).unwrap();
+        }
+        let full = storage.live_size_bytes().unwrap();
+        let rounds = || storage.get_stats().unwrap().total_collection_rounds;
+
+        enforce_size_cap(&storage, full as f64 * 2.0 / 1024.0 / 1024.0);
+        assert_eq!(rounds(), 100);
+
+        let cap_mb = full as f64 * 0.75 / 1024.0 / 1024.0;
+        enforce_size_cap(&storage, cap_mb);
+        // Pruning goes down to 90% of the cap, leaving room for the next rounds
+        assert!(rounds() < 100);
+        assert!(storage.live_size_bytes().unwrap() as f64 <= cap_mb * 1024.0 * 1024.0 * 0.9);
+    }
+}
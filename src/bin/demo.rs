@@ -1,15 +1,28 @@
+use clap::Parser;
 use std::time::Duration;
 use tokio::time;
-use thrud::collectors::{GPUCollector, CPUCollector, Collector};
+use thrud::collectors::{GPUCollector, CPUCollector, SensorsCollector, Collector, MetricValue};
+use thrud::units::TemperatureUnit;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Print live GPU, CPU and temperature metrics every 2 seconds")]
+struct Args {
+    /// Unit temperatures are displayed in: celsius or fahrenheit
+    #[arg(long, default_value_t = TemperatureUnit::Celsius)]
+    units: TemperatureUnit,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
     println!("Thrud System Metrics Demo");
     println!("========================");
     println!("Press Ctrl+C to stop\n");
 
     let gpu_collector = GPUCollector::new();
     let cpu_collector = CPUCollector::new();
+    let sensors_collector = SensorsCollector::new();
     let mut interval = time::interval(Duration::from_secs(2));
 
     loop {
@@ -21,7 +34,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         match gpu_collector.collect() {
             Ok(metrics) => {
                 if !metrics.is_empty() {
-                    display_gpu_metrics(metrics);
+                    display_gpu_metrics(metrics, args.units);
                 }
             }
             Err(e) => {
@@ -40,12 +53,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 eprintln!("❌ Error collecting CPU metrics: {}", e);
             }
         }
+
+        // Collect and display temperature sensors
+        match sensors_collector.collect() {
+            Ok(metrics) => display_temperatures(metrics, args.units),
+            Err(e) => {
+                eprintln!("❌ Error collecting sensor metrics: {}", e);
+            }
+        }
         
         println!();
     }
 }
 
-fn display_gpu_metrics(metrics: Vec<thrud::collectors::Metric>) {
+fn display_gpu_metrics(metrics: Vec<thrud::collectors::Metric>, units: TemperatureUnit) {
     println!("\n📊 GPU Metrics");
     
     for metric in metrics {
@@ -54,7 +75,11 @@ fn display_gpu_metrics(metrics: Vec<thrud::collectors::Metric>) {
             let parts: Vec<&str> = metric.name.split('.').collect();
             if parts.len() == 3 {
                 let gpu_index = parts[1];
-                let utilization: f64 = metric.value.parse().unwrap_or(0.0);
+                let utilization = match metric.value {
+                    MetricValue::Float(v) => v,
+                    MetricValue::Integer(v) => v as f64,
+                    _ => 0.0,
+                };
                 
                 let percentage = (utilization * 100.0) as i32;
                 let bar_length = 20;
@@ -62,12 +87,46 @@ fn display_gpu_metrics(metrics: Vec<thrud::collectors::Metric>) {
                 let bar = "█".repeat(filled) + &"░".repeat(bar_length - filled);
                 println!("  🔥 GPU {}: {:3}% [{}]", gpu_index, percentage, bar);
             }
+        } else if let Some(celsius) = celsius_value(&metric) {
+            println!("  🌡️  {}: {}", metric.name, units.format(celsius));
         } else {
             println!("  📈 {}: {}", metric.name, metric.value);
         }
     }
 }
 
+fn display_temperatures(metrics: Vec<thrud::collectors::Metric>, units: TemperatureUnit) {
+    let mut temperatures: Vec<(String, f64)> = metrics
+        .iter()
+        .filter_map(|metric| {
+            let celsius = celsius_value(metric)?;
+            let sensor = metric.metadata.get("sensor_key").cloned().unwrap_or_else(|| metric.name.clone());
+            Some((sensor, celsius))
+        })
+        .collect();
+    if temperatures.is_empty() {
+        return;
+    }
+
+    println!("\n🌡️  Temperatures");
+    temperatures.sort_by(|a, b| a.0.cmp(&b.0));
+    for (sensor, celsius) in temperatures {
+        println!("  {}: {}", sensor, units.format(celsius));
+    }
+}
+
+/// Value of a temperature metric in degrees Celsius, as all collectors report them
+fn celsius_value(metric: &thrud::collectors::Metric) -> Option<f64> {
+    if metric.unit.as_deref() != Some("celsius") && !metric.name.ends_with(".temperature") {
+        return None;
+    }
+    match metric.value {
+        MetricValue::Float(v) => Some(v),
+        MetricValue::Integer(v) => Some(v as f64),
+        _ => None,
+    }
+}
+
 fn display_cpu_metrics(metrics: Vec<thrud::collectors::Metric>) {
     println!("\n🖥️  CPU Metrics (Tick Counts)");
     
@@ -118,10 +177,8 @@ fn display_cpu_metrics(metrics: Vec<thrud::collectors::Metric>) {
                 let core_id = parts[2];
                 let tick_type = parts[3].replace("_ticks", "");
                 
-                if !shown_cores.contains(core_id) && shown_cores.len() < 4 {
-                    if tick_type == "idle" {
-                        shown_cores.insert(core_id.to_string());
-                    }
+                if !shown_cores.contains(core_id) && shown_cores.len() < 4 && tick_type == "idle" {
+                    shown_cores.insert(core_id.to_string());
                 }
                 
                 if shown_cores.contains(core_id) && shown_cores.len() <= 4 {
@@ -0,0 +1,202 @@
+use chrono::{DateTime, Utc};
+use clap::{Parser, ValueEnum};
+use std::io::{BufWriter, Write};
+use std::time::Duration;
+use thrud::storage::SqliteStorage;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// One metric's samples as a JSON object of columns (timestamp, value and each
+    /// metadata key), ready for a dataframe; requires --metric
+    Columns,
+    /// One metric's samples, with the same columns, as an Arrow IPC stream
+    /// (e.g. `pl.read_ipc_stream`); requires --metric
+    #[cfg(feature = "arrow")]
+    Arrow,
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Export stored metrics from Thrud database", long_about = None)]
+struct Args {
+    /// Database path (default: $THRUD_DB, $XDG_DATA_HOME/thrud/thrud.db or ~/.thrud/thrud.db)
+    #[arg(long)]
+    database: Option<String>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "csv")]
+    format: ExportFormat,
+
+    /// Only export metrics at or after this time (RFC3339, e.g. 2024-01-01T00:00:00Z)
+    #[arg(short, long)]
+    since: Option<String>,
+
+    /// Only export metrics at or before this time (RFC3339; columns and arrow formats only)
+    #[arg(short, long)]
+    until: Option<String>,
+
+    /// Metric to export (columns and arrow formats)
+    #[arg(short, long)]
+    metric: Option<String>,
+
+    /// Keep running and stream every collection round as it is stored, one JSON
+    /// metric per line (as written by thrud-collector --output jsonl), after
+    /// exporting the rounds already stored
+    #[arg(long, conflicts_with_all = ["format", "until", "metric"])]
+    follow: bool,
+
+    /// Seconds between checks for new collection rounds when following
+    #[arg(long, default_value = "1.0", requires = "follow")]
+    poll_interval: f64,
+}
+
+/// Collection rounds read per query when following
+const FOLLOW_BATCH_ROUNDS: usize = 500;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let since = parse_time("--since", args.since.as_deref());
+    let until = parse_time("--until", args.until.as_deref());
+
+    let storage = match SqliteStorage::open_read_only(args.database.clone()) {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let stdout = std::io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+
+    if args.follow {
+        if args.poll_interval <= 0.0 {
+            eprintln!("Error: Poll interval must be positive");
+            std::process::exit(1);
+        }
+        return follow(&storage, &mut writer, since, Duration::from_secs_f64(args.poll_interval));
+    }
+
+    let count = match args.format {
+        ExportFormat::Csv => storage.export_csv(writer, since)?,
+        ExportFormat::Columns => {
+            let metric = required_metric(&args, "columns");
+            let batch = storage.export_columns(metric, since, until)?;
+            serde_json::to_writer(&mut writer, &batch)?;
+            writeln!(writer)?;
+            writer.flush()?;
+            batch.len()
+        }
+        #[cfg(feature = "arrow")]
+        ExportFormat::Arrow => {
+            let metric = required_metric(&args, "arrow");
+            let batch = storage.export_arrow(metric, since, until)?;
+            let mut stream = arrow::ipc::writer::StreamWriter::try_new(&mut writer, &batch.schema())?;
+            stream.write(&batch)?;
+            stream.finish()?;
+            drop(stream);
+            writer.flush()?;
+            batch.num_rows()
+        }
+    };
+
+    eprintln!("Exported {} metrics", count);
+    Ok(())
+}
+
+/// Write the metrics of every stored collection round as JSON lines, then poll for
+/// new rounds and write those as they arrive. Runs until interrupted or stdout is
+/// closed.
+fn follow<W: Write>(storage: &SqliteStorage, writer: &mut W, since: Option<DateTime<Utc>>, poll_interval: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cursor = 0;
+    loop {
+        let next = follow_step(storage, writer, since, cursor)?;
+
+        // Only wait when caught up; after new rounds more may be waiting already
+        if next == cursor {
+            std::thread::sleep(poll_interval);
+        }
+        cursor = next;
+    }
+}
+
+/// Write the metrics of up to [`FOLLOW_BATCH_ROUNDS`] rounds stored after
+/// `cursor` as JSON lines, returning the cursor to continue from
+fn follow_step<W: Write>(storage: &SqliteStorage, writer: &mut W, since: Option<DateTime<Utc>>, cursor: i64) -> Result<i64, Box<dyn std::error::Error>> {
+    let (metrics, next) = storage.metrics_after_round(cursor, FOLLOW_BATCH_ROUNDS)?;
+    for metric in metrics.iter().filter(|metric| since.is_none_or(|since| metric.timestamp >= since)) {
+        serde_json::to_writer(&mut *writer, metric)?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(next)
+}
+
+/// The --metric a single-metric format needs, exiting with an error if it is missing
+fn required_metric<'a>(args: &'a Args, format: &str) -> &'a str {
+    match &args.metric {
+        Some(metric) => metric,
+        None => {
+            eprintln!("Error: --format {} requires --metric", format);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parse an RFC3339 time flag, exiting with an error if it is malformed
+fn parse_time(flag: &str, value: Option<&str>) -> Option<DateTime<Utc>> {
+    value.map(|s| match DateTime::parse_from_rfc3339(s) {
+        Ok(t) => t.with_timezone(&Utc),
+        Err(e) => {
+            eprintln!("Error: Invalid {} '{}': {}", flag, s, e);
+            std::process::exit(1);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn database_flag_selects_the_file() {
+        let args = Args::try_parse_from(["thrud-export", "--database", "other.db", "--since", "2024-01-01T00:00:00Z"]).unwrap();
+        assert_eq!(args.database.as_deref(), Some("other.db"));
+
+        let args = Args::try_parse_from(["thrud-export", "--database", "other.db", "--follow"]).unwrap();
+        assert_eq!(args.database.as_deref(), Some("other.db"));
+        assert!(args.follow);
+    }
+
+    #[test]
+    fn follow_emits_only_rounds_stored_since_the_last_step() {
+        use thrud::collectors::Metric;
+        use thrud::storage::Storage;
+
+        let path = std::env::temp_dir().join(format!("thrud-export-{}.db", uuid::Uuid::new_v4())).to_string_lossy().into_owned();
+        let writer_storage = SqliteStorage::new(Some(path.clone())).unwrap();
+        writer_storage.initialize().unwrap();
+        writer_storage.store_metrics(vec![Metric::gauge("cpu.utilization", 0.25)]).unwrap();
+
+        let storage = SqliteStorage::open_read_only(Some(path.clone())).unwrap();
+        let mut output = Vec::new();
+        let cursor = follow_step(&storage, &mut output, None, 0).unwrap();
+        assert_eq!(String::from_utf8_lossy(&output).lines().count(), 1);
+
+        // Caught up: nothing new to write and the cursor stays put
+        output.clear();
+        assert_eq!(follow_step(&storage, &mut output, None, cursor).unwrap(), cursor);
+        assert!(output.is_empty());
+
+        writer_storage.store_metrics(vec![Metric::gauge("cpu.utilization", 0.75)]).unwrap();
+        let next = follow_step(&storage, &mut output, None, cursor).unwrap();
+        assert!(next > cursor);
+        let lines: Vec<serde_json::Value> = String::from_utf8_lossy(&output).lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["value"], serde_json::json!({"Float": 0.75}));
+
+        drop((storage, writer_storage));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,334 @@
+use clap::Parser;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Take, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thrud::export::grafana::{self, QueryRequest, SearchRequest};
+use thrud::storage::SqliteStorage;
+
+/// Requests with a larger body are rejected
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// Requests whose request line and headers together are larger are rejected
+const MAX_HEADER_BYTES: u64 = 16 * 1024;
+/// Requests with more headers are rejected
+const MAX_HEADERS: usize = 100;
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Accepted connections waiting for a worker, per worker
+const QUEUED_PER_WORKER: usize = 4;
+/// How long a 503 for a connection turned away may take to write
+const BUSY_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Serve stored metrics to Grafana as a SimpleJSON datasource",
+    long_about = "Serve stored metrics to Grafana as a SimpleJSON datasource.\n\n\
+                  Add a SimpleJSON (or JSON) datasource pointing at http://<listen address>. \
+                  `/search` lists metric names and `/query` returns their samples over the \
+                  panel's time range."
+)]
+struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:3030")]
+    listen: String,
+
+    /// Database path (default: $THRUD_DB, $XDG_DATA_HOME/thrud/thrud.db or ~/.thrud/thrud.db)
+    #[arg(long)]
+    database: Option<String>,
+
+    /// Number of threads serving requests
+    #[arg(long, default_value = "4")]
+    workers: usize,
+}
+
+/// Status line and JSON body of a response
+type Response = (&'static str, String);
+
+/// Method, path without the query string, and body of a request
+type Request = (String, String, Vec<u8>);
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if args.workers == 0 {
+        eprintln!("Error: Workers must be at least 1");
+        std::process::exit(1);
+    }
+
+    let storage = match SqliteStorage::open_read_only(args.database.clone()) {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let listener = match TcpListener::bind(&args.listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Error: Cannot listen on {}: {}", args.listen, e);
+            std::process::exit(1);
+        }
+    };
+    println!("📡 Serving {} to Grafana on http://{}", storage.db_path(), listener.local_addr()?);
+
+    serve(listener, Arc::new(storage), args.workers);
+    Ok(())
+}
+
+/// Accept connections, handing them to `workers` threads. Connections that
+/// arrive while every worker is busy and the queue is full get a 503 rather
+/// than a thread of their own.
+fn serve(listener: TcpListener, storage: Arc<SqliteStorage>, workers: usize) {
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(workers * QUEUED_PER_WORKER);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..workers {
+        let receiver = Arc::clone(&receiver);
+        let storage = Arc::clone(&storage);
+        std::thread::spawn(move || loop {
+            // Hold the lock only while waiting, not while serving
+            let stream = match receiver.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+                Ok(stream) => stream,
+                Err(_) => return,
+            };
+            if let Err(e) = handle_connection(stream, &storage) {
+                eprintln!("❌ Request error: {}", e);
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        if let Err(TrySendError::Full(stream)) = sender.try_send(stream) {
+            let _ = stream.set_write_timeout(Some(BUSY_TIMEOUT));
+            let _ = write_response(&stream, ("503 Service Unavailable", error_body("Server busy, try again")));
+        }
+    }
+}
+
+/// Answer a single HTTP/1.1 request and close the connection
+fn handle_connection(stream: TcpStream, storage: &SqliteStorage) -> Result<(), Box<dyn std::error::Error>> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match read_request(&mut reader) {
+        Ok((method, path, body)) => route(&method, &path, &body, storage),
+        Err(response) => response,
+    };
+    write_response(&stream, response)?;
+    Ok(())
+}
+
+/// Read a request, or the error response to send when it is malformed, too
+/// large or doesn't arrive in time
+fn read_request(reader: &mut impl BufRead) -> Result<Request, Response> {
+    // The request line and headers share one size budget, so a client can't make
+    // the server buffer an endless line or endless headers
+    let mut head = reader.by_ref().take(MAX_HEADER_BYTES);
+    let request_line = read_head_line(&mut head)?;
+    let (method, path) = parse_request_line(&request_line)
+        .ok_or_else(|| ("400 Bad Request", error_body("Malformed request line")))?;
+
+    let mut content_length = 0;
+    let mut headers = 0;
+    loop {
+        let header = read_head_line(&mut head)?;
+        if header.trim().is_empty() {
+            break;
+        }
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return Err(headers_too_large());
+        }
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| ("400 Bad Request", error_body("Malformed header")))?;
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .trim()
+                .parse()
+                .map_err(|_| ("400 Bad Request", error_body("Invalid Content-Length")))?;
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err(("413 Payload Too Large", error_body("Request body too large")));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(read_error)?;
+    let path = path.split('?').next().unwrap_or_default();
+    Ok((method.to_string(), path.to_string(), body))
+}
+
+/// Read one line of the request head; empty at the end of the stream. A line
+/// cut short by the head's size budget is an error.
+fn read_head_line<R: BufRead>(head: &mut Take<R>) -> Result<String, Response> {
+    let mut line = String::new();
+    head.read_line(&mut line).map_err(read_error)?;
+    if !line.ends_with('\n') && head.limit() == 0 {
+        return Err(headers_too_large());
+    }
+    Ok(line)
+}
+
+fn headers_too_large() -> Response {
+    ("431 Request Header Fields Too Large", error_body("Request headers too large"))
+}
+
+/// Method and target of an HTTP/1.x request line such as `POST /query HTTP/1.1`
+fn parse_request_line(line: &str) -> Option<(&str, &str)> {
+    let mut parts = line.trim_end_matches(['\r', '\n']).split(' ');
+    let (method, path, version) = (parts.next()?, parts.next()?, parts.next()?);
+    let valid = parts.next().is_none()
+        && !method.is_empty()
+        && method.bytes().all(|b| b.is_ascii_uppercase())
+        && path.starts_with('/')
+        && version.starts_with("HTTP/1.");
+    valid.then_some((method, path))
+}
+
+fn read_error(e: std::io::Error) -> Response {
+    match e.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => ("408 Request Timeout", error_body("Request timed out")),
+        _ => ("400 Bad Request", error_body(&format!("Cannot read request: {}", e))),
+    }
+}
+
+fn write_response(mut stream: &TcpStream, (status, body): Response) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: accept, content-type\r\n\
+         Access-Control-Allow-Methods: GET, POST, OPTIONS\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn route(method: &str, path: &str, body: &[u8], storage: &SqliteStorage) -> Response {
+    let result = match (method, path) {
+        // Grafana checks the datasource by requesting the root path
+        (_, "/") | ("OPTIONS", _) => Ok("{}".to_string()),
+        ("POST", "/search") => parse_body::<SearchRequest>(body)
+            .and_then(|request| grafana::search(storage, &request))
+            .and_then(|names| Ok(serde_json::to_string(&names)?)),
+        ("POST", "/query") => parse_body::<QueryRequest>(body)
+            .and_then(|request| grafana::query(storage, &request))
+            .and_then(|series| Ok(serde_json::to_string(&series)?)),
+        // No annotations are stored
+        ("POST", "/annotations") => Ok("[]".to_string()),
+        _ => return ("404 Not Found", error_body(&format!("No route for {} {}", method, path))),
+    };
+
+    match result {
+        Ok(body) => ("200 OK", body),
+        Err(e) => ("400 Bad Request", error_body(&e.to_string())),
+    }
+}
+
+/// Parse a JSON request body; an empty body reads as `{}`
+fn parse_body<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, Box<dyn std::error::Error>> {
+    let body = if body.trim_ascii().is_empty() { b"{}".as_slice() } else { body };
+    Ok(serde_json::from_slice(body)?)
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    fn status(raw: &str) -> &'static str {
+        match read_request(&mut Cursor::new(raw.as_bytes())) {
+            Ok(_) => "ok",
+            Err((status, _)) => status,
+        }
+    }
+
+    #[test]
+    fn parses_request_lines() {
+        assert_eq!(parse_request_line("POST /query HTTP/1.1\r\n"), Some(("POST", "/query")));
+        assert_eq!(parse_request_line("GET / HTTP/1.0\n"), Some(("GET", "/")));
+        assert_eq!(parse_request_line(""), None);
+        assert_eq!(parse_request_line("GARBAGE\r\n"), None);
+        assert_eq!(parse_request_line("GET /\r\n"), None);
+        assert_eq!(parse_request_line("GET query HTTP/1.1\r\n"), None);
+        assert_eq!(parse_request_line("get / HTTP/1.1\r\n"), None);
+        assert_eq!(parse_request_line("GET / SPDY/3\r\n"), None);
+        assert_eq!(parse_request_line("GET / HTTP/1.1 extra\r\n"), None);
+    }
+
+    #[test]
+    fn reads_a_request_with_a_body() {
+        let raw = "POST /search?x=1 HTTP/1.1\r\nContent-Length: 13\r\n\r\n{\"target\":\"\"}";
+        let (method, path, body) = read_request(&mut Cursor::new(raw.as_bytes())).unwrap();
+
+        assert_eq!(method, "POST");
+        assert_eq!(path, "/search");
+        assert_eq!(body, b"{\"target\":\"\"}");
+    }
+
+    #[test]
+    fn rejects_malformed_requests() {
+        assert_eq!(status("GARBAGE\r\n\r\n"), "400 Bad Request");
+        assert_eq!(status("POST /query HTTP/1.1\r\nContent-Length: lots\r\n\r\n"), "400 Bad Request");
+        assert_eq!(status("POST /query HTTP/1.1\r\nno colon here\r\n\r\n"), "400 Bad Request");
+        assert_eq!(status("POST /query HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort"), "400 Bad Request");
+        assert_eq!(status("POST /query HTTP/1.1\r\nContent-Length: 99999999\r\n\r\n"), "413 Payload Too Large");
+    }
+
+    #[test]
+    fn rejects_oversized_request_heads() {
+        let too_large = "431 Request Header Fields Too Large";
+        let long_line = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEADER_BYTES as usize));
+        assert_eq!(status(&long_line), too_large);
+
+        let long_header = format!("GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(MAX_HEADER_BYTES as usize));
+        assert_eq!(status(&long_header), too_large);
+
+        let many_headers = format!("GET / HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(MAX_HEADERS + 1));
+        assert_eq!(status(&many_headers), too_large);
+        let enough_headers = format!("GET / HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(MAX_HEADERS));
+        assert_eq!(status(&enough_headers), "ok");
+    }
+
+    #[test]
+    fn endless_header_is_cut_off() {
+        // Never ends its line: without a limit this would read forever
+        let endless = Cursor::new(b"GET / HTTP/1.1\r\nX: ".to_vec()).chain(std::io::repeat(b'a'));
+
+        let result = read_request(&mut BufReader::new(endless));
+        assert!(matches!(result, Err(("431 Request Header Fields Too Large", _))));
+    }
+
+    #[test]
+    fn server_answers_bad_request_lines_with_400() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let storage = Arc::new(SqliteStorage::new_in_memory().unwrap());
+        std::thread::spawn(move || serve(listener, storage, 2));
+
+        let request = |raw: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(raw.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        assert!(request("GARBAGE\r\n\r\n").starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(request("GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 200 OK"));
+        assert!(request("GET /nowhere HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 404 Not Found"));
+        let search = request("POST /search HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}");
+        assert!(search.starts_with("HTTP/1.1 200 OK"), "{}", search);
+        assert!(search.ends_with("[]"), "{}", search);
+    }
+}
@@ -0,0 +1,113 @@
+use clap::Parser;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use thrud::collectors::Metric;
+use thrud::storage::{ImportSummary, SqliteStorage, Storage};
+use thrud::ThrudError;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Import metrics from JSON lines (as written by thrud-collector --output jsonl) into the Thrud database"
+)]
+struct Args {
+    /// File to read, one metric per line (default: stdin)
+    input: Option<String>,
+
+    /// Database path (default: $THRUD_DB, $XDG_DATA_HOME/thrud/thrud.db or ~/.thrud/thrud.db)
+    #[arg(long)]
+    database: Option<String>,
+}
+
+/// Import `reader`'s metrics, one JSON object per line, into `storage`. Returns
+/// what was imported, the number of malformed lines skipped and the error that
+/// ended reading early, if any; what was read before it is still stored.
+fn import(storage: &SqliteStorage, reader: impl BufRead) -> Result<(ImportSummary, usize, Option<std::io::Error>), ThrudError> {
+    let mut skipped = 0usize;
+    let mut read_error = None;
+    let metrics = reader
+        .split(b'\n')
+        .enumerate()
+        .map_while(|(index, line)| match line {
+            Ok(line) => Some((index + 1, line)),
+            Err(e) => {
+                read_error = Some(e);
+                None
+            }
+        })
+        .filter(|(_, line)| !line.trim_ascii().is_empty())
+        .filter_map(|(number, line)| match serde_json::from_slice::<Metric>(&line) {
+            Ok(metric) => Some(metric),
+            Err(e) => {
+                eprintln!("Warning: Skipping line {}: {}", number, e);
+                skipped += 1;
+                None
+            }
+        });
+    let summary = storage.import_metrics(metrics)?;
+    Ok((summary, skipped, read_error))
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let reader: Box<dyn BufRead> = match &args.input {
+        Some(path) if path != "-" => match File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => {
+                eprintln!("Error: Cannot open {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        _ => Box::new(BufReader::new(std::io::stdin())),
+    };
+
+    let storage = SqliteStorage::new(args.database.clone())?;
+    storage.initialize()?;
+
+    let (summary, skipped, read_error) = import(&storage, reader)?;
+
+    println!(
+        "Imported {} metrics in {} collection rounds into {}",
+        summary.metrics,
+        summary.rounds,
+        storage.db_path()
+    );
+    if skipped > 0 {
+        println!("Skipped {} malformed lines", skipped);
+    }
+    if let Some(e) = read_error {
+        eprintln!("Error: Stopped reading input: {}", e);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NDJSON: &str = r#"{"name":"cpu.utilization","value":{"Float":0.5},"metadata":{},"timestamp":"2024-01-01T12:00:00Z"}
+{"name":"gpu.0.utilization","value":{"Float":0.25},"metadata":{"gpu_index":"0"},"timestamp":"2024-01-01T12:00:00.010Z"}
+not json
+
+{"name":"cpu.utilization","value":{"Float":0.75},"metadata":{},"timestamp":"2024-01-01T12:00:05Z"}
+{"name":"cpu.utilization","value":{"Float":0.25}}
+"#;
+
+    #[test]
+    fn import_counts_stored_metrics_and_skipped_lines() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+
+        let (summary, skipped, read_error) = import(&storage, NDJSON.as_bytes()).unwrap();
+
+        assert_eq!((summary.metrics, summary.rounds), (3, 2));
+        // The blank line is ignored, the garbage and the metric missing fields skipped
+        assert_eq!(skipped, 2);
+        assert!(read_error.is_none());
+        let stats = storage.get_stats().unwrap();
+        assert_eq!(stats.total_metrics, 3);
+        assert_eq!(stats.total_collection_rounds, 2);
+    }
+}
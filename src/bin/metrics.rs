@@ -0,0 +1,140 @@
+use clap::Parser;
+use std::fmt::Write;
+use thrud::storage::SqliteStorage;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "List the metric names stored in the Thrud database with their latest value and when they were last seen"
+)]
+struct Args {
+    /// Only list metric names containing this text, e.g. "gpu"
+    filter: Option<String>,
+
+    /// Database path (default: $THRUD_DB, $XDG_DATA_HOME/thrud/thrud.db or ~/.thrud/thrud.db)
+    #[arg(long)]
+    database: Option<String>,
+
+    /// Print the metrics as a JSON array
+    #[arg(long)]
+    json: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let storage = match SqliteStorage::open_read_only(args.database) {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    print!("{}", list(&storage, args.filter.as_deref(), args.json)?);
+    Ok(())
+}
+
+/// The stored metric names containing `filter`, sorted, each with its latest
+/// value and when it was last seen, as a table or a JSON array
+fn list(storage: &SqliteStorage, filter: Option<&str>, json: bool) -> Result<String, Box<dyn std::error::Error>> {
+    let mut names = storage.metric_names()?;
+    if let Some(filter) = filter {
+        names.retain(|name| name.contains(filter));
+    }
+    let name_refs: Vec<&str> = names.iter().map(String::as_str).collect();
+    let latest = storage.get_latest_values(&name_refs)?;
+
+    let mut output = String::new();
+    if json {
+        let metrics: Vec<serde_json::Value> = names
+            .iter()
+            .filter_map(|name| latest.get(name))
+            .map(|metric| {
+                serde_json::json!({
+                    "name": metric.name,
+                    "value": metric.value,
+                    "unit": metric.unit,
+                    "last_seen": metric.timestamp,
+                })
+            })
+            .collect();
+        writeln!(output, "{}", serde_json::to_string_pretty(&metrics)?)?;
+        return Ok(output);
+    }
+
+    if names.is_empty() {
+        writeln!(output, "No metrics found. Make sure the collector is running and has stored data.")?;
+        return Ok(output);
+    }
+
+    let width = names.iter().map(String::len).max().unwrap_or(0);
+    for name in &names {
+        let Some(metric) = latest.get(name) else {
+            continue;
+        };
+        let value = match &metric.unit {
+            Some(unit) => format!("{} {}", metric.value, unit),
+            None => metric.value.to_string(),
+        };
+        writeln!(
+            output,
+            "{:<width$}  {:<24}  {}",
+            name,
+            value,
+            metric.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+            width = width
+        )?;
+    }
+    writeln!(output, "\n{} metrics", names.len())?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use thrud::collectors::Metric;
+    use thrud::storage::Storage;
+
+    /// Two rounds of GPU utilization and one of memory and CPU
+    fn storage() -> SqliteStorage {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        storage
+            .store_metrics(vec![
+                Metric::gauge("gpu.0.utilization", 0.25).with_unit("ratio"),
+                Metric::counter("memory.used_bytes", 1024),
+                Metric::gauge("cpu.utilization", 0.5),
+            ])
+            .unwrap();
+        storage.store_metrics(vec![Metric::gauge("gpu.0.utilization", 0.75).with_unit("ratio")]).unwrap();
+        storage
+    }
+
+    #[test]
+    fn lists_each_name_once_in_order_with_its_latest_value() {
+        let output = list(&storage(), None, false).unwrap();
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 5, "{}", output);
+        assert!(lines[0].starts_with("cpu.utilization ") && lines[0].contains(" 0.5 "), "{}", lines[0]);
+        assert!(lines[1].starts_with("gpu.0.utilization ") && lines[1].contains(" 0.75 ratio "), "{}", lines[1]);
+        assert!(lines[2].starts_with("memory.used_bytes ") && lines[2].contains(" 1024 "), "{}", lines[2]);
+        assert_eq!(lines[4], "3 metrics");
+    }
+
+    #[test]
+    fn filter_and_json_keep_to_matching_names() {
+        let output = list(&storage(), Some("utilization"), true).unwrap();
+
+        let metrics: Vec<serde_json::Value> = serde_json::from_str(&output).unwrap();
+        let names: Vec<&str> = metrics.iter().map(|metric| metric["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["cpu.utilization", "gpu.0.utilization"]);
+        assert_eq!(metrics[1]["value"], serde_json::json!({ "Float": 0.75 }));
+    }
+
+    #[test]
+    fn empty_database_says_so() {
+        let output = list(&SqliteStorage::new_in_memory().unwrap(), None, false).unwrap();
+        assert!(output.starts_with("No metrics found."), "{}", output);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::collectors::{parse_bridge_json, Collector, Metric, MetricKind, MetricValue};
+use crate::ThrudError;
+use std::collections::HashMap;
+#[cfg(target_os = "macos")]
+use std::ffi::CStr;
+#[cfg(target_os = "macos")]
+use std::os::raw::c_char;
+
+#[cfg(target_os = "macos")]
+extern "C" {
+    fn collect_ane_metrics_json() -> *const c_char;
+    fn free_ane_string(ptr: *const c_char);
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ANEEnergy {
+    group: String,
+    channel: String,
+    energy_mj: i64,
+    /// Missing on the first round, which has no previous sample to measure from
+    #[serde(default)]
+    power_watts: Option<f64>,
+}
+
+pub struct AppleSiliconANECollector;
+
+impl Default for AppleSiliconANECollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppleSiliconANECollector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    #[cfg(target_os = "macos")]
+    fn collect_macos(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let json_ptr = unsafe { collect_ane_metrics_json() };
+
+        if json_ptr.is_null() {
+            return Ok(vec![]);
+        }
+
+        let json_str = unsafe {
+            CStr::from_ptr(json_ptr).to_string_lossy().into_owned()
+        };
+
+        unsafe {
+            free_ane_string(json_ptr);
+        }
+
+        self.parse_metrics(&json_str)
+    }
+
+    /// Convert the bridge JSON payload into power and cumulative energy metrics
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn parse_metrics(&self, json_str: &str) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let readings: Vec<ANEEnergy> = parse_bridge_json("ANE", json_str)?;
+
+        let mut metrics = Vec::new();
+
+        for reading in readings {
+            let mut metadata = HashMap::new();
+            metadata.insert("sampler".to_string(), format!("{}/{}", reading.group, reading.channel));
+
+            if let Some(power_watts) = reading.power_watts {
+                metrics.push(Metric::new(
+                    "ane.power_watts".to_string(),
+                    MetricValue::Float(power_watts),
+                    metadata.clone(),
+                ).with_unit("watts"));
+            }
+            metrics.push(Metric::new(
+                "ane.energy_mj".to_string(),
+                MetricValue::Integer(reading.energy_mj),
+                metadata,
+            ).with_unit("millijoules").with_kind(MetricKind::Counter));
+        }
+
+        Ok(metrics)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn collect_other(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        Ok(vec![])
+    }
+}
+
+impl Collector for AppleSiliconANECollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "macos")]
+        {
+            Ok(self.collect_macos()?)
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Ok(self.collect_other()?)
+        }
+    }
+
+    fn name(&self) -> &str {
+        "apple_silicon_ane"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_power_and_energy_per_sampler() {
+        let json = r#"[{"group": "Energy Model", "channel": "ANE0", "energy_mj": 1200, "power_watts": 0.75}]"#;
+        let metrics = AppleSiliconANECollector::new().parse_metrics(json).unwrap();
+
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics[0].name, "ane.power_watts");
+        assert_eq!(metrics[0].value, MetricValue::Float(0.75));
+        assert_eq!(metrics[1].name, "ane.energy_mj");
+        assert_eq!(metrics[1].value, MetricValue::Integer(1200));
+        assert_eq!(metrics[1].kind, MetricKind::Counter);
+        assert!(metrics.iter().all(|m| m.metadata["sampler"] == "Energy Model/ANE0"));
+    }
+
+    #[test]
+    fn first_round_reports_energy_without_power() {
+        let json = r#"[{"group": "Energy Model", "channel": "ANE0", "energy_mj": 1200}]"#;
+        let metrics = AppleSiliconANECollector::new().parse_metrics(json).unwrap();
+
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].name, "ane.energy_mj");
+        assert_eq!(metrics[0].value, MetricValue::Integer(1200));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn reports_nothing_off_macos() {
+        assert!(crate::collectors::ANECollector::new().collect().unwrap().is_empty());
+    }
+}
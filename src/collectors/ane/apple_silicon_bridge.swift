@@ -0,0 +1,99 @@
+import Foundation
+
+let energyGroup = "Energy Model"
+
+// Kept across calls: power is the energy used since the previous collection round
+let energySampler = IOReportSampler(group: energyGroup)
+
+struct ANEEnergy {
+    let channel: String
+    let energyMj: Int64
+    /// nil until there is a previous sample to measure from
+    let powerWatts: Double?
+}
+
+func energyToMillijoules(_ value: Int64, unit: String) -> Double {
+    switch unit.trimmingCharacters(in: .whitespaces) {
+    case "nJ":
+        return Double(value) / 1_000_000.0
+    case "uJ", "µJ":
+        return Double(value) / 1_000.0
+    default:
+        return Double(value)
+    }
+}
+
+func aneChannelValues(_ sample: CFDictionary) -> [String: Double] {
+    var values: [String: Double] = [:]
+
+    guard let channels = (sample as NSDictionary)["IOReportChannels"] as? [NSDictionary] else {
+        return values
+    }
+
+    for channel in channels {
+        let item = channel as CFDictionary
+        guard let name = IOReportChannelGetChannelName(item)?.takeUnretainedValue() as String?,
+              name.hasPrefix("ANE") else {
+            continue
+        }
+        let unit = IOReportChannelGetUnitLabel(item)?.takeUnretainedValue() as String? ?? "mJ"
+        values[name] = energyToMillijoules(IOReportSimpleGetIntegerValue(item, 0), unit: unit)
+    }
+
+    return values
+}
+
+func collectANEEnergy() -> [ANEEnergy] {
+    guard let sample = energySampler?.sample() else {
+        return []
+    }
+
+    let totals = aneChannelValues(sample.current)
+    let deltas = sample.delta.map(aneChannelValues)
+
+    return totals.keys.sorted().map { channel in
+        ANEEnergy(
+            channel: channel,
+            energyMj: Int64(totals[channel] ?? 0),
+            powerWatts: deltas.map { sample.elapsed > 0 ? ($0[channel] ?? 0) / 1000.0 / sample.elapsed : 0 }
+        )
+    }
+}
+
+// C-style function for FFI
+@_cdecl("collect_ane_metrics_json")
+func collectANEMetricsJSON() -> UnsafePointer<CChar>? {
+    let readings = collectANEEnergy()
+
+    var jsonArray: [[String: Any]] = []
+
+    for reading in readings {
+        var entry: [String: Any] = [
+            "group": energyGroup,
+            "channel": reading.channel,
+            "energy_mj": reading.energyMj
+        ]
+        if let powerWatts = reading.powerWatts {
+            entry["power_watts"] = powerWatts
+        }
+        jsonArray.append(entry)
+    }
+
+    do {
+        let jsonData = try JSONSerialization.data(withJSONObject: jsonArray, options: [])
+        if let jsonString = String(data: jsonData, encoding: .utf8) {
+            return UnsafePointer(strdup(jsonString))
+        }
+    } catch {
+        return nil
+    }
+
+    return nil
+}
+
+@_cdecl("free_ane_string")
+func freeANEString(_ ptr: UnsafePointer<CChar>?) {
+    if let ptr = ptr {
+        free(UnsafeMutableRawPointer(mutating: ptr))
+    }
+}
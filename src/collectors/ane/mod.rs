@@ -0,0 +1,41 @@
+pub mod apple_silicon;
+
+use super::{Collector, Metric};
+use crate::ThrudError;
+
+pub struct ANECollector {
+    #[cfg(target_os = "macos")]
+    apple_silicon: apple_silicon::AppleSiliconANECollector,
+}
+
+impl Default for ANECollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ANECollector {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(target_os = "macos")]
+            apple_silicon: apple_silicon::AppleSiliconANECollector::new(),
+        }
+    }
+}
+
+impl Collector for ANECollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "macos")]
+        {
+            self.apple_silicon.collect()
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Ok(vec![])
+        }
+    }
+
+    fn name(&self) -> &str {
+        "ane"
+    }
+}
@@ -1,5 +1,10 @@
-use crate::collectors::{Collector, Metric};
+use crate::collectors::names::{self, IDLE_TICKS, TOTAL_TICKS};
+use crate::collectors::{parse_bridge_json, Collector, Metric, MetricKind, MetricValue};
+use crate::ThrudError;
+use std::collections::HashMap;
+#[cfg(target_os = "macos")]
 use std::ffi::CStr;
+#[cfg(target_os = "macos")]
 use std::os::raw::c_char;
 
 #[cfg(target_os = "macos")]
@@ -25,14 +30,72 @@ struct CoreTickCounts {
     idle_ticks: i32,
 }
 
+#[derive(Debug, serde::Deserialize)]
+struct DVFSStateResidency {
+    frequency_mhz: i64,
+    residency: i64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ClusterFrequencyStates {
+    cluster_id: i32,
+    #[serde(rename = "type")]
+    core_type: i32,
+    states: Vec<DVFSStateResidency>,
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct CPUMetricsData {
     cores: Vec<CoreInfo>,
     tick_counts: Vec<CoreTickCounts>,
+    #[serde(default)]
+    cluster_frequencies: Vec<ClusterFrequencyStates>,
+}
+
+/// Residency-weighted average frequency across the active DVFS states of a cluster.
+///
+/// The bridge reports how long (in IOReport residency units) the cluster spent in
+/// each active performance state since the previous round (nothing on the first
+/// round), with idle/off states already removed. The effective frequency is
+/// `sum(freq_i * residency_i) / sum(residency_i)`, rounded to the nearest MHz.
+/// Returns `None` when the cluster never left its idle states, since no frequency
+/// applies.
+fn weighted_frequency_mhz(states: &[DVFSStateResidency]) -> Option<i64> {
+    let total_residency: i128 = states.iter().map(|s| s.residency.max(0) as i128).sum();
+    if total_residency == 0 {
+        return None;
+    }
+
+    let weighted: i128 = states
+        .iter()
+        .map(|s| s.frequency_mhz as i128 * s.residency.max(0) as i128)
+        .sum();
+
+    Some(((weighted as f64) / (total_residency as f64)).round() as i64)
+}
+
+fn core_type_name(core_type: i32) -> &'static str {
+    match core_type {
+        1 => names::EFFICIENCY,
+        2 => names::PERFORMANCE,
+        _ => "unknown",
+    }
+}
+
+fn tick_metric(name: String, ticks: i64, metadata: &HashMap<String, String>) -> Metric {
+    Metric::new(name, MetricValue::Integer(ticks), metadata.clone())
+        .with_unit("ticks")
+        .with_kind(MetricKind::Counter)
 }
 
 pub struct AppleSiliconCPUCollector;
 
+impl Default for AppleSiliconCPUCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AppleSiliconCPUCollector {
     pub fn new() -> Self {
         Self
@@ -47,15 +110,21 @@ impl AppleSiliconCPUCollector {
         }
 
         let json_str = unsafe {
-            CStr::from_ptr(json_ptr).to_str()?
+            CStr::from_ptr(json_ptr).to_string_lossy().into_owned()
         };
 
-        let cpu_data: CPUMetricsData = serde_json::from_str(json_str)?;
-        
         unsafe {
             free_string(json_ptr);
         }
 
+        self.parse_metrics(&json_str)
+    }
+
+    /// Convert the bridge JSON payload into tick count metrics
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn parse_metrics(&self, json_str: &str) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let cpu_data: CPUMetricsData = parse_bridge_json("CPU", json_str)?;
+
         let mut metrics = Vec::new();
 
         // Calculate per-core type and per-cluster aggregations
@@ -65,28 +134,40 @@ impl AppleSiliconCPUCollector {
         let mut performance_total = 0i64;
         
         // Map cluster_id -> (idle_ticks, total_ticks) for efficiency and performance cores
-        let mut efficiency_clusters: std::collections::HashMap<i32, (i64, i64)> = std::collections::HashMap::new();
-        let mut performance_clusters: std::collections::HashMap<i32, (i64, i64)> = std::collections::HashMap::new();
+        let mut efficiency_clusters: HashMap<i32, (i64, i64)> = HashMap::new();
+        let mut performance_clusters: HashMap<i32, (i64, i64)> = HashMap::new();
 
         // Export raw tick counts for each core and aggregate
         for tick_data in &cpu_data.tick_counts {
             let core_info = cpu_data.cores.iter().find(|c| c.id == tick_data.core_id);
             
             let idle_ticks = tick_data.idle_ticks as i64;
-            let total_ticks = (tick_data.user_ticks + tick_data.system_ticks + tick_data.nice_ticks + tick_data.idle_ticks) as i64;
+            let total_ticks = tick_data.user_ticks as i64
+                + tick_data.system_ticks as i64
+                + tick_data.nice_ticks as i64
+                + tick_data.idle_ticks as i64;
             
             if let Some(core_info) = core_info {
+                let core_type = core_type_name(core_info.core_type);
+                let mut metadata = HashMap::new();
+                metadata.insert("core_id".to_string(), tick_data.core_id.to_string());
+                metadata.insert("core_type".to_string(), core_type.to_string());
+                metadata.insert("cluster_id".to_string(), core_info.cluster_id.to_string());
+
+                // unknown core types still export individual metrics
+                metrics.push(tick_metric(
+                    names::cpu_core_ticks(core_type, tick_data.core_id, IDLE_TICKS),
+                    idle_ticks,
+                    &metadata,
+                ));
+                metrics.push(tick_metric(
+                    names::cpu_core_ticks(core_type, tick_data.core_id, TOTAL_TICKS),
+                    total_ticks,
+                    &metadata,
+                ));
+
                 match core_info.core_type {
                     1 => { // efficiency core
-                        metrics.push(Metric::new(
-                            format!("cpu.efficiency_core.{}.idle_ticks", tick_data.core_id),
-                            idle_ticks.to_string(),
-                        ));
-                        metrics.push(Metric::new(
-                            format!("cpu.efficiency_core.{}.total_ticks", tick_data.core_id),
-                            total_ticks.to_string(),
-                        ));
-                        
                         efficiency_idle += idle_ticks;
                         efficiency_total += total_ticks;
                         
@@ -95,15 +176,6 @@ impl AppleSiliconCPUCollector {
                         cluster_entry.1 += total_ticks;
                     },
                     2 => { // performance core
-                        metrics.push(Metric::new(
-                            format!("cpu.performance_core.{}.idle_ticks", tick_data.core_id),
-                            idle_ticks.to_string(),
-                        ));
-                        metrics.push(Metric::new(
-                            format!("cpu.performance_core.{}.total_ticks", tick_data.core_id),
-                            total_ticks.to_string(),
-                        ));
-                        
                         performance_idle += idle_ticks;
                         performance_total += total_ticks;
                         
@@ -111,61 +183,59 @@ impl AppleSiliconCPUCollector {
                         cluster_entry.0 += idle_ticks;
                         cluster_entry.1 += total_ticks;
                     },
-                    _ => {
-                        // unknown core type, still export individual metrics
-                        metrics.push(Metric::new(
-                            format!("cpu.unknown_core.{}.idle_ticks", tick_data.core_id),
-                            idle_ticks.to_string(),
-                        ));
-                        metrics.push(Metric::new(
-                            format!("cpu.unknown_core.{}.total_ticks", tick_data.core_id),
-                            total_ticks.to_string(),
-                        ));
-                    }
+                    _ => {}
                 }
             }
         }
 
         // Add per-core-type aggregations
-        metrics.push(Metric::new(
-            "cpu.efficiency.idle_ticks".to_string(),
-            efficiency_idle.to_string(),
-        ));
-        metrics.push(Metric::new(
-            "cpu.efficiency.total_ticks".to_string(),
-            efficiency_total.to_string(),
-        ));
-        
-        metrics.push(Metric::new(
-            "cpu.performance.idle_ticks".to_string(),
-            performance_idle.to_string(),
-        ));
-        metrics.push(Metric::new(
-            "cpu.performance.total_ticks".to_string(),
-            performance_total.to_string(),
-        ));
+        for (core_type, idle, total) in [
+            (names::EFFICIENCY, efficiency_idle, efficiency_total),
+            (names::PERFORMANCE, performance_idle, performance_total),
+        ] {
+            let mut metadata = HashMap::new();
+            metadata.insert("core_type".to_string(), core_type.to_string());
+
+            metrics.push(tick_metric(names::cpu_type_ticks(core_type, IDLE_TICKS), idle, &metadata));
+            metrics.push(tick_metric(names::cpu_type_ticks(core_type, TOTAL_TICKS), total, &metadata));
+        }
 
         // Add per-cluster aggregations
-        for (cluster_id, (idle, total)) in efficiency_clusters {
-            metrics.push(Metric::new(
-                format!("cpu.efficiency_cluster.{}.idle_ticks", cluster_id),
-                idle.to_string(),
-            ));
-            metrics.push(Metric::new(
-                format!("cpu.efficiency_cluster.{}.total_ticks", cluster_id),
-                total.to_string(),
-            ));
+        for (core_type, clusters) in [
+            (names::EFFICIENCY, efficiency_clusters),
+            (names::PERFORMANCE, performance_clusters),
+        ] {
+            for (cluster_id, (idle, total)) in clusters {
+                let mut metadata = HashMap::new();
+                metadata.insert("cluster_id".to_string(), cluster_id.to_string());
+                metadata.insert("core_type".to_string(), core_type.to_string());
+
+                metrics.push(tick_metric(
+                    names::cpu_cluster_ticks(core_type, cluster_id, IDLE_TICKS),
+                    idle,
+                    &metadata,
+                ));
+                metrics.push(tick_metric(
+                    names::cpu_cluster_ticks(core_type, cluster_id, TOTAL_TICKS),
+                    total,
+                    &metadata,
+                ));
+            }
         }
-        
-        for (cluster_id, (idle, total)) in performance_clusters {
-            metrics.push(Metric::new(
-                format!("cpu.performance_cluster.{}.idle_ticks", cluster_id),
-                idle.to_string(),
-            ));
-            metrics.push(Metric::new(
-                format!("cpu.performance_cluster.{}.total_ticks", cluster_id),
-                total.to_string(),
-            ));
+
+        // Add per-cluster frequency from DVFS state residency
+        for cluster in &cpu_data.cluster_frequencies {
+            if let Some(frequency_mhz) = weighted_frequency_mhz(&cluster.states) {
+                let mut metadata = HashMap::new();
+                metadata.insert("cluster_id".to_string(), cluster.cluster_id.to_string());
+                metadata.insert("core_type".to_string(), core_type_name(cluster.core_type).to_string());
+
+                metrics.push(Metric::new(
+                    names::CPU_CLUSTER_FREQUENCY_MHZ.to_string(),
+                    MetricValue::Integer(frequency_mhz),
+                    metadata,
+                ).with_unit("MHz"));
+            }
         }
 
         Ok(metrics)
@@ -178,18 +248,140 @@ impl AppleSiliconCPUCollector {
 }
 
 impl Collector for AppleSiliconCPUCollector {
-    fn collect(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
         #[cfg(target_os = "macos")]
         {
-            self.collect_macos()
+            Ok(self.collect_macos()?)
         }
         #[cfg(not(target_os = "macos"))]
         {
-            self.collect_other()
+            Ok(self.collect_other()?)
         }
     }
 
     fn name(&self) -> &str {
         "apple_silicon_cpu"
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two efficiency cores in cluster 0 and one performance core in cluster 1
+    const BRIDGE_JSON: &str = r#"{
+        "cores": [
+            {"id": 0, "type": 1, "cluster_id": 0},
+            {"id": 1, "type": 1, "cluster_id": 0},
+            {"id": 2, "type": 2, "cluster_id": 1}
+        ],
+        "tick_counts": [
+            {"core_id": 0, "user_ticks": 10, "system_ticks": 5, "nice_ticks": 1, "idle_ticks": 84},
+            {"core_id": 1, "user_ticks": 20, "system_ticks": 10, "nice_ticks": 0, "idle_ticks": 70},
+            {"core_id": 2, "user_ticks": 50, "system_ticks": 25, "nice_ticks": 5, "idle_ticks": 120}
+        ]
+    }"#;
+
+    fn find<'a>(metrics: &'a [Metric], name: &str) -> &'a Metric {
+        metrics
+            .iter()
+            .find(|m| m.name == name)
+            .unwrap_or_else(|| panic!("missing metric {}", name))
+    }
+
+    fn ticks(metrics: &[Metric], name: &str) -> i64 {
+        match find(metrics, name).value {
+            MetricValue::Integer(ticks) => ticks,
+            ref other => panic!("{} is not an integer: {:?}", name, other),
+        }
+    }
+
+    #[test]
+    fn parses_per_core_ticks_with_metadata() {
+        let metrics = AppleSiliconCPUCollector::new().parse_metrics(BRIDGE_JSON).unwrap();
+
+        let idle = find(&metrics, &names::cpu_core_ticks(names::PERFORMANCE, 2, IDLE_TICKS));
+        assert!(matches!(idle.value, MetricValue::Integer(120)));
+        assert_eq!(idle.metadata["core_id"], "2");
+        assert_eq!(idle.metadata["core_type"], names::PERFORMANCE);
+        assert_eq!(idle.metadata["cluster_id"], "1");
+        assert_eq!(idle.unit.as_deref(), Some("ticks"));
+        assert_eq!(idle.kind, MetricKind::Counter);
+
+        // Total is user + system + nice + idle
+        assert_eq!(ticks(&metrics, &names::cpu_core_ticks(names::EFFICIENCY, 0, TOTAL_TICKS)), 100);
+        assert_eq!(ticks(&metrics, &names::cpu_core_ticks(names::PERFORMANCE, 2, TOTAL_TICKS)), 200);
+    }
+
+    #[test]
+    fn sums_ticks_per_core_type_and_cluster() {
+        let metrics = AppleSiliconCPUCollector::new().parse_metrics(BRIDGE_JSON).unwrap();
+
+        assert_eq!(ticks(&metrics, names::CPU_EFFICIENCY_IDLE_TICKS), 154);
+        assert_eq!(ticks(&metrics, names::CPU_EFFICIENCY_TOTAL_TICKS), 200);
+        assert_eq!(ticks(&metrics, names::CPU_PERFORMANCE_IDLE_TICKS), 120);
+        assert_eq!(ticks(&metrics, names::CPU_PERFORMANCE_TOTAL_TICKS), 200);
+
+        let cluster = find(&metrics, &names::cpu_cluster_ticks(names::EFFICIENCY, 0, TOTAL_TICKS));
+        assert!(matches!(cluster.value, MetricValue::Integer(200)));
+        assert_eq!(cluster.metadata["cluster_id"], "0");
+        assert_eq!(cluster.metadata["core_type"], names::EFFICIENCY);
+        assert_eq!(ticks(&metrics, &names::cpu_cluster_ticks(names::PERFORMANCE, 1, IDLE_TICKS)), 120);
+    }
+
+    fn states(table: &[(i64, i64)]) -> Vec<DVFSStateResidency> {
+        table
+            .iter()
+            .map(|&(frequency_mhz, residency)| DVFSStateResidency { frequency_mhz, residency })
+            .collect()
+    }
+
+    #[test]
+    fn frequency_is_the_residency_weighted_average() {
+        // (600*1 + 1200*2 + 2400*1) / 4 = 1350
+        assert_eq!(weighted_frequency_mhz(&states(&[(600, 100), (1200, 200), (2400, 100)])), Some(1350));
+        // Rounded to the nearest MHz: (1000*2 + 1001*1) / 3 = 1000.33
+        assert_eq!(weighted_frequency_mhz(&states(&[(1000, 2), (1001, 1)])), Some(1000));
+        assert_eq!(weighted_frequency_mhz(&states(&[(3000, 7)])), Some(3000));
+    }
+
+    #[test]
+    fn idle_cluster_has_no_frequency() {
+        assert_eq!(weighted_frequency_mhz(&[]), None);
+        assert_eq!(weighted_frequency_mhz(&states(&[(600, 0), (1200, 0)])), None);
+        // Negative residencies are treated as zero
+        assert_eq!(weighted_frequency_mhz(&states(&[(600, -5), (1200, 10)])), Some(1200));
+    }
+
+    #[test]
+    fn reports_cluster_frequency_with_metadata() {
+        let json = r#"{
+            "cores": [],
+            "tick_counts": [],
+            "cluster_frequencies": [
+                {"cluster_id": 0, "type": 1, "states": [{"frequency_mhz": 1000, "residency": 3}, {"frequency_mhz": 2000, "residency": 1}]},
+                {"cluster_id": 1, "type": 2, "states": [{"frequency_mhz": 3000, "residency": 0}]}
+            ]
+        }"#;
+        let metrics = AppleSiliconCPUCollector::new().parse_metrics(json).unwrap();
+
+        let frequencies: Vec<_> = metrics.iter().filter(|m| m.name == names::CPU_CLUSTER_FREQUENCY_MHZ).collect();
+        // The idle performance cluster reports no frequency
+        assert_eq!(frequencies.len(), 1);
+        assert_eq!(frequencies[0].value, MetricValue::Integer(1250));
+        assert_eq!(frequencies[0].metadata["cluster_id"], "0");
+        assert_eq!(frequencies[0].metadata["core_type"], names::EFFICIENCY);
+        assert_eq!(frequencies[0].unit.as_deref(), Some("MHz"));
+    }
+
+    #[test]
+    fn core_type_totals_are_reported_without_cores() {
+        let metrics = AppleSiliconCPUCollector::new()
+            .parse_metrics(r#"{"cores": [], "tick_counts": []}"#)
+            .unwrap();
+
+        assert_eq!(ticks(&metrics, names::CPU_EFFICIENCY_TOTAL_TICKS), 0);
+        assert_eq!(ticks(&metrics, names::CPU_PERFORMANCE_TOTAL_TICKS), 0);
+        assert!(metrics.iter().all(|m| m.name != names::CPU_CLUSTER_FREQUENCY_MHZ));
+    }
+}
@@ -21,12 +21,27 @@ struct CPUCoreTicks {
     let idleTicks: Int32
 }
 
+struct DVFSStateResidency {
+    let frequencyMhz: Int64
+    let residency: Int64
+}
+
+struct ClusterFrequencyStates {
+    let clusterId: Int32
+    let type: CoreType
+    let states: [DVFSStateResidency]
+}
+
 struct CPUMetrics {
     let cores: [CoreInfo]
     let coreTickCounts: [CPUCoreTicks]
     let totalCores: Int32
+    let clusterFrequencies: [ClusterFrequencyStates]
 }
 
+// Kept across calls: residencies cover the time since the previous collection round
+let dvfsSampler = IOReportSampler(group: "CPU Stats", subgroup: "CPU Complex Performance States")
+
 func detectCoreTopology() -> [CoreInfo] {
     var cores: [CoreInfo] = []
     
@@ -159,12 +174,108 @@ func getCurrentCPUTicks(numCPUs: Int32) -> [CPUCoreTicks] {
     return coreTickCounts
 }
 
+// DVFS frequency tables (Hz, voltage pairs) published by the power manager device.
+// voltage-states1 covers efficiency clusters and voltage-states5 performance clusters.
+func readDVFSFrequencies() -> [CoreType: [Int64]] {
+    var tables: [CoreType: [Int64]] = [:]
+
+    var iterator = io_iterator_t()
+    if IOServiceGetMatchingServices(kIOMainPortDefault, IOServiceMatching("AppleARMIODevice"), &iterator) != kIOReturnSuccess {
+        return tables
+    }
+    defer { IOObjectRelease(iterator) }
+
+    var entry = IOIteratorNext(iterator)
+    while entry != 0 {
+        defer { IOObjectRelease(entry) }
+
+        var props: Unmanaged<CFMutableDictionary>?
+        if IORegistryEntryCreateCFProperties(entry, &props, kCFAllocatorDefault, 0) == kIOReturnSuccess,
+           let properties = props?.takeRetainedValue() as? [String: Any],
+           properties["voltage-states1-sram"] != nil {
+            for (key, type) in [("voltage-states1-sram", CoreType.efficiency), ("voltage-states5-sram", CoreType.performance)] {
+                guard let data = properties[key] as? Data else {
+                    continue
+                }
+                let words = data.withUnsafeBytes { Array($0.bindMemory(to: UInt32.self)) }
+                // Entries alternate frequency (Hz) and voltage
+                tables[type] = stride(from: 0, to: words.count - 1, by: 2).map { Int64(words[$0]) / 1_000_000 }
+            }
+            return tables
+        }
+
+        entry = IOIteratorNext(iterator)
+    }
+
+    return tables
+}
+
+// Residency per DVFS state since the previous call for each CPU complex (ECPU,
+// PCPU, PCPU1, ...), empty on the first call. Idle/off states are skipped so the
+// remaining states line up with the frequency table.
+func collectClusterFrequencies(cores: [CoreInfo]) -> [ClusterFrequencyStates] {
+    let frequencies = readDVFSFrequencies()
+    guard !frequencies.isEmpty,
+          let delta = dvfsSampler?.sample()?.delta,
+          let items = (delta as NSDictionary)["IOReportChannels"] as? [NSDictionary] else {
+        return []
+    }
+
+    // Cluster ids in registry order for each core type, matched to ECPU/PCPU/PCPU1 by position
+    var clusterIds: [CoreType: [Int32]] = [:]
+    for core in cores where !(clusterIds[core.type]?.contains(core.clusterId) ?? false) {
+        clusterIds[core.type, default: []].append(core.clusterId)
+    }
+
+    var result: [ClusterFrequencyStates] = []
+
+    for item in items {
+        let channel = item as CFDictionary
+        guard let name = IOReportChannelGetChannelName(channel)?.takeUnretainedValue() as String? else {
+            continue
+        }
+
+        let type: CoreType
+        if name.hasPrefix("ECPU") {
+            type = .efficiency
+        } else if name.hasPrefix("PCPU") {
+            type = .performance
+        } else {
+            continue
+        }
+
+        let position = Int(name.dropFirst(4)) ?? 0
+        guard let table = frequencies[type],
+              let ids = clusterIds[type], position < ids.count else {
+            continue
+        }
+
+        var states: [DVFSStateResidency] = []
+        var activeIndex = 0
+        for index in 0..<IOReportStateGetCount(channel) {
+            let stateName = IOReportStateGetNameForIndex(channel, index)?.takeUnretainedValue() as String? ?? ""
+            if stateName.hasPrefix("IDLE") || stateName.hasPrefix("OFF") || stateName.hasPrefix("DOWN") {
+                continue
+            }
+            if activeIndex < table.count {
+                states.append(DVFSStateResidency(frequencyMhz: table[activeIndex], residency: IOReportStateGetResidency(channel, index)))
+            }
+            activeIndex += 1
+        }
+
+        result.append(ClusterFrequencyStates(clusterId: ids[position], type: type, states: states))
+    }
+
+    return result
+}
+
 func collectCPUMetrics() -> CPUMetrics {
     let cores = detectCoreTopology()
     let numCPUs = getCPUCount()
     let coreTickCounts = getCurrentCPUTicks(numCPUs: numCPUs)
+    let clusterFrequencies = collectClusterFrequencies(cores: cores)
     
-    return CPUMetrics(cores: cores, coreTickCounts: coreTickCounts, totalCores: numCPUs)
+    return CPUMetrics(cores: cores, coreTickCounts: coreTickCounts, totalCores: numCPUs, clusterFrequencies: clusterFrequencies)
 }
 
 // C-style function for FFI
@@ -204,6 +315,17 @@ func collectCPUMetricsJSON() -> UnsafePointer<CChar>? {
     }
     jsonData["tick_counts"] = tickCountsArray
     
+    // DVFS state residency per cluster
+    var clusterFrequenciesArray: [[String: Any]] = []
+    for cluster in metrics.clusterFrequencies {
+        clusterFrequenciesArray.append([
+            "cluster_id": cluster.clusterId,
+            "type": cluster.type.rawValue,
+            "states": cluster.states.map { ["frequency_mhz": $0.frequencyMhz, "residency": $0.residency] }
+        ])
+    }
+    jsonData["cluster_frequencies"] = clusterFrequenciesArray
+    
     do {
         let jsonDataSerialized = try JSONSerialization.data(withJSONObject: jsonData, options: [])
         if let jsonString = String(data: jsonDataSerialized, encoding: .utf8) {
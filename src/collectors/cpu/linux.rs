@@ -0,0 +1,201 @@
+use crate::collectors::names::{self, IDLE_TICKS, TOTAL_TICKS};
+use crate::collectors::{Collector, Metric, MetricKind, MetricValue};
+use crate::ThrudError;
+use std::collections::HashMap;
+
+const PROC_STAT: &str = "/proc/stat";
+
+/// Columns of a `cpuN` line in `/proc/stat`, in order. Later columns (guest time)
+/// are already included in `user`/`nice` and are ignored.
+const TICK_COLUMNS: usize = 8;
+const IDLE: usize = 3;
+const IOWAIT: usize = 4;
+
+/// Per-CPU tick counters read from `/proc/stat`.
+///
+/// Metric names and metadata match the Apple Silicon collector so the same
+/// aggregations and charts apply. Linux doesn't distinguish core types, so every
+/// CPU is reported as a `performance` core in cluster 0. Idle ticks include
+/// iowait, and total ticks also count irq, softirq and steal time.
+pub struct LinuxCPUCollector;
+
+impl Default for LinuxCPUCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinuxCPUCollector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Convert `/proc/stat` contents into tick count metrics
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn parse_metrics(&self, contents: &str) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let core_type = names::PERFORMANCE;
+        let mut metrics = Vec::new();
+        let mut type_idle = 0i64;
+        let mut type_total = 0i64;
+
+        for core in parse_proc_stat(contents)? {
+            type_idle += core.idle_ticks;
+            type_total += core.total_ticks;
+
+            let mut metadata = HashMap::new();
+            metadata.insert("core_id".to_string(), core.core_id.to_string());
+            metadata.insert("core_type".to_string(), core_type.to_string());
+            metadata.insert("cluster_id".to_string(), "0".to_string());
+
+            metrics.push(tick_metric(
+                names::cpu_core_ticks(core_type, core.core_id, IDLE_TICKS),
+                core.idle_ticks,
+                &metadata,
+            ));
+            metrics.push(tick_metric(
+                names::cpu_core_ticks(core_type, core.core_id, TOTAL_TICKS),
+                core.total_ticks,
+                &metadata,
+            ));
+        }
+
+        if metrics.is_empty() {
+            return Ok(metrics);
+        }
+
+        let mut metadata = HashMap::new();
+        metadata.insert("core_type".to_string(), core_type.to_string());
+        metrics.push(tick_metric(names::cpu_type_ticks(core_type, IDLE_TICKS), type_idle, &metadata));
+        metrics.push(tick_metric(names::cpu_type_ticks(core_type, TOTAL_TICKS), type_total, &metadata));
+
+        metadata.insert("cluster_id".to_string(), "0".to_string());
+        metrics.push(tick_metric(names::cpu_cluster_ticks(core_type, 0, IDLE_TICKS), type_idle, &metadata));
+        metrics.push(tick_metric(names::cpu_cluster_ticks(core_type, 0, TOTAL_TICKS), type_total, &metadata));
+
+        Ok(metrics)
+    }
+}
+
+/// Idle and total ticks of one `cpuN` line
+#[derive(Debug, PartialEq)]
+struct CoreTicks {
+    core_id: i32,
+    idle_ticks: i64,
+    total_ticks: i64,
+}
+
+/// Tick counts of every `cpuN` line in `/proc/stat` contents, in order. The
+/// aggregate `cpu` line and other lines are skipped.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_proc_stat(contents: &str) -> Result<Vec<CoreTicks>, Box<dyn std::error::Error>> {
+    let mut cores = Vec::new();
+
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(core_id) = fields
+            .next()
+            .and_then(|label| label.strip_prefix("cpu"))
+            .filter(|id| !id.is_empty())
+        else {
+            continue;
+        };
+        let core_id: i32 = core_id
+            .parse()
+            .map_err(|_| format!("Invalid CPU label in {}: cpu{}", PROC_STAT, core_id))?;
+
+        let ticks = fields
+            .take(TICK_COLUMNS)
+            .map(|field| field.parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Invalid tick count for cpu{} in {}: {}", core_id, PROC_STAT, e))?;
+        if ticks.len() <= IDLE {
+            return Err(format!("Too few tick columns for cpu{} in {}", core_id, PROC_STAT).into());
+        }
+
+        cores.push(CoreTicks {
+            core_id,
+            idle_ticks: ticks[IDLE] + ticks.get(IOWAIT).copied().unwrap_or(0),
+            total_ticks: ticks.iter().sum(),
+        });
+    }
+
+    Ok(cores)
+}
+
+fn tick_metric(name: String, ticks: i64, metadata: &HashMap<String, String>) -> Metric {
+    Metric::new(name, MetricValue::Integer(ticks), metadata.clone())
+        .with_unit("ticks")
+        .with_kind(MetricKind::Counter)
+}
+
+impl Collector for LinuxCPUCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "linux")]
+        {
+            let contents = std::fs::read_to_string(PROC_STAT)?;
+            Ok(self.parse_metrics(&contents)?)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(vec![])
+        }
+    }
+
+    fn name(&self) -> &str {
+        "linux_cpu"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_STAT_SNIPPET: &str = "\
+cpu  4705 150 1120 16250 520 0 29 0 0 0
+cpu0 2400 100 600 8000 300 0 20 0 0 0
+cpu1 2305 50 520 8250 220 0 9 0 12 0
+intr 114930548 113199788 3 0 5 263 0 4 [...]
+ctxt 1990473
+btime 1062191376
+procs_running 2
+";
+
+    #[test]
+    fn parses_each_cpu_line() {
+        let cores = parse_proc_stat(PROC_STAT_SNIPPET).unwrap();
+
+        assert_eq!(
+            cores,
+            [
+                // idle + iowait; user through steal, without the guest columns
+                CoreTicks { core_id: 0, idle_ticks: 8300, total_ticks: 11420 },
+                CoreTicks { core_id: 1, idle_ticks: 8470, total_ticks: 11354 },
+            ]
+        );
+    }
+
+    #[test]
+    fn old_kernels_without_iowait_still_parse() {
+        let cores = parse_proc_stat("cpu0 10 0 5 85\n").unwrap();
+        assert_eq!(cores, [CoreTicks { core_id: 0, idle_ticks: 85, total_ticks: 100 }]);
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        assert!(parse_proc_stat("cpu0 10 x 5 85\n").is_err());
+        assert!(parse_proc_stat("cpu0 10 0 5\n").is_err());
+        assert!(parse_proc_stat("cpuX 10 0 5 85\n").is_err());
+    }
+
+    #[test]
+    fn metrics_cover_cores_and_their_sums() {
+        let metrics = LinuxCPUCollector::new().parse_metrics(PROC_STAT_SNIPPET).unwrap();
+        let value = |name: &str| metrics.iter().find(|m| m.name == name).map(|m| m.value.clone());
+
+        assert_eq!(metrics.len(), 8);
+        assert_eq!(value("cpu.performance_core.1.idle_ticks"), Some(MetricValue::Integer(8470)));
+        assert_eq!(value("cpu.performance.total_ticks"), Some(MetricValue::Integer(22774)));
+        assert_eq!(value("cpu.performance_cluster.0.idle_ticks"), Some(MetricValue::Integer(16770)));
+        assert!(metrics.iter().all(|m| m.kind == MetricKind::Counter));
+    }
+}
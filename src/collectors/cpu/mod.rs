@@ -1,10 +1,20 @@
 pub mod apple_silicon;
+pub mod linux;
 
 use super::{Collector, Metric};
+use crate::ThrudError;
 
 pub struct CPUCollector {
     #[cfg(target_os = "macos")]
     apple_silicon: apple_silicon::AppleSiliconCPUCollector,
+    #[cfg(target_os = "linux")]
+    linux: linux::LinuxCPUCollector,
+}
+
+impl Default for CPUCollector {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CPUCollector {
@@ -12,17 +22,23 @@ impl CPUCollector {
         Self {
             #[cfg(target_os = "macos")]
             apple_silicon: apple_silicon::AppleSiliconCPUCollector::new(),
+            #[cfg(target_os = "linux")]
+            linux: linux::LinuxCPUCollector::new(),
         }
     }
 }
 
 impl Collector for CPUCollector {
-    fn collect(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
         #[cfg(target_os = "macos")]
         {
             self.apple_silicon.collect()
         }
-        #[cfg(not(target_os = "macos"))]
+        #[cfg(target_os = "linux")]
+        {
+            self.linux.collect()
+        }
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
         {
             Ok(vec![])
         }
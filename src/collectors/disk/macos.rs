@@ -0,0 +1,134 @@
+use crate::collectors::{parse_bridge_json, Collector, Metric, MetricKind, MetricValue};
+use crate::ThrudError;
+use std::collections::HashMap;
+#[cfg(target_os = "macos")]
+use std::ffi::CStr;
+#[cfg(target_os = "macos")]
+use std::os::raw::c_char;
+
+#[cfg(target_os = "macos")]
+extern "C" {
+    fn collect_disk_metrics_json() -> *const c_char;
+    fn free_disk_string(ptr: *const c_char);
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct DiskStats {
+    device: String,
+    read_bytes: i64,
+    write_bytes: i64,
+    read_ops: i64,
+    write_ops: i64,
+}
+
+pub struct MacOSDiskCollector;
+
+impl Default for MacOSDiskCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MacOSDiskCollector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    #[cfg(target_os = "macos")]
+    fn collect_macos(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let json_ptr = unsafe { collect_disk_metrics_json() };
+
+        if json_ptr.is_null() {
+            return Ok(vec![]);
+        }
+
+        let json_str = unsafe {
+            CStr::from_ptr(json_ptr).to_string_lossy().into_owned()
+        };
+
+        unsafe {
+            free_disk_string(json_ptr);
+        }
+
+        self.parse_metrics(&json_str)
+    }
+
+    /// Convert the bridge JSON payload into cumulative per-device counters
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn parse_metrics(&self, json_str: &str) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let disks: Vec<DiskStats> = parse_bridge_json("disk", json_str)?;
+
+        let mut metrics = Vec::new();
+
+        for disk in disks {
+            let mut metadata = HashMap::new();
+            metadata.insert("device".to_string(), disk.device.clone());
+
+            for (name, value, unit) in [
+                ("disk.read_bytes", disk.read_bytes, "bytes"),
+                ("disk.write_bytes", disk.write_bytes, "bytes"),
+                ("disk.read_ops", disk.read_ops, "operations"),
+                ("disk.write_ops", disk.write_ops, "operations"),
+            ] {
+                metrics.push(
+                    Metric::new(name.to_string(), MetricValue::Integer(value), metadata.clone())
+                        .with_unit(unit)
+                        .with_kind(MetricKind::Counter),
+                );
+            }
+        }
+
+        Ok(metrics)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn collect_other(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        Ok(vec![])
+    }
+}
+
+impl Collector for MacOSDiskCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "macos")]
+        {
+            Ok(self.collect_macos()?)
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Ok(self.collect_other()?)
+        }
+    }
+
+    fn name(&self) -> &str {
+        "macos_disk"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cumulative_counters_per_device() {
+        let json = r#"[
+            {"device": "disk0", "read_bytes": 4096, "write_bytes": 8192, "read_ops": 3, "write_ops": 5},
+            {"device": "disk4", "read_bytes": 0, "write_bytes": 0, "read_ops": 0, "write_ops": 0}
+        ]"#;
+        let metrics = MacOSDiskCollector::new().parse_metrics(json).unwrap();
+
+        assert_eq!(metrics.len(), 8);
+        let disk0: Vec<_> = metrics.iter().filter(|m| m.metadata["device"] == "disk0").collect();
+        let value = |name: &str| disk0.iter().find(|m| m.name == name).map(|m| m.value.clone());
+        assert_eq!(value("disk.read_bytes"), Some(MetricValue::Integer(4096)));
+        assert_eq!(value("disk.write_bytes"), Some(MetricValue::Integer(8192)));
+        assert_eq!(value("disk.read_ops"), Some(MetricValue::Integer(3)));
+        assert_eq!(value("disk.write_ops"), Some(MetricValue::Integer(5)));
+        assert!(disk0.iter().all(|m| m.kind == MetricKind::Counter));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn reports_nothing_off_macos() {
+        assert!(crate::collectors::DiskCollector::new().collect().unwrap().is_empty());
+    }
+}
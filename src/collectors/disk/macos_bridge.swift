@@ -0,0 +1,103 @@
+import Foundation
+import IOKit
+
+struct DiskStats {
+    let device: String
+    let readBytes: Int64
+    let writeBytes: Int64
+    let readOps: Int64
+    let writeOps: Int64
+}
+
+func findBSDName(_ entry: io_registry_entry_t) -> String? {
+    // The BSD name lives on the IOMedia object below the block storage driver
+    var iterator = io_iterator_t()
+    let options = IOOptionBits(kIORegistryIterateRecursively)
+    if IORegistryEntryCreateIterator(entry, kIOServicePlane, options, &iterator) != kIOReturnSuccess {
+        return nil
+    }
+    defer { IOObjectRelease(iterator) }
+
+    var child = IOIteratorNext(iterator)
+    while child != 0 {
+        defer { IOObjectRelease(child) }
+        if IOObjectConformsTo(child, "IOMedia") != 0,
+           let name = IORegistryEntryCreateCFProperty(child, "BSD Name" as CFString, kCFAllocatorDefault, 0)?
+               .takeRetainedValue() as? String {
+            return name
+        }
+        child = IOIteratorNext(iterator)
+    }
+
+    return nil
+}
+
+func collectDiskStats() -> [DiskStats] {
+    var iterator = io_iterator_t()
+    if IOServiceGetMatchingServices(kIOMainPortDefault, IOServiceMatching("IOBlockStorageDriver"), &iterator) != kIOReturnSuccess {
+        return []
+    }
+    defer { IOObjectRelease(iterator) }
+
+    var disks: [DiskStats] = []
+
+    var driver = IOIteratorNext(iterator)
+    while driver != 0 {
+        defer { IOObjectRelease(driver) }
+
+        var props: Unmanaged<CFMutableDictionary>?
+        if IORegistryEntryCreateCFProperties(driver, &props, kCFAllocatorDefault, 0) == kIOReturnSuccess,
+           let properties = props?.takeRetainedValue() as? [String: Any],
+           let stats = properties["Statistics"] as? [String: Any],
+           let device = findBSDName(driver) {
+
+            disks.append(DiskStats(
+                device: device,
+                readBytes: (stats["Bytes (Read)"] as? NSNumber)?.int64Value ?? 0,
+                writeBytes: (stats["Bytes (Write)"] as? NSNumber)?.int64Value ?? 0,
+                readOps: (stats["Operations (Read)"] as? NSNumber)?.int64Value ?? 0,
+                writeOps: (stats["Operations (Write)"] as? NSNumber)?.int64Value ?? 0
+            ))
+        }
+
+        driver = IOIteratorNext(iterator)
+    }
+
+    return disks
+}
+
+// C-style function for FFI
+@_cdecl("collect_disk_metrics_json")
+func collectDiskMetricsJSON() -> UnsafePointer<CChar>? {
+    let disks = collectDiskStats()
+
+    var jsonArray: [[String: Any]] = []
+
+    for disk in disks {
+        jsonArray.append([
+            "device": disk.device,
+            "read_bytes": disk.readBytes,
+            "write_bytes": disk.writeBytes,
+            "read_ops": disk.readOps,
+            "write_ops": disk.writeOps
+        ])
+    }
+
+    do {
+        let jsonData = try JSONSerialization.data(withJSONObject: jsonArray, options: [])
+        if let jsonString = String(data: jsonData, encoding: .utf8) {
+            return UnsafePointer(strdup(jsonString))
+        }
+    } catch {
+        return nil
+    }
+
+    return nil
+}
+
+@_cdecl("free_disk_string")
+func freeDiskString(_ ptr: UnsafePointer<CChar>?) {
+    if let ptr = ptr {
+        free(UnsafeMutableRawPointer(mutating: ptr))
+    }
+}
@@ -0,0 +1,58 @@
+//! Disk I/O collector.
+//!
+//! Byte and operation counters are exported as cumulative totals since boot, one
+//! set per device (`device` metadata, e.g. `disk0`). Rates are derived at query
+//! time by pairing consecutive samples, e.g.:
+//!
+//! ```sql
+//! SELECT
+//!     json_extract(metadata, '$.device') AS device,
+//!     timestamp,
+//!     (value_int - LAG(value_int) OVER w) * 1000.0
+//!         / (timestamp - LAG(timestamp) OVER w) AS read_bytes_per_second
+//! FROM metrics
+//! WHERE name = 'disk.read_bytes'
+//! WINDOW w AS (PARTITION BY json_extract(metadata, '$.device') ORDER BY timestamp)
+//! ```
+
+pub mod macos;
+
+use super::{Collector, Metric};
+use crate::ThrudError;
+
+pub struct DiskCollector {
+    #[cfg(target_os = "macos")]
+    macos: macos::MacOSDiskCollector,
+}
+
+impl Default for DiskCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiskCollector {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(target_os = "macos")]
+            macos: macos::MacOSDiskCollector::new(),
+        }
+    }
+}
+
+impl Collector for DiskCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "macos")]
+        {
+            self.macos.collect()
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Ok(vec![])
+        }
+    }
+
+    fn name(&self) -> &str {
+        "disk"
+    }
+}
@@ -0,0 +1,138 @@
+use super::{names, Metric};
+
+/// Selects metrics by name using glob patterns.
+///
+/// `*` matches any run of characters (including dots) and `?` matches exactly
+/// one. A metric is kept when it matches at least one include pattern (or no
+/// include patterns are given) and no exclude pattern, so excludes win when both
+/// match.
+///
+/// Patterns that are metric name aliases (see [`super::names::ALIASES`]) select
+/// the canonical name instead.
+#[derive(Debug, Clone, Default)]
+pub struct MetricFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl MetricFilter {
+    pub fn new(include: Vec<String>, exclude: Vec<String>) -> Self {
+        let canonical = |patterns: Vec<String>| -> Vec<String> {
+            patterns.iter().map(|p| names::canonical(p).to_string()).collect()
+        };
+        Self { include: canonical(include), exclude: canonical(exclude) }
+    }
+
+    /// Whether the filter keeps every metric
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether a metric with this name passes the filter
+    pub fn allows(&self, name: &str) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|p| glob_match(p, name));
+        included && !self.exclude.iter().any(|p| glob_match(p, name))
+    }
+
+    /// Drop the metrics that don't pass the filter
+    pub fn apply(&self, metrics: Vec<Metric>) -> Vec<Metric> {
+        if self.is_empty() {
+            return metrics;
+        }
+        metrics.into_iter().filter(|m| self.allows(&m.name)).collect()
+    }
+}
+
+/// Match `text` against a glob `pattern` supporting `*` and `?`
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it's currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` absorb one more character and retry
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> MetricFilter {
+        let owned = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect();
+        MetricFilter::new(owned(include), owned(exclude))
+    }
+
+    #[test]
+    fn globs_match_across_dots_and_single_characters() {
+        assert!(glob_match("cpu.*", "cpu.performance_core.3.idle_ticks"));
+        assert!(glob_match("gpu_utilization.?", "gpu_utilization.1"));
+        assert!(!glob_match("gpu_utilization.?", "gpu_utilization.10"));
+        assert!(glob_match("*.idle_ticks", "cpu.efficiency.idle_ticks"));
+        assert!(!glob_match("cpu.*", "gpu_utilization"));
+    }
+
+    #[test]
+    fn include_keeps_only_matching_metrics() {
+        let filter = filter(&["cpu.*", "memory.*"], &[]);
+        assert!(filter.allows("cpu.performance.total_ticks"));
+        assert!(filter.allows("memory.used_bytes"));
+        assert!(!filter.allows("gpu_utilization"));
+    }
+
+    #[test]
+    fn exclude_drops_matching_metrics() {
+        let filter = filter(&[], &["process.*"]);
+        assert!(filter.allows("gpu_utilization"));
+        assert!(!filter.allows("process.cpu_percent"));
+    }
+
+    #[test]
+    fn exclude_overrides_include() {
+        let filter = filter(&["cpu.*"], &["cpu.*_core.*"]);
+        assert!(filter.allows("cpu.performance.idle_ticks"));
+        assert!(!filter.allows("cpu.performance_core.0.idle_ticks"));
+
+        let metrics = vec![
+            Metric::counter("cpu.performance.idle_ticks", 10),
+            Metric::counter("cpu.performance_core.0.idle_ticks", 5),
+            Metric::gauge("gpu_utilization", 0.5),
+        ];
+        let kept: Vec<String> = filter.apply(metrics).into_iter().map(|m| m.name).collect();
+        assert_eq!(kept, ["cpu.performance.idle_ticks"]);
+    }
+
+    #[test]
+    fn aliases_select_the_canonical_name() {
+        let filter = filter(&["cpu.p.idle_ticks"], &[]);
+        assert!(filter.allows(names::CPU_PERFORMANCE_IDLE_TICKS));
+    }
+
+    #[test]
+    fn empty_filter_keeps_everything() {
+        assert!(filter(&[], &[]).is_empty());
+        assert!(filter(&[], &[]).allows("anything.at.all"));
+    }
+}
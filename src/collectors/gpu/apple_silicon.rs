@@ -1,5 +1,9 @@
-use crate::collectors::{Collector, Metric};
+use crate::collectors::{names, parse_bridge_json, Collector, Metric, MetricValue};
+use std::collections::HashMap;
+use crate::ThrudError;
+#[cfg(target_os = "macos")]
 use std::ffi::CStr;
+#[cfg(target_os = "macos")]
 use std::os::raw::c_char;
 
 #[cfg(target_os = "macos")]
@@ -10,11 +14,23 @@ extern "C" {
 
 #[derive(Debug, serde::Deserialize)]
 struct GPUInfo {
+    name: Option<String>,
     utilization: Option<f64>,
+    /// Memory in use, when the GPU reports it
+    memory_used_bytes: Option<i64>,
+    /// Memory allocated to an Apple Silicon GPU out of system memory, or the VRAM
+    /// of a discrete GPU, when reported
+    memory_total_bytes: Option<i64>,
 }
 
 pub struct AppleSiliconGPUCollector;
 
+impl Default for AppleSiliconGPUCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AppleSiliconGPUCollector {
     pub fn new() -> Self {
         Self
@@ -29,23 +45,42 @@ impl AppleSiliconGPUCollector {
         }
 
         let json_str = unsafe {
-            CStr::from_ptr(json_ptr).to_str()?
+            CStr::from_ptr(json_ptr).to_string_lossy().into_owned()
         };
 
-        let gpu_infos: Vec<GPUInfo> = serde_json::from_str(json_str)?;
-        
         unsafe {
             free_string(json_ptr);
         }
 
+        self.parse_metrics(&json_str)
+    }
+
+    /// Convert the bridge JSON payload into utilization and memory metrics
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn parse_metrics(&self, json_str: &str) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let gpu_infos: Vec<GPUInfo> = parse_bridge_json("GPU", json_str)?;
+
         let mut metrics = Vec::new();
 
         for (index, gpu) in gpu_infos.iter().enumerate() {
+            let tagged = |metric: Metric| {
+                let metric = metric.with_tag("gpu_index", index.to_string());
+                match &gpu.name {
+                    Some(name) => metric.with_tag("gpu_name", name),
+                    None => metric,
+                }
+            };
+
             if let Some(utilization) = gpu.utilization {
-                metrics.push(Metric::new(
-                    format!("gpu.{}.utilization", index),
-                    utilization.to_string(),
-                ));
+                metrics.push(tagged(Metric::gauge(names::gpu_utilization(index), utilization).with_unit("ratio")));
+            }
+            if let Some(used) = gpu.memory_used_bytes {
+                let metric = Metric::new(names::gpu_memory_used_bytes(index), MetricValue::Integer(used), HashMap::new());
+                metrics.push(tagged(metric.with_unit("bytes")));
+            }
+            if let Some(total) = gpu.memory_total_bytes {
+                let metric = Metric::new(names::gpu_memory_total_bytes(index), MetricValue::Integer(total), HashMap::new());
+                metrics.push(tagged(metric.with_unit("bytes")));
             }
         }
 
@@ -60,18 +95,52 @@ impl AppleSiliconGPUCollector {
 }
 
 impl Collector for AppleSiliconGPUCollector {
-    fn collect(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
         #[cfg(target_os = "macos")]
         {
-            self.collect_macos()
+            Ok(self.collect_macos()?)
         }
         #[cfg(not(target_os = "macos"))]
         {
-            self.collect_other()
+            Ok(self.collect_other()?)
         }
     }
 
     fn name(&self) -> &str {
         "apple_silicon_gpu"
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metric<'a>(metrics: &'a [Metric], name: &str) -> Option<&'a Metric> {
+        metrics.iter().find(|m| m.name == name)
+    }
+
+    #[test]
+    fn parses_memory_fields_when_reported() {
+        let json = r#"[{"name": "Apple M2", "utilization": 0.5, "memory_used_bytes": 1073741824, "memory_total_bytes": 17179869184}]"#;
+        let metrics = AppleSiliconGPUCollector::new().parse_metrics(json).unwrap();
+
+        assert_eq!(metrics.len(), 3);
+        let used = metric(&metrics, &names::gpu_memory_used_bytes(0)).unwrap();
+        assert_eq!(used.value, MetricValue::Integer(1_073_741_824));
+        assert_eq!(used.unit.as_deref(), Some("bytes"));
+        let total = metric(&metrics, &names::gpu_memory_total_bytes(0)).unwrap();
+        assert_eq!(total.value, MetricValue::Integer(17_179_869_184));
+        assert_eq!(total.unit.as_deref(), Some("bytes"));
+        assert!(metrics.iter().all(|m| m.metadata["gpu_index"] == "0" && m.metadata["gpu_name"] == "Apple M2"));
+    }
+
+    #[test]
+    fn memory_fields_are_optional() {
+        let json = r#"[{"name": "Apple M2", "utilization": 0.5}, {"utilization": 0.25, "memory_used_bytes": 1024}]"#;
+        let metrics = AppleSiliconGPUCollector::new().parse_metrics(json).unwrap();
+
+        let metric_names: Vec<&str> = metrics.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(metric_names, [names::gpu_utilization(0), names::gpu_utilization(1), names::gpu_memory_used_bytes(1)]);
+        assert!(!metrics[2].metadata.contains_key("gpu_name"));
+    }
+}
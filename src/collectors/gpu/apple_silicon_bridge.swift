@@ -4,6 +4,8 @@ import IOKit
 struct GPUInfo {
     let name: String
     let utilization: Double?
+    let memoryUsedBytes: Int64?
+    let memoryTotalBytes: Int64?
 }
 
 func fetchIOService(_ name: String) -> [NSDictionary]? {
@@ -80,8 +82,25 @@ func collectGPUMetrics() -> [GPUInfo] {
         // Get utilization percentage
         let utilization: Int? = stats["Device Utilization %"] as? Int ?? stats["GPU Activity(%)"] as? Int
         let utilizationPercent = utilization != nil ? Double(utilization!) / 100.0 : nil
-        
-        gpuInfos.append(GPUInfo(name: gpuName, utilization: utilizationPercent))
+
+        // Apple Silicon GPUs share system memory and report what they have allocated
+        // and what is in use of it; discrete GPUs report used and free VRAM
+        let inUse = (stats["In use system memory"] as? NSNumber)?.int64Value
+        let allocated = (stats["Alloc system memory"] as? NSNumber)?.int64Value
+        let vramUsed = (stats["vramUsedBytes"] as? NSNumber)?.int64Value
+        let vramFree = (stats["vramFreeBytes"] as? NSNumber)?.int64Value
+        let memoryUsed = inUse ?? vramUsed
+        var memoryTotal = allocated
+        if memoryTotal == nil, let used = vramUsed, let free = vramFree {
+            memoryTotal = used + free
+        }
+
+        gpuInfos.append(GPUInfo(
+            name: gpuName,
+            utilization: utilizationPercent,
+            memoryUsedBytes: memoryUsed,
+            memoryTotalBytes: memoryTotal
+        ))
     }
     
     return gpuInfos
@@ -102,6 +121,12 @@ func collectGPUMetricsJSON() -> UnsafePointer<CChar>? {
         if let utilization = gpu.utilization {
             jsonGPU["utilization"] = utilization
         }
+        if let memoryUsed = gpu.memoryUsedBytes {
+            jsonGPU["memory_used_bytes"] = memoryUsed
+        }
+        if let memoryTotal = gpu.memoryTotalBytes {
+            jsonGPU["memory_total_bytes"] = memoryTotal
+        }
         
         jsonArray.append(jsonGPU)
     }
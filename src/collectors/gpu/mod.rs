@@ -1,12 +1,19 @@
 pub mod apple_silicon;
 
 use super::{Collector, Metric};
+use crate::ThrudError;
 
 pub struct GPUCollector {
     #[cfg(target_os = "macos")]
     apple_silicon: apple_silicon::AppleSiliconGPUCollector,
 }
 
+impl Default for GPUCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GPUCollector {
     pub fn new() -> Self {
         Self {
@@ -17,7 +24,7 @@ impl GPUCollector {
 }
 
 impl Collector for GPUCollector {
-    fn collect(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
         #[cfg(target_os = "macos")]
         {
             self.apple_silicon.collect()
@@ -0,0 +1,86 @@
+import Foundation
+
+// IOReport is a private framework without headers; declare the entry points the
+// bridges use. build.rs compiles this file into every bridge that samples IOReport.
+@_silgen_name("IOReportCopyChannelsInGroup")
+func IOReportCopyChannelsInGroup(_ group: CFString?, _ subgroup: CFString?, _ a: UInt64, _ b: UInt64, _ c: UInt64) -> Unmanaged<CFMutableDictionary>?
+
+@_silgen_name("IOReportCreateSubscription")
+func IOReportCreateSubscription(_ a: UnsafeMutableRawPointer?, _ channels: CFMutableDictionary, _ subscribed: UnsafeMutablePointer<Unmanaged<CFMutableDictionary>?>, _ b: UInt64, _ c: CFTypeRef?) -> OpaquePointer?
+
+@_silgen_name("IOReportCreateSamples")
+func IOReportCreateSamples(_ subscription: OpaquePointer, _ channels: CFMutableDictionary, _ a: CFTypeRef?) -> Unmanaged<CFDictionary>?
+
+@_silgen_name("IOReportCreateSamplesDelta")
+func IOReportCreateSamplesDelta(_ previous: CFDictionary, _ current: CFDictionary, _ a: CFTypeRef?) -> Unmanaged<CFDictionary>?
+
+@_silgen_name("IOReportChannelGetChannelName")
+func IOReportChannelGetChannelName(_ channel: CFDictionary) -> Unmanaged<CFString>?
+
+@_silgen_name("IOReportChannelGetUnitLabel")
+func IOReportChannelGetUnitLabel(_ channel: CFDictionary) -> Unmanaged<CFString>?
+
+@_silgen_name("IOReportSimpleGetIntegerValue")
+func IOReportSimpleGetIntegerValue(_ channel: CFDictionary, _ index: Int32) -> Int64
+
+@_silgen_name("IOReportStateGetCount")
+func IOReportStateGetCount(_ channel: CFDictionary) -> Int32
+
+@_silgen_name("IOReportStateGetNameForIndex")
+func IOReportStateGetNameForIndex(_ channel: CFDictionary, _ index: Int32) -> Unmanaged<CFString>?
+
+@_silgen_name("IOReportStateGetResidency")
+func IOReportStateGetResidency(_ channel: CFDictionary, _ index: Int32) -> Int64
+
+/// A sample of subscribed channels, with what they accumulated since the previous one
+struct IOReportSample {
+    let current: CFDictionary
+    /// nil for the first sample of a subscription
+    let delta: CFDictionary?
+    /// Seconds since the previous sample, 0 for the first one
+    let elapsed: TimeInterval
+}
+
+/// A subscription to an IOReport channel group that remembers its last sample, so
+/// rates and residencies cover the time since the previous collection round
+/// instead of a sleep inside the current one.
+final class IOReportSampler {
+    private let subscription: OpaquePointer
+    private let channels: CFMutableDictionary
+    private var previous: (sample: CFDictionary, time: Date)?
+    private let lock = NSLock()
+
+    init?(group: String, subgroup: String? = nil) {
+        guard let available = IOReportCopyChannelsInGroup(group as CFString, subgroup as CFString?, 0, 0, 0)?.takeRetainedValue() else {
+            return nil
+        }
+
+        var subscribed: Unmanaged<CFMutableDictionary>?
+        guard let subscription = IOReportCreateSubscription(nil, available, &subscribed, 0, nil),
+              let channels = subscribed?.takeRetainedValue() else {
+            return nil
+        }
+
+        self.subscription = subscription
+        self.channels = channels
+    }
+
+    func sample() -> IOReportSample? {
+        lock.lock()
+        defer { lock.unlock() }
+
+        guard let current = IOReportCreateSamples(subscription, channels, nil)?.takeRetainedValue() else {
+            return nil
+        }
+        let now = Date()
+
+        var result = IOReportSample(current: current, delta: nil, elapsed: 0)
+        if let previous = previous,
+           let delta = IOReportCreateSamplesDelta(previous.sample, current, nil)?.takeRetainedValue() {
+            result = IOReportSample(current: current, delta: delta, elapsed: now.timeIntervalSince(previous.time))
+        }
+
+        previous = (current, now)
+        return result
+    }
+}
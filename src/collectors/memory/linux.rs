@@ -0,0 +1,123 @@
+use crate::collectors::{names, Collector, Metric, MetricValue};
+use crate::ThrudError;
+use std::collections::HashMap;
+
+const PROC_MEMINFO: &str = "/proc/meminfo";
+
+/// Memory usage read from `/proc/meminfo`.
+///
+/// Used memory is `MemTotal - MemAvailable`, so reclaimable page cache counts as
+/// available. meminfo reports sizes in kB (KiB), which are converted to bytes.
+pub struct LinuxMemoryCollector;
+
+impl Default for LinuxMemoryCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinuxMemoryCollector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Convert `/proc/meminfo` contents into memory metrics
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn parse_metrics(&self, contents: &str) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let mut fields: HashMap<&str, i64> = HashMap::new();
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let mut parts = value.split_whitespace();
+            let (Some(amount), unit) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            let amount: i64 = amount
+                .parse()
+                .map_err(|_| format!("Invalid value for {} in {}: {}", key, PROC_MEMINFO, value.trim()))?;
+            let bytes = match unit {
+                Some("kB") => amount * 1024,
+                None => amount,
+                Some(other) => return Err(format!("Unknown unit for {} in {}: {}", key, PROC_MEMINFO, other).into()),
+            };
+            fields.insert(key.trim(), bytes);
+        }
+
+        let field = |key: &str| -> Result<i64, Box<dyn std::error::Error>> {
+            fields
+                .get(key)
+                .copied()
+                .ok_or_else(|| format!("Missing {} in {}", key, PROC_MEMINFO).into())
+        };
+
+        let total = field("MemTotal")?;
+        let available = field("MemAvailable")?;
+        let swap_used = field("SwapTotal")? - field("SwapFree")?;
+
+        Ok([
+            (names::MEMORY_TOTAL_BYTES, total),
+            (names::MEMORY_AVAILABLE_BYTES, available),
+            (names::MEMORY_USED_BYTES, total - available),
+            (names::MEMORY_SWAP_USED_BYTES, swap_used),
+        ]
+        .into_iter()
+        .map(|(name, bytes)| {
+            Metric::new(name.to_string(), MetricValue::Integer(bytes), HashMap::new()).with_unit("bytes")
+        })
+        .collect())
+    }
+}
+
+impl Collector for LinuxMemoryCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "linux")]
+        {
+            let contents = std::fs::read_to_string(PROC_MEMINFO)?;
+            Ok(self.parse_metrics(&contents)?)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(vec![])
+        }
+    }
+
+    fn name(&self) -> &str {
+        "linux_memory"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMINFO_SNIPPET: &str = "\
+MemTotal:       16326428 kB
+MemFree:         1210548 kB
+MemAvailable:    9012332 kB
+Buffers:          412380 kB
+Cached:          7034100 kB
+SwapTotal:       2097148 kB
+SwapFree:        1572860 kB
+HugePages_Total:       0
+";
+
+    #[test]
+    fn kilobytes_are_converted_to_bytes() {
+        let metrics = LinuxMemoryCollector::new().parse_metrics(MEMINFO_SNIPPET).unwrap();
+        let value = |name: &str| metrics.iter().find(|m| m.name == name).map(|m| m.value.clone());
+
+        assert_eq!(value(names::MEMORY_TOTAL_BYTES), Some(MetricValue::Integer(16326428 * 1024)));
+        assert_eq!(value(names::MEMORY_AVAILABLE_BYTES), Some(MetricValue::Integer(9012332 * 1024)));
+        assert_eq!(value(names::MEMORY_USED_BYTES), Some(MetricValue::Integer((16326428 - 9012332) * 1024)));
+        assert_eq!(value(names::MEMORY_SWAP_USED_BYTES), Some(MetricValue::Integer((2097148 - 1572860) * 1024)));
+        assert!(metrics.iter().all(|m| m.unit.as_deref() == Some("bytes")));
+    }
+
+    #[test]
+    fn missing_fields_and_unknown_units_are_errors() {
+        let collector = LinuxMemoryCollector::new();
+        assert!(collector.parse_metrics("MemTotal: 1024 kB\n").is_err());
+        assert!(collector.parse_metrics(&MEMINFO_SNIPPET.replace("SwapFree:        1572860 kB", "SwapFree: 1 MB")).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+//! Memory collector.
+//!
+//! Reports total, available and used physical memory plus used swap, all in
+//! bytes. Only Linux is supported so far; other platforms report nothing.
+
+pub mod linux;
+
+use super::{Collector, Metric};
+use crate::ThrudError;
+
+pub struct MemoryCollector {
+    #[cfg(target_os = "linux")]
+    linux: linux::LinuxMemoryCollector,
+}
+
+impl Default for MemoryCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryCollector {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(target_os = "linux")]
+            linux: linux::LinuxMemoryCollector::new(),
+        }
+    }
+}
+
+impl Collector for MemoryCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "linux")]
+        {
+            self.linux.collect()
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(vec![])
+        }
+    }
+
+    fn name(&self) -> &str {
+        "memory"
+    }
+}
@@ -1,7 +1,385 @@
 pub mod types;
-pub mod gpu;
-pub mod cpu;
+pub mod schedule;
+pub mod filter;
+pub mod names;
+pub mod throttle;
+pub mod transform;
 
 pub use types::*;
-pub use gpu::GPUCollector;
-pub use cpu::CPUCollector;
\ No newline at end of file
+pub use schedule::Schedule;
+pub use filter::MetricFilter;
+pub use throttle::ErrorThrottle;
+pub use transform::{MetricTransform, TransformRule};
+
+/// Declare the built-in collectors as `module::Type`: each module is declared
+/// and its collector re-exported, and the collector is added to
+/// [`BUILTIN_COLLECTORS`], so [`CollectorRegistry::new`] and every binary pick it
+/// up. Adding a collector takes one line here; the type needs a `new()`
+/// constructor.
+macro_rules! register_collector {
+    ($($module:ident::$collector:ident),* $(,)?) => {
+        $(
+            pub mod $module;
+            pub use $module::$collector;
+        )*
+
+        /// Constructors of the built-in collectors, as registered by [`CollectorRegistry::new`]
+        pub const BUILTIN_COLLECTORS: &[fn() -> Box<dyn Collector>] = &[$(|| Box::new($collector::new())),*];
+    };
+}
+
+register_collector! {
+    gpu::GPUCollector,
+    cpu::CPUCollector,
+    disk::DiskCollector,
+    ane::ANECollector,
+    power::PowerCollector,
+    thermal::ThermalCollector,
+    sensors::SensorsCollector,
+    memory::MemoryCollector,
+    process::ProcessCollector,
+}
+
+use crate::ThrudError;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Outcome of running a single collector
+pub type CollectorResult = Result<Vec<Metric>, ThrudError>;
+
+/// Longest excerpt of a malformed bridge payload quoted in its parse error
+const BRIDGE_EXCERPT_CHARS: usize = 80;
+
+/// Parse the JSON payload of a Swift bridge. A malformed payload, e.g. output cut
+/// short, fails only the collector that read it; the error names the bridge and
+/// quotes the start of the payload so garbage can be told apart from a field
+/// the bridge stopped sending.
+pub(crate) fn parse_bridge_json<T: serde::de::DeserializeOwned>(bridge: &str, json: &str) -> Result<T, Box<dyn std::error::Error>> {
+    serde_json::from_str(json).map_err(|e| {
+        let excerpt: String = json.chars().take(BRIDGE_EXCERPT_CHARS).collect();
+        let truncated = if json.chars().count() > BRIDGE_EXCERPT_CHARS { "..." } else { "" };
+        format!("Malformed JSON from the {} bridge: {} (output: {:?}{})", bridge, e, excerpt, truncated).into()
+    })
+}
+
+/// Collectors keyed by `Collector::name`, so they can be enabled by name
+pub struct CollectorRegistry {
+    collectors: HashMap<String, Arc<dyn Collector>>,
+}
+
+impl Default for CollectorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CollectorRegistry {
+    /// Create a registry with all built-in collectors ([`BUILTIN_COLLECTORS`]) registered
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        for collector in BUILTIN_COLLECTORS {
+            registry.register(collector());
+        }
+        registry
+    }
+
+    /// Create a registry with no collectors registered
+    pub fn empty() -> Self {
+        Self {
+            collectors: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, collector: Box<dyn Collector>) {
+        self.collectors.insert(collector.name().to_string(), Arc::from(collector));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Collector> {
+        self.collectors.get(name).map(|c| c.as_ref())
+    }
+
+    /// Shared handle to a collector, for running it on another thread
+    pub fn get_shared(&self, name: &str) -> Option<Arc<dyn Collector>> {
+        self.collectors.get(name).cloned()
+    }
+
+    /// Run [`Collector::init`] on the named collectors in name order, stopping at
+    /// the first failure. Must be called before any of them is handed out by
+    /// [`Self::get_shared`].
+    pub fn init(&mut self, enabled: &[&str]) -> Result<(), ThrudError> {
+        for name in self.enabled_names(enabled) {
+            let collector = self.collectors.get_mut(&name).and_then(Arc::get_mut).ok_or_else(|| {
+                ThrudError::collector_failed(&name, "cannot initialize a collector that is in use")
+            })?;
+            collector.init().map_err(|e| ThrudError::collector_failed(&name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Run [`Collector::shutdown`] on the named collectors in name order. A
+    /// collector still running on another thread (e.g. one stuck past its timeout)
+    /// can't be shut down; the names of any such collectors are returned.
+    pub fn shutdown(&mut self, enabled: &[&str]) -> Vec<String> {
+        let mut busy = Vec::new();
+        for name in self.enabled_names(enabled) {
+            match self.collectors.get_mut(&name).and_then(Arc::get_mut) {
+                Some(collector) => collector.shutdown(),
+                None => busy.push(name),
+            }
+        }
+        busy
+    }
+
+    /// Registered names among `enabled`, sorted
+    fn enabled_names(&self, enabled: &[&str]) -> Vec<String> {
+        self.names()
+            .into_iter()
+            .filter(|name| enabled.contains(name))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Registered collector names, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.collectors.keys().map(|n| n.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    /// Run every registered collector, dropping the output of any that fail
+    pub fn collect_all(&self) -> Vec<Metric> {
+        Self::flatten(self.collect_each(&self.names()))
+    }
+
+    /// Run only the named collectors, dropping the output of any that fail
+    pub fn collect_enabled(&self, enabled: &[&str]) -> Vec<Metric> {
+        Self::flatten(self.collect_each(enabled))
+    }
+
+    /// Run the named collectors in name order and return each one's result, so
+    /// callers can report failures. Names that aren't registered are skipped, and
+    /// failures are reported as [`ThrudError::CollectorFailed`].
+    pub fn collect_each(&self, enabled: &[&str]) -> Vec<(&str, CollectorResult)> {
+        self.names()
+            .into_iter()
+            .filter(|name| enabled.contains(name))
+            .map(|name| {
+                let result = self.collectors[name]
+                    .collect()
+                    .map_err(|e| ThrudError::collector_failed(name, e));
+                (name, result)
+            })
+            .collect()
+    }
+
+    fn flatten(results: Vec<(&str, CollectorResult)>) -> Vec<Metric> {
+        results
+            .into_iter()
+            .filter_map(|(_, result)| result.ok())
+            .flatten()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts its `collect` calls and returns one metric, or fails if `failing`
+    struct Fake {
+        name: &'static str,
+        failing: bool,
+        collects: Arc<AtomicUsize>,
+    }
+
+    impl Fake {
+        fn counted(name: &'static str) -> (Box<dyn Collector>, Arc<AtomicUsize>) {
+            let collects = Arc::new(AtomicUsize::new(0));
+            (Box::new(Self { name, failing: false, collects: collects.clone() }), collects)
+        }
+    }
+
+    impl Collector for Fake {
+        fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+            self.collects.fetch_add(1, Ordering::SeqCst);
+            if self.failing {
+                return Err(ThrudError::Other("bridge unavailable".to_string()));
+            }
+            Ok(vec![Metric::gauge(format!("{}.value", self.name), 1.0)])
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    fn names(metrics: &[Metric]) -> Vec<&str> {
+        metrics.iter().map(|m| m.name.as_str()).collect()
+    }
+
+    #[test]
+    fn only_enabled_collectors_run() {
+        let mut registry = CollectorRegistry::empty();
+        let (a, a_collects) = Fake::counted("a");
+        let (b, b_collects) = Fake::counted("b");
+        registry.register(a);
+        registry.register(b);
+
+        let metrics = registry.collect_enabled(&["b", "not_registered"]);
+
+        assert_eq!(names(&metrics), ["b.value"]);
+        assert_eq!(a_collects.load(Ordering::SeqCst), 0);
+        assert_eq!(b_collects.load(Ordering::SeqCst), 1);
+
+        assert_eq!(names(&registry.collect_all()), ["a.value", "b.value"]);
+        assert_eq!(a_collects.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn registering_a_name_again_replaces_the_collector() {
+        let mut registry = CollectorRegistry::empty();
+        let (first, first_collects) = Fake::counted("a");
+        let (second, second_collects) = Fake::counted("a");
+        registry.register(first);
+        registry.register(second);
+
+        assert_eq!(registry.names(), ["a"]);
+        registry.collect_all();
+        assert_eq!(first_collects.load(Ordering::SeqCst), 0);
+        assert_eq!(second_collects.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn failures_are_reported_per_collector() {
+        let mut registry = CollectorRegistry::empty();
+        let (ok, _) = Fake::counted("ok");
+        registry.register(ok);
+        registry.register(Box::new(Fake { name: "broken", failing: true, collects: Arc::default() }));
+
+        let results = registry.collect_each(&["ok", "broken"]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "broken");
+        assert!(matches!(&results[0].1, Err(ThrudError::CollectorFailed { .. })));
+        assert!(results[1].1.is_ok());
+
+        // The failing collector's output is dropped, not the round
+        assert_eq!(names(&registry.collect_all()), ["ok.value"]);
+    }
+
+    /// Counts its `init` and `shutdown` calls; `init` fails if `failing`
+    #[derive(Default)]
+    struct Lifecycle {
+        name: &'static str,
+        failing: bool,
+        inits: Arc<AtomicUsize>,
+        shutdowns: Arc<AtomicUsize>,
+    }
+
+    impl Collector for Lifecycle {
+        fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+            Ok(Vec::new())
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn init(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.inits.fetch_add(1, Ordering::SeqCst);
+            if self.failing {
+                return Err("no device".into());
+            }
+            Ok(())
+        }
+
+        fn shutdown(&mut self) {
+            self.shutdowns.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn init_and_shutdown_run_once_per_enabled_collector() {
+        let mut registry = CollectorRegistry::empty();
+        let enabled = Lifecycle { name: "enabled", ..Default::default() };
+        let disabled = Lifecycle { name: "disabled", ..Default::default() };
+        let (inits, shutdowns) = (enabled.inits.clone(), enabled.shutdowns.clone());
+        let (disabled_inits, disabled_shutdowns) = (disabled.inits.clone(), disabled.shutdowns.clone());
+        registry.register(Box::new(enabled));
+        registry.register(Box::new(disabled));
+
+        registry.init(&["enabled"]).unwrap();
+        registry.collect_enabled(&["enabled"]);
+        assert!(registry.shutdown(&["enabled"]).is_empty());
+
+        assert_eq!(inits.load(Ordering::SeqCst), 1);
+        assert_eq!(shutdowns.load(Ordering::SeqCst), 1);
+        assert_eq!(disabled_inits.load(Ordering::SeqCst), 0);
+        assert_eq!(disabled_shutdowns.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn failed_init_names_the_collector() {
+        let mut registry = CollectorRegistry::empty();
+        registry.register(Box::new(Lifecycle { name: "broken", failing: true, ..Default::default() }));
+
+        let error = registry.init(&["broken"]).unwrap_err();
+        assert!(matches!(error, ThrudError::CollectorFailed { .. }));
+        assert!(error.to_string().contains("broken"), "{}", error);
+    }
+
+    #[test]
+    fn collector_in_use_is_not_shut_down() {
+        let mut registry = CollectorRegistry::empty();
+        let collector = Lifecycle { name: "stuck", ..Default::default() };
+        let shutdowns = collector.shutdowns.clone();
+        registry.register(Box::new(collector));
+
+        let running = registry.get_shared("stuck").unwrap();
+        assert_eq!(registry.shutdown(&["stuck"]), ["stuck"]);
+        assert_eq!(shutdowns.load(Ordering::SeqCst), 0);
+
+        drop(running);
+        assert!(registry.shutdown(&["stuck"]).is_empty());
+        assert_eq!(shutdowns.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn builtin_collectors_are_registered_by_default() {
+        let registry = CollectorRegistry::new();
+
+        assert!(registry.get("cpu").is_some());
+        assert!(registry.get("gpu").is_some());
+        assert_eq!(registry.names().len(), BUILTIN_COLLECTORS.len());
+        assert!(CollectorRegistry::empty().names().is_empty());
+    }
+
+    #[test]
+    fn malformed_bridge_json_quotes_a_bounded_excerpt() {
+        let payload = format!("[{{\"utilization\": 0.5}}, {}", "x".repeat(200));
+
+        let error = parse_bridge_json::<Vec<serde_json::Value>>("GPU", &payload).unwrap_err().to_string();
+
+        assert!(error.starts_with("Malformed JSON from the GPU bridge: "), "{}", error);
+        let excerpt: String = payload.chars().take(BRIDGE_EXCERPT_CHARS).collect();
+        assert!(error.contains(&format!("{:?}...", excerpt)), "{}", error);
+        assert!(!error.contains(&"x".repeat(BRIDGE_EXCERPT_CHARS)), "{}", error);
+    }
+
+    #[test]
+    fn short_malformed_bridge_json_is_quoted_whole() {
+        let error = parse_bridge_json::<Vec<serde_json::Value>>("CPU", "[{\"core\": 1").unwrap_err().to_string();
+
+        assert!(error.contains("CPU bridge"), "{}", error);
+        assert!(error.ends_with(r#"(output: "[{\"core\": 1")"#), "{}", error);
+    }
+
+    #[test]
+    fn excerpt_is_cut_on_a_character_boundary() {
+        let payload = "é".repeat(100);
+
+        let error = parse_bridge_json::<serde_json::Value>("ANE", &payload).unwrap_err().to_string();
+
+        assert!(error.contains(&format!("{:?}...", "é".repeat(BRIDGE_EXCERPT_CHARS))), "{}", error);
+    }
+}
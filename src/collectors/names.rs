@@ -0,0 +1,185 @@
+//! Canonical metric names.
+//!
+//! Collectors build the names they emit from this module, and chart generation
+//! and aggregations look them up here, so a rename only has to happen in one
+//! place. Other spellings that users may type are resolved through [`canonical`].
+
+/// Core type of Apple Silicon performance cores (and of every Linux core)
+pub const PERFORMANCE: &str = "performance";
+/// Core type of Apple Silicon efficiency cores
+pub const EFFICIENCY: &str = "efficiency";
+
+/// Cumulative idle ticks counter suffix
+pub const IDLE_TICKS: &str = "idle_ticks";
+/// Cumulative total ticks counter suffix
+pub const TOTAL_TICKS: &str = "total_ticks";
+
+pub const CPU_PERFORMANCE_IDLE_TICKS: &str = "cpu.performance.idle_ticks";
+pub const CPU_PERFORMANCE_TOTAL_TICKS: &str = "cpu.performance.total_ticks";
+pub const CPU_EFFICIENCY_IDLE_TICKS: &str = "cpu.efficiency.idle_ticks";
+pub const CPU_EFFICIENCY_TOTAL_TICKS: &str = "cpu.efficiency.total_ticks";
+pub const CPU_CLUSTER_FREQUENCY_MHZ: &str = "cpu.cluster.frequency_mhz";
+/// GLOB pattern matching every per-core tick counter
+pub const CPU_CORE_TICKS_GLOB: &str = "cpu.*_core.*_ticks";
+
+/// GLOB pattern matching every per-GPU utilization ratio
+pub const GPU_UTILIZATION_GLOB: &str = "gpu.*.utilization";
+/// GLOB pattern matching every per-GPU temperature, in degrees Celsius
+pub const GPU_TEMPERATURE_GLOB: &str = "gpu.*.temperature";
+/// GLOB pattern matching every per-GPU busy ticks counter
+pub const GPU_BUSY_TICKS_GLOB: &str = "gpu.*.busy_ticks";
+/// GLOB pattern matching every per-GPU total ticks counter
+pub const GPU_TOTAL_TICKS_GLOB: &str = "gpu.*.total_ticks";
+/// GLOB pattern matching every per-GPU memory in use, in bytes
+pub const GPU_MEMORY_USED_GLOB: &str = "gpu.*.memory_used_bytes";
+/// GLOB pattern matching every per-GPU memory total, in bytes
+pub const GPU_MEMORY_TOTAL_GLOB: &str = "gpu.*.memory_total_bytes";
+
+pub const MEMORY_TOTAL_BYTES: &str = "memory.total_bytes";
+pub const MEMORY_AVAILABLE_BYTES: &str = "memory.available_bytes";
+pub const MEMORY_USED_BYTES: &str = "memory.used_bytes";
+pub const MEMORY_SWAP_USED_BYTES: &str = "memory.swap_used_bytes";
+pub const MEMORY_COMPRESSED_BYTES: &str = "memory.compressed_bytes";
+
+/// Thermal pressure level, 0 (nominal) to 3 (critical)
+pub const THERMAL_PRESSURE_LEVEL: &str = "thermal.pressure_level";
+/// Whether the thermal pressure level is high enough for the OS to throttle
+pub const THERMAL_CPU_THROTTLED: &str = "thermal.cpu_throttled";
+
+/// CPU usage of one process since its previous sample, in percent of one core
+pub const PROCESS_CPU_PERCENT: &str = "proc.cpu_percent";
+/// Resident memory of one process
+pub const PROCESS_RSS_BYTES: &str = "proc.rss_bytes";
+
+/// Time one collector's `collect()` call took, added to the round by the
+/// collector binary with `collector` metadata
+pub const COLLECTOR_DURATION_MS: &str = "collector.duration_ms";
+
+/// Alternative spellings and the canonical names they stand for
+pub const ALIASES: &[(&str, &str)] = &[
+    ("cpu_performance_idle_ticks", CPU_PERFORMANCE_IDLE_TICKS),
+    ("cpu_performance_total_ticks", CPU_PERFORMANCE_TOTAL_TICKS),
+    ("cpu_efficiency_idle_ticks", CPU_EFFICIENCY_IDLE_TICKS),
+    ("cpu_efficiency_total_ticks", CPU_EFFICIENCY_TOTAL_TICKS),
+    ("cpu.p.idle_ticks", CPU_PERFORMANCE_IDLE_TICKS),
+    ("cpu.p.total_ticks", CPU_PERFORMANCE_TOTAL_TICKS),
+    ("cpu.e.idle_ticks", CPU_EFFICIENCY_IDLE_TICKS),
+    ("cpu.e.total_ticks", CPU_EFFICIENCY_TOTAL_TICKS),
+    ("memory_total_bytes", MEMORY_TOTAL_BYTES),
+    ("memory_available_bytes", MEMORY_AVAILABLE_BYTES),
+    ("memory_used_bytes", MEMORY_USED_BYTES),
+    ("memory_swap_used_bytes", MEMORY_SWAP_USED_BYTES),
+    ("memory_compressed_bytes", MEMORY_COMPRESSED_BYTES),
+];
+
+/// The canonical name for `name`, or `name` itself if it isn't an alias
+pub fn canonical(name: &str) -> &str {
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, canonical)| canonical)
+}
+
+/// Counter summed over every core of one type, e.g. `cpu.performance.idle_ticks`
+pub fn cpu_type_ticks(core_type: &str, counter: &str) -> String {
+    format!("cpu.{}.{}", core_type, counter)
+}
+
+/// Counter of a single core, e.g. `cpu.performance_core.3.idle_ticks`
+pub fn cpu_core_ticks(core_type: &str, core_id: impl std::fmt::Display, counter: &str) -> String {
+    format!("cpu.{}_core.{}.{}", core_type, core_id, counter)
+}
+
+/// Counter summed over one cluster, e.g. `cpu.efficiency_cluster.0.total_ticks`
+pub fn cpu_cluster_ticks(core_type: &str, cluster_id: impl std::fmt::Display, counter: &str) -> String {
+    format!("cpu.{}_cluster.{}.{}", core_type, cluster_id, counter)
+}
+
+/// Core id and counter suffix of a per-core tick counter name
+pub fn parse_cpu_core_ticks(name: &str) -> Option<(i64, &str)> {
+    let mut parts = name.split('.');
+    match (parts.next(), parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some("cpu"), Some(core_type), Some(core_id), Some(counter), None) if core_type.ends_with("_core") => {
+            Some((core_id.parse().ok()?, counter))
+        }
+        _ => None,
+    }
+}
+
+/// Utilization ratio of one GPU, e.g. `gpu.0.utilization`
+pub fn gpu_utilization(gpu_index: usize) -> String {
+    format!("gpu.{}.utilization", gpu_index)
+}
+
+/// Memory in use by one GPU in bytes, e.g. `gpu.0.memory_used_bytes`
+pub fn gpu_memory_used_bytes(gpu_index: usize) -> String {
+    format!("gpu.{}.memory_used_bytes", gpu_index)
+}
+
+/// Memory available to one GPU in bytes, e.g. `gpu.0.memory_total_bytes`
+pub fn gpu_memory_total_bytes(gpu_index: usize) -> String {
+    format!("gpu.{}.memory_total_bytes", gpu_index)
+}
+
+/// GPU index of a per-GPU utilization ratio name
+pub fn parse_gpu_utilization(name: &str) -> Option<i64> {
+    name.strip_prefix("gpu.")?.strip_suffix(".utilization")?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether SQLite's GLOB operator, as used in the aggregation queries, matches
+    fn sql_glob(pattern: &str, name: &str) -> bool {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.query_row("SELECT ?1 GLOB ?2", [name, pattern], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn type_constants_match_their_builders() {
+        assert_eq!(cpu_type_ticks(PERFORMANCE, IDLE_TICKS), CPU_PERFORMANCE_IDLE_TICKS);
+        assert_eq!(cpu_type_ticks(PERFORMANCE, TOTAL_TICKS), CPU_PERFORMANCE_TOTAL_TICKS);
+        assert_eq!(cpu_type_ticks(EFFICIENCY, IDLE_TICKS), CPU_EFFICIENCY_IDLE_TICKS);
+        assert_eq!(cpu_type_ticks(EFFICIENCY, TOTAL_TICKS), CPU_EFFICIENCY_TOTAL_TICKS);
+    }
+
+    #[test]
+    fn core_tick_glob_matches_only_per_core_counters() {
+        for core_type in [PERFORMANCE, EFFICIENCY] {
+            for counter in [IDLE_TICKS, TOTAL_TICKS] {
+                assert!(sql_glob(CPU_CORE_TICKS_GLOB, &cpu_core_ticks(core_type, 11, counter)));
+                assert!(!sql_glob(CPU_CORE_TICKS_GLOB, &cpu_type_ticks(core_type, counter)));
+                assert!(!sql_glob(CPU_CORE_TICKS_GLOB, &cpu_cluster_ticks(core_type, 0, counter)));
+            }
+        }
+    }
+
+    #[test]
+    fn gpu_globs_match_their_per_gpu_names() {
+        for index in [0, 12] {
+            assert!(sql_glob(GPU_UTILIZATION_GLOB, &gpu_utilization(index)));
+            assert!(sql_glob(GPU_MEMORY_USED_GLOB, &gpu_memory_used_bytes(index)));
+            assert!(sql_glob(GPU_MEMORY_TOTAL_GLOB, &gpu_memory_total_bytes(index)));
+            assert!(!sql_glob(GPU_MEMORY_TOTAL_GLOB, &gpu_memory_used_bytes(index)));
+            assert!(!sql_glob(GPU_UTILIZATION_GLOB, &gpu_memory_used_bytes(index)));
+        }
+        // The averaged GPU metric isn't a per-GPU one
+        assert!(!sql_glob(GPU_UTILIZATION_GLOB, "gpu_utilization"));
+    }
+
+    #[test]
+    fn per_core_and_per_gpu_names_parse_back() {
+        assert_eq!(parse_cpu_core_ticks(&cpu_core_ticks(EFFICIENCY, 7, TOTAL_TICKS)), Some((7, TOTAL_TICKS)));
+        assert_eq!(parse_cpu_core_ticks(CPU_EFFICIENCY_TOTAL_TICKS), None);
+        assert_eq!(parse_gpu_utilization(&gpu_utilization(3)), Some(3));
+    }
+
+    #[test]
+    fn aliases_resolve_to_canonical_names() {
+        assert_eq!(canonical("cpu.e.idle_ticks"), CPU_EFFICIENCY_IDLE_TICKS);
+        assert_eq!(canonical("memory_used_bytes"), MEMORY_USED_BYTES);
+        assert_eq!(canonical(MEMORY_USED_BYTES), MEMORY_USED_BYTES);
+        assert!(ALIASES.iter().all(|(_, name)| canonical(name) == *name));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::collectors::{parse_bridge_json, Collector, Metric, MetricValue};
+use crate::ThrudError;
+use std::collections::HashMap;
+#[cfg(target_os = "macos")]
+use std::ffi::CStr;
+#[cfg(target_os = "macos")]
+use std::os::raw::c_char;
+
+#[cfg(target_os = "macos")]
+extern "C" {
+    fn collect_power_metrics_json() -> *const c_char;
+    fn free_power_string(ptr: *const c_char);
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct BatteryInfo {
+    name: String,
+    current_capacity: i64,
+    max_capacity: i64,
+    is_charging: bool,
+    /// Minutes until empty; absent or negative while the OS is still estimating
+    time_to_empty_minutes: Option<i64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct PowerSourcesData {
+    #[serde(default)]
+    batteries: Vec<BatteryInfo>,
+    adapter_watts: Option<f64>,
+}
+
+pub struct MacOSPowerCollector;
+
+impl Default for MacOSPowerCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MacOSPowerCollector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    #[cfg(target_os = "macos")]
+    fn collect_macos(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let json_ptr = unsafe { collect_power_metrics_json() };
+
+        if json_ptr.is_null() {
+            return Ok(vec![]);
+        }
+
+        let json_str = unsafe {
+            CStr::from_ptr(json_ptr).to_string_lossy().into_owned()
+        };
+
+        unsafe {
+            free_power_string(json_ptr);
+        }
+
+        self.parse_metrics(&json_str)
+    }
+
+    /// Convert the bridge JSON payload into battery and adapter metrics
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn parse_metrics(&self, json_str: &str) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let data: PowerSourcesData = parse_bridge_json("power", json_str)?;
+
+        let mut metrics = Vec::new();
+
+        for battery in data.batteries {
+            let mut metadata = HashMap::new();
+            metadata.insert("battery".to_string(), battery.name);
+
+            if battery.max_capacity > 0 {
+                metrics.push(Metric::new(
+                    "power.battery_percent".to_string(),
+                    MetricValue::Float(battery.current_capacity as f64 * 100.0 / battery.max_capacity as f64),
+                    metadata.clone(),
+                ).with_unit("percent"));
+            }
+            metrics.push(Metric::new(
+                "power.is_charging".to_string(),
+                MetricValue::Boolean(battery.is_charging),
+                metadata.clone(),
+            ));
+            if let Some(minutes) = battery.time_to_empty_minutes.filter(|m| *m >= 0) {
+                metrics.push(Metric::new(
+                    "power.time_to_empty_minutes".to_string(),
+                    MetricValue::Integer(minutes),
+                    metadata,
+                ).with_unit("minutes"));
+            }
+        }
+
+        if let Some(watts) = data.adapter_watts {
+            metrics.push(Metric::new(
+                "power.adapter_watts".to_string(),
+                MetricValue::Float(watts),
+                HashMap::new(),
+            ).with_unit("watts"));
+        }
+
+        Ok(metrics)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn collect_other(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        Ok(vec![])
+    }
+}
+
+impl Collector for MacOSPowerCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "macos")]
+        {
+            Ok(self.collect_macos()?)
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Ok(self.collect_other()?)
+        }
+    }
+
+    fn name(&self) -> &str {
+        "macos_power"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value<'a>(metrics: &'a [Metric], name: &str) -> Option<&'a MetricValue> {
+        metrics.iter().find(|m| m.name == name).map(|m| &m.value)
+    }
+
+    #[test]
+    fn parses_battery_and_adapter() {
+        let json = r#"{
+            "batteries": [{"name": "InternalBattery-0", "current_capacity": 3, "max_capacity": 4, "is_charging": false, "time_to_empty_minutes": 245}],
+            "adapter_watts": 96.0
+        }"#;
+        let metrics = MacOSPowerCollector::new().parse_metrics(json).unwrap();
+
+        assert_eq!(metrics.len(), 4);
+        assert_eq!(value(&metrics, "power.battery_percent"), Some(&MetricValue::Float(75.0)));
+        assert_eq!(value(&metrics, "power.is_charging"), Some(&MetricValue::Boolean(false)));
+        assert_eq!(value(&metrics, "power.time_to_empty_minutes"), Some(&MetricValue::Integer(245)));
+        assert_eq!(value(&metrics, "power.adapter_watts"), Some(&MetricValue::Float(96.0)));
+        assert!(metrics[..3].iter().all(|m| m.metadata["battery"] == "InternalBattery-0"));
+    }
+
+    #[test]
+    fn skips_time_to_empty_while_estimating() {
+        let json = r#"{"batteries": [{"name": "b", "current_capacity": 50, "max_capacity": 100, "is_charging": true, "time_to_empty_minutes": -1}]}"#;
+        let metrics = MacOSPowerCollector::new().parse_metrics(json).unwrap();
+
+        assert_eq!(value(&metrics, "power.is_charging"), Some(&MetricValue::Boolean(true)));
+        assert_eq!(value(&metrics, "power.time_to_empty_minutes"), None);
+        assert_eq!(value(&metrics, "power.adapter_watts"), None);
+    }
+
+    #[test]
+    fn desktop_reports_only_the_adapter() {
+        let metrics = MacOSPowerCollector::new().parse_metrics(r#"{"adapter_watts": 150.0}"#).unwrap();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].name, "power.adapter_watts");
+
+        assert!(MacOSPowerCollector::new().parse_metrics("{}").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+import Foundation
+import IOKit.ps
+
+struct BatteryInfo {
+    let name: String
+    let currentCapacity: Int
+    let maxCapacity: Int
+    let isCharging: Bool
+    let timeToEmptyMinutes: Int?
+}
+
+func collectBatteries() -> [BatteryInfo] {
+    guard let info = IOPSCopyPowerSourcesInfo()?.takeRetainedValue(),
+          let sources = IOPSCopyPowerSourcesList(info)?.takeRetainedValue() as? [CFTypeRef] else {
+        return []
+    }
+
+    var batteries: [BatteryInfo] = []
+
+    for source in sources {
+        guard let description = IOPSGetPowerSourceDescription(info, source)?.takeUnretainedValue() as? [String: Any] else {
+            continue
+        }
+
+        // Only internal batteries; UPS and accessory sources are skipped
+        guard description[kIOPSTypeKey] as? String == kIOPSInternalBatteryType else {
+            continue
+        }
+
+        let timeToEmpty = description[kIOPSTimeToEmptyKey] as? Int
+
+        batteries.append(BatteryInfo(
+            name: description[kIOPSNameKey] as? String ?? "battery",
+            currentCapacity: description[kIOPSCurrentCapacityKey] as? Int ?? 0,
+            maxCapacity: description[kIOPSMaxCapacityKey] as? Int ?? 0,
+            isCharging: description[kIOPSIsChargingKey] as? Bool ?? false,
+            timeToEmptyMinutes: timeToEmpty
+        ))
+    }
+
+    return batteries
+}
+
+func adapterWatts() -> Double? {
+    guard let details = IOPSCopyExternalPowerAdapterDetails()?.takeRetainedValue() as? [String: Any] else {
+        return nil
+    }
+    if let watts = details[kIOPSPowerAdapterWattsKey] as? Int {
+        return Double(watts)
+    }
+    return details[kIOPSPowerAdapterWattsKey] as? Double
+}
+
+// C-style function for FFI
+@_cdecl("collect_power_metrics_json")
+func collectPowerMetricsJSON() -> UnsafePointer<CChar>? {
+    var jsonBatteries: [[String: Any]] = []
+
+    for battery in collectBatteries() {
+        var jsonBattery: [String: Any] = [
+            "name": battery.name,
+            "current_capacity": battery.currentCapacity,
+            "max_capacity": battery.maxCapacity,
+            "is_charging": battery.isCharging
+        ]
+
+        if let minutes = battery.timeToEmptyMinutes {
+            jsonBattery["time_to_empty_minutes"] = minutes
+        }
+
+        jsonBatteries.append(jsonBattery)
+    }
+
+    var payload: [String: Any] = ["batteries": jsonBatteries]
+    if let watts = adapterWatts() {
+        payload["adapter_watts"] = watts
+    }
+
+    do {
+        let jsonData = try JSONSerialization.data(withJSONObject: payload, options: [])
+        if let jsonString = String(data: jsonData, encoding: .utf8) {
+            return UnsafePointer(strdup(jsonString))
+        }
+    } catch {
+        return nil
+    }
+
+    return nil
+}
+
+@_cdecl("free_power_string")
+func freePowerString(_ ptr: UnsafePointer<CChar>?) {
+    if let ptr = ptr {
+        free(UnsafeMutableRawPointer(mutating: ptr))
+    }
+}
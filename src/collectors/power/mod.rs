@@ -0,0 +1,47 @@
+//! Battery and power adapter collector.
+//!
+//! Laptops report charge level, charging state and estimated time to empty per
+//! battery (`battery` metadata, e.g. `InternalBattery-0`). Desktops have no
+//! battery and report at most the connected adapter's wattage.
+
+pub mod macos;
+
+use super::{Collector, Metric};
+use crate::ThrudError;
+
+pub struct PowerCollector {
+    #[cfg(target_os = "macos")]
+    macos: macos::MacOSPowerCollector,
+}
+
+impl Default for PowerCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PowerCollector {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(target_os = "macos")]
+            macos: macos::MacOSPowerCollector::new(),
+        }
+    }
+}
+
+impl Collector for PowerCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "macos")]
+        {
+            self.macos.collect()
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Ok(vec![])
+        }
+    }
+
+    fn name(&self) -> &str {
+        "power"
+    }
+}
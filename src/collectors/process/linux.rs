@@ -0,0 +1,126 @@
+#[cfg(target_os = "linux")]
+use std::os::raw::{c_int, c_long};
+
+/// `USER_HZ` assumed when `sysconf` can't report it; 100 on every mainstream
+/// Linux architecture
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const DEFAULT_USER_HZ: u64 = 100;
+
+/// `_SC_CLK_TCK` from `<unistd.h>` (the same in glibc and musl)
+#[cfg(target_os = "linux")]
+const SC_CLK_TCK: c_int = 2;
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn sysconf(name: c_int) -> c_long;
+}
+
+/// Clock ticks per second of the CPU times in `/proc/<pid>/stat` (`USER_HZ`)
+#[cfg(target_os = "linux")]
+fn clock_ticks_per_second() -> u64 {
+    let ticks = unsafe { sysconf(SC_CLK_TCK) };
+    if ticks > 0 {
+        ticks as u64
+    } else {
+        DEFAULT_USER_HZ
+    }
+}
+
+/// Process id, command name and user plus system CPU time in nanoseconds from
+/// the contents of `/proc/<pid>/stat`, whose CPU times count `ticks_per_second`
+/// ticks a second. The name is in parentheses and may itself contain spaces or
+/// parentheses, so fields are counted from the last `)`.
+pub fn parse_stat(contents: &str, ticks_per_second: u64) -> Option<(i32, String, u64)> {
+    let (head, tail) = contents.rsplit_once(')')?;
+    let (pid, name) = head.split_once(" (")?;
+
+    // Fields after the name start at field 3 (state); utime and stime are 14 and 15
+    let mut fields = tail.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+
+    Some((
+        pid.trim().parse().ok()?,
+        name.to_string(),
+        ((utime + stime) as u128 * 1_000_000_000 / ticks_per_second.max(1) as u128) as u64,
+    ))
+}
+
+/// Resident memory in bytes from the `VmRSS` line of `/proc/<pid>/status`.
+/// Kernel threads have no such line and use no user memory, so they count as 0.
+pub fn parse_status_rss(contents: &str) -> Option<i64> {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .map_or(Some(0), |value| {
+            let kib: i64 = value.trim().strip_suffix("kB")?.trim().parse().ok()?;
+            Some(kib * 1024)
+        })
+}
+
+/// Sample every process in `/proc`. Processes that exit while being read are
+/// skipped.
+#[cfg(target_os = "linux")]
+pub fn sample_processes() -> Result<Vec<super::ProcessSample>, Box<dyn std::error::Error>> {
+    let ticks_per_second = clock_ticks_per_second();
+    let mut samples = Vec::new();
+    for entry in std::fs::read_dir("/proc")? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some(pid) = file_name.to_str().filter(|name| name.bytes().all(|b| b.is_ascii_digit())) else {
+            continue;
+        };
+
+        let (Ok(stat), Ok(status)) = (
+            std::fs::read_to_string(format!("/proc/{}/stat", pid)),
+            std::fs::read_to_string(format!("/proc/{}/status", pid)),
+        ) else {
+            continue;
+        };
+        if let (Some((pid, name, cpu_time_ns)), Some(rss_bytes)) = (parse_stat(&stat, ticks_per_second), parse_status_rss(&status)) {
+            samples.push(super::ProcessSample { pid, name, cpu_time_ns, rss_bytes });
+        }
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `/proc/<pid>/stat` of a process named `my (odd) proc` with utime 250 and stime 50
+    const STAT: &str = "4242 (my (odd) proc) S 1 4242 4242 0 -1 4194560 1200 0 3 0 250 50 0 0 20 0 4 0 12345 1000000 500 18446744073709551615";
+
+    #[test]
+    fn stat_name_may_contain_parentheses() {
+        let (pid, name, _) = parse_stat(STAT, DEFAULT_USER_HZ).unwrap();
+        assert_eq!(pid, 4242);
+        assert_eq!(name, "my (odd) proc");
+    }
+
+    #[test]
+    fn cpu_time_uses_the_clock_tick_rate() {
+        // 300 ticks are 3s at 100 Hz and 1.2s at 250 Hz
+        assert_eq!(parse_stat(STAT, 100).unwrap().2, 3_000_000_000);
+        assert_eq!(parse_stat(STAT, 250).unwrap().2, 1_200_000_000);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn clock_tick_rate_is_read_from_sysconf() {
+        assert!(clock_ticks_per_second() > 0);
+    }
+
+    #[test]
+    fn truncated_stat_is_rejected() {
+        assert_eq!(parse_stat("4242 (proc) S 1 4242", DEFAULT_USER_HZ), None);
+    }
+
+    #[test]
+    fn rss_is_read_from_status_in_bytes() {
+        assert_eq!(parse_status_rss("Name:\tproc\nVmRSS:\t    2048 kB\nThreads:\t4\n"), Some(2048 * 1024));
+        // Kernel threads have no VmRSS line
+        assert_eq!(parse_status_rss("Name:\tkthreadd\nThreads:\t1\n"), Some(0));
+        assert_eq!(parse_status_rss("VmRSS:\t2048 MB\n"), None);
+    }
+}
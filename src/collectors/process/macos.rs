@@ -0,0 +1,105 @@
+//! Process sampling through `libproc`, which is part of libSystem.
+//!
+//! Only processes owned by the current user (or every process when running as
+//! root) can be inspected; the rest are skipped.
+
+#[cfg(target_os = "macos")]
+use std::os::raw::{c_int, c_void};
+
+/// `PROC_PIDTASKINFO` flavor of `proc_pidinfo`, filling a `proc_taskinfo`
+#[cfg(target_os = "macos")]
+const PROC_PIDTASKINFO: c_int = 4;
+
+/// Size of the buffer `proc_name` fills, enough for `2 * MAXCOMLEN` plus NUL
+#[cfg(target_os = "macos")]
+const PROC_NAME_BUFFER: usize = 64;
+
+/// `struct proc_taskinfo` from `<sys/proc_info.h>`; only some fields are read
+#[cfg(target_os = "macos")]
+#[repr(C)]
+#[derive(Default)]
+#[allow(dead_code)]
+struct ProcTaskInfo {
+    pti_virtual_size: u64,
+    pti_resident_size: u64,
+    /// CPU time in Mach absolute time units
+    pti_total_user: u64,
+    pti_total_system: u64,
+    pti_threads_user: u64,
+    pti_threads_system: u64,
+    pti_policy: i32,
+    pti_faults: i32,
+    pti_pageins: i32,
+    pti_cow_faults: i32,
+    pti_messages_sent: i32,
+    pti_messages_received: i32,
+    pti_syscalls_mach: i32,
+    pti_syscalls_unix: i32,
+    pti_csw: i32,
+    pti_threadnum: i32,
+    pti_numrunning: i32,
+    pti_priority: i32,
+}
+
+/// `struct mach_timebase_info`, the ratio converting Mach absolute time to nanoseconds
+#[cfg(target_os = "macos")]
+#[repr(C)]
+#[derive(Default)]
+struct MachTimebaseInfo {
+    numer: u32,
+    denom: u32,
+}
+
+#[cfg(target_os = "macos")]
+extern "C" {
+    fn proc_listallpids(buffer: *mut c_void, buffersize: c_int) -> c_int;
+    fn proc_pidinfo(pid: c_int, flavor: c_int, arg: u64, buffer: *mut c_void, buffersize: c_int) -> c_int;
+    fn proc_name(pid: c_int, buffer: *mut c_void, buffersize: u32) -> c_int;
+    fn mach_timebase_info(info: *mut MachTimebaseInfo) -> c_int;
+}
+
+/// Sample every process that can be inspected
+#[cfg(target_os = "macos")]
+pub fn sample_processes() -> Result<Vec<super::ProcessSample>, Box<dyn std::error::Error>> {
+    let mut timebase = MachTimebaseInfo::default();
+    if unsafe { mach_timebase_info(&mut timebase) } != 0 || timebase.denom == 0 {
+        return Err("mach_timebase_info failed".into());
+    }
+
+    // Leave room for processes started between sizing the buffer and filling it
+    let count = unsafe { proc_listallpids(std::ptr::null_mut(), 0) };
+    if count <= 0 {
+        return Err("proc_listallpids failed".into());
+    }
+    let mut pids = vec![0 as c_int; count as usize + 64];
+    let buffer_size = (pids.len() * std::mem::size_of::<c_int>()) as c_int;
+    let count = unsafe { proc_listallpids(pids.as_mut_ptr().cast(), buffer_size) };
+    if count <= 0 {
+        return Err("proc_listallpids failed".into());
+    }
+    pids.truncate((count as usize).min(pids.len()));
+
+    let mut samples = Vec::with_capacity(pids.len());
+    for pid in pids.into_iter().filter(|&pid| pid > 0) {
+        let mut info = ProcTaskInfo::default();
+        let info_size = std::mem::size_of::<ProcTaskInfo>() as c_int;
+        let filled = unsafe { proc_pidinfo(pid, PROC_PIDTASKINFO, 0, (&mut info as *mut ProcTaskInfo).cast(), info_size) };
+        if filled != info_size {
+            continue;
+        }
+
+        let mut name = [0u8; PROC_NAME_BUFFER];
+        let length = unsafe { proc_name(pid, name.as_mut_ptr().cast(), name.len() as u32) };
+        let name = String::from_utf8_lossy(&name[..length.max(0) as usize]).into_owned();
+
+        let cpu_ticks = info.pti_total_user + info.pti_total_system;
+        let cpu_time_ns = (cpu_ticks as u128 * timebase.numer as u128 / timebase.denom as u128) as u64;
+        samples.push(super::ProcessSample {
+            pid,
+            name,
+            cpu_time_ns,
+            rss_bytes: info.pti_resident_size as i64,
+        });
+    }
+    Ok(samples)
+}
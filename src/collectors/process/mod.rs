@@ -0,0 +1,194 @@
+//! Process collector.
+//!
+//! Reports CPU usage (`proc.cpu_percent`) and resident memory (`proc.rss_bytes`)
+//! of the processes using the most CPU, with `pid` and `name` metadata. CPU usage
+//! is measured between consecutive samples, so a process is reported from its
+//! second sample on and the first round reports nothing. Supported on Linux
+//! (`/proc`) and macOS (`libproc`); other platforms report nothing.
+
+pub mod linux;
+pub mod macos;
+
+use super::{names, Collector, Metric, MetricValue};
+use crate::ThrudError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Number of processes reported unless configured otherwise
+pub const DEFAULT_TOP_N: usize = 10;
+/// Upper bound on the number of processes reported, to keep rounds small
+pub const MAX_TOP_N: usize = 100;
+
+/// CPU time and resident memory of one process when it was sampled
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessSample {
+    pub pid: i32,
+    pub name: String,
+    /// User plus system CPU time over the process's lifetime
+    pub cpu_time_ns: u64,
+    pub rss_bytes: i64,
+}
+
+pub struct ProcessCollector {
+    top_n: usize,
+    /// When the previous sample was taken and each process's CPU time in it
+    previous: Mutex<Option<(Instant, HashMap<i32, u64>)>>,
+}
+
+impl Default for ProcessCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessCollector {
+    pub fn new() -> Self {
+        Self {
+            top_n: DEFAULT_TOP_N,
+            previous: Mutex::new(None),
+        }
+    }
+
+    /// Report the `n` processes using the most CPU, at most [`MAX_TOP_N`]
+    pub fn with_top_n(mut self, n: usize) -> Self {
+        self.top_n = n.min(MAX_TOP_N);
+        self
+    }
+
+    fn sample(&self) -> Result<Vec<ProcessSample>, Box<dyn std::error::Error>> {
+        #[cfg(target_os = "linux")]
+        {
+            linux::sample_processes()
+        }
+        #[cfg(target_os = "macos")]
+        {
+            macos::sample_processes()
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        {
+            Ok(vec![])
+        }
+    }
+}
+
+/// Metrics for the `top_n` processes in `samples` that used the most CPU since
+/// `previous`, CPU times sampled `elapsed` ago. Processes missing from `previous`
+/// or whose CPU time went backwards (a reused pid) are skipped. A process busy on
+/// several cores can exceed 100%.
+pub fn top_process_metrics(
+    samples: &[ProcessSample],
+    previous: &HashMap<i32, u64>,
+    elapsed: Duration,
+    top_n: usize,
+) -> Vec<Metric> {
+    let elapsed_ns = elapsed.as_nanos() as f64;
+    if elapsed_ns <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut busiest: Vec<(f64, &ProcessSample)> = samples
+        .iter()
+        .filter_map(|sample| {
+            let before = *previous.get(&sample.pid)?;
+            let used = sample.cpu_time_ns.checked_sub(before)?;
+            Some((used as f64 * 100.0 / elapsed_ns, sample))
+        })
+        .collect();
+    busiest.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.pid.cmp(&b.1.pid)));
+
+    busiest
+        .into_iter()
+        .take(top_n)
+        .flat_map(|(cpu_percent, sample)| {
+            let metadata = HashMap::from([
+                ("pid".to_string(), sample.pid.to_string()),
+                ("name".to_string(), sample.name.clone()),
+            ]);
+            [
+                Metric::new(names::PROCESS_CPU_PERCENT.to_string(), MetricValue::Float(cpu_percent), metadata.clone())
+                    .with_unit("percent"),
+                Metric::new(names::PROCESS_RSS_BYTES.to_string(), MetricValue::Integer(sample.rss_bytes), metadata)
+                    .with_unit("bytes"),
+            ]
+        })
+        .collect()
+}
+
+impl Collector for ProcessCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        let samples = self.sample()?;
+        let now = Instant::now();
+        let cpu_times = samples.iter().map(|s| (s.pid, s.cpu_time_ns)).collect();
+
+        let mut previous = self.previous.lock().map_err(|_| "Process sample lock poisoned")?;
+        let metrics = match previous.as_ref() {
+            Some((taken, before)) => top_process_metrics(&samples, before, now.duration_since(*taken), self.top_n),
+            None => Vec::new(),
+        };
+        *previous = Some((now, cpu_times));
+        Ok(metrics)
+    }
+
+    fn name(&self) -> &str {
+        "process"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND_NS: u64 = 1_000_000_000;
+
+    fn sample(pid: i32, name: &str, cpu_time_ns: u64) -> ProcessSample {
+        ProcessSample { pid, name: name.to_string(), cpu_time_ns, rss_bytes: pid as i64 * 1024 }
+    }
+
+    fn reported(metrics: &[Metric]) -> Vec<(String, f64)> {
+        metrics
+            .iter()
+            .filter(|m| m.name == names::PROCESS_CPU_PERCENT)
+            .map(|m| match m.value {
+                MetricValue::Float(percent) => (m.metadata["name"].clone(), percent),
+                ref other => panic!("unexpected value {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn busiest_processes_are_reported_first() {
+        let previous = HashMap::from([(1, 0), (2, 5 * SECOND_NS), (3, SECOND_NS), (4, 0)]);
+        let samples = [
+            sample(1, "idle", 0),
+            sample(2, "compiler", 6 * SECOND_NS),
+            // Busy on two cores
+            sample(3, "encoder", 5 * SECOND_NS),
+            sample(4, "shell", SECOND_NS / 10),
+        ];
+
+        let metrics = top_process_metrics(&samples, &previous, Duration::from_secs(2), 3);
+
+        assert_eq!(
+            reported(&metrics),
+            [("encoder".to_string(), 200.0), ("compiler".to_string(), 50.0), ("shell".to_string(), 5.0)]
+        );
+        let rss = metrics.iter().find(|m| m.name == names::PROCESS_RSS_BYTES && m.metadata["pid"] == "3").unwrap();
+        assert_eq!(rss.value, MetricValue::Integer(3 * 1024));
+    }
+
+    #[test]
+    fn new_and_reused_pids_are_skipped() {
+        let previous = HashMap::from([(1, 10 * SECOND_NS)]);
+        // pid 1 was reused by a process with less CPU time, pid 2 is new
+        let samples = [sample(1, "reused", SECOND_NS), sample(2, "new", SECOND_NS)];
+
+        assert!(top_process_metrics(&samples, &previous, Duration::from_secs(1), 10).is_empty());
+    }
+
+    #[test]
+    fn first_collection_reports_nothing() {
+        let collector = ProcessCollector::new();
+        assert!(collector.collect().unwrap().is_empty());
+    }
+}
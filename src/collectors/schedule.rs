@@ -0,0 +1,189 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::Duration;
+
+/// Fire times closer than this are treated as the same instant and share a round
+const COALESCE_WINDOW: Duration = Duration::from_millis(1);
+
+/// Independent per-collector timers, ordered by next fire time.
+///
+/// Fire times are offsets from the schedule start computed as `interval * n`, so
+/// they don't drift. Collectors due within [`COALESCE_WINDOW`] of each other are
+/// returned together so their metrics share a collection round.
+///
+/// A fire time is skipped once the following one has passed too, e.g. after the
+/// machine slept or the process was stopped: the collector moves on to its first
+/// fire time after now, like tokio's `MissedTickBehavior::Skip`, instead of
+/// firing every missed tick in a burst of back-to-back rounds whose tiny
+/// intervals skew rate calculations.
+pub struct Schedule {
+    entries: Vec<(String, Duration)>,
+    /// (next fire offset, number of fires so far, entry index)
+    heap: BinaryHeap<Reverse<(Duration, u32, usize)>>,
+}
+
+impl Schedule {
+    /// Create a schedule where every collector first fires at offset zero
+    pub fn new(entries: Vec<(String, Duration)>) -> Self {
+        let heap = (0..entries.len())
+            .map(|index| Reverse((Duration::ZERO, 0, index)))
+            .collect();
+        Self { entries, heap }
+    }
+
+    /// Offset of the next fire time. Returns `None` for an empty schedule.
+    pub fn next_offset(&self) -> Option<Duration> {
+        self.heap.peek().map(|Reverse((offset, _, _))| *offset)
+    }
+
+    /// Collectors due `elapsed` after the schedule started, advancing each of them
+    /// to its following fire time. Call [`Schedule::skip_missed`] first so a
+    /// wake-up long after [`Schedule::next_offset`] finds nothing due and the
+    /// caller waits for the next fire time instead.
+    pub fn take_due(&mut self, elapsed: Duration) -> Vec<&str> {
+        let mut fired = Vec::new();
+        while let Some(&Reverse((next, count, index))) = self.heap.peek() {
+            if next > elapsed + COALESCE_WINDOW {
+                break;
+            }
+            self.heap.pop();
+            fired.push((index, count + 1));
+        }
+        fired.sort_unstable();
+
+        for &(index, count) in &fired {
+            self.heap.push(Reverse((self.entries[index].1 * count, count, index)));
+        }
+
+        fired
+            .iter()
+            .map(|&(index, _)| self.entries[index].0.as_str())
+            .collect()
+    }
+
+    /// Move every collector whose following fire time has also passed by
+    /// `elapsed` to its first fire time after `elapsed`, returning how many fire
+    /// times were skipped, counting each collector's separately
+    pub fn skip_missed(&mut self, elapsed: Duration) -> u64 {
+        let mut missed = 0;
+        self.heap = std::mem::take(&mut self.heap)
+            .into_iter()
+            .map(|Reverse((next, count, index))| {
+                let interval = self.entries[index].1;
+                if interval.is_zero() || next + interval > elapsed {
+                    return Reverse((next, count, index));
+                }
+                let upcoming = u32::try_from(elapsed.as_nanos() / interval.as_nanos() + 1).unwrap_or(u32::MAX);
+                missed += u64::from(upcoming - count);
+                Reverse((interval * upcoming, upcoming, index))
+            })
+            .collect();
+        missed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(entries: &[(&str, u64)]) -> Schedule {
+        Schedule::new(entries.iter().map(|&(name, ms)| (name.to_string(), Duration::from_millis(ms))).collect())
+    }
+
+    /// Fire the schedule at each of its fire times up to `until`, as the collector
+    /// loop does, returning (offset in ms, collectors fired) per round
+    fn simulate(schedule: &mut Schedule, until: Duration) -> Vec<(u128, Vec<String>)> {
+        let mut rounds = Vec::new();
+        while let Some(next) = schedule.next_offset().filter(|next| *next <= until) {
+            let due = schedule.take_due(next).into_iter().map(str::to_string).collect();
+            rounds.push((next.as_millis(), due));
+        }
+        rounds
+    }
+
+    #[test]
+    fn each_collector_fires_at_its_own_cadence() {
+        let mut schedule = schedule(&[("gpu", 500), ("disk", 5000)]);
+        let rounds = simulate(&mut schedule, Duration::from_secs(10));
+
+        let fires = |name: &str| -> Vec<u128> {
+            rounds.iter().filter(|(_, due)| due.iter().any(|n| n == name)).map(|(at, _)| *at).collect()
+        };
+        assert_eq!(fires("gpu"), (0..=20).map(|n| n * 500).collect::<Vec<_>>());
+        assert_eq!(fires("disk"), [0, 5000, 10000]);
+    }
+
+    #[test]
+    fn coinciding_collectors_share_a_round() {
+        let mut schedule = schedule(&[("gpu", 500), ("cpu", 1000), ("disk", 1500)]);
+        let rounds = simulate(&mut schedule, Duration::from_millis(3000));
+
+        let expected: Vec<(u128, Vec<String>)> = [
+            (0, vec!["gpu", "cpu", "disk"]),
+            (500, vec!["gpu"]),
+            (1000, vec!["gpu", "cpu"]),
+            (1500, vec!["gpu", "disk"]),
+            (2000, vec!["gpu", "cpu"]),
+            (2500, vec!["gpu"]),
+            (3000, vec!["gpu", "cpu", "disk"]),
+        ]
+        .into_iter()
+        .map(|(at, due)| (at, due.into_iter().map(str::to_string).collect()))
+        .collect();
+        assert_eq!(rounds, expected);
+    }
+
+    #[test]
+    fn fire_times_do_not_drift() {
+        let mut schedule = schedule(&[("cpu", 300)]);
+        // Each round is handled a little late
+        for n in 0..100u32 {
+            let next = schedule.next_offset().unwrap();
+            assert_eq!(next, Duration::from_millis(300) * n);
+            assert_eq!(schedule.take_due(next + Duration::from_micros(700)), ["cpu"]);
+        }
+    }
+
+    #[test]
+    fn long_stall_resumes_with_one_round_instead_of_a_burst() {
+        let mut schedule = schedule(&[("cpu", 500), ("disk", 2000)]);
+        assert_eq!(schedule.take_due(Duration::ZERO), ["cpu", "disk"]);
+
+        // The process is stopped for about 10s; the loop wakes up at 10.3s
+        let woke = Duration::from_millis(10_300);
+        assert_eq!(schedule.skip_missed(woke), 20 + 5);
+        assert!(schedule.take_due(woke).is_empty());
+
+        // One round at the first fire time after the stall, then the usual cadence
+        let rounds = simulate(&mut schedule, Duration::from_millis(12_000));
+        let expected: Vec<(u128, Vec<String>)> = [
+            (10_500, vec!["cpu"]),
+            (11_000, vec!["cpu"]),
+            (11_500, vec!["cpu"]),
+            (12_000, vec!["cpu", "disk"]),
+        ]
+        .into_iter()
+        .map(|(at, due)| (at, due.into_iter().map(str::to_string).collect()))
+        .collect();
+        assert_eq!(rounds, expected);
+    }
+
+    #[test]
+    fn waking_less_than_an_interval_late_fires_once() {
+        let mut schedule = schedule(&[("cpu", 500)]);
+        schedule.take_due(Duration::ZERO);
+
+        // Late for the 500ms tick but not yet past the 1000ms one: nothing is skipped
+        let woke = Duration::from_millis(900);
+        assert_eq!(schedule.skip_missed(woke), 0);
+        assert_eq!(schedule.take_due(woke), ["cpu"]);
+        assert_eq!(schedule.next_offset(), Some(Duration::from_millis(1000)));
+    }
+
+    #[test]
+    fn empty_schedule_never_fires() {
+        let mut schedule = schedule(&[]);
+        assert_eq!(schedule.next_offset(), None);
+        assert!(schedule.take_due(Duration::from_secs(1)).is_empty());
+    }
+}
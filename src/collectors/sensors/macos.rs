@@ -0,0 +1,153 @@
+use crate::collectors::{parse_bridge_json, Collector, Metric, MetricValue};
+use crate::ThrudError;
+use std::collections::HashMap;
+#[cfg(target_os = "macos")]
+use std::ffi::CStr;
+#[cfg(target_os = "macos")]
+use std::os::raw::c_char;
+
+#[cfg(target_os = "macos")]
+extern "C" {
+    fn collect_sensors_metrics_json() -> *const c_char;
+    fn free_sensors_string(ptr: *const c_char);
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct FanReading {
+    id: i32,
+    rpm: f64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TemperatureReading {
+    sensor: String,
+    celsius: f64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SensorsData {
+    #[serde(default)]
+    fans: Vec<FanReading>,
+    #[serde(default)]
+    temperatures: Vec<TemperatureReading>,
+}
+
+pub struct MacOSSensorsCollector;
+
+impl Default for MacOSSensorsCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MacOSSensorsCollector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    #[cfg(target_os = "macos")]
+    fn collect_macos(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let json_ptr = unsafe { collect_sensors_metrics_json() };
+
+        if json_ptr.is_null() {
+            return Ok(vec![]);
+        }
+
+        let json_str = unsafe {
+            CStr::from_ptr(json_ptr).to_string_lossy().into_owned()
+        };
+
+        unsafe {
+            free_sensors_string(json_ptr);
+        }
+
+        self.parse_metrics(&json_str)
+    }
+
+    /// Convert the bridge JSON payload into fan speed and temperature metrics
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn parse_metrics(&self, json_str: &str) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let data: SensorsData = parse_bridge_json("sensors", json_str)?;
+
+        let mut metrics = Vec::new();
+
+        for fan in data.fans {
+            let mut metadata = HashMap::new();
+            metadata.insert("fan_id".to_string(), fan.id.to_string());
+
+            metrics.push(Metric::new(
+                "sensors.fan.rpm".to_string(),
+                MetricValue::Integer(fan.rpm.round() as i64),
+                metadata,
+            ).with_unit("rpm"));
+        }
+
+        for temperature in data.temperatures {
+            let mut metadata = HashMap::new();
+            metadata.insert("sensor_key".to_string(), temperature.sensor);
+
+            metrics.push(Metric::new(
+                "sensors.temp.celsius".to_string(),
+                MetricValue::Float(temperature.celsius),
+                metadata,
+            ).with_unit("celsius"));
+        }
+
+        Ok(metrics)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn collect_other(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        Ok(vec![])
+    }
+}
+
+impl Collector for MacOSSensorsCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "macos")]
+        {
+            Ok(self.collect_macos()?)
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Ok(self.collect_other()?)
+        }
+    }
+
+    fn name(&self) -> &str {
+        "macos_sensors"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fans_and_temperatures() {
+        let json = r#"{
+            "fans": [{"id": 0, "rpm": 1799.6}, {"id": 1, "rpm": 0}],
+            "temperatures": [{"sensor": "TB0T", "celsius": 31.5}]
+        }"#;
+        let metrics = MacOSSensorsCollector::new().parse_metrics(json).unwrap();
+
+        assert_eq!(metrics.len(), 3);
+        assert_eq!(metrics[0].name, "sensors.fan.rpm");
+        assert_eq!(metrics[0].value, MetricValue::Integer(1800));
+        assert_eq!(metrics[0].metadata["fan_id"], "0");
+        assert_eq!(metrics[1].value, MetricValue::Integer(0));
+        assert_eq!(metrics[2].name, "sensors.temp.celsius");
+        assert_eq!(metrics[2].value, MetricValue::Float(31.5));
+        assert_eq!(metrics[2].metadata["sensor_key"], "TB0T");
+    }
+
+    #[test]
+    fn fanless_machines_report_no_fans() {
+        let metrics = MacOSSensorsCollector::new()
+            .parse_metrics(r#"{"temperatures": [{"sensor": "TB0T", "celsius": 30.0}]}"#)
+            .unwrap();
+        assert!(metrics.iter().all(|m| m.name != "sensors.fan.rpm"));
+
+        assert!(MacOSSensorsCollector::new().parse_metrics(r#"{"fans": []}"#).unwrap().is_empty());
+    }
+}
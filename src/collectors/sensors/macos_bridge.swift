@@ -0,0 +1,207 @@
+import Foundation
+import IOKit
+
+// MARK: - SMC (fan speeds)
+
+// Layout of the AppleSMC user client's key data structure
+struct SMCVersion {
+    var major: UInt8 = 0
+    var minor: UInt8 = 0
+    var build: UInt8 = 0
+    var reserved: UInt8 = 0
+    var release: UInt16 = 0
+}
+
+struct SMCPLimitData {
+    var version: UInt16 = 0
+    var length: UInt16 = 0
+    var cpuPLimit: UInt32 = 0
+    var gpuPLimit: UInt32 = 0
+    var memPLimit: UInt32 = 0
+}
+
+struct SMCKeyInfo {
+    var dataSize: UInt32 = 0
+    var dataType: UInt32 = 0
+    var dataAttributes: UInt8 = 0
+}
+
+typealias SMCBytes = (
+    UInt8, UInt8, UInt8, UInt8, UInt8, UInt8, UInt8, UInt8,
+    UInt8, UInt8, UInt8, UInt8, UInt8, UInt8, UInt8, UInt8,
+    UInt8, UInt8, UInt8, UInt8, UInt8, UInt8, UInt8, UInt8,
+    UInt8, UInt8, UInt8, UInt8, UInt8, UInt8, UInt8, UInt8
+)
+
+struct SMCKeyData {
+    var key: UInt32 = 0
+    var vers = SMCVersion()
+    var pLimitData = SMCPLimitData()
+    var keyInfo = SMCKeyInfo()
+    var result: UInt8 = 0
+    var status: UInt8 = 0
+    var data8: UInt8 = 0
+    var data32: UInt32 = 0
+    var bytes: SMCBytes = (
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
+    )
+}
+
+let kSMCHandleYPCEvent: UInt32 = 2
+let kSMCReadKey: UInt8 = 5
+let kSMCGetKeyInfo: UInt8 = 9
+
+func fourCharCode(_ s: String) -> UInt32 {
+    return s.utf8.reduce(0) { ($0 << 8) | UInt32($1) }
+}
+
+func smcCall(_ conn: io_connect_t, _ input: inout SMCKeyData) -> SMCKeyData? {
+    var output = SMCKeyData()
+    var outputSize = MemoryLayout<SMCKeyData>.stride
+    let result = IOConnectCallStructMethod(
+        conn, kSMCHandleYPCEvent,
+        &input, MemoryLayout<SMCKeyData>.stride,
+        &output, &outputSize
+    )
+    return result == kIOReturnSuccess && output.result == 0 ? output : nil
+}
+
+/// Read an SMC key, returning its type code and raw bytes
+func smcRead(_ conn: io_connect_t, _ key: String) -> (UInt32, [UInt8])? {
+    var input = SMCKeyData()
+    input.key = fourCharCode(key)
+    input.data8 = kSMCGetKeyInfo
+    guard let info = smcCall(conn, &input) else {
+        return nil
+    }
+
+    input.keyInfo.dataSize = info.keyInfo.dataSize
+    input.data8 = kSMCReadKey
+    guard let output = smcCall(conn, &input) else {
+        return nil
+    }
+
+    let bytes = withUnsafeBytes(of: output.bytes) { Array($0.prefix(Int(info.keyInfo.dataSize))) }
+    return (info.keyInfo.dataType, bytes)
+}
+
+/// Decode the numeric SMC types used by fan keys
+func smcValue(_ type: UInt32, _ bytes: [UInt8]) -> Double? {
+    switch type {
+    case fourCharCode("flt ") where bytes.count >= 4:
+        // Little-endian float on Apple Silicon
+        let bits = UInt32(bytes[0]) | UInt32(bytes[1]) << 8 | UInt32(bytes[2]) << 16 | UInt32(bytes[3]) << 24
+        return Double(Float(bitPattern: bits))
+    case fourCharCode("fpe2") where bytes.count >= 2:
+        // Big-endian unsigned fixed point with 2 fractional bits on Intel
+        return Double(UInt16(bytes[0]) << 8 | UInt16(bytes[1])) / 4.0
+    case fourCharCode("ui8 ") where bytes.count >= 1:
+        return Double(bytes[0])
+    default:
+        return nil
+    }
+}
+
+func collectFans() -> [(Int, Double)] {
+    let service = IOServiceGetMatchingService(kIOMainPortDefault, IOServiceMatching("AppleSMC"))
+    if service == 0 {
+        return []
+    }
+    defer { IOObjectRelease(service) }
+
+    var conn: io_connect_t = 0
+    if IOServiceOpen(service, mach_task_self_, 0, &conn) != kIOReturnSuccess {
+        return []
+    }
+    defer { IOServiceClose(conn) }
+
+    // Fanless machines report no FNum key or a count of zero
+    guard let (type, bytes) = smcRead(conn, "FNum"), let count = smcValue(type, bytes) else {
+        return []
+    }
+
+    var fans: [(Int, Double)] = []
+    for id in 0..<Int(count) {
+        if let (type, bytes) = smcRead(conn, "F\(id)Ac"), let rpm = smcValue(type, bytes) {
+            fans.append((id, rpm))
+        }
+    }
+    return fans
+}
+
+// MARK: - HID (temperatures)
+
+@_silgen_name("IOHIDEventSystemClientCreate")
+func IOHIDEventSystemClientCreate(_ allocator: CFAllocator?) -> Unmanaged<AnyObject>?
+
+@_silgen_name("IOHIDEventSystemClientSetMatching")
+func IOHIDEventSystemClientSetMatching(_ client: AnyObject, _ matching: CFDictionary) -> Int32
+
+@_silgen_name("IOHIDEventSystemClientCopyServices")
+func IOHIDEventSystemClientCopyServices(_ client: AnyObject) -> Unmanaged<CFArray>?
+
+@_silgen_name("IOHIDServiceClientCopyProperty")
+func IOHIDServiceClientCopyProperty(_ service: AnyObject, _ key: CFString) -> Unmanaged<AnyObject>?
+
+@_silgen_name("IOHIDServiceClientCopyEvent")
+func IOHIDServiceClientCopyEvent(_ service: AnyObject, _ type: Int64, _ options: Int32, _ timestamp: Int64) -> Unmanaged<AnyObject>?
+
+@_silgen_name("IOHIDEventGetFloatValue")
+func IOHIDEventGetFloatValue(_ event: AnyObject, _ field: Int32) -> Double
+
+let kIOHIDEventTypeTemperature: Int64 = 15
+
+func collectTemperatures() -> [(String, Double)] {
+    guard let client = IOHIDEventSystemClientCreate(kCFAllocatorDefault)?.takeRetainedValue() else {
+        return []
+    }
+
+    // Vendor usage page 0xff00, usage 5: temperature sensors
+    let matching: [String: Int] = ["PrimaryUsagePage": 0xff00, "PrimaryUsage": 5]
+    _ = IOHIDEventSystemClientSetMatching(client, matching as CFDictionary)
+
+    guard let services = IOHIDEventSystemClientCopyServices(client)?.takeRetainedValue() as? [AnyObject] else {
+        return []
+    }
+
+    var temperatures: [(String, Double)] = []
+    for service in services {
+        guard let name = IOHIDServiceClientCopyProperty(service, "Product" as CFString)?.takeRetainedValue() as? String,
+              let event = IOHIDServiceClientCopyEvent(service, kIOHIDEventTypeTemperature, 0, 0)?.takeRetainedValue() else {
+            continue
+        }
+        let celsius = IOHIDEventGetFloatValue(event, Int32(kIOHIDEventTypeTemperature << 16))
+        if celsius > 0 {
+            temperatures.append((name, celsius))
+        }
+    }
+    return temperatures
+}
+
+// C-style function for FFI
+@_cdecl("collect_sensors_metrics_json")
+func collectSensorsMetricsJSON() -> UnsafePointer<CChar>? {
+    let payload: [String: Any] = [
+        "fans": collectFans().map { ["id": $0.0, "rpm": $0.1] },
+        "temperatures": collectTemperatures().map { ["sensor": $0.0, "celsius": $0.1] }
+    ]
+
+    do {
+        let jsonData = try JSONSerialization.data(withJSONObject: payload, options: [])
+        if let jsonString = String(data: jsonData, encoding: .utf8) {
+            return UnsafePointer(strdup(jsonString))
+        }
+    } catch {
+        return nil
+    }
+
+    return nil
+}
+
+@_cdecl("free_sensors_string")
+func freeSensorsString(_ ptr: UnsafePointer<CChar>?) {
+    if let ptr = ptr {
+        free(UnsafeMutableRawPointer(mutating: ptr))
+    }
+}
@@ -0,0 +1,48 @@
+//! Fan and temperature sensors collector.
+//!
+//! Fan speeds come from the SMC (`sensors.fan.rpm`, `fan_id` metadata) and
+//! temperatures from the HID thermal sensors (`sensors.temp.celsius`,
+//! `sensor_key` metadata holding the sensor's product name). Fanless machines
+//! simply report no fan metrics.
+
+pub mod macos;
+
+use super::{Collector, Metric};
+use crate::ThrudError;
+
+pub struct SensorsCollector {
+    #[cfg(target_os = "macos")]
+    macos: macos::MacOSSensorsCollector,
+}
+
+impl Default for SensorsCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SensorsCollector {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(target_os = "macos")]
+            macos: macos::MacOSSensorsCollector::new(),
+        }
+    }
+}
+
+impl Collector for SensorsCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "macos")]
+        {
+            self.macos.collect()
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Ok(vec![])
+        }
+    }
+
+    fn name(&self) -> &str {
+        "sensors"
+    }
+}
@@ -0,0 +1,147 @@
+use crate::collectors::{names, parse_bridge_json, Collector, Metric, MetricValue};
+use crate::ThrudError;
+use std::collections::HashMap;
+#[cfg(target_os = "macos")]
+use std::ffi::CStr;
+#[cfg(target_os = "macos")]
+use std::os::raw::c_char;
+
+#[cfg(target_os = "macos")]
+extern "C" {
+    fn collect_thermal_metrics_json() -> *const c_char;
+    fn free_thermal_string(ptr: *const c_char);
+}
+
+/// Pressure level at and above which the OS throttles performance
+const THROTTLED_LEVEL: i64 = 2;
+
+#[derive(Debug, serde::Deserialize)]
+struct ThermalData {
+    thermal_state: String,
+}
+
+/// Map an `NSProcessInfo.thermalState` name to its 0-3 pressure level
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn pressure_level(state: &str) -> Option<i64> {
+    match state {
+        "nominal" => Some(0),
+        "fair" => Some(1),
+        "serious" => Some(2),
+        "critical" => Some(3),
+        _ => None,
+    }
+}
+
+pub struct MacOSThermalCollector;
+
+impl Default for MacOSThermalCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MacOSThermalCollector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    #[cfg(target_os = "macos")]
+    fn collect_macos(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let json_ptr = unsafe { collect_thermal_metrics_json() };
+
+        if json_ptr.is_null() {
+            return Ok(vec![]);
+        }
+
+        let json_str = unsafe {
+            CStr::from_ptr(json_ptr).to_string_lossy().into_owned()
+        };
+
+        unsafe {
+            free_thermal_string(json_ptr);
+        }
+
+        self.parse_metrics(&json_str)
+    }
+
+    /// Convert the bridge JSON payload into pressure level and throttle metrics
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn parse_metrics(&self, json_str: &str) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        let data: ThermalData = parse_bridge_json("thermal", json_str)?;
+
+        let level = pressure_level(&data.thermal_state)
+            .ok_or_else(|| format!("Unknown thermal state: {}", data.thermal_state))?;
+
+        let mut metadata = HashMap::new();
+        metadata.insert("thermal_state".to_string(), data.thermal_state);
+
+        Ok(vec![
+            Metric::new(
+                names::THERMAL_PRESSURE_LEVEL.to_string(),
+                MetricValue::Integer(level),
+                metadata.clone(),
+            ),
+            Metric::new(
+                names::THERMAL_CPU_THROTTLED.to_string(),
+                MetricValue::Boolean(level >= THROTTLED_LEVEL),
+                metadata,
+            ),
+        ])
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn collect_other(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>> {
+        Ok(vec![])
+    }
+}
+
+impl Collector for MacOSThermalCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "macos")]
+        {
+            Ok(self.collect_macos()?)
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Ok(self.collect_other()?)
+        }
+    }
+
+    fn name(&self) -> &str {
+        "macos_thermal"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_each_thermal_state_to_its_level() {
+        for (state, level) in [("nominal", 0), ("fair", 1), ("serious", 2), ("critical", 3)] {
+            assert_eq!(pressure_level(state), Some(level), "{}", state);
+        }
+        assert_eq!(pressure_level("Nominal"), None);
+        assert_eq!(pressure_level(""), None);
+    }
+
+    #[test]
+    fn reports_throttling_from_the_serious_level() {
+        for (state, level, throttled) in [("nominal", 0, false), ("fair", 1, false), ("serious", 2, true), ("critical", 3, true)] {
+            let json = format!(r#"{{"thermal_state": "{}"}}"#, state);
+            let metrics = MacOSThermalCollector::new().parse_metrics(&json).unwrap();
+
+            assert_eq!(metrics[0].name, names::THERMAL_PRESSURE_LEVEL);
+            assert_eq!(metrics[0].value, MetricValue::Integer(level));
+            assert_eq!(metrics[1].name, names::THERMAL_CPU_THROTTLED);
+            assert_eq!(metrics[1].value, MetricValue::Boolean(throttled), "{}", state);
+            assert_eq!(metrics[1].metadata["thermal_state"], state);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_states() {
+        let error = MacOSThermalCollector::new().parse_metrics(r#"{"thermal_state": "melting"}"#).unwrap_err();
+        assert!(error.to_string().contains("melting"));
+    }
+}
@@ -0,0 +1,42 @@
+import Foundation
+
+func thermalStateName(_ state: ProcessInfo.ThermalState) -> String {
+    switch state {
+    case .nominal:
+        return "nominal"
+    case .fair:
+        return "fair"
+    case .serious:
+        return "serious"
+    case .critical:
+        return "critical"
+    @unknown default:
+        return "unknown"
+    }
+}
+
+// C-style function for FFI
+@_cdecl("collect_thermal_metrics_json")
+func collectThermalMetricsJSON() -> UnsafePointer<CChar>? {
+    let payload: [String: Any] = [
+        "thermal_state": thermalStateName(ProcessInfo.processInfo.thermalState)
+    ]
+
+    do {
+        let jsonData = try JSONSerialization.data(withJSONObject: payload, options: [])
+        if let jsonString = String(data: jsonData, encoding: .utf8) {
+            return UnsafePointer(strdup(jsonString))
+        }
+    } catch {
+        return nil
+    }
+
+    return nil
+}
+
+@_cdecl("free_thermal_string")
+func freeThermalString(_ ptr: UnsafePointer<CChar>?) {
+    if let ptr = ptr {
+        free(UnsafeMutableRawPointer(mutating: ptr))
+    }
+}
@@ -0,0 +1,48 @@
+//! Thermal pressure collector.
+//!
+//! Reports the OS thermal state as `thermal.pressure_level` (0 nominal, 1 fair,
+//! 2 serious, 3 critical) and whether it is high enough that the system is
+//! throttling (`thermal.cpu_throttled`). Low utilization while throttled means
+//! the chip is capped, not idle.
+
+pub mod macos;
+
+use super::{Collector, Metric};
+use crate::ThrudError;
+
+pub struct ThermalCollector {
+    #[cfg(target_os = "macos")]
+    macos: macos::MacOSThermalCollector,
+}
+
+impl Default for ThermalCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThermalCollector {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(target_os = "macos")]
+            macos: macos::MacOSThermalCollector::new(),
+        }
+    }
+}
+
+impl Collector for ThermalCollector {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError> {
+        #[cfg(target_os = "macos")]
+        {
+            self.macos.collect()
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            Ok(vec![])
+        }
+    }
+
+    fn name(&self) -> &str {
+        "thermal"
+    }
+}
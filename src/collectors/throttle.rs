@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Suppresses repeats of identical error messages.
+///
+/// The first occurrence of a message is reported right away; repeats within the
+/// following `window` are only counted, and once the window has passed the count
+/// is reported as a single summary. A message that keeps repeating gets one
+/// summary per window, and one that stops is forgotten after a quiet window, so
+/// its next occurrence is reported in full again.
+#[derive(Debug)]
+pub struct ErrorThrottle {
+    window: Duration,
+    /// Message -> (start of its current window, repeats suppressed in it)
+    seen: HashMap<String, (Instant, u64)>,
+}
+
+impl ErrorThrottle {
+    pub fn new(window: Duration) -> Self {
+        Self { window, seen: HashMap::new() }
+    }
+
+    /// Whether `message` should be reported now, counting it as suppressed if not
+    pub fn should_report(&mut self, message: &str, now: Instant) -> bool {
+        match self.seen.get_mut(message) {
+            Some((_, suppressed)) => {
+                *suppressed += 1;
+                false
+            }
+            None => {
+                self.seen.insert(message.to_string(), (now, 0));
+                true
+            }
+        }
+    }
+
+    /// Summaries of the messages whose window has passed with repeats suppressed,
+    /// e.g. "gpu: bridge timeout (repeated 137 times in the last 10s)", in message
+    /// order. Starts a new window for each of them.
+    pub fn summaries(&mut self, now: Instant) -> Vec<String> {
+        let window = self.window;
+        self.seen
+            .retain(|_, (start, suppressed)| now.duration_since(*start) < window || *suppressed > 0);
+
+        let mut summaries: Vec<String> = self
+            .seen
+            .iter_mut()
+            .filter(|(_, (start, suppressed))| now.duration_since(*start) >= window && *suppressed > 0)
+            .map(|(message, (start, suppressed))| {
+                let summary = format!(
+                    "{} (repeated {} times in the last {}s)",
+                    message,
+                    suppressed,
+                    now.duration_since(*start).as_secs()
+                );
+                *start = now;
+                *suppressed = 0;
+                summary
+            })
+            .collect();
+        summaries.sort();
+        summaries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn repeats_are_suppressed_and_summarized_after_the_window() {
+        let mut throttle = ErrorThrottle::new(WINDOW);
+        let start = Instant::now();
+
+        assert!(throttle.should_report("gpu: bridge timeout", start));
+        for second in 1..=5 {
+            assert!(!throttle.should_report("gpu: bridge timeout", start + Duration::from_secs(second)));
+        }
+        // Nothing to summarize until the window has passed
+        assert!(throttle.summaries(start + Duration::from_secs(9)).is_empty());
+
+        assert_eq!(
+            throttle.summaries(start + WINDOW),
+            ["gpu: bridge timeout (repeated 5 times in the last 10s)"]
+        );
+        // The summary starts a new window with nothing suppressed in it yet
+        assert!(throttle.summaries(start + WINDOW).is_empty());
+    }
+
+    #[test]
+    fn different_messages_are_throttled_separately() {
+        let mut throttle = ErrorThrottle::new(WINDOW);
+        let start = Instant::now();
+
+        assert!(throttle.should_report("gpu: bridge timeout", start));
+        assert!(throttle.should_report("disk: no such device", start));
+        assert!(!throttle.should_report("gpu: bridge timeout", start));
+        assert!(!throttle.should_report("disk: no such device", start));
+        assert!(!throttle.should_report("disk: no such device", start));
+
+        assert_eq!(
+            throttle.summaries(start + WINDOW),
+            [
+                "disk: no such device (repeated 2 times in the last 10s)",
+                "gpu: bridge timeout (repeated 1 times in the last 10s)",
+            ]
+        );
+    }
+
+    #[test]
+    fn message_that_stopped_is_reported_in_full_again() {
+        let mut throttle = ErrorThrottle::new(WINDOW);
+        let start = Instant::now();
+
+        assert!(throttle.should_report("gpu: bridge timeout", start));
+        // A quiet window forgets the message
+        assert!(throttle.summaries(start + WINDOW).is_empty());
+        assert!(throttle.should_report("gpu: bridge timeout", start + WINDOW));
+    }
+}
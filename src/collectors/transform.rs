@@ -0,0 +1,131 @@
+use super::filter::glob_match;
+use super::{names, Metric, MetricValue};
+use serde::Deserialize;
+
+/// Linear rescaling of a metric's value: `value * scale + offset`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransformRule {
+    pub scale: f64,
+    pub offset: f64,
+}
+
+impl Default for TransformRule {
+    fn default() -> Self {
+        Self { scale: 1.0, offset: 0.0 }
+    }
+}
+
+/// Rescales the values of metrics selected by name, e.g. to turn a 0-1 ratio
+/// into a percentage before it is stored.
+///
+/// Rules are keyed by glob patterns, matched like [`super::MetricFilter`]
+/// patterns (aliases included). A metric is rescaled by the first rule whose
+/// pattern matches it, in the order the rules are given, so a specific pattern
+/// listed before a broader one overrides it. Integer and float values become
+/// floats; other values are left unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct MetricTransform {
+    rules: Vec<(String, TransformRule)>,
+}
+
+impl MetricTransform {
+    pub fn new(rules: &[(String, TransformRule)]) -> Self {
+        let rules = rules
+            .iter()
+            .map(|(pattern, rule)| (names::canonical(pattern).to_string(), *rule))
+            .collect();
+        Self { rules }
+    }
+
+    /// Whether no metric is changed
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The rule applied to metrics with this name, if any
+    pub fn rule_for(&self, name: &str) -> Option<&TransformRule> {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, name))
+            .map(|(_, rule)| rule)
+    }
+
+    /// Rescale the metrics matched by a rule, passing the rest through
+    pub fn apply(&self, mut metrics: Vec<Metric>) -> Vec<Metric> {
+        if self.is_empty() {
+            return metrics;
+        }
+        for metric in &mut metrics {
+            let Some(rule) = self.rule_for(&metric.name) else {
+                continue;
+            };
+            let value = match metric.value {
+                MetricValue::Integer(v) => v as f64,
+                MetricValue::Float(v) => v,
+                _ => continue,
+            };
+            metric.value = MetricValue::Float(value * rule.scale + rule.offset);
+        }
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(rules: &[(&str, f64, f64)]) -> MetricTransform {
+        let rules: Vec<(String, TransformRule)> = rules
+            .iter()
+            .map(|&(pattern, scale, offset)| (pattern.to_string(), TransformRule { scale, offset }))
+            .collect();
+        MetricTransform::new(&rules)
+    }
+
+    #[test]
+    fn scale_rule_rescales_matching_metrics() {
+        let transform = transform(&[("sensors.temp.*", 1.8, 32.0)]);
+
+        let metrics = transform.apply(vec![
+            Metric::gauge("sensors.temp.celsius", 100.0),
+            Metric::counter("sensors.temp.samples", 10),
+        ]);
+
+        assert_eq!(metrics[0].value, MetricValue::Float(212.0));
+        assert_eq!(metrics[1].value, MetricValue::Float(50.0));
+    }
+
+    #[test]
+    fn unmatched_and_non_numeric_metrics_pass_through() {
+        let transform = transform(&[("sensors.*", 2.0, 0.0)]);
+        let metrics = vec![
+            Metric::counter("cpu.performance.idle_ticks", 7),
+            Metric::new("sensors.fan.state".to_string(), MetricValue::String("auto".to_string()), Default::default()),
+        ];
+
+        let transformed = transform.apply(metrics.clone());
+
+        assert_eq!(transformed[0].value, metrics[0].value);
+        assert_eq!(transformed[1].value, metrics[1].value);
+        assert_eq!(MetricTransform::default().apply(metrics).len(), 2);
+    }
+
+    #[test]
+    fn first_declared_rule_wins_when_patterns_overlap() {
+        // Sorted by pattern, "sensors.*" would come first
+        let specific_first = transform(&[("sensors.temp.celsius", 1.0, 1.0), ("sensors.*", 1.0, 100.0)]);
+        let broad_first = transform(&[("sensors.*", 1.0, 100.0), ("sensors.temp.celsius", 1.0, 1.0)]);
+
+        assert_eq!(specific_first.rule_for("sensors.temp.celsius").map(|rule| rule.offset), Some(1.0));
+        assert_eq!(broad_first.rule_for("sensors.temp.celsius").map(|rule| rule.offset), Some(100.0));
+        assert_eq!(specific_first.rule_for("sensors.fan.rpm").map(|rule| rule.offset), Some(100.0));
+    }
+
+    #[test]
+    fn aliases_select_the_canonical_name() {
+        let transform = transform(&[("memory_used_bytes", 0.5, 0.0)]);
+
+        assert!(transform.rule_for(names::MEMORY_USED_BYTES).is_some());
+    }
+}
@@ -1,24 +1,215 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fmt;
+use crate::ThrudError;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MetricValue {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Boolean(bool),
+    /// A distribution as `(value, count)` buckets, e.g. the time spent at each
+    /// DVFS frequency
+    Histogram { buckets: Vec<(f64, u64)> },
+}
+
+impl fmt::Display for MetricValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricValue::Integer(v) => write!(f, "{}", v),
+            MetricValue::Float(v) => write!(f, "{}", v),
+            MetricValue::String(v) => write!(f, "{}", v),
+            MetricValue::Boolean(v) => write!(f, "{}", v),
+            MetricValue::Histogram { buckets } => {
+                let buckets: Vec<String> = buckets.iter().map(|(value, count)| format!("{}: {}", value, count)).collect();
+                write!(f, "[{}]", buckets.join(", "))
+            }
+        }
+    }
+}
+
+/// How consecutive samples of a metric relate to each other
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricKind {
+    /// A point-in-time reading such as a utilization or temperature
+    #[default]
+    Gauge,
+    /// A monotonically increasing total; rates come from deltas between samples
+    Counter,
+}
+
+impl MetricKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Gauge => "gauge",
+            MetricKind::Counter => "counter",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "gauge" => Some(MetricKind::Gauge),
+            "counter" => Some(MetricKind::Counter),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Metric {
     pub name: String,
-    pub value: String,
+    pub value: MetricValue,
+    pub metadata: HashMap<String, String>,
     pub timestamp: DateTime<Utc>,
+    /// Unit of the value, e.g. "bytes" or "celsius"; `None` for unitless values
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub kind: MetricKind,
 }
 
 impl Metric {
-    pub fn new(name: String, value: String) -> Self {
+    pub fn new(name: String, value: MetricValue, metadata: HashMap<String, String>) -> Self {
         Self {
             name,
             value,
+            metadata,
             timestamp: Utc::now(),
+            unit: None,
+            kind: MetricKind::Gauge,
         }
     }
+
+    /// A point-in-time floating-point reading with no metadata, e.g.
+    /// `Metric::gauge("gpu.0.utilization", 0.5).with_tag("gpu_index", "0")`
+    pub fn gauge(name: impl Into<String>, value: f64) -> Self {
+        Self::new(name.into(), MetricValue::Float(value), HashMap::new())
+    }
+
+    /// A monotonically increasing total with no metadata, e.g.
+    /// `Metric::counter("cpu.performance.idle_ticks", 100).with_tag("core_type", "performance")`
+    pub fn counter(name: impl Into<String>, value: i64) -> Self {
+        Self::new(name.into(), MetricValue::Integer(value), HashMap::new()).with_kind(MetricKind::Counter)
+    }
+
+    /// Add a metadata entry, replacing any existing value for `key`
+    pub fn with_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_unit(mut self, unit: &str) -> Self {
+        self.unit = Some(unit.to_string());
+        self
+    }
+
+    pub fn with_kind(mut self, kind: MetricKind) -> Self {
+        self.kind = kind;
+        self
+    }
 }
 
-pub trait Collector {
-    fn collect(&self) -> Result<Vec<Metric>, Box<dyn std::error::Error>>;
+/// Collectors may run on a blocking thread pool, so they must be shareable across threads
+pub trait Collector: Send + Sync {
+    fn collect(&self) -> Result<Vec<Metric>, ThrudError>;
     fn name(&self) -> &str;
-}
\ No newline at end of file
+
+    /// Acquire resources held for the collector's lifetime (e.g. FFI handles),
+    /// called once before the first `collect`
+    fn init(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Release whatever `init` acquired, called once after the last `collect`
+    fn shutdown(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Everything but the timestamp, which differs between any two `new` calls
+    fn assert_equivalent(built: &Metric, verbose: &Metric) {
+        assert_eq!(built.name, verbose.name);
+        assert_eq!(built.value, verbose.value);
+        assert_eq!(built.metadata, verbose.metadata);
+        assert_eq!(built.unit, verbose.unit);
+        assert_eq!(built.kind, verbose.kind);
+    }
+
+    #[test]
+    fn gauge_builder_matches_the_verbose_form() {
+        let built = Metric::gauge("gpu.0.utilization", 0.5).with_tag("gpu_index", "0");
+
+        let metadata = HashMap::from([("gpu_index".to_string(), "0".to_string())]);
+        let verbose = Metric::new("gpu.0.utilization".to_string(), MetricValue::Float(0.5), metadata);
+
+        assert_equivalent(&built, &verbose);
+    }
+
+    #[test]
+    fn counter_builder_matches_the_verbose_form() {
+        let built = Metric::counter("cpu_user_ticks", 100).with_tag("core_id", "0").with_tag("core_type", "performance");
+
+        let metadata = HashMap::from([
+            ("core_id".to_string(), "0".to_string()),
+            ("core_type".to_string(), "performance".to_string()),
+        ]);
+        let mut verbose = Metric::new("cpu_user_ticks".to_string(), MetricValue::Integer(100), metadata);
+        verbose.kind = MetricKind::Counter;
+
+        assert_equivalent(&built, &verbose);
+    }
+
+    #[test]
+    fn repeated_tag_keeps_the_last_value() {
+        let metric = Metric::gauge("gpu.0.utilization", 0.5).with_tag("gpu_index", "0").with_tag("gpu_index", "1");
+
+        assert_eq!(metric.metadata.len(), 1);
+        assert_eq!(metric.metadata["gpu_index"], "1");
+    }
+
+    #[test]
+    fn unit_and_kind_round_trip_through_json() {
+        let metric = Metric::counter("memory.swap_used_bytes", 4096).with_unit("bytes");
+
+        let json = serde_json::to_value(&metric).unwrap();
+        assert_eq!(json["unit"], "bytes");
+        assert_eq!(json["kind"], "counter");
+
+        let parsed: Metric = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.unit.as_deref(), Some("bytes"));
+        assert_eq!(parsed.kind, MetricKind::Counter);
+    }
+
+    #[test]
+    fn metrics_without_unit_and_kind_are_unitless_gauges() {
+        let json = serde_json::json!({
+            "name": "cpu.utilization",
+            "value": { "Float": 0.5 },
+            "metadata": {},
+            "timestamp": "2024-01-01T00:00:00Z",
+        });
+
+        let metric: Metric = serde_json::from_value(json).unwrap();
+
+        assert_eq!(metric.unit, None);
+        assert_eq!(metric.kind, MetricKind::Gauge);
+    }
+
+    #[test]
+    fn histogram_round_trips_through_json() {
+        let value = MetricValue::Histogram { buckets: vec![(600.0, 3), (1296.0, 0), (3228.5, 12)] };
+
+        let json = serde_json::to_value(&value).unwrap();
+        assert_eq!(json, serde_json::json!({ "Histogram": { "buckets": [[600.0, 3], [1296.0, 0], [3228.5, 12]] } }));
+        assert_eq!(serde_json::from_value::<MetricValue>(json).unwrap(), value);
+
+        let empty = MetricValue::Histogram { buckets: Vec::new() };
+        assert_eq!(serde_json::from_str::<MetricValue>(&serde_json::to_string(&empty).unwrap()).unwrap(), empty);
+    }
+}
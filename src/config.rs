@@ -0,0 +1,259 @@
+//! Collector configuration file support.
+//!
+//! A config file sets defaults for the collector binary; command-line flags
+//! override it. Example `thrud.toml`:
+//!
+//! ```toml
+//! interval = 5.0             # default collection interval in seconds
+//! database = "~/.thrud/thrud.db"
+//! retention_hours = 24
+//! max_db_mb = 500
+//! collectors = ["cpu", "gpu", "disk"]
+//!
+//! [collector.gpu]
+//! interval = 0.5             # per-collector override
+//!
+//! [transform."sensors.temp.celsius"]
+//! offset = -2.5              # store value * scale + offset, here to calibrate a sensor
+//! ```
+//!
+//! Transforms apply in the order they are declared. Aggregations and charts
+//! expect the units the collectors report (GPU utilization as a 0-1 ratio, for
+//! example), so transforms are meant for metrics they don't read.
+
+use crate::collectors::TransformRule;
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// Default collection interval in seconds when neither CLI nor file sets one
+pub const DEFAULT_INTERVAL_SECS: f64 = 5.0;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Default collection interval in seconds
+    pub interval: Option<f64>,
+    /// Database path; a leading `~/` expands to $HOME
+    pub database: Option<String>,
+    /// Delete stored data older than this many hours
+    pub retention_hours: Option<f64>,
+    /// Prune the oldest rounds once the database's live data exceeds this many MB
+    pub max_db_mb: Option<f64>,
+    /// Collectors to run; all registered collectors when unset
+    pub collectors: Option<Vec<String>>,
+    /// Per-collector settings keyed by collector name
+    pub collector: HashMap<String, CollectorConfig>,
+    /// Value rescaling applied before metrics are stored as (metric name glob,
+    /// rule) pairs, in the order the config declares them
+    #[serde(deserialize_with = "ordered_rules")]
+    pub transform: Vec<(String, TransformRule)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectorConfig {
+    /// Collection interval in seconds for this collector
+    pub interval: Option<f64>,
+}
+
+impl Config {
+    /// Load a config file
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read config {}: {}", path, e))?;
+        Self::parse(&contents).map_err(|e| format!("Invalid config {}: {}", path, e).into())
+    }
+
+    /// Parse config file contents
+    pub fn parse(contents: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Layer `overrides` (typically from CLI flags) on top of this config; any
+    /// value set in `overrides` wins
+    pub fn merge(self, overrides: Config) -> Config {
+        let mut collector = self.collector;
+        for (name, settings) in overrides.collector {
+            let entry = collector.entry(name).or_default();
+            if settings.interval.is_some() {
+                entry.interval = settings.interval;
+            }
+        }
+
+        Config {
+            interval: overrides.interval.or(self.interval),
+            database: overrides.database.or(self.database),
+            retention_hours: overrides.retention_hours.or(self.retention_hours),
+            max_db_mb: overrides.max_db_mb.or(self.max_db_mb),
+            collectors: overrides.collectors.or(self.collectors),
+            collector,
+            // The first matching rule applies, so overriding rules go first
+            transform: overrides.transform.into_iter().chain(self.transform).collect(),
+        }
+    }
+
+    /// Default collection interval, falling back to [`DEFAULT_INTERVAL_SECS`]
+    pub fn interval(&self) -> f64 {
+        self.interval.unwrap_or(DEFAULT_INTERVAL_SECS)
+    }
+
+    /// Collection interval for a collector: its own setting, else the default
+    pub fn collector_interval(&self, name: &str) -> f64 {
+        self.collector
+            .get(name)
+            .and_then(|c| c.interval)
+            .unwrap_or_else(|| self.interval())
+    }
+
+    /// Database path with `~/` expanded, if one is configured. A `~/` path is an
+    /// error when HOME is unset rather than silently landing in the working directory.
+    pub fn database_path(&self) -> Result<Option<String>, Box<dyn Error>> {
+        let home_dir = std::env::var("HOME").ok().filter(|home| !home.is_empty());
+        self.database
+            .as_ref()
+            .map(|path| expand_home(path, home_dir.as_deref()))
+            .transpose()
+    }
+}
+
+/// Read a table of transform rules keeping its declaration order, which decides
+/// the rule applied when patterns overlap
+fn ordered_rules<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(String, TransformRule)>, D::Error> {
+    struct RulesVisitor;
+
+    impl<'de> Visitor<'de> for RulesVisitor {
+        type Value = Vec<(String, TransformRule)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a table of transform rules keyed by metric name glob")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut rules = Vec::new();
+            while let Some(entry) = map.next_entry()? {
+                rules.push(entry);
+            }
+            Ok(rules)
+        }
+    }
+
+    deserializer.deserialize_map(RulesVisitor)
+}
+
+/// Expand a leading `~/` in `path` to `home_dir`
+fn expand_home(path: &str, home_dir: Option<&str>) -> Result<String, Box<dyn Error>> {
+    let Some(rest) = path.strip_prefix("~/") else {
+        return Ok(path.to_string());
+    };
+    match home_dir {
+        Some(home_dir) => Ok(format!("{}/{}", home_dir, rest)),
+        None => Err(format!(
+            "Cannot expand database path {}: HOME is not set. Pass --database with a full path, \
+             or remove `database` from the config to use $THRUD_DB",
+            path
+        )
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_documented_example() {
+        let config = Config::parse(
+            r#"
+            interval = 5.0             # default collection interval in seconds
+            database = "~/.thrud/thrud.db"
+            retention_hours = 24
+            max_db_mb = 500
+            collectors = ["cpu", "gpu", "disk"]
+
+            [collector.gpu]
+            interval = 0.5             # per-collector override
+
+            [transform."sensors.temp.celsius"]
+            offset = -2.5              # store value * scale + offset, here to calibrate a sensor
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.interval, Some(5.0));
+        assert_eq!(config.database.as_deref(), Some("~/.thrud/thrud.db"));
+        assert_eq!(config.retention_hours, Some(24.0));
+        assert_eq!(config.max_db_mb, Some(500.0));
+        assert_eq!(config.collectors, Some(vec!["cpu".to_string(), "gpu".to_string(), "disk".to_string()]));
+        assert_eq!(config.collector_interval("gpu"), 0.5);
+        assert_eq!(config.collector_interval("cpu"), 5.0);
+        assert_eq!(config.transform, [("sensors.temp.celsius".to_string(), TransformRule { scale: 1.0, offset: -2.5 })]);
+    }
+
+    #[test]
+    fn transforms_keep_their_declaration_order() {
+        let config = Config::parse(
+            r#"
+            [transform."sensors.*"]
+            scale = 2.0
+            [transform."power.adapter_watts"]
+            scale = 3.0
+            [transform."ane.*"]
+            scale = 4.0
+            "#,
+        )
+        .unwrap();
+
+        let patterns: Vec<&str> = config.transform.iter().map(|(pattern, _)| pattern.as_str()).collect();
+        assert_eq!(patterns, ["sensors.*", "power.adapter_watts", "ane.*"]);
+    }
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let config = Config::parse("").unwrap();
+
+        assert_eq!(config, Config::default());
+        assert_eq!(config.interval(), DEFAULT_INTERVAL_SECS);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_bad_values() {
+        assert!(Config::parse("intervall = 5").is_err());
+        assert!(Config::parse("[collector.gpu]\nrate = 1").is_err());
+        assert!(Config::parse("interval = \"fast\"").is_err());
+        assert!(Config::parse("interval = ").is_err());
+        assert!(Config::parse("interval = 1\ninterval = 2").is_err());
+    }
+
+    #[test]
+    fn database_path_expands_home() {
+        assert_eq!(expand_home("~/.thrud/thrud.db", Some("/home/me")).unwrap(), "/home/me/.thrud/thrud.db");
+        assert_eq!(expand_home("/var/lib/thrud.db", None).unwrap(), "/var/lib/thrud.db");
+    }
+
+    #[test]
+    fn database_path_without_home_is_an_error() {
+        let error = expand_home("~/.thrud/thrud.db", None).unwrap_err().to_string();
+
+        assert!(error.contains("HOME is not set"), "{}", error);
+        assert!(error.contains("--database"), "{}", error);
+        assert!(error.contains("THRUD_DB"), "{}", error);
+    }
+
+    #[test]
+    fn overrides_win_when_merged() {
+        let file = Config::parse("interval = 5\nretention_hours = 24\n[collector.gpu]\ninterval = 0.5").unwrap();
+        let mut overrides = Config { interval: Some(1.0), ..Config::default() };
+        overrides.collector.insert("cpu".to_string(), CollectorConfig { interval: Some(2.0) });
+
+        let merged = file.merge(overrides);
+
+        assert_eq!(merged.interval(), 1.0);
+        assert_eq!(merged.retention_hours, Some(24.0));
+        assert_eq!(merged.collector_interval("gpu"), 0.5);
+        assert_eq!(merged.collector_interval("cpu"), 2.0);
+        assert_eq!(merged.collector_interval("disk"), 1.0);
+    }
+}
@@ -0,0 +1,119 @@
+use std::error::Error;
+use std::fmt;
+
+/// Errors returned by the `Collector` and `Storage` traits.
+///
+/// Keeping the underlying SQLite error lets callers tell a transient lock (see
+/// [`ThrudError::is_busy`]) from a schema or I/O problem.
+#[derive(Debug)]
+pub enum ThrudError {
+    Sqlite(rusqlite::Error),
+    Io(std::io::Error),
+    Serialization(serde_json::Error),
+    /// A collector could not produce its metrics
+    CollectorFailed { name: String, message: String },
+    /// A collection round with no metrics was passed to storage
+    EmptyMetrics,
+    /// A collection round identical to the previous one was skipped, see
+    /// `SqliteStorage::with_round_dedup`
+    DuplicateRound,
+    Other(String),
+}
+
+impl ThrudError {
+    /// Attribute a failure to the named collector
+    pub fn collector_failed(name: &str, error: impl fmt::Display) -> Self {
+        ThrudError::CollectorFailed {
+            name: name.to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    /// Whether this is SQLite reporting the database as busy or locked by another
+    /// connection, which is worth retrying
+    pub fn is_busy(&self) -> bool {
+        matches!(
+            self,
+            ThrudError::Sqlite(rusqlite::Error::SqliteFailure(e, _))
+                if matches!(e.code, rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+        )
+    }
+}
+
+impl fmt::Display for ThrudError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThrudError::Sqlite(e) => write!(f, "SQLite error: {}", e),
+            ThrudError::Io(e) => write!(f, "I/O error: {}", e),
+            ThrudError::Serialization(e) => write!(f, "Serialization error: {}", e),
+            ThrudError::CollectorFailed { name, message } => write!(f, "{}: {}", name, message),
+            ThrudError::EmptyMetrics => write!(f, "Cannot store empty metrics collection"),
+            ThrudError::DuplicateRound => write!(f, "Skipped collection round identical to the previous one"),
+            ThrudError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ThrudError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ThrudError::Sqlite(e) => Some(e),
+            ThrudError::Io(e) => Some(e),
+            ThrudError::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ThrudError {
+    fn from(e: rusqlite::Error) -> Self {
+        ThrudError::Sqlite(e)
+    }
+}
+
+impl From<std::io::Error> for ThrudError {
+    fn from(e: std::io::Error) -> Self {
+        ThrudError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ThrudError {
+    fn from(e: serde_json::Error) -> Self {
+        ThrudError::Serialization(e)
+    }
+}
+
+impl From<String> for ThrudError {
+    fn from(message: String) -> Self {
+        ThrudError::Other(message)
+    }
+}
+
+impl From<&str> for ThrudError {
+    fn from(message: &str) -> Self {
+        ThrudError::Other(message.to_string())
+    }
+}
+
+/// Recovers the specific variant from errors raised by helpers that still
+/// return `Box<dyn Error>`
+impl From<Box<dyn Error>> for ThrudError {
+    fn from(e: Box<dyn Error>) -> Self {
+        let e = match e.downcast::<ThrudError>() {
+            Ok(e) => return *e,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<rusqlite::Error>() {
+            Ok(e) => return ThrudError::Sqlite(*e),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<std::io::Error>() {
+            Ok(e) => return ThrudError::Io(*e),
+            Err(e) => e,
+        };
+        match e.downcast::<serde_json::Error>() {
+            Ok(e) => ThrudError::Serialization(*e),
+            Err(e) => ThrudError::Other(e.to_string()),
+        }
+    }
+}
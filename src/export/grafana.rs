@@ -0,0 +1,237 @@
+use crate::collectors::{Metric, MetricValue};
+use crate::storage::SqliteStorage;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Body of a SimpleJSON `/search` request; `target` is what the user has typed so far
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchRequest {
+    #[serde(default)]
+    pub target: String,
+}
+
+/// Body of a SimpleJSON `/query` request. Fields Grafana sends but thrud doesn't
+/// use, such as `interval` or a target's `refId`, are ignored.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryRequest {
+    pub range: TimeRange,
+    pub targets: Vec<QueryTarget>,
+    /// Most points Grafana wants per series; longer series are thinned out
+    pub max_data_points: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimeRange {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueryTarget {
+    /// Metric name, as returned by `/search`
+    pub target: String,
+}
+
+/// One time series of a `/query` response. Datapoints are `[value, epoch_ms]`
+/// pairs, value first, as Grafana expects.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Series {
+    pub target: String,
+    pub datapoints: Vec<(f64, i64)>,
+}
+
+/// Stored metric names containing the search `target`, sorted
+pub fn search(storage: &SqliteStorage, request: &SearchRequest) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut names = storage.metric_names()?;
+    names.retain(|name| name.contains(request.target.trim()));
+    Ok(names)
+}
+
+/// Time series of every requested metric within the request's time range
+pub fn query(storage: &SqliteStorage, request: &QueryRequest) -> Result<Vec<Series>, Box<dyn Error>> {
+    let mut series = Vec::new();
+    for target in &request.targets {
+        let mut metrics = storage.query_metrics(&target.target, Some(request.range.from))?;
+        metrics.retain(|metric| metric.timestamp <= request.range.to);
+        series.extend(to_series(&target.target, &metrics, request.max_data_points));
+    }
+    Ok(series)
+}
+
+/// Grafana series for the samples of one metric. Samples with different metadata
+/// (e.g. one per process) form separate series named `name{key="value",...}`,
+/// with values quoted as JSON strings so commas and quotes in them (e.g. a GPU
+/// name) can't run into the next label; a metric with a single metadata set
+/// keeps its plain name. Integer, float and
+/// boolean (0/1) values are charted; strings and histograms are skipped.
+pub fn to_series(name: &str, metrics: &[Metric], max_data_points: Option<usize>) -> Vec<Series> {
+    let mut groups: BTreeMap<BTreeMap<&String, &String>, Vec<(f64, i64)>> = BTreeMap::new();
+    for metric in metrics {
+        if let Some(value) = datapoint_value(&metric.value) {
+            groups
+                .entry(metric.metadata.iter().collect())
+                .or_default()
+                .push((value, metric.timestamp.timestamp_millis()));
+        }
+    }
+
+    let labelled = groups.len() > 1;
+    groups
+        .into_iter()
+        .map(|(metadata, datapoints)| {
+            let target = if labelled {
+                let labels: Vec<String> = metadata
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, serde_json::Value::from(v.as_str())))
+                    .collect();
+                format!("{}{{{}}}", name, labels.join(","))
+            } else {
+                name.to_string()
+            };
+            Series { target, datapoints: thin(datapoints, max_data_points) }
+        })
+        .collect()
+}
+
+fn datapoint_value(value: &MetricValue) -> Option<f64> {
+    match value {
+        MetricValue::Integer(v) => Some(*v as f64),
+        MetricValue::Float(v) if v.is_finite() => Some(*v),
+        MetricValue::Boolean(v) => Some(if *v { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+}
+
+/// Keep every n-th point so at most `max_points` remain, always keeping the newest
+fn thin(datapoints: Vec<(f64, i64)>, max_points: Option<usize>) -> Vec<(f64, i64)> {
+    let Some(max_points) = max_points.filter(|&max| max > 0 && datapoints.len() > max) else {
+        return datapoints;
+    };
+    let step = datapoints.len().div_ceil(max_points);
+    let last = datapoints.len() - 1;
+    datapoints
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| (last - i).is_multiple_of(step))
+        .map(|(_, point)| point)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage;
+    use serde_json::json;
+
+    fn sample(name: &str, value: MetricValue, at_ms: i64) -> Metric {
+        let mut metric = Metric::new(name.to_string(), value, Default::default());
+        metric.timestamp = DateTime::from_timestamp_millis(at_ms).unwrap();
+        metric
+    }
+
+    fn storage() -> SqliteStorage {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        storage
+            .store_metrics(vec![
+                sample("gpu.0.utilization", MetricValue::Float(0.25), 1_000),
+                sample("cpu.performance.idle_ticks", MetricValue::Integer(40), 1_000),
+                sample("thermal.cpu_throttled", MetricValue::Boolean(true), 1_000),
+            ])
+            .unwrap();
+        storage
+            .store_metrics(vec![sample("gpu.0.utilization", MetricValue::Float(0.5), 2_000)])
+            .unwrap();
+        storage
+    }
+
+    fn query_json(storage: &SqliteStorage, body: serde_json::Value) -> serde_json::Value {
+        let request: QueryRequest = serde_json::from_value(body).unwrap();
+        serde_json::to_value(query(storage, &request).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn search_responds_with_matching_names() {
+        let storage = storage();
+
+        let all = search(&storage, &SearchRequest::default()).unwrap();
+        assert_eq!(
+            serde_json::to_value(all).unwrap(),
+            json!(["cpu.performance.idle_ticks", "gpu.0.utilization", "thermal.cpu_throttled"])
+        );
+
+        let gpu = search(&storage, &SearchRequest { target: " gpu ".to_string() }).unwrap();
+        assert_eq!(serde_json::to_value(gpu).unwrap(), json!(["gpu.0.utilization"]));
+    }
+
+    #[test]
+    fn query_responds_with_value_then_timestamp_datapoints() {
+        let storage = storage();
+
+        let response = query_json(
+            &storage,
+            json!({
+                "range": { "from": "1970-01-01T00:00:00Z", "to": "1970-01-01T00:00:05Z" },
+                "targets": [
+                    { "target": "gpu.0.utilization", "refId": "A" },
+                    { "target": "thermal.cpu_throttled", "refId": "B" }
+                ],
+                "interval": "1s"
+            }),
+        );
+
+        assert_eq!(
+            response,
+            json!([
+                { "target": "gpu.0.utilization", "datapoints": [[0.25, 1000], [0.5, 2000]] },
+                { "target": "thermal.cpu_throttled", "datapoints": [[1.0, 1000]] }
+            ])
+        );
+    }
+
+    #[test]
+    fn query_keeps_to_the_range_and_max_data_points() {
+        let storage = storage();
+
+        let response = query_json(
+            &storage,
+            json!({
+                "range": { "from": "1970-01-01T00:00:01.500Z", "to": "1970-01-01T00:00:05Z" },
+                "targets": [{ "target": "gpu.0.utilization" }, { "target": "not.stored" }]
+            }),
+        );
+        assert_eq!(response, json!([{ "target": "gpu.0.utilization", "datapoints": [[0.5, 2000]] }]));
+
+        let response = query_json(
+            &storage,
+            json!({
+                "range": { "from": "1970-01-01T00:00:00Z", "to": "1970-01-01T00:00:05Z" },
+                "targets": [{ "target": "gpu.0.utilization" }],
+                "maxDataPoints": 1
+            }),
+        );
+        // Thinning keeps the newest point
+        assert_eq!(response, json!([{ "target": "gpu.0.utilization", "datapoints": [[0.5, 2000]] }]));
+    }
+
+    #[test]
+    fn series_with_several_metadata_sets_are_labelled() {
+        let metrics = vec![
+            sample("proc.cpu_percent", MetricValue::Float(10.0), 1_000).with_tag("pid", "1"),
+            sample("proc.cpu_percent", MetricValue::Float(20.0), 1_000).with_tag("pid", "2"),
+            sample("proc.cpu_percent", MetricValue::String("n/a".to_string()), 1_000).with_tag("pid", "3"),
+        ];
+
+        let series = serde_json::to_value(to_series("proc.cpu_percent", &metrics, None)).unwrap();
+
+        assert_eq!(
+            series,
+            json!([
+                { "target": "proc.cpu_percent{pid=\"1\"}", "datapoints": [[10.0, 1000]] },
+                { "target": "proc.cpu_percent{pid=\"2\"}", "datapoints": [[20.0, 1000]] }
+            ])
+        );
+    }
+}
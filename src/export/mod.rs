@@ -0,0 +1,9 @@
+//! Sinks that ship collected metrics to external systems alongside SQLite storage,
+//! and the Grafana datasource serving stored metrics.
+
+pub mod grafana;
+pub mod otlp;
+pub mod statsd;
+
+pub use otlp::OtlpExporter;
+pub use statsd::StatsdSink;
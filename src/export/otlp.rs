@@ -0,0 +1,197 @@
+use crate::collectors::{Metric, MetricValue};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const DEFAULT_PATH: &str = "/v1/metrics";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pushes metrics to an OpenTelemetry collector using OTLP/HTTP with JSON encoding.
+///
+/// Each metric name becomes an OTLP gauge whose data points carry the metric's
+/// metadata as attributes. Integer and Float values map to `asInt`/`asDouble`
+/// data points; String and Boolean values have no numeric form and are skipped.
+/// Only plain `http://` endpoints are supported.
+#[derive(Debug, Clone)]
+pub struct OtlpExporter {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl OtlpExporter {
+    /// Create an exporter for `http://host:port[/path]`; the path defaults to `/v1/metrics`
+    pub fn new(endpoint: &str) -> Result<Self, Box<dyn Error>> {
+        let rest = endpoint
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("OTLP endpoint must start with http://: {}", endpoint))?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) if i + 1 < rest.len() => (&rest[..i], &rest[i..]),
+            Some(i) => (&rest[..i], DEFAULT_PATH),
+            None => (rest, DEFAULT_PATH),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| format!("Invalid OTLP port: {}", port))?),
+            None => (authority, 4318),
+        };
+        if host.is_empty() {
+            return Err(format!("Missing host in OTLP endpoint: {}", endpoint).into());
+        }
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// Export metrics as one request, returning the number of data points sent
+    pub fn export(&self, metrics: &[Metric]) -> Result<usize, Box<dyn Error>> {
+        let (payload, data_points) = build_payload(metrics);
+        if data_points == 0 {
+            return Ok(0);
+        }
+        let body = serde_json::to_vec(&payload)?;
+
+        let addr = (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| format!("Cannot resolve OTLP host {}", self.host))?;
+        let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.path,
+            self.host,
+            self.port,
+            body.len()
+        )?;
+        stream.write_all(&body)?;
+        stream.flush()?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let status_line = response.lines().next().unwrap_or_default();
+        let status: u16 = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| format!("Malformed OTLP response: {}", status_line))?;
+        if !(200..300).contains(&status) {
+            return Err(format!("OTLP endpoint returned {}", status_line).into());
+        }
+
+        Ok(data_points)
+    }
+}
+
+/// Build an OTLP `ExportMetricsServiceRequest` JSON payload, returning it with
+/// the number of data points it contains
+pub fn build_payload(metrics: &[Metric]) -> (serde_json::Value, usize) {
+    let mut gauges: BTreeMap<&str, Vec<serde_json::Value>> = BTreeMap::new();
+
+    for metric in metrics {
+        let mut point = serde_json::json!({
+            "timeUnixNano": metric
+                .timestamp
+                .timestamp_nanos_opt()
+                .unwrap_or_default()
+                .to_string(),
+            "attributes": attributes(metric),
+        });
+        match &metric.value {
+            // OTLP JSON encodes 64-bit integers as strings
+            MetricValue::Integer(v) => point["asInt"] = serde_json::json!(v.to_string()),
+            MetricValue::Float(v) if v.is_finite() => point["asDouble"] = serde_json::json!(v),
+            _ => continue,
+        }
+        gauges.entry(&metric.name).or_default().push(point);
+    }
+
+    let data_points = gauges.values().map(Vec::len).sum();
+    let otlp_metrics: Vec<serde_json::Value> = gauges
+        .into_iter()
+        .map(|(name, points)| {
+            serde_json::json!({
+                "name": name,
+                "gauge": { "dataPoints": points },
+            })
+        })
+        .collect();
+
+    let payload = serde_json::json!({
+        "resourceMetrics": [{
+            "resource": {
+                "attributes": [
+                    { "key": "service.name", "value": { "stringValue": "thrud" } }
+                ]
+            },
+            "scopeMetrics": [{
+                "scope": { "name": "thrud", "version": env!("CARGO_PKG_VERSION") },
+                "metrics": otlp_metrics,
+            }]
+        }]
+    });
+
+    (payload, data_points)
+}
+
+fn attributes(metric: &Metric) -> Vec<serde_json::Value> {
+    let mut keys: Vec<&String> = metric.metadata.keys().collect();
+    keys.sort();
+    keys.into_iter()
+        .map(|key| {
+            serde_json::json!({
+                "key": key,
+                "value": { "stringValue": metric.metadata[key] },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_has_a_data_point_per_numeric_metric() {
+        let round = vec![
+            Metric::counter("cpu.performance.total_ticks", 9_000_000_000).with_tag("core_type", "performance"),
+            Metric::gauge("gpu.0.utilization", 0.25).with_tag("gpu_index", "0"),
+            Metric::gauge("gpu.1.utilization", 0.75).with_tag("gpu_index", "1"),
+            Metric::gauge("gpu.0.utilization", f64::NAN),
+            Metric::new("thermal.cpu_throttled".to_string(), MetricValue::Boolean(false), Default::default()),
+            Metric::new("host.model".to_string(), MetricValue::String("Mac14,6".to_string()), Default::default()),
+        ];
+
+        let (payload, data_points) = build_payload(&round);
+
+        assert_eq!(data_points, 3);
+        let metrics = payload["resourceMetrics"][0]["scopeMetrics"][0]["metrics"].as_array().unwrap();
+        let names: Vec<&str> = metrics.iter().map(|m| m["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["cpu.performance.total_ticks", "gpu.0.utilization", "gpu.1.utilization"]);
+
+        let ticks = &metrics[0]["gauge"]["dataPoints"][0];
+        assert_eq!(ticks["asInt"], "9000000000");
+        assert_eq!(ticks["attributes"][0]["key"], "core_type");
+        assert_eq!(ticks["attributes"][0]["value"]["stringValue"], "performance");
+        assert_eq!(ticks["timeUnixNano"], round[0].timestamp.timestamp_nanos_opt().unwrap().to_string());
+
+        let gpu0 = metrics[1]["gauge"]["dataPoints"].as_array().unwrap();
+        assert_eq!(gpu0.len(), 1);
+        assert_eq!(gpu0[0]["asDouble"], 0.25);
+    }
+
+    #[test]
+    fn empty_round_has_no_data_points() {
+        let (payload, data_points) = build_payload(&[]);
+
+        assert_eq!(data_points, 0);
+        assert_eq!(payload["resourceMetrics"][0]["scopeMetrics"][0]["metrics"], serde_json::json!([]));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::collectors::{Metric, MetricValue};
+use std::error::Error;
+use std::net::UdpSocket;
+
+/// Keep datagrams under a typical Ethernet MTU so they aren't fragmented
+const MAX_PACKET_BYTES: usize = 1432;
+
+/// Sends metrics as StatsD gauges over UDP, with metadata as DogStatsD tags.
+///
+/// Each metric becomes `name:value|g|#key:value,...`. Booleans are sent as 0/1
+/// and string metrics are skipped since gauges must be numeric. Lines are
+/// batched into newline-separated packets.
+pub struct StatsdSink {
+    socket: UdpSocket,
+}
+
+impl StatsdSink {
+    /// Create a sink sending to `addr` (`host:port`)
+    pub fn new(addr: &str) -> Result<Self, Box<dyn Error>> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket
+            .connect(addr)
+            .map_err(|e| format!("Cannot resolve StatsD address {}: {}", addr, e))?;
+        Ok(Self { socket })
+    }
+
+    /// Send metrics, returning the number of gauge lines sent
+    pub fn send(&self, metrics: &[Metric]) -> Result<usize, Box<dyn Error>> {
+        let mut packet = String::new();
+        let mut sent = 0;
+
+        for line in metrics.iter().filter_map(format_line) {
+            if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET_BYTES {
+                self.socket.send(packet.as_bytes())?;
+                packet.clear();
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(&line);
+            sent += 1;
+        }
+
+        if !packet.is_empty() {
+            self.socket.send(packet.as_bytes())?;
+        }
+
+        Ok(sent)
+    }
+}
+
+/// Format a metric as a DogStatsD gauge line, or `None` for non-numeric values
+fn format_line(metric: &Metric) -> Option<String> {
+    let value = match &metric.value {
+        MetricValue::Integer(v) => v.to_string(),
+        MetricValue::Float(v) if v.is_finite() => v.to_string(),
+        MetricValue::Boolean(v) => if *v { "1" } else { "0" }.to_string(),
+        MetricValue::Float(_) | MetricValue::String(_) | MetricValue::Histogram { .. } => return None,
+    };
+
+    let mut line = format!("{}:{}|g", sanitize(&metric.name), value);
+
+    if !metric.metadata.is_empty() {
+        let mut tags: Vec<String> = metric
+            .metadata
+            .iter()
+            .map(|(k, v)| format!("{}:{}", sanitize(k), sanitize(v)))
+            .collect();
+        tags.sort();
+        line.push_str("|#");
+        line.push_str(&tags.join(","));
+    }
+
+    Some(line)
+}
+
+/// Replace characters that are delimiters in the StatsD protocol. Dots are kept
+/// as the hierarchy separator, so `cpu.performance.total_ticks` stays as-is.
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '/') { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// A loopback socket to receive packets on, and a sink sending to it
+    fn loopback() -> (UdpSocket, StatsdSink) {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let sink = StatsdSink::new(&receiver.local_addr().unwrap().to_string()).unwrap();
+        (receiver, sink)
+    }
+
+    /// Receive packets until `count` lines have arrived
+    fn receive_lines(receiver: &UdpSocket, count: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut buf = [0u8; 65536];
+        while lines.len() < count {
+            let len = receiver.recv(&mut buf).unwrap();
+            assert!(len <= MAX_PACKET_BYTES);
+            lines.extend(std::str::from_utf8(&buf[..len]).unwrap().lines().map(str::to_string));
+        }
+        lines
+    }
+
+    #[test]
+    fn sends_gauges_with_tags_over_udp() {
+        let (receiver, sink) = loopback();
+        let metrics = vec![
+            Metric::gauge("gpu.0.utilization", 0.5).with_tag("gpu_name", "Apple M2 Max"),
+            Metric::counter("cpu.performance.total_ticks", 1234),
+            Metric::new("thermal.cpu_throttled".to_string(), MetricValue::Boolean(true), Default::default()),
+            Metric::new("host.model".to_string(), MetricValue::String("Mac14,6".to_string()), Default::default()),
+        ];
+
+        assert_eq!(sink.send(&metrics).unwrap(), 3);
+
+        let lines = receive_lines(&receiver, 3);
+        assert_eq!(lines, [
+            "gpu.0.utilization:0.5|g|#gpu_name:Apple_M2_Max",
+            "cpu.performance.total_ticks:1234|g",
+            "thermal.cpu_throttled:1|g",
+        ]);
+    }
+
+    #[test]
+    fn splits_large_rounds_into_packets_under_the_mtu() {
+        let (receiver, sink) = loopback();
+        let metrics: Vec<Metric> = (0..200).map(|core| Metric::counter(format!("cpu.core.{}.idle_ticks", core), core)).collect();
+
+        assert_eq!(sink.send(&metrics).unwrap(), 200);
+
+        let lines = receive_lines(&receiver, 200);
+        for (core, line) in lines.iter().enumerate() {
+            let (name, rest) = line.split_once(':').unwrap();
+            assert_eq!(name, format!("cpu.core.{}.idle_ticks", core));
+            assert_eq!(rest, format!("{}|g", core));
+        }
+    }
+
+    #[test]
+    fn sanitizes_protocol_delimiters() {
+        let metric = Metric::gauge("disk:read|bytes", f64::NAN);
+        assert_eq!(format_line(&metric), None);
+
+        let metric = Metric::gauge("disk:read|bytes", 1.0).with_tag("mount", "/Volumes/My Disk,1");
+        assert_eq!(format_line(&metric).unwrap(), "disk_read_bytes:1|g|#mount:/Volumes/My_Disk_1");
+    }
+}
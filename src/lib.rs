@@ -1,5 +1,10 @@
 pub mod collectors;
+pub mod config;
+pub mod error;
+pub mod export;
 pub mod storage;
+pub mod units;
 
 pub use collectors::*;
+pub use error::ThrudError;
 pub use storage::*;
\ No newline at end of file
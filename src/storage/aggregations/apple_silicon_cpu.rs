@@ -0,0 +1,322 @@
+use super::{array_schema, clamp_percent, object_schema, parse_param, parse_window_seconds, Aggregation, AggregationResult, MAX_SAMPLES_PARAM, ParamSpec, ROUND_ID_PARAM, WINDOW_SECONDS_PARAM};
+use crate::collectors::names;
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+/// Utilization of a single core over the aggregation window.
+///
+/// Floating-point error and skew between when counters are read can push the
+/// computed values slightly out of range, so `utilization_percent` is clamped
+/// to [0, 100] and the tick rates to [0, ∞).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CPURate {
+    pub core_id: i64,
+    pub core_type: String,
+    pub cluster_id: i64,
+    pub utilization_percent: f64,
+    pub busy_ticks_per_second: f64,
+    pub idle_ticks_per_second: f64,
+    pub sample_count: i64,
+}
+
+/// Utilization across the cores of one cluster: the average, lowest and highest
+/// per-core utilization, clamped to [0, 100], and how far apart the cores are
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterAggregate {
+    pub core_type: String,
+    pub cluster_id: i64,
+    pub core_count: usize,
+    pub avg_utilization: f64,
+    pub min_core_utilization: f64,
+    pub max_core_utilization: f64,
+    /// Population standard deviation of the per-core utilizations; 0 for
+    /// perfectly balanced cores
+    pub stddev_utilization: f64,
+}
+
+/// Per-core and per-cluster CPU utilization derived from cumulative tick counters.
+///
+/// Consecutive samples of each core are paired with `LAG` and the tick deltas are
+/// summed over the window, so utilization is weighted by elapsed ticks rather than
+/// averaged per sample.
+///
+/// With a `round_id` param only the interval ending at that round is reported,
+/// paired with the previous sample of each core within `window_seconds` before it.
+///
+/// With a `max_samples` param only the newest `max_samples` collection rounds of
+/// the window are read, so each core contributes at most that many samples (and
+/// one interval fewer). This bounds the rows the window functions see at
+/// subsecond collection intervals, at the cost of a shorter effective window.
+pub struct AppleSiliconCPU;
+
+const CORE_RATES_QUERY: &str = "
+    WITH anchor AS (
+        SELECT COALESCE(
+            (SELECT MAX(timestamp) FROM metrics WHERE collection_round_id = ?5),
+            CAST(strftime('%s', 'now') AS INTEGER) * 1000
+        ) AS end_ts
+    ),
+    -- Rounds are bounded by when they were stored, which is later than the
+    -- samples they hold, so a scoped query counts back from the round itself
+    target_round AS (
+        SELECT
+            (SELECT seq FROM collection_rounds WHERE id = ?5) AS target_seq,
+            COALESCE(
+                (SELECT timestamp_ms FROM collection_rounds WHERE id = ?5),
+                CAST(strftime('%s', 'now') AS INTEGER) * 1000
+            ) AS target_ms
+    ),
+    recent_rounds AS (
+        SELECT id
+        FROM collection_rounds, target_round
+        WHERE metrics_count > 0
+          AND timestamp_ms >= target_ms - ?1
+          AND (target_seq IS NULL OR seq <= target_seq)
+        ORDER BY seq DESC
+        LIMIT COALESCE(?6, -1)
+    ),
+    core_samples AS (
+        SELECT
+            collection_round_id AS round_id,
+            MAX(timestamp) AS ts,
+            CAST(json_extract(metadata, '$.core_id') AS INTEGER) AS core_id,
+            json_extract(metadata, '$.core_type') AS core_type,
+            CAST(json_extract(metadata, '$.cluster_id') AS INTEGER) AS cluster_id,
+            MAX(CASE WHEN name GLOB '*.' || ?3 THEN value_int END) AS idle_ticks,
+            MAX(CASE WHEN name GLOB '*.' || ?4 THEN value_int END) AS total_ticks
+        FROM metrics, anchor
+        WHERE name GLOB ?2
+          AND timestamp >= end_ts - ?1
+          AND (?5 IS NULL OR timestamp <= end_ts)
+          AND (?6 IS NULL OR collection_round_id IN (SELECT id FROM recent_rounds))
+        GROUP BY collection_round_id, core_id
+    ),
+    core_deltas AS (
+        SELECT
+            round_id,
+            core_id,
+            core_type,
+            cluster_id,
+            idle_ticks - LAG(idle_ticks) OVER w AS raw_idle,
+            total_ticks - LAG(total_ticks) OVER w AS raw_total,
+            ts - LAG(ts) OVER w AS raw_ms
+        FROM core_samples
+        WINDOW w AS (PARTITION BY core_id ORDER BY ts)
+    ),
+    -- A counter that went backwards was reset (e.g. by a reboot); drop that interval
+    valid_deltas AS (
+        SELECT
+            core_id,
+            core_type,
+            cluster_id,
+            CASE WHEN raw_total >= 0 AND raw_idle >= 0 AND raw_idle <= raw_total THEN raw_idle END AS delta_idle,
+            CASE WHEN raw_total >= 0 AND raw_idle >= 0 AND raw_idle <= raw_total THEN raw_total END AS delta_total,
+            CASE WHEN raw_total >= 0 AND raw_idle >= 0 AND raw_idle <= raw_total THEN raw_ms END AS delta_ms
+        FROM core_deltas
+        WHERE raw_total IS NOT NULL
+          AND (?5 IS NULL OR round_id = ?5)
+    )
+    SELECT
+        core_id,
+        core_type,
+        cluster_id,
+        CASE WHEN SUM(delta_total) > 0
+            THEN (SUM(delta_total) - SUM(delta_idle)) * 100.0 / SUM(delta_total)
+            ELSE 0.0 END AS utilization_percent,
+        CASE WHEN SUM(delta_ms) > 0
+            THEN (SUM(delta_total) - SUM(delta_idle)) * 1000.0 / SUM(delta_ms)
+            ELSE 0.0 END AS busy_ticks_per_second,
+        CASE WHEN SUM(delta_ms) > 0
+            THEN SUM(delta_idle) * 1000.0 / SUM(delta_ms)
+            ELSE 0.0 END AS idle_ticks_per_second,
+        COUNT(delta_total) AS sample_count
+    FROM valid_deltas
+    GROUP BY core_id, core_type, cluster_id
+    ORDER BY core_id";
+
+impl CPURate {
+    /// This rate with utilization clamped to [0, 100] and tick rates to [0, ∞)
+    fn clamped(self) -> Self {
+        Self {
+            utilization_percent: clamp_percent(self.utilization_percent),
+            busy_ticks_per_second: self.busy_ticks_per_second.max(0.0),
+            idle_ticks_per_second: self.idle_ticks_per_second.max(0.0),
+            ..self
+        }
+    }
+}
+
+impl AppleSiliconCPU {
+    fn core_rates(&self, conn: &Connection, window_seconds: i64, round_id: Option<&str>, max_samples: Option<i64>) -> Result<Vec<CPURate>, Box<dyn Error>> {
+        let mut stmt = conn.prepare(CORE_RATES_QUERY)?;
+        let query_params = params![
+            window_seconds * 1000,
+            names::CPU_CORE_TICKS_GLOB,
+            names::IDLE_TICKS,
+            names::TOTAL_TICKS,
+            round_id,
+            max_samples
+        ];
+        let rows = stmt.query_map(query_params, |row| {
+            Ok(CPURate {
+                core_id: row.get(0)?,
+                core_type: row.get::<_, Option<String>>(1)?.unwrap_or_else(|| "unknown".to_string()),
+                cluster_id: row.get::<_, Option<i64>>(2)?.unwrap_or(-1),
+                utilization_percent: row.get(3)?,
+                busy_ticks_per_second: row.get(4)?,
+                idle_ticks_per_second: row.get(5)?,
+                sample_count: row.get(6)?,
+            })
+        })?;
+
+        let mut rates = Vec::new();
+        for rate in rows {
+            rates.push(rate?.clamped());
+        }
+        Ok(rates)
+    }
+
+    fn cluster_aggregates(&self, rates: &[CPURate]) -> Vec<ClusterAggregate> {
+        let mut clusters: BTreeMap<(String, i64), Vec<f64>> = BTreeMap::new();
+        for rate in rates {
+            clusters
+                .entry((rate.core_type.clone(), rate.cluster_id))
+                .or_default()
+                .push(rate.utilization_percent);
+        }
+
+        clusters
+            .into_iter()
+            .map(|((core_type, cluster_id), utilizations)| {
+                let count = utilizations.len() as f64;
+                let mean = utilizations.iter().sum::<f64>() / count;
+                let variance = utilizations.iter().map(|u| (u - mean).powi(2)).sum::<f64>() / count;
+                ClusterAggregate {
+                    core_type,
+                    cluster_id,
+                    core_count: utilizations.len(),
+                    avg_utilization: clamp_percent(mean),
+                    min_core_utilization: clamp_percent(utilizations.iter().copied().fold(f64::INFINITY, f64::min)),
+                    max_core_utilization: clamp_percent(utilizations.iter().copied().fold(f64::NEG_INFINITY, f64::max)),
+                    stddev_utilization: variance.sqrt(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Aggregation for AppleSiliconCPU {
+    fn name(&self) -> &str {
+        "apple_silicon_cpu"
+    }
+
+    fn description(&self) -> &str {
+        "Per-core and per-cluster CPU utilization from tick counters over a time window"
+    }
+
+    fn param_schema(&self) -> &[ParamSpec] {
+        &[WINDOW_SECONDS_PARAM, ROUND_ID_PARAM, MAX_SAMPLES_PARAM]
+    }
+
+    fn result_schema(&self) -> serde_json::Value {
+        object_schema(serde_json::json!({
+            "window_seconds": { "type": "integer" },
+            "round_id": { "type": ["string", "null"] },
+            "max_samples": { "type": ["integer", "null"] },
+            "cores": array_schema(object_schema(serde_json::json!({
+                "core_id": { "type": "integer" },
+                "core_type": { "type": "string" },
+                "cluster_id": { "type": "integer" },
+                "utilization_percent": { "type": "number", "minimum": 0, "maximum": 100 },
+                "busy_ticks_per_second": { "type": "number", "minimum": 0 },
+                "idle_ticks_per_second": { "type": "number", "minimum": 0 },
+                "sample_count": { "type": "integer" },
+            }))),
+            "clusters": array_schema(object_schema(serde_json::json!({
+                "core_type": { "type": "string" },
+                "cluster_id": { "type": "integer" },
+                "core_count": { "type": "integer" },
+                "avg_utilization": { "type": "number", "minimum": 0, "maximum": 100 },
+                "min_core_utilization": { "type": "number", "minimum": 0, "maximum": 100 },
+                "max_core_utilization": { "type": "number", "minimum": 0, "maximum": 100 },
+                "stddev_utilization": { "type": "number", "minimum": 0 },
+            }))),
+        }))
+    }
+
+    fn execute(&self, conn: &Connection, params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+        let window_seconds = parse_window_seconds(params)?;
+        let round_id = params.get("round_id").map(String::as_str);
+        let max_samples: Option<i64> = parse_param(params, &MAX_SAMPLES_PARAM)?;
+        if let Some(round_id) = round_id {
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM collection_rounds WHERE id = ?1)",
+                [round_id],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                return Err(format!("Unknown collection round: {}", round_id).into());
+            }
+        }
+
+        let cores = self.core_rates(conn, window_seconds, round_id, max_samples)?;
+        let clusters = self.cluster_aggregates(&cores);
+
+        Ok(AggregationResult {
+            name: self.name().to_string(),
+            timestamp: Utc::now(),
+            data: serde_json::json!({
+                "window_seconds": window_seconds,
+                "round_id": round_id,
+                "max_samples": max_samples,
+                "cores": cores,
+                "clusters": clusters,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(core_id: i64, utilization_percent: f64) -> CPURate {
+        CPURate {
+            core_id,
+            core_type: names::PERFORMANCE.to_string(),
+            cluster_id: 0,
+            utilization_percent,
+            busy_ticks_per_second: utilization_percent,
+            idle_ticks_per_second: 100.0 - utilization_percent,
+            sample_count: 1,
+        }
+    }
+
+    #[test]
+    fn core_rates_are_clamped_to_their_range() {
+        let over = rate(0, 101.0).clamped();
+        assert_eq!(over.utilization_percent, 100.0);
+        assert_eq!(over.busy_ticks_per_second, 101.0);
+        assert_eq!(over.idle_ticks_per_second, 0.0);
+
+        let under = rate(0, -0.5).clamped();
+        assert_eq!(under.utilization_percent, 0.0);
+        assert_eq!(under.busy_ticks_per_second, 0.0);
+        assert_eq!(under.idle_ticks_per_second, 100.5);
+    }
+
+    #[test]
+    fn cluster_utilization_is_clamped_to_100() {
+        let clusters = AppleSiliconCPU.cluster_aggregates(&[rate(0, 101.0), rate(1, 103.0)]);
+
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].avg_utilization, 100.0);
+        assert_eq!(clusters[0].min_core_utilization, 100.0);
+        assert_eq!(clusters[0].max_core_utilization, 100.0);
+        // The spread between the raw values is kept
+        assert_eq!(clusters[0].stddev_utilization, 1.0);
+    }
+}
@@ -0,0 +1,143 @@
+use super::{array_schema, object_schema, parse_window_seconds, Aggregation, AggregationResult, ParamSpec, WINDOW_SECONDS_PARAM};
+use crate::collectors::names;
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Utilization and temperature statistics of a single GPU over the window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GPUStats {
+    pub gpu_index: i64,
+    pub gpu_name: Option<String>,
+    pub avg_utilization: Option<f64>,
+    pub min_utilization: Option<f64>,
+    pub peak_utilization: Option<f64>,
+    pub avg_temperature_c: Option<f64>,
+    pub sample_count: i64,
+}
+
+/// GPU utilization and temperature statistics over a time window.
+///
+/// The collector stores utilization as a 0-1 fraction; results are reported as
+/// percentages. Temperature is averaged from `gpu.*.temperature` samples when the
+/// collector provides them and is `null` otherwise. The top-level figures cover
+/// all GPUs; `gpus` breaks them down by `gpu_index` metadata.
+pub struct AppleSiliconGPU;
+
+const GPU_STATS_QUERY: &str = "
+    SELECT
+        AVG(CASE WHEN name GLOB ?2 THEN COALESCE(value_float, value_int) END) * 100.0,
+        MIN(CASE WHEN name GLOB ?2 THEN COALESCE(value_float, value_int) END) * 100.0,
+        MAX(CASE WHEN name GLOB ?2 THEN COALESCE(value_float, value_int) END) * 100.0,
+        AVG(CASE WHEN name GLOB ?3 THEN COALESCE(value_float, value_int) END),
+        COUNT(CASE WHEN name GLOB ?2 THEN 1 END)
+    FROM metrics
+    WHERE (name GLOB ?2 OR name GLOB ?3)
+      AND timestamp >= CAST(strftime('%s', 'now') AS INTEGER) * 1000 - ?1";
+
+const PER_GPU_STATS_QUERY: &str = "
+    SELECT
+        CAST(json_extract(metadata, '$.gpu_index') AS INTEGER) AS gpu_index,
+        MAX(json_extract(metadata, '$.gpu_name')),
+        AVG(CASE WHEN name GLOB ?2 THEN COALESCE(value_float, value_int) END) * 100.0,
+        MIN(CASE WHEN name GLOB ?2 THEN COALESCE(value_float, value_int) END) * 100.0,
+        MAX(CASE WHEN name GLOB ?2 THEN COALESCE(value_float, value_int) END) * 100.0,
+        AVG(CASE WHEN name GLOB ?3 THEN COALESCE(value_float, value_int) END),
+        COUNT(CASE WHEN name GLOB ?2 THEN 1 END)
+    FROM metrics
+    WHERE (name GLOB ?2 OR name GLOB ?3)
+      AND timestamp >= CAST(strftime('%s', 'now') AS INTEGER) * 1000 - ?1
+    GROUP BY gpu_index
+    ORDER BY gpu_index";
+
+impl AppleSiliconGPU {
+    fn per_gpu_stats(&self, conn: &Connection, window_seconds: i64) -> Result<Vec<GPUStats>, Box<dyn Error>> {
+        let mut stmt = conn.prepare(PER_GPU_STATS_QUERY)?;
+        let rows = stmt.query_map(params![window_seconds * 1000, names::GPU_UTILIZATION_GLOB, names::GPU_TEMPERATURE_GLOB], |row| {
+            Ok(GPUStats {
+                gpu_index: row.get::<_, Option<i64>>(0)?.unwrap_or(-1),
+                gpu_name: row.get(1)?,
+                avg_utilization: row.get(2)?,
+                min_utilization: row.get(3)?,
+                peak_utilization: row.get(4)?,
+                avg_temperature_c: row.get(5)?,
+                sample_count: row.get(6)?,
+            })
+        })?;
+
+        let mut stats = Vec::new();
+        for gpu in rows {
+            stats.push(gpu?);
+        }
+        Ok(stats)
+    }
+}
+
+impl Aggregation for AppleSiliconGPU {
+    fn name(&self) -> &str {
+        "apple_silicon_gpu"
+    }
+
+    fn description(&self) -> &str {
+        "Average, minimum and peak GPU utilization and average temperature over a time window"
+    }
+
+    fn param_schema(&self) -> &[ParamSpec] {
+        &[WINDOW_SECONDS_PARAM]
+    }
+
+    fn result_schema(&self) -> serde_json::Value {
+        object_schema(serde_json::json!({
+            "window_seconds": { "type": "integer" },
+            "avg_utilization": { "type": ["number", "null"] },
+            "min_utilization": { "type": ["number", "null"] },
+            "peak_utilization": { "type": ["number", "null"] },
+            "avg_temperature_c": { "type": ["number", "null"] },
+            "sample_count": { "type": "integer" },
+            "gpus": array_schema(object_schema(serde_json::json!({
+                "gpu_index": { "type": "integer" },
+                "gpu_name": { "type": ["string", "null"] },
+                "avg_utilization": { "type": ["number", "null"] },
+                "min_utilization": { "type": ["number", "null"] },
+                "peak_utilization": { "type": ["number", "null"] },
+                "avg_temperature_c": { "type": ["number", "null"] },
+                "sample_count": { "type": "integer" },
+            }))),
+        }))
+    }
+
+    fn execute(&self, conn: &Connection, params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+        let window_seconds = parse_window_seconds(params)?;
+
+        let (avg_utilization, min_utilization, peak_utilization, avg_temperature_c, sample_count) = conn.query_row(
+            GPU_STATS_QUERY,
+            params![window_seconds * 1000, names::GPU_UTILIZATION_GLOB, names::GPU_TEMPERATURE_GLOB],
+            |row| {
+                Ok((
+                    row.get::<_, Option<f64>>(0)?,
+                    row.get::<_, Option<f64>>(1)?,
+                    row.get::<_, Option<f64>>(2)?,
+                    row.get::<_, Option<f64>>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            },
+        )?;
+        let gpus = self.per_gpu_stats(conn, window_seconds)?;
+
+        Ok(AggregationResult {
+            name: self.name().to_string(),
+            timestamp: Utc::now(),
+            data: serde_json::json!({
+                "window_seconds": window_seconds,
+                "avg_utilization": avg_utilization,
+                "min_utilization": min_utilization,
+                "peak_utilization": peak_utilization,
+                "avg_temperature_c": avg_temperature_c,
+                "sample_count": sample_count,
+                "gpus": gpus,
+            }),
+        })
+    }
+}
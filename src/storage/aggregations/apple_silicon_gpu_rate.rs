@@ -0,0 +1,131 @@
+use super::{array_schema, clamp_percent, object_schema, parse_window_seconds, Aggregation, AggregationResult, ParamSpec, WINDOW_SECONDS_PARAM};
+use crate::collectors::names;
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Busy fraction of a single GPU over the aggregation window, with
+/// `utilization_percent` clamped to [0, 100] and the tick rate to [0, ∞) as for
+/// CPU cores
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GPURate {
+    pub gpu_index: i64,
+    pub utilization_percent: f64,
+    pub busy_ticks_per_second: f64,
+    pub sample_count: i64,
+}
+
+/// Per-GPU utilization derived from cumulative `gpu.*.busy_ticks` and
+/// `gpu.*.total_ticks` counters.
+///
+/// Works like `apple_silicon_cpu`: consecutive samples of each GPU are paired with
+/// `LAG` and the tick deltas are summed over the window. GPUs are told apart by
+/// their `gpu_index` metadata.
+pub struct AppleSiliconGPURate;
+
+const GPU_RATES_QUERY: &str = "
+    WITH gpu_samples AS (
+        SELECT
+            MAX(timestamp) AS ts,
+            CAST(json_extract(metadata, '$.gpu_index') AS INTEGER) AS gpu_index,
+            MAX(CASE WHEN name GLOB ?2 THEN value_int END) AS busy_ticks,
+            MAX(CASE WHEN name GLOB ?3 THEN value_int END) AS total_ticks
+        FROM metrics
+        WHERE (name GLOB ?2 OR name GLOB ?3)
+          AND timestamp >= CAST(strftime('%s', 'now') AS INTEGER) * 1000 - ?1
+        GROUP BY collection_round_id, gpu_index
+    ),
+    gpu_deltas AS (
+        SELECT
+            gpu_index,
+            busy_ticks - LAG(busy_ticks) OVER w AS raw_busy,
+            total_ticks - LAG(total_ticks) OVER w AS raw_total,
+            ts - LAG(ts) OVER w AS raw_ms
+        FROM gpu_samples
+        WINDOW w AS (PARTITION BY gpu_index ORDER BY ts)
+    ),
+    -- A counter that went backwards was reset (e.g. by a reboot); drop that interval
+    valid_deltas AS (
+        SELECT
+            gpu_index,
+            CASE WHEN raw_total >= 0 AND raw_busy >= 0 AND raw_busy <= raw_total THEN raw_busy END AS delta_busy,
+            CASE WHEN raw_total >= 0 AND raw_busy >= 0 AND raw_busy <= raw_total THEN raw_total END AS delta_total,
+            CASE WHEN raw_total >= 0 AND raw_busy >= 0 AND raw_busy <= raw_total THEN raw_ms END AS delta_ms
+        FROM gpu_deltas
+        WHERE raw_total IS NOT NULL
+    )
+    SELECT
+        gpu_index,
+        CASE WHEN SUM(delta_total) > 0
+            THEN SUM(delta_busy) * 100.0 / SUM(delta_total)
+            ELSE 0.0 END AS utilization_percent,
+        CASE WHEN SUM(delta_ms) > 0
+            THEN SUM(delta_busy) * 1000.0 / SUM(delta_ms)
+            ELSE 0.0 END AS busy_ticks_per_second,
+        COUNT(delta_total) AS sample_count
+    FROM valid_deltas
+    GROUP BY gpu_index
+    ORDER BY gpu_index";
+
+impl AppleSiliconGPURate {
+    fn gpu_rates(&self, conn: &Connection, window_seconds: i64) -> Result<Vec<GPURate>, Box<dyn Error>> {
+        let mut stmt = conn.prepare(GPU_RATES_QUERY)?;
+        let rows = stmt.query_map(params![window_seconds * 1000, names::GPU_BUSY_TICKS_GLOB, names::GPU_TOTAL_TICKS_GLOB], |row| {
+            Ok(GPURate {
+                gpu_index: row.get::<_, Option<i64>>(0)?.unwrap_or(-1),
+                utilization_percent: clamp_percent(row.get(1)?),
+                busy_ticks_per_second: row.get::<_, f64>(2)?.max(0.0),
+                sample_count: row.get(3)?,
+            })
+        })?;
+
+        let mut rates = Vec::new();
+        for rate in rows {
+            rates.push(rate?);
+        }
+        Ok(rates)
+    }
+}
+
+impl Aggregation for AppleSiliconGPURate {
+    fn name(&self) -> &str {
+        "apple_silicon_gpu_rate"
+    }
+
+    fn description(&self) -> &str {
+        "Per-GPU utilization from busy/total tick counters over a time window"
+    }
+
+    fn param_schema(&self) -> &[ParamSpec] {
+        &[WINDOW_SECONDS_PARAM]
+    }
+
+    fn result_schema(&self) -> serde_json::Value {
+        object_schema(serde_json::json!({
+            "window_seconds": { "type": "integer" },
+            "gpus": array_schema(object_schema(serde_json::json!({
+                "gpu_index": { "type": "integer" },
+                "utilization_percent": { "type": "number", "minimum": 0, "maximum": 100 },
+                "busy_ticks_per_second": { "type": "number", "minimum": 0 },
+                "sample_count": { "type": "integer" },
+            }))),
+        }))
+    }
+
+    fn execute(&self, conn: &Connection, params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+        let window_seconds = parse_window_seconds(params)?;
+
+        let gpus = self.gpu_rates(conn, window_seconds)?;
+
+        Ok(AggregationResult {
+            name: self.name().to_string(),
+            timestamp: Utc::now(),
+            data: serde_json::json!({
+                "window_seconds": window_seconds,
+                "gpus": gpus,
+            }),
+        })
+    }
+}
@@ -0,0 +1,108 @@
+use super::{object_schema, parse_window_seconds, Aggregation, AggregationResult, ParamSpec, WINDOW_SECONDS_PARAM};
+use crate::collectors::names;
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// How work is split between efficiency and performance cores over a time window.
+///
+/// Active (non-idle) ticks of the `cpu.efficiency.*` and `cpu.performance.*`
+/// cluster counters are paired between consecutive rounds with `LAG`, as in
+/// `apple_silicon_cpu`, and summed over the window. `efficiency_fraction` is the
+/// share of all active ticks run on efficiency cores: near 1 when the scheduler
+/// keeps work on them, near 0 when it all lands on performance cores, and `null`
+/// when the window has no activity to split.
+pub struct EfficiencyBalance;
+
+const ACTIVE_TICKS_QUERY: &str = "
+    WITH cluster_samples AS (
+        SELECT
+            MAX(timestamp) AS ts,
+            SUM(CASE WHEN name = ?2 THEN value_int END) AS e_idle,
+            SUM(CASE WHEN name = ?3 THEN value_int END) AS e_total,
+            SUM(CASE WHEN name = ?4 THEN value_int END) AS p_idle,
+            SUM(CASE WHEN name = ?5 THEN value_int END) AS p_total
+        FROM metrics
+        WHERE name IN (?2, ?3, ?4, ?5)
+          AND timestamp >= CAST(strftime('%s', 'now') AS INTEGER) * 1000 - ?1
+        GROUP BY collection_round_id
+    ),
+    cluster_deltas AS (
+        SELECT
+            e_idle - LAG(e_idle) OVER w AS raw_e_idle,
+            e_total - LAG(e_total) OVER w AS raw_e_total,
+            p_idle - LAG(p_idle) OVER w AS raw_p_idle,
+            p_total - LAG(p_total) OVER w AS raw_p_total
+        FROM cluster_samples
+        WINDOW w AS (ORDER BY ts)
+    )
+    -- A counter that went backwards was reset (e.g. by a reboot); drop that interval
+    SELECT
+        SUM(raw_e_total - raw_e_idle),
+        SUM(raw_p_total - raw_p_idle),
+        COUNT(*)
+    FROM cluster_deltas
+    WHERE raw_e_total >= 0 AND raw_e_idle >= 0 AND raw_e_idle <= raw_e_total
+      AND raw_p_total >= 0 AND raw_p_idle >= 0 AND raw_p_idle <= raw_p_total";
+
+impl Aggregation for EfficiencyBalance {
+    fn name(&self) -> &str {
+        "efficiency_balance"
+    }
+
+    fn description(&self) -> &str {
+        "Fraction of active CPU ticks run on efficiency rather than performance cores over a time window"
+    }
+
+    fn param_schema(&self) -> &[ParamSpec] {
+        &[WINDOW_SECONDS_PARAM]
+    }
+
+    fn result_schema(&self) -> serde_json::Value {
+        object_schema(serde_json::json!({
+            "window_seconds": { "type": "integer" },
+            "sample_count": { "type": "integer" },
+            "efficiency_active_ticks": { "type": "integer" },
+            "performance_active_ticks": { "type": "integer" },
+            "efficiency_fraction": { "type": ["number", "null"], "minimum": 0, "maximum": 1 },
+        }))
+    }
+
+    fn execute(&self, conn: &Connection, params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+        let window_seconds = parse_window_seconds(params)?;
+
+        let (efficiency_active, performance_active, sample_count) = conn.query_row(
+            ACTIVE_TICKS_QUERY,
+            params![
+                window_seconds * 1000,
+                names::CPU_EFFICIENCY_IDLE_TICKS,
+                names::CPU_EFFICIENCY_TOTAL_TICKS,
+                names::CPU_PERFORMANCE_IDLE_TICKS,
+                names::CPU_PERFORMANCE_TOTAL_TICKS
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, Option<i64>>(0)?.unwrap_or(0),
+                    row.get::<_, Option<i64>>(1)?.unwrap_or(0),
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )?;
+
+        let total_active = efficiency_active + performance_active;
+        let efficiency_fraction = (total_active > 0).then(|| efficiency_active as f64 / total_active as f64);
+
+        Ok(AggregationResult {
+            name: self.name().to_string(),
+            timestamp: Utc::now(),
+            data: serde_json::json!({
+                "window_seconds": window_seconds,
+                "sample_count": sample_count,
+                "efficiency_active_ticks": efficiency_active,
+                "performance_active_ticks": performance_active,
+                "efficiency_fraction": efficiency_fraction,
+            }),
+        })
+    }
+}
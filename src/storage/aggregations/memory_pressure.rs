@@ -0,0 +1,103 @@
+use super::{object_schema, Aggregation, AggregationResult, ParamSpec};
+use crate::collectors::names;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Memory pressure from the latest memory sample, in the spirit of the macOS
+/// memory pressure indicator.
+///
+/// Pressure is the share of physical memory that is used or compressed
+/// (`memory.compressed_bytes` counts as zero on platforms that don't report it),
+/// capped at 100%. Any swap in use is reported as `swap_active`, since paging out
+/// is what turns the macOS indicator from green to yellow.
+pub struct MemoryPressure;
+
+const LATEST_MEMORY_QUERY: &str = "
+    SELECT
+        MAX(timestamp),
+        MAX(CASE WHEN name = ?1 THEN value_int END),
+        MAX(CASE WHEN name = ?2 THEN value_int END),
+        MAX(CASE WHEN name = ?3 THEN value_int END),
+        MAX(CASE WHEN name = ?4 THEN value_int END)
+    FROM metrics
+    WHERE collection_round_id = (
+        SELECT collection_round_id
+        FROM metrics
+        WHERE name = ?2
+        ORDER BY timestamp DESC, id DESC
+        LIMIT 1
+    )
+      AND name IN (?1, ?2, ?3, ?4)";
+
+impl Aggregation for MemoryPressure {
+    fn name(&self) -> &str {
+        "memory_pressure"
+    }
+
+    fn description(&self) -> &str {
+        "Memory pressure score and swap activity from the latest memory sample"
+    }
+
+    fn param_schema(&self) -> &[ParamSpec] {
+        &[]
+    }
+
+    fn result_schema(&self) -> serde_json::Value {
+        object_schema(serde_json::json!({
+            "sample_timestamp": { "type": ["string", "null"], "format": "date-time" },
+            "pressure_percent": { "type": ["number", "null"], "minimum": 0, "maximum": 100 },
+            "swap_active": { "type": ["boolean", "null"] },
+            "total_bytes": { "type": ["integer", "null"] },
+            "used_bytes": { "type": ["integer", "null"] },
+            "swap_used_bytes": { "type": ["integer", "null"] },
+            "compressed_bytes": { "type": ["integer", "null"] },
+        }))
+    }
+
+    fn execute(&self, conn: &Connection, _params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+        // Every column is NULL when no memory sample has been stored yet
+        let (sample_ms, total, used, swap_used, compressed) = conn.query_row(
+            LATEST_MEMORY_QUERY,
+            params![
+                names::MEMORY_TOTAL_BYTES,
+                names::MEMORY_USED_BYTES,
+                names::MEMORY_SWAP_USED_BYTES,
+                names::MEMORY_COMPRESSED_BYTES,
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, Option<i64>>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                    row.get::<_, Option<i64>>(3)?,
+                    row.get::<_, Option<i64>>(4)?,
+                ))
+            },
+        )?;
+
+        let pressure_percent = match (total, used) {
+            (Some(total), Some(used)) if total > 0 => {
+                let pressed = used + compressed.unwrap_or(0);
+                Some((pressed as f64 * 100.0 / total as f64).clamp(0.0, 100.0))
+            }
+            _ => None,
+        };
+        let sample_timestamp = sample_ms.and_then(DateTime::from_timestamp_millis);
+
+        Ok(AggregationResult {
+            name: self.name().to_string(),
+            timestamp: Utc::now(),
+            data: serde_json::json!({
+                "sample_timestamp": sample_timestamp,
+                "pressure_percent": pressure_percent,
+                "swap_active": used.map(|_| swap_used.unwrap_or(0) > 0),
+                "total_bytes": total,
+                "used_bytes": used,
+                "swap_used_bytes": swap_used,
+                "compressed_bytes": compressed,
+            }),
+        })
+    }
+}
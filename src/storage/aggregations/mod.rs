@@ -0,0 +1,466 @@
+pub mod apple_silicon_cpu;
+pub mod apple_silicon_gpu;
+pub mod apple_silicon_gpu_rate;
+pub mod efficiency_balance;
+pub mod memory_pressure;
+pub mod percentiles;
+pub mod spike_detector;
+pub mod system_overview;
+pub mod thermal_pressure;
+
+pub use apple_silicon_cpu::AppleSiliconCPU;
+pub use apple_silicon_gpu::AppleSiliconGPU;
+pub use apple_silicon_gpu_rate::AppleSiliconGPURate;
+pub use efficiency_balance::EfficiencyBalance;
+pub use memory_pressure::MemoryPressure;
+pub use percentiles::Percentiles;
+pub use spike_detector::SpikeDetector;
+pub use system_overview::SystemOverview;
+pub use thermal_pressure::ThermalPressure;
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::sync::Mutex;
+
+/// Number of aggregation results kept by default
+pub const DEFAULT_RESULT_CACHE_CAPACITY: usize = 32;
+
+/// Output of an aggregation run. `data` holds the aggregation-specific JSON payload.
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregationResult {
+    pub name: String,
+    pub timestamp: DateTime<Utc>,
+    pub data: serde_json::Value,
+}
+
+/// Type a parameter value must parse as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamType {
+    Text,
+    /// An integer greater than zero, such as a sample count
+    PositiveInteger,
+    /// A window length in seconds, from 1 up to [`MAX_WINDOW_SECONDS`]
+    WindowSeconds,
+    /// A finite number greater than zero, such as a threshold
+    PositiveFloat,
+    /// A comma-separated list of numbers
+    FloatList,
+}
+
+impl ParamType {
+    /// What a value of this type looks like, for error messages and help output
+    pub fn describe(&self) -> &'static str {
+        match self {
+            ParamType::Text => "text",
+            ParamType::PositiveInteger => "a positive integer",
+            ParamType::WindowSeconds => "a positive number of seconds, at most 315360000 (10 years)",
+            ParamType::PositiveFloat => "a positive number",
+            ParamType::FloatList => "a comma-separated list of numbers",
+        }
+    }
+
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            ParamType::Text => true,
+            ParamType::PositiveInteger => value.trim().parse::<i64>().is_ok_and(|v| v > 0),
+            ParamType::WindowSeconds => value.trim().parse::<i64>().is_ok_and(|v| (1..=MAX_WINDOW_SECONDS).contains(&v)),
+            ParamType::PositiveFloat => value.trim().parse::<f64>().is_ok_and(|v| v.is_finite() && v > 0.0),
+            ParamType::FloatList => value
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .all(|v| v.parse::<f64>().is_ok_and(f64::is_finite)),
+        }
+    }
+}
+
+/// A parameter an aggregation understands
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub param_type: ParamType,
+    pub required: bool,
+    pub description: &'static str,
+}
+
+/// Longest accepted `window_seconds`, ten years. Windows are converted to
+/// milliseconds, so an unbounded value could overflow
+pub const MAX_WINDOW_SECONDS: i64 = 10 * 365 * 24 * 60 * 60;
+
+/// The `window_seconds` parameter shared by the windowed aggregations
+pub const WINDOW_SECONDS_PARAM: ParamSpec = ParamSpec {
+    name: "window_seconds",
+    param_type: ParamType::WindowSeconds,
+    required: false,
+    description: "Length of the time window in seconds (default 60)",
+};
+
+/// The `round_id` parameter of aggregations that can be scoped to a single
+/// collection round, e.g. to recompute the rates of one snapshot
+pub const ROUND_ID_PARAM: ParamSpec = ParamSpec {
+    name: "round_id",
+    param_type: ParamType::Text,
+    required: false,
+    description: "Only report the collection round with this id (default: the whole window)",
+};
+
+/// The `max_samples` parameter of window aggregations that can trade precision
+/// for speed by only reading the newest collection rounds of the window
+pub const MAX_SAMPLES_PARAM: ParamSpec = ParamSpec {
+    name: "max_samples",
+    param_type: ParamType::PositiveInteger,
+    required: false,
+    description: "Only use the samples of the newest this many collection rounds in the window (default: all)",
+};
+
+/// The value of the optional parameter `spec`, `None` when absent. Aggregations
+/// may be run without going through `validate_params`, so the value is checked
+/// against the parameter's type here too.
+pub fn parse_param<T>(params: &HashMap<String, String>, spec: &ParamSpec) -> Result<Option<T>, Box<dyn Error>>
+where
+    T: std::str::FromStr,
+    T::Err: Error + 'static,
+{
+    let Some(value) = params.get(spec.name) else {
+        return Ok(None);
+    };
+    if !spec.param_type.accepts(value) {
+        return Err(format!("Invalid value '{}' for {}: expected {}", value, spec.name, spec.param_type.describe()).into());
+    }
+    Ok(Some(value.trim().parse()?))
+}
+
+/// The `window_seconds` parameter, 60 when absent
+pub fn parse_window_seconds(params: &HashMap<String, String>) -> Result<i64, Box<dyn Error>> {
+    Ok(parse_param(params, &WINDOW_SECONDS_PARAM)?.unwrap_or(60))
+}
+
+/// Clamp a utilization percentage to [0, 100]
+pub fn clamp_percent(percent: f64) -> f64 {
+    percent.clamp(0.0, 100.0)
+}
+
+/// Check `params` against an aggregation's schema, rejecting unknown keys,
+/// missing required keys and values of the wrong type
+pub fn validate_params(aggregation: &dyn Aggregation, params: &HashMap<String, String>) -> Result<(), String> {
+    let schema = aggregation.param_schema();
+
+    let mut keys: Vec<&String> = params.keys().collect();
+    keys.sort();
+    for key in keys {
+        let Some(spec) = schema.iter().find(|spec| spec.name == key.as_str()) else {
+            let expected: Vec<&str> = schema.iter().map(|spec| spec.name).collect();
+            return Err(if expected.is_empty() {
+                format!("Unknown parameter '{}': {} takes no parameters", key, aggregation.name())
+            } else {
+                format!("Unknown parameter '{}' for {} (expected: {})", key, aggregation.name(), expected.join(", "))
+            });
+        };
+        if !spec.param_type.accepts(&params[key]) {
+            return Err(format!(
+                "Invalid value '{}' for {}: expected {}",
+                params[key],
+                key,
+                spec.param_type.describe()
+            ));
+        }
+    }
+
+    if let Some(missing) = schema.iter().find(|spec| spec.required && !params.contains_key(spec.name)) {
+        return Err(format!("{} requires a {} parameter", aggregation.name(), missing.name));
+    }
+    Ok(())
+}
+
+/// JSON Schema of an object with `properties`, all of them required
+pub fn object_schema(properties: serde_json::Value) -> serde_json::Value {
+    let required: Vec<String> = properties.as_object().map(|p| p.keys().cloned().collect()).unwrap_or_default();
+    serde_json::json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// JSON Schema of an array of `items`
+pub fn array_schema(items: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({ "type": "array", "items": items })
+}
+
+/// SQL-based transformation computing derived metrics from raw collector output
+pub trait Aggregation {
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+    /// Parameters accepted by `execute`; anything else is rejected by the registry
+    fn param_schema(&self) -> &[ParamSpec];
+    /// JSON Schema of the `data` of this aggregation's results, so consumers
+    /// don't have to guess its shape
+    fn result_schema(&self) -> serde_json::Value;
+    fn execute(&self, conn: &Connection, params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>>;
+}
+
+/// Identifies an aggregation run: name, sorted params, the newest metric
+/// timestamp (epoch milliseconds) at the time it ran and, for windowed
+/// aggregations, the window end (epoch seconds, the granularity the window
+/// queries use for "now")
+type CacheKey = (String, Vec<(String, String)>, Option<i64>, Option<i64>);
+
+pub struct AggregationRegistry {
+    aggregations: HashMap<String, Box<dyn Aggregation>>,
+    /// Recent results, least recently used first
+    cache: Mutex<VecDeque<(CacheKey, AggregationResult)>>,
+    cache_capacity: usize,
+}
+
+impl Default for AggregationRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AggregationRegistry {
+    /// Create a registry with all built-in aggregations registered
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Box::new(AppleSiliconCPU));
+        registry.register(Box::new(AppleSiliconGPU));
+        registry.register(Box::new(AppleSiliconGPURate));
+        registry.register(Box::new(EfficiencyBalance));
+        registry.register(Box::new(MemoryPressure));
+        registry.register(Box::new(Percentiles));
+        registry.register(Box::new(SpikeDetector));
+        registry.register(Box::new(SystemOverview::new()));
+        registry.register(Box::new(ThermalPressure));
+        registry
+    }
+
+    /// Create a registry with no aggregations registered
+    pub fn empty() -> Self {
+        Self {
+            aggregations: HashMap::new(),
+            cache: Mutex::new(VecDeque::new()),
+            cache_capacity: DEFAULT_RESULT_CACHE_CAPACITY,
+        }
+    }
+
+    /// Keep at most `capacity` results; zero disables caching
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache_capacity = capacity;
+        self
+    }
+
+    pub fn register(&mut self, aggregation: Box<dyn Aggregation>) {
+        self.aggregations.insert(aggregation.name().to_string(), aggregation);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Aggregation> {
+        self.aggregations.get(name).map(|a| a.as_ref())
+    }
+
+    /// List (name, description) pairs sorted by name
+    pub fn list(&self) -> Vec<(&str, &str)> {
+        let mut list: Vec<(&str, &str)> = self
+            .aggregations
+            .values()
+            .map(|a| (a.name(), a.description()))
+            .collect();
+        list.sort_by(|a, b| a.0.cmp(b.0));
+        list
+    }
+
+    /// Validate params and run an aggregation, reusing an earlier result for the
+    /// same name and params as long as no newer metric has been stored since it
+    /// ran and, for a windowed aggregation, its window still ends at the same second
+    pub fn execute(&self, name: &str, conn: &Connection, params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+        let aggregation = self
+            .get(name)
+            .ok_or_else(|| format!("Unknown aggregation: {}", name))?;
+        validate_params(aggregation, params)?;
+        if self.cache_capacity == 0 {
+            return aggregation.execute(conn, params);
+        }
+
+        let newest: Option<i64> = conn.query_row("SELECT MAX(timestamp) FROM metrics", [], |row| row.get(0))?;
+        let mut sorted_params: Vec<(String, String)> = params.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        sorted_params.sort();
+        // Windows are relative to now, so the same data gives a different result
+        // once the window has moved on
+        let windowed = aggregation.param_schema().iter().any(|spec| spec.name == WINDOW_SECONDS_PARAM.name);
+        let now = Utc::now().timestamp();
+        let window_end = windowed.then_some(now);
+        let key = (name.to_string(), sorted_params, newest, window_end);
+
+        {
+            let mut cache = self.cache.lock().unwrap();
+            if let Some(position) = cache.iter().position(|(k, _)| *k == key) {
+                let entry = cache.remove(position).unwrap();
+                let result = entry.1.clone();
+                cache.push_back(entry);
+                return Ok(result);
+            }
+        }
+
+        let result = aggregation.execute(conn, params)?;
+
+        let mut cache = self.cache.lock().unwrap();
+        // Results from older snapshots or windows can no longer be hit
+        cache.retain(|(k, _)| k.2 == newest && k.3.is_none_or(|end| end == now));
+        if cache.len() >= self.cache_capacity {
+            cache.pop_front();
+        }
+        cache.push_back((key, result.clone()));
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn window_seconds_is_bounded() {
+        assert!(ParamType::WindowSeconds.accepts("60"));
+        assert!(ParamType::WindowSeconds.accepts(&MAX_WINDOW_SECONDS.to_string()));
+        assert!(!ParamType::WindowSeconds.accepts(&(MAX_WINDOW_SECONDS + 1).to_string()));
+        assert!(!ParamType::WindowSeconds.accepts("9223372036854775807"));
+        assert!(!ParamType::WindowSeconds.accepts("0"));
+        assert!(!ParamType::WindowSeconds.accepts("-5"));
+    }
+
+    #[test]
+    fn validate_rejects_an_overflowing_window() {
+        let error = validate_params(&ThermalPressure, &params(&[("window_seconds", "9223372036854775807")])).unwrap_err();
+        assert!(error.contains("window_seconds"), "{}", error);
+    }
+
+    #[test]
+    fn validate_rejects_unknown_and_mistyped_params() {
+        assert!(validate_params(&ThermalPressure, &params(&[("window_seconds", "300")])).is_ok());
+        assert!(validate_params(&ThermalPressure, &params(&[])).is_ok());
+
+        let error = validate_params(&ThermalPressure, &params(&[("window", "300")])).unwrap_err();
+        assert!(error.contains("Unknown parameter 'window'"), "{}", error);
+
+        let error = validate_params(&ThermalPressure, &params(&[("window_seconds", "soon")])).unwrap_err();
+        assert!(error.contains("Invalid value 'soon'"), "{}", error);
+
+        let error = validate_params(&MemoryPressure, &params(&[("window_seconds", "60")])).unwrap_err();
+        assert!(error.contains("takes no parameters"), "{}", error);
+    }
+
+    #[test]
+    fn validate_requires_required_params() {
+        let error = validate_params(&Percentiles, &params(&[])).unwrap_err();
+        assert!(error.contains("requires a metric_name parameter"), "{}", error);
+    }
+
+    /// Counts its runs, so tests can tell a cached result from a fresh one
+    struct Counting {
+        name: &'static str,
+        params: &'static [ParamSpec],
+        runs: AtomicUsize,
+    }
+
+    impl Aggregation for Counting {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn description(&self) -> &str {
+            "Counts its runs"
+        }
+
+        fn param_schema(&self) -> &[ParamSpec] {
+            self.params
+        }
+
+        fn result_schema(&self) -> serde_json::Value {
+            object_schema(serde_json::json!({ "runs": { "type": "integer" } }))
+        }
+
+        fn execute(&self, _conn: &Connection, _params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+            let runs = self.runs.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(AggregationResult {
+                name: self.name.to_string(),
+                timestamp: Utc::now(),
+                data: serde_json::json!({ "runs": runs }),
+            })
+        }
+    }
+
+    /// A registry holding a windowed `windowed` and an unwindowed `latest`
+    /// counting aggregation, with a connection to run them on
+    fn counting_registry() -> (AggregationRegistry, Connection) {
+        let mut registry = AggregationRegistry::empty();
+        registry.register(Box::new(Counting { name: "windowed", params: &[WINDOW_SECONDS_PARAM], runs: AtomicUsize::new(0) }));
+        registry.register(Box::new(Counting { name: "latest", params: &[], runs: AtomicUsize::new(0) }));
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE metrics (timestamp INTEGER)", []).unwrap();
+        (registry, conn)
+    }
+
+    fn runs(registry: &AggregationRegistry, conn: &Connection, name: &str) -> i64 {
+        registry.execute(name, conn, &params(&[])).unwrap().data["runs"].as_i64().unwrap()
+    }
+
+    /// Sleep until just after the next whole second
+    fn sleep_into_next_second() {
+        let millis = Utc::now().timestamp_subsec_millis() as u64;
+        std::thread::sleep(std::time::Duration::from_millis(1000 - millis + 20));
+    }
+
+    #[test]
+    fn cache_reuses_results_until_a_newer_metric_is_stored() {
+        let (registry, conn) = counting_registry();
+
+        assert_eq!(runs(&registry, &conn, "latest"), 1);
+        assert_eq!(runs(&registry, &conn, "latest"), 1);
+
+        conn.execute("INSERT INTO metrics (timestamp) VALUES (1)", []).unwrap();
+        assert_eq!(runs(&registry, &conn, "latest"), 2);
+    }
+
+    #[test]
+    fn cache_expires_windowed_results_as_the_window_moves() {
+        let (registry, conn) = counting_registry();
+        conn.execute("INSERT INTO metrics (timestamp) VALUES (1)", []).unwrap();
+
+        // Both runs must land in the same second for the second to be cached
+        if Utc::now().timestamp_subsec_millis() > 900 {
+            sleep_into_next_second();
+        }
+        assert_eq!(runs(&registry, &conn, "windowed"), 1);
+        assert_eq!(runs(&registry, &conn, "windowed"), 1);
+        assert_eq!(runs(&registry, &conn, "latest"), 1);
+
+        // No new metrics, but the window now ends a second later
+        sleep_into_next_second();
+        assert_eq!(runs(&registry, &conn, "windowed"), 2);
+        assert_eq!(runs(&registry, &conn, "latest"), 1);
+    }
+
+    #[test]
+    fn zero_capacity_disables_the_cache() {
+        let (registry, conn) = counting_registry();
+        let registry = registry.with_cache_capacity(0);
+
+        assert_eq!(runs(&registry, &conn, "latest"), 1);
+        assert_eq!(runs(&registry, &conn, "latest"), 2);
+    }
+
+    #[test]
+    fn parse_window_seconds_defaults_and_checks() {
+        assert_eq!(parse_window_seconds(&params(&[])).unwrap(), 60);
+        assert_eq!(parse_window_seconds(&params(&[("window_seconds", " 300 ")])).unwrap(), 300);
+        assert!(parse_window_seconds(&params(&[("window_seconds", "9223372036854775807")])).is_err());
+    }
+}
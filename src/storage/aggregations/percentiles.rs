@@ -0,0 +1,124 @@
+use super::{object_schema, parse_window_seconds, Aggregation, AggregationResult, ParamSpec, ParamType, WINDOW_SECONDS_PARAM};
+use crate::collectors::names;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Nearest-rank percentiles of a single metric's numeric values over a time window.
+///
+/// Params: `metric_name` (required), `window_seconds` (default 60) and
+/// `percentiles`, a comma-separated list in 0-100 (default `50,95,99`). The value
+/// for percentile p is the ceil(p/100 * n)-th smallest sample, so p0 is the
+/// minimum and p100 the maximum.
+pub struct Percentiles;
+
+const PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "metric_name",
+        param_type: ParamType::Text,
+        required: true,
+        description: "Metric whose values are ranked, e.g. gpu.0.utilization",
+    },
+    WINDOW_SECONDS_PARAM,
+    ParamSpec {
+        name: "percentiles",
+        param_type: ParamType::FloatList,
+        required: false,
+        description: "Percentiles to report, each in 0-100 (default 50,95,99)",
+    },
+];
+
+const PERCENTILE_QUERY: &str = "
+    WITH samples AS (
+        SELECT COALESCE(value_float, value_int) AS v
+        FROM metrics
+        WHERE name = ?1
+          AND COALESCE(value_float, value_int) IS NOT NULL
+          AND timestamp >= CAST(strftime('%s', 'now') AS INTEGER) * 1000 - ?2
+    ),
+    ranked AS (
+        SELECT v, ROW_NUMBER() OVER (ORDER BY v) AS rank, COUNT(*) OVER () AS n
+        FROM samples
+    )
+    SELECT v
+    FROM ranked
+    WHERE rank = MAX(1, CAST(?3 / 100.0 * n AS INTEGER) + (?3 / 100.0 * n > CAST(?3 / 100.0 * n AS INTEGER)))";
+
+const SAMPLE_COUNT_QUERY: &str = "
+    SELECT COUNT(*)
+    FROM metrics
+    WHERE name = ?1
+      AND COALESCE(value_float, value_int) IS NOT NULL
+      AND timestamp >= CAST(strftime('%s', 'now') AS INTEGER) * 1000 - ?2";
+
+impl Aggregation for Percentiles {
+    fn name(&self) -> &str {
+        "percentiles"
+    }
+
+    fn description(&self) -> &str {
+        "Percentiles (e.g. p50/p95/p99) of a metric's values over a time window"
+    }
+
+    fn param_schema(&self) -> &[ParamSpec] {
+        PARAMS
+    }
+
+    fn result_schema(&self) -> serde_json::Value {
+        object_schema(serde_json::json!({
+            "metric_name": { "type": "string" },
+            "window_seconds": { "type": "integer" },
+            "sample_count": { "type": "integer" },
+            // Keyed by the requested percentile, e.g. "p99"; null without samples
+            "percentiles": {
+                "type": "object",
+                "additionalProperties": { "type": ["number", "null"] },
+            },
+        }))
+    }
+
+    fn execute(&self, conn: &Connection, params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+        let metric_name = params
+            .get("metric_name")
+            .map(|name| names::canonical(name))
+            .ok_or("percentiles requires a metric_name parameter")?;
+        let window_seconds = parse_window_seconds(params)?;
+        let requested = params.get("percentiles").map(String::as_str).unwrap_or("50,95,99");
+
+        let mut percentiles = Vec::new();
+        for p in requested.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let value: f64 = p.parse().map_err(|_| format!("Invalid percentile: {}", p))?;
+            if !(0.0..=100.0).contains(&value) {
+                return Err(format!("Percentile out of range 0-100: {}", p).into());
+            }
+            percentiles.push((p.to_string(), value));
+        }
+
+        let sample_count: i64 = conn.query_row(
+            SAMPLE_COUNT_QUERY,
+            params![metric_name, window_seconds * 1000],
+            |row| row.get(0),
+        )?;
+
+        let mut values = serde_json::Map::new();
+        let mut stmt = conn.prepare(PERCENTILE_QUERY)?;
+        for (label, p) in percentiles {
+            let value: Option<f64> = stmt
+                .query_row(params![metric_name, window_seconds * 1000, p], |row| row.get(0))
+                .optional()?;
+            values.insert(format!("p{}", label), serde_json::json!(value));
+        }
+
+        Ok(AggregationResult {
+            name: self.name().to_string(),
+            timestamp: Utc::now(),
+            data: serde_json::json!({
+                "metric_name": metric_name,
+                "window_seconds": window_seconds,
+                "sample_count": sample_count,
+                "percentiles": values,
+            }),
+        })
+    }
+}
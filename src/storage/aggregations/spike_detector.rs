@@ -0,0 +1,195 @@
+use super::{array_schema, object_schema, parse_param, parse_window_seconds, Aggregation, AggregationResult, ParamSpec, ParamType, WINDOW_SECONDS_PARAM};
+use crate::collectors::names;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// A sample whose z-score is beyond the threshold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spike {
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+    pub z_score: f64,
+}
+
+/// Samples of a metric that stand out from the rest of the window.
+///
+/// Params: `metric_name` (required), `window_seconds` (default 60) and
+/// `z_threshold` (default 3). The mean and population standard deviation are
+/// taken over every sample in the window, and samples whose z-score is beyond
+/// the threshold in either direction are reported; a negative `z_score` is a
+/// dip. One outlier among n samples can reach at most z = (n-1)/sqrt(n), so a
+/// window with few samples needs a lower threshold.
+pub struct SpikeDetector;
+
+const DEFAULT_Z_THRESHOLD: f64 = 3.0;
+
+const PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "metric_name",
+        param_type: ParamType::Text,
+        required: true,
+        description: "Metric to scan for spikes, e.g. gpu.0.utilization",
+    },
+    WINDOW_SECONDS_PARAM,
+    Z_THRESHOLD_PARAM,
+];
+
+const Z_THRESHOLD_PARAM: ParamSpec = ParamSpec {
+    name: "z_threshold",
+    param_type: ParamType::PositiveFloat,
+    required: false,
+    description: "Standard deviations from the mean that count as a spike (default 3)",
+};
+
+const SAMPLES_QUERY: &str = "
+    SELECT timestamp, COALESCE(value_float, value_int)
+    FROM metrics
+    WHERE name = ?1
+      AND COALESCE(value_float, value_int) IS NOT NULL
+      AND timestamp >= CAST(strftime('%s', 'now') AS INTEGER) * 1000 - ?2
+    ORDER BY timestamp, id";
+
+impl Aggregation for SpikeDetector {
+    fn name(&self) -> &str {
+        "spike_detector"
+    }
+
+    fn description(&self) -> &str {
+        "Samples of a metric whose z-score over a time window exceeds a threshold"
+    }
+
+    fn param_schema(&self) -> &[ParamSpec] {
+        PARAMS
+    }
+
+    fn result_schema(&self) -> serde_json::Value {
+        object_schema(serde_json::json!({
+            "metric_name": { "type": "string" },
+            "window_seconds": { "type": "integer" },
+            "z_threshold": { "type": "number" },
+            "sample_count": { "type": "integer" },
+            "mean": { "type": ["number", "null"] },
+            "stddev": { "type": ["number", "null"] },
+            "spikes": array_schema(object_schema(serde_json::json!({
+                "timestamp": { "type": "string", "format": "date-time" },
+                "value": { "type": "number" },
+                "z_score": { "type": "number" },
+            }))),
+        }))
+    }
+
+    fn execute(&self, conn: &Connection, params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+        let metric_name = params
+            .get("metric_name")
+            .map(|name| names::canonical(name))
+            .ok_or("spike_detector requires a metric_name parameter")?;
+        let window_seconds = parse_window_seconds(params)?;
+        let z_threshold: f64 = parse_param(params, &Z_THRESHOLD_PARAM)?.unwrap_or(DEFAULT_Z_THRESHOLD);
+
+        let mut stmt = conn.prepare(SAMPLES_QUERY)?;
+        let samples = stmt
+            .query_map(params![metric_name, window_seconds * 1000], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (mean, stddev) = if samples.is_empty() {
+            (None, None)
+        } else {
+            let n = samples.len() as f64;
+            let mean = samples.iter().map(|(_, v)| v).sum::<f64>() / n;
+            let variance = samples.iter().map(|(_, v)| (v - mean).powi(2)).sum::<f64>() / n;
+            (Some(mean), Some(variance.sqrt()))
+        };
+
+        // A constant series has no spikes (and no defined z-scores)
+        let spikes: Vec<Spike> = match (mean, stddev) {
+            (Some(mean), Some(stddev)) if stddev > 0.0 => samples
+                .iter()
+                .map(|&(ms, value)| (ms, value, (value - mean) / stddev))
+                .filter(|(_, _, z)| z.abs() > z_threshold)
+                .map(|(ms, value, z_score)| Spike {
+                    timestamp: DateTime::from_timestamp_millis(ms).unwrap_or_default(),
+                    value,
+                    z_score,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Ok(AggregationResult {
+            name: self.name().to_string(),
+            timestamp: Utc::now(),
+            data: serde_json::json!({
+                "metric_name": metric_name,
+                "window_seconds": window_seconds,
+                "z_threshold": z_threshold,
+                "sample_count": samples.len(),
+                "mean": mean,
+                "stddev": stddev,
+                "spikes": spikes,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    /// A metrics table holding a flat `gpu.0.utilization` series of 20 samples
+    /// one second apart, the sixth newest of them an outlier. Returns the
+    /// outlier's timestamp in milliseconds.
+    fn flat_series_with_outlier(conn: &Connection) -> i64 {
+        conn.execute(
+            "CREATE TABLE metrics (id INTEGER PRIMARY KEY, name TEXT, value_int INTEGER, value_float REAL, timestamp INTEGER)",
+            [],
+        )
+        .unwrap();
+        let now = Utc::now().timestamp_millis();
+        for seconds_ago in 0..20 {
+            let value = if seconds_ago == 5 { 0.9 } else { 0.1 };
+            conn.execute(
+                "INSERT INTO metrics (name, value_float, timestamp) VALUES ('gpu.0.utilization', ?1, ?2)",
+                params![value, now - seconds_ago * 1000],
+            )
+            .unwrap();
+        }
+        now - 5000
+    }
+
+    #[test]
+    fn flags_the_outlier_of_a_flat_series() {
+        let conn = Connection::open_in_memory().unwrap();
+        let outlier = flat_series_with_outlier(&conn);
+
+        let result = SpikeDetector.execute(&conn, &params(&[("metric_name", "gpu.0.utilization")])).unwrap();
+
+        assert_eq!(result.data["sample_count"], 20);
+        let spikes: Vec<Spike> = serde_json::from_value(result.data["spikes"].clone()).unwrap();
+        assert_eq!(spikes.len(), 1, "{:?}", spikes);
+        assert_eq!(spikes[0].timestamp.timestamp_millis(), outlier);
+        assert_eq!(spikes[0].value, 0.9);
+        assert!(spikes[0].z_score > DEFAULT_Z_THRESHOLD);
+    }
+
+    #[test]
+    fn invalid_z_threshold_is_an_error() {
+        let conn = Connection::open_in_memory().unwrap();
+        flat_series_with_outlier(&conn);
+
+        for z_threshold in ["high", "-1", "0"] {
+            let error = SpikeDetector
+                .execute(&conn, &params(&[("metric_name", "gpu.0.utilization"), ("z_threshold", z_threshold)]))
+                .unwrap_err();
+            assert!(error.to_string().contains("z_threshold"), "{}", error);
+        }
+    }
+}
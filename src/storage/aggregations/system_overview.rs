@@ -0,0 +1,130 @@
+use super::{
+    object_schema, parse_window_seconds, Aggregation, AggregationRegistry, AggregationResult, AppleSiliconCPU, AppleSiliconGPU,
+    ParamSpec, ThermalPressure, WINDOW_SECONDS_PARAM,
+};
+use chrono::Utc;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// CPU, GPU and thermal summaries over the same time window in one result, for
+/// dashboards that want a single call.
+///
+/// Runs the `apple_silicon_cpu`, `apple_silicon_gpu` and `thermal_pressure`
+/// aggregations with this aggregation's `window_seconds` and nests their data
+/// under `cpu`, `gpu` and `thermal`. If any of them fails, so does the overview.
+pub struct SystemOverview {
+    /// The aggregations combined, uncached since the overview itself is cached
+    parts: AggregationRegistry,
+}
+
+/// Key in the combined result and the aggregation providing it
+const PARTS: &[(&str, &str)] = &[
+    ("cpu", "apple_silicon_cpu"),
+    ("gpu", "apple_silicon_gpu"),
+    ("thermal", "thermal_pressure"),
+];
+
+impl Default for SystemOverview {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemOverview {
+    pub fn new() -> Self {
+        let mut parts = AggregationRegistry::empty().with_cache_capacity(0);
+        parts.register(Box::new(AppleSiliconCPU));
+        parts.register(Box::new(AppleSiliconGPU));
+        parts.register(Box::new(ThermalPressure));
+        Self { parts }
+    }
+}
+
+impl Aggregation for SystemOverview {
+    fn name(&self) -> &str {
+        "system_overview"
+    }
+
+    fn description(&self) -> &str {
+        "CPU, GPU and thermal aggregations over the same time window, combined into one result"
+    }
+
+    fn param_schema(&self) -> &[ParamSpec] {
+        &[WINDOW_SECONDS_PARAM]
+    }
+
+    /// The schemas of the combined aggregations nested under their keys
+    fn result_schema(&self) -> serde_json::Value {
+        let mut properties = serde_json::Map::new();
+        properties.insert("window_seconds".to_string(), serde_json::json!({ "type": "integer" }));
+        for (key, aggregation) in PARTS {
+            if let Some(part) = self.parts.get(aggregation) {
+                properties.insert(key.to_string(), part.result_schema());
+            }
+        }
+        object_schema(serde_json::Value::Object(properties))
+    }
+
+    fn execute(&self, conn: &Connection, params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+        let window_seconds = parse_window_seconds(params)?;
+
+        let mut data = serde_json::Map::new();
+        data.insert("window_seconds".to_string(), window_seconds.into());
+        for (key, aggregation) in PARTS {
+            let result = self.parts.execute(aggregation, conn, params)?;
+            data.insert(key.to_string(), result.data);
+        }
+
+        Ok(AggregationResult {
+            name: self.name().to_string(),
+            timestamp: Utc::now(),
+            data: serde_json::Value::Object(data),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collectors::{names, Metric, MetricValue};
+    use crate::storage::{SqliteStorage, Storage};
+
+    /// Two rounds of one core's tick counters, a GPU sample and a thermal level
+    fn store_rounds(storage: &SqliteStorage) {
+        for (round, (idle, total)) in [(100, 200), (130, 300)].into_iter().enumerate() {
+            storage
+                .store_metrics(vec![
+                    Metric::counter(names::cpu_core_ticks(names::PERFORMANCE, 0, names::IDLE_TICKS), idle)
+                        .with_tag("core_id", "0")
+                        .with_tag("core_type", names::PERFORMANCE),
+                    Metric::counter(names::cpu_core_ticks(names::PERFORMANCE, 0, names::TOTAL_TICKS), total)
+                        .with_tag("core_id", "0")
+                        .with_tag("core_type", names::PERFORMANCE),
+                    Metric::gauge(names::gpu_utilization(0), 0.25 * (round + 1) as f64).with_tag("gpu_index", "0"),
+                    Metric::new(names::THERMAL_PRESSURE_LEVEL.to_string(), MetricValue::Integer(round as i64 + 1), HashMap::new()),
+                ])
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn result_holds_each_part_under_its_key() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        store_rounds(&storage);
+        let conn = storage.get_connection().unwrap();
+        let params = HashMap::from([("window_seconds".to_string(), "300".to_string())]);
+
+        let overview = SystemOverview::new().execute(&conn, &params).unwrap().data;
+
+        assert_eq!(overview["window_seconds"], 300);
+        for (key, aggregation) in PARTS {
+            let part = SystemOverview::new().parts.execute(aggregation, &conn, &params).unwrap().data;
+            assert_eq!(overview[key], part, "{}", key);
+            assert_eq!(overview[key]["window_seconds"], 300, "{}", key);
+        }
+        assert_eq!(overview["cpu"]["cores"].as_array().map(Vec::len), Some(1));
+        assert_eq!(overview["gpu"]["sample_count"], 2);
+        assert_eq!(overview["thermal"]["max_level"], 2);
+    }
+}
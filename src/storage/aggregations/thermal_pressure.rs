@@ -0,0 +1,110 @@
+use super::{object_schema, parse_window_seconds, Aggregation, AggregationResult, ParamSpec, WINDOW_SECONDS_PARAM};
+use crate::collectors::names;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Thermal pressure over a time window.
+///
+/// Levels run from 0 (nominal) to 3 (critical), as stored by the thermal
+/// collector. `throttled_percent` is the share of samples in the window taken
+/// while the OS was throttling; every figure is `null` when the window has no
+/// thermal samples (e.g. on platforms without a thermal collector).
+pub struct ThermalPressure;
+
+const LEVEL_STATS_QUERY: &str = "
+    SELECT
+        AVG(CASE WHEN name = ?2 THEN value_int END),
+        MAX(CASE WHEN name = ?2 THEN value_int END),
+        AVG(CASE WHEN name = ?3 THEN value_bool END) * 100.0,
+        COUNT(CASE WHEN name = ?2 THEN 1 END)
+    FROM metrics
+    WHERE name IN (?2, ?3)
+      AND timestamp >= CAST(strftime('%s', 'now') AS INTEGER) * 1000 - ?1";
+
+const LATEST_LEVEL_QUERY: &str = "
+    SELECT timestamp, value_int, json_extract(metadata, '$.thermal_state')
+    FROM metrics
+    WHERE name = ?2
+      AND timestamp >= CAST(strftime('%s', 'now') AS INTEGER) * 1000 - ?1
+    ORDER BY timestamp DESC, id DESC
+    LIMIT 1";
+
+impl Aggregation for ThermalPressure {
+    fn name(&self) -> &str {
+        "thermal_pressure"
+    }
+
+    fn description(&self) -> &str {
+        "Latest, average and peak thermal pressure and time spent throttled over a time window"
+    }
+
+    fn param_schema(&self) -> &[ParamSpec] {
+        &[WINDOW_SECONDS_PARAM]
+    }
+
+    fn result_schema(&self) -> serde_json::Value {
+        object_schema(serde_json::json!({
+            "window_seconds": { "type": "integer" },
+            "sample_count": { "type": "integer" },
+            "latest_timestamp": { "type": ["string", "null"], "format": "date-time" },
+            "latest_level": { "type": ["integer", "null"], "minimum": 0, "maximum": 3 },
+            "latest_state": { "type": ["string", "null"] },
+            "avg_level": { "type": ["number", "null"] },
+            "max_level": { "type": ["integer", "null"] },
+            "throttled_percent": { "type": ["number", "null"] },
+        }))
+    }
+
+    fn execute(&self, conn: &Connection, params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+        let window_seconds = parse_window_seconds(params)?;
+        let window_ms = window_seconds * 1000;
+
+        let (avg_level, max_level, throttled_percent, sample_count) = conn.query_row(
+            LEVEL_STATS_QUERY,
+            params![window_ms, names::THERMAL_PRESSURE_LEVEL, names::THERMAL_CPU_THROTTLED],
+            |row| {
+                Ok((
+                    row.get::<_, Option<f64>>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, Option<f64>>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            },
+        )?;
+
+        let latest = conn
+            .query_row(
+                LATEST_LEVEL_QUERY,
+                params![window_ms, names::THERMAL_PRESSURE_LEVEL],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, Option<i64>>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                    ))
+                },
+            )
+            .optional()?;
+        let (latest_timestamp, latest_level, latest_state) = match latest {
+            Some((ms, level, state)) => (DateTime::from_timestamp_millis(ms), level, state),
+            None => (None, None, None),
+        };
+
+        Ok(AggregationResult {
+            name: self.name().to_string(),
+            timestamp: Utc::now(),
+            data: serde_json::json!({
+                "window_seconds": window_seconds,
+                "sample_count": sample_count,
+                "latest_timestamp": latest_timestamp,
+                "latest_level": latest_level,
+                "latest_state": latest_state,
+                "avg_level": avg_level,
+                "max_level": max_level,
+                "throttled_percent": throttled_percent,
+            }),
+        })
+    }
+}
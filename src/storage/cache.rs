@@ -0,0 +1,98 @@
+use crate::collectors::Metric;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Metrics from one collection round held in memory
+#[derive(Debug, Clone)]
+pub struct CachedRound {
+    pub timestamp: DateTime<Utc>,
+    pub metrics: Vec<Metric>,
+}
+
+/// Ring buffer of the most recent collection rounds, for reading live metrics
+/// without going through SQLite.
+///
+/// Once `capacity` rounds are held, pushing a round evicts the oldest one. A
+/// capacity of zero keeps nothing.
+pub struct RecentCache {
+    capacity: usize,
+    rounds: Mutex<VecDeque<CachedRound>>,
+}
+
+impl RecentCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            rounds: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Add a round, evicting the oldest one when the cache is full
+    pub fn push(&self, timestamp: DateTime<Utc>, metrics: Vec<Metric>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut rounds = self.rounds.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        while rounds.len() >= self.capacity {
+            rounds.pop_front();
+        }
+        rounds.push_back(CachedRound { timestamp, metrics });
+    }
+
+    /// Copy of the cached rounds, oldest first
+    pub fn snapshot(&self) -> Vec<CachedRound> {
+        let rounds = self.rounds.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        rounds.iter().cloned().collect()
+    }
+
+    /// The most recently pushed round, if any
+    pub fn latest(&self) -> Option<CachedRound> {
+        let rounds = self.rounds.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        rounds.back().cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rounds.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, second).unwrap()
+    }
+
+    #[test]
+    fn full_cache_evicts_the_oldest_round() {
+        let cache = RecentCache::new(3);
+        for second in 0..5 {
+            cache.push(at(second), vec![Metric::gauge("gpu_utilization", second as f64)]);
+        }
+
+        assert_eq!(cache.len(), 3);
+        let timestamps: Vec<_> = cache.snapshot().iter().map(|round| round.timestamp).collect();
+        assert_eq!(timestamps, [at(2), at(3), at(4)]);
+        assert_eq!(cache.latest().unwrap().timestamp, at(4));
+    }
+
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let cache = RecentCache::new(0);
+        cache.push(at(0), vec![Metric::gauge("gpu_utilization", 0.5)]);
+
+        assert!(cache.is_empty());
+        assert!(cache.latest().is_none());
+    }
+}
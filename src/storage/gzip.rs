@@ -0,0 +1,78 @@
+//! Gzip (RFC 1952) compression of stored chart data, backed by `flate2`.
+//!
+//! Any standard gzip stream decompresses, so data written by other gzip tools
+//! (or by earlier thrud versions) reads back too.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::error::Error;
+use std::io::{Read, Write};
+
+/// Gzip-compress `data`
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    // Writing to a Vec can't fail
+    encoder.write_all(data).expect("gzip into memory");
+    encoder.finish().expect("gzip into memory")
+}
+
+/// Decompress gzip `data`, checking its CRC and length
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut out = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `printf hello | gzip -n`
+    const HELLO_GZIP: [u8; 25] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00, 0x86, 0xa6,
+        0x10, 0x36, 0x05, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn round_trips_chart_data() {
+        let chart = "▁▂▃▄▅▆▇█ cpu 42%\n".repeat(20);
+        let compressed = compress(chart.as_bytes());
+
+        assert!(compressed.len() < chart.len());
+        assert_eq!(decompress(&compressed).unwrap(), chart.as_bytes());
+    }
+
+    #[test]
+    fn round_trips_empty_and_incompressible_data() {
+        assert_eq!(decompress(&compress(b"")).unwrap(), b"");
+
+        let noise: Vec<u8> = (0..4096u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        assert_eq!(decompress(&compress(&noise)).unwrap(), noise);
+    }
+
+    #[test]
+    fn decompresses_output_of_the_gzip_tool() {
+        assert_eq!(decompress(&HELLO_GZIP).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn compresses_to_a_standard_gzip_stream() {
+        let compressed = compress(b"hello");
+
+        // Magic, deflate method, and the same CRC-32 and length trailer as gzip(1)
+        assert_eq!(compressed[..3], HELLO_GZIP[..3]);
+        assert_eq!(compressed[compressed.len() - 8..], HELLO_GZIP[HELLO_GZIP.len() - 8..]);
+    }
+
+    #[test]
+    fn rejects_corrupt_data() {
+        assert!(decompress(b"not gzip at all").is_err());
+
+        let mut corrupt = HELLO_GZIP;
+        corrupt[HELLO_GZIP.len() - 8] ^= 0xff;
+        assert!(decompress(&corrupt).is_err());
+
+        assert!(decompress(&HELLO_GZIP[..HELLO_GZIP.len() - 4]).is_err());
+    }
+}
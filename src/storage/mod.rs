@@ -1,25 +1,93 @@
 pub mod sqlite;
+pub mod aggregations;
+pub mod cache;
+pub mod gzip;
+pub mod partition;
 
 pub use sqlite::*;
+pub use cache::{CachedRound, RecentCache};
+pub use aggregations::{Aggregation, AggregationRegistry, AggregationResult, ParamSpec, ParamType};
 
 use crate::collectors::Metric;
+use crate::ThrudError;
 use chrono::{DateTime, Utc};
-use std::error::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollectionRound {
     pub id: String,
     pub timestamp: DateTime<Utc>,
     pub metrics_count: usize,
+    /// Machine that collected the round; `None` for imported rounds and rounds
+    /// stored before hostnames were recorded
+    pub hostname: Option<String>,
+    /// Version of thrud that stored the round, `None` like `hostname`
+    pub thrud_version: Option<String>,
+}
+
+/// What `SqliteStorage::import_metrics` stored
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ImportSummary {
+    pub rounds: usize,
+    pub metrics: usize,
+}
+
+/// Samples of one metric laid out column by column, as returned by
+/// `SqliteStorage::export_columns`. Serialized as a JSON object of equal-length
+/// arrays, which dataframe libraries load directly (e.g. `pl.DataFrame(json)`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ColumnBatch {
+    /// Sample times in epoch milliseconds
+    pub timestamp: Vec<i64>,
+    /// Numeric values, booleans as 0/1; `None` for strings and histograms
+    pub value: Vec<Option<f64>>,
+    /// One column per metadata key, `None` where a sample lacks the key
+    #[serde(flatten)]
+    pub metadata: BTreeMap<String, Vec<Option<String>>>,
+}
+
+impl ColumnBatch {
+    pub fn len(&self) -> usize {
+        self.timestamp.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamp.is_empty()
+    }
+
+    /// The batch as an Arrow `RecordBatch`: a UTC millisecond `timestamp`
+    /// column, a nullable Float64 `value` column and a nullable Utf8 column per
+    /// metadata key
+    #[cfg(feature = "arrow")]
+    pub fn to_record_batch(&self) -> Result<arrow::record_batch::RecordBatch, arrow::error::ArrowError> {
+        use arrow::array::{ArrayRef, Float64Array, StringArray, TimestampMillisecondArray};
+        use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+        use std::sync::Arc;
+
+        let mut fields = vec![
+            Field::new("timestamp", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), false),
+            Field::new("value", DataType::Float64, true),
+        ];
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(TimestampMillisecondArray::from(self.timestamp.clone()).with_timezone("UTC")),
+            Arc::new(Float64Array::from(self.value.clone())),
+        ];
+        for (key, values) in &self.metadata {
+            fields.push(Field::new(key, DataType::Utf8, true));
+            columns.push(Arc::new(StringArray::from(values.clone())));
+        }
+        arrow::record_batch::RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+    }
 }
 
 pub trait Storage {
-    fn initialize(&self) -> Result<(), Box<dyn Error>>;
-    fn store_metrics(&self, metrics: Vec<Metric>) -> Result<CollectionRound, Box<dyn Error>>;
-    fn get_stats(&self) -> Result<StorageStats, Box<dyn Error>>;
+    fn initialize(&self) -> Result<(), ThrudError>;
+    fn store_metrics(&self, metrics: Vec<Metric>) -> Result<CollectionRound, ThrudError>;
+    fn get_stats(&self) -> Result<StorageStats, ThrudError>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct StorageStats {
     pub total_metrics: i64,
     pub total_collection_rounds: i64,
@@ -27,8 +95,52 @@ pub struct StorageStats {
     pub database_size_bytes: Option<u64>,
 }
 
-#[derive(Debug, Clone)]
+/// Findings of `SqliteStorage::health_check`
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    /// Messages from `PRAGMA integrity_check`, just `ok` for an intact database.
+    /// The other checks are skipped, and left empty, when it fails
+    pub integrity: Vec<String>,
+    pub schema_version: u32,
+    pub missing_tables: Vec<String>,
+    pub missing_indexes: Vec<String>,
+    /// Row count of each expected table that exists
+    pub row_counts: BTreeMap<String, i64>,
+    /// Metrics whose collection round no longer exists
+    pub orphaned_metrics: i64,
+    /// Charts whose collection round no longer exists
+    pub orphaned_charts: i64,
+}
+
+impl HealthReport {
+    pub fn integrity_ok(&self) -> bool {
+        self.integrity.len() == 1 && self.integrity[0] == "ok"
+    }
+
+    /// Whether the database is intact, complete and free of orphaned rows
+    pub fn is_healthy(&self) -> bool {
+        self.integrity_ok()
+            && self.missing_tables.is_empty()
+            && self.missing_indexes.is_empty()
+            && self.orphaned_metrics == 0
+            && self.orphaned_charts == 0
+    }
+}
+
+/// Metrics charted by the collector and queried by `thrud-chart-query` by default
+pub const DEFAULT_CHART_METRICS: &[&str] = &[
+    "performance_cores_utilization",
+    "efficiency_cores_utilization",
+    "gpu_utilization",
+];
+
+/// Pseudo metric name asking `generate_and_store_charts` for one
+/// `core_utilization.<core_id>` chart per CPU core
+pub const PER_CORE_CHARTS: &str = "core_utilization";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chart {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub id: Option<i64>,
     pub collection_round_id: String,
     pub metric_name: String,
@@ -38,10 +150,12 @@ pub struct Chart {
     pub timestamp: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ChartType {
     Bar,
     Braille,
+    Line,
 }
 
 impl ChartType {
@@ -49,13 +163,16 @@ impl ChartType {
         match self {
             ChartType::Bar => "bar",
             ChartType::Braille => "braille",
+            ChartType::Line => "line",
         }
     }
     
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "bar" => Some(ChartType::Bar),
             "braille" => Some(ChartType::Braille),
+            "line" => Some(ChartType::Line),
             _ => None,
         }
     }
@@ -0,0 +1,262 @@
+//! Day-partitioned layout of the metrics table.
+//!
+//! A partitioned database keeps each UTC day's metrics in its own table,
+//! `metrics_YYYYMMDD`, and `metrics` becomes a view unioning them, so reads are
+//! unchanged while dropping a whole day is a cheap `DROP TABLE` instead of a
+//! `DELETE` that fragments the file. When no day has data the view reads the
+//! empty `metrics_partition_template` table, which also gives every partition
+//! its columns.
+//!
+//! Whether a database is partitioned is read from its schema (`metrics` being a
+//! view), so every connection agrees on the layout. SQLite caps a compound
+//! `SELECT` at 500 terms, so past 500 days the partitions are unioned in groups
+//! of at most 500 by `metrics_partition_group_N` views, and `metrics` unions the
+//! groups.
+
+use super::sqlite::CREATE_METRICS_TABLE;
+use chrono::{DateTime, NaiveDate};
+use rusqlite::{params, Connection, Result as SqliteResult};
+
+/// Name prefix of partition tables, followed by the day as `YYYYMMDD`
+pub const TABLE_PREFIX: &str = "metrics_";
+
+/// Empty table read by the view when there are no partitions
+const TEMPLATE_TABLE: &str = "metrics_partition_template";
+
+/// Matches partition table names in `sqlite_master`
+const TABLE_GLOB: &str = "metrics_[0-9][0-9][0-9][0-9][0-9][0-9][0-9][0-9]";
+
+/// Every metrics column, in `CREATE_METRICS_TABLE` order. Databases migrated from
+/// older layouts may have them in a different order, so copies spell them out.
+const COLUMNS: &str = "id, collection_round_id, name, value_int, value_float, value_text, value_bool, value_json, metadata, timestamp, unit, kind";
+
+const DAY_MS: i64 = 86_400_000;
+
+/// Most terms of one compound `SELECT`, SQLite's default
+/// `SQLITE_MAX_COMPOUND_SELECT`
+const MAX_VIEW_TERMS: usize = 500;
+
+/// Name prefix of the views each unioning up to `MAX_VIEW_TERMS` partitions
+const GROUP_VIEW_PREFIX: &str = "metrics_partition_group_";
+
+/// Index suffixes created on each partition, mirroring the `idx_metrics_*` indexes
+/// of an unpartitioned database
+const INDEXES: &[(&str, &str)] = &[
+    ("collection_round", "collection_round_id"),
+    ("name", "name"),
+    ("timestamp", "timestamp"),
+    ("core_type", "json_extract(metadata, '$.core_type')"),
+];
+
+/// Whether the database uses the day-partitioned layout
+pub fn is_partitioned(conn: &Connection) -> SqliteResult<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'view' AND name = 'metrics'",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+}
+
+/// Partition table holding metrics of `day`
+pub fn table_name(day: NaiveDate) -> String {
+    format!("{}{}", TABLE_PREFIX, day.format("%Y%m%d"))
+}
+
+/// UTC day of an epoch-millisecond timestamp
+pub fn day_of(timestamp_ms: i64) -> NaiveDate {
+    DateTime::from_timestamp_millis(timestamp_ms.div_euclid(DAY_MS) * DAY_MS)
+        .map(|t| t.date_naive())
+        .unwrap_or_default()
+}
+
+fn day_start_ms(day: NaiveDate) -> i64 {
+    day.and_hms_opt(0, 0, 0).map(|t| t.and_utc().timestamp_millis()).unwrap_or_default()
+}
+
+/// Days that have a partition table, oldest first
+pub fn days(conn: &Connection) -> SqliteResult<Vec<NaiveDate>> {
+    let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name GLOB ?1")?;
+    let names = stmt.query_map([TABLE_GLOB], |row| row.get::<_, String>(0))?;
+    let mut days = Vec::new();
+    for name in names {
+        if let Ok(day) = NaiveDate::parse_from_str(&name?[TABLE_PREFIX.len()..], "%Y%m%d") {
+            days.push(day);
+        }
+    }
+    days.sort();
+    Ok(days)
+}
+
+/// Tables holding metric rows: the partitions of a partitioned database, or
+/// `metrics` itself
+pub fn metric_tables(conn: &Connection) -> SqliteResult<Vec<String>> {
+    if is_partitioned(conn)? {
+        Ok(days(conn)?.into_iter().map(table_name).collect())
+    } else {
+        Ok(vec!["metrics".to_string()])
+    }
+}
+
+/// Names of the indexes every partition should have
+pub fn expected_indexes(conn: &Connection) -> SqliteResult<Vec<String>> {
+    Ok(days(conn)?
+        .into_iter()
+        .flat_map(|day| INDEXES.iter().map(move |(suffix, _)| format!("idx_{}_{}", table_name(day), suffix)))
+        .collect())
+}
+
+/// Partition table for `day`, creating it and adding it to the view if needed.
+///
+/// New partitions continue the largest metric id of the existing ones, so ids
+/// keep increasing across days as they do in a single table.
+pub fn ensure_partition(conn: &Connection, day: NaiveDate) -> SqliteResult<String> {
+    let table = table_name(day);
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [&table],
+        |row| row.get(0),
+    )?;
+    if exists > 0 {
+        return Ok(table);
+    }
+
+    create_table(conn, &table)?;
+    conn.execute(
+        "INSERT INTO sqlite_sequence (name, seq)
+         SELECT ?1, COALESCE(MAX(seq), 0) FROM sqlite_sequence WHERE name GLOB ?2",
+        params![table, TABLE_GLOB],
+    )?;
+    rebuild_view(conn)?;
+    Ok(table)
+}
+
+/// Drop the partition of `day` if it exists, returning the number of metrics it held
+pub fn drop_partition(conn: &Connection, day: NaiveDate) -> SqliteResult<usize> {
+    if !days(conn)?.contains(&day) {
+        return Ok(0);
+    }
+    let table = table_name(day);
+    let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?;
+    // The view must stop referring to the table before it can go
+    rebuild_view_without(conn, Some(day))?;
+    conn.execute(&format!("DROP TABLE {}", table), [])?;
+    Ok(count as usize)
+}
+
+/// Delete metrics older than `cutoff_ms`: days entirely before it are dropped and
+/// the rest of the cutoff's own day is deleted row by row. Returns the number of
+/// metrics removed.
+pub fn prune_older_than(conn: &Connection, cutoff_ms: i64) -> SqliteResult<usize> {
+    let cutoff_day = day_of(cutoff_ms);
+    let mut removed = 0;
+    for day in days(conn)? {
+        if day < cutoff_day {
+            removed += drop_partition(conn, day)?;
+        } else if day == cutoff_day && day_start_ms(day) < cutoff_ms {
+            removed += conn.execute(&format!("DELETE FROM {} WHERE timestamp < ?1", table_name(day)), [cutoff_ms])?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Drop partitions left without rows, e.g. after deleting rounds from them
+pub fn drop_empty(conn: &Connection) -> SqliteResult<()> {
+    for day in days(conn)? {
+        let empty: bool = conn.query_row(&format!("SELECT NOT EXISTS (SELECT 1 FROM {})", table_name(day)), [], |row| row.get(0))?;
+        if empty {
+            drop_partition(conn, day)?;
+        }
+    }
+    Ok(())
+}
+
+/// Move the rows of an unpartitioned `metrics` table into day partitions and
+/// replace the table with the view. Must run inside a transaction.
+pub fn convert(conn: &Connection) -> SqliteResult<()> {
+    if is_partitioned(conn)? {
+        return Ok(());
+    }
+
+    create_table(conn, TEMPLATE_TABLE)?;
+    let mut stmt = conn.prepare("SELECT DISTINCT timestamp / ?1 FROM metrics ORDER BY 1")?;
+    let day_numbers: Vec<i64> = stmt.query_map([DAY_MS], |row| row.get(0))?.collect::<SqliteResult<_>>()?;
+    drop(stmt);
+
+    for day_number in day_numbers {
+        let table = table_name(day_of(day_number * DAY_MS));
+        create_table(conn, &table)?;
+        conn.execute(
+            &format!(
+                "INSERT INTO {table} ({COLUMNS}) SELECT {COLUMNS} FROM metrics
+                 WHERE timestamp >= ?1 AND timestamp < ?2 ORDER BY id"
+            ),
+            params![day_number * DAY_MS, (day_number + 1) * DAY_MS],
+        )?;
+    }
+
+    conn.execute("DROP TABLE metrics", [])?;
+    rebuild_view(conn)
+}
+
+/// Create a metrics table named `table` with the partition indexes
+fn create_table(conn: &Connection, table: &str) -> SqliteResult<()> {
+    conn.execute(&CREATE_METRICS_TABLE.replacen("metrics (", &format!("{} (", table), 1), [])?;
+    if table == TEMPLATE_TABLE {
+        return Ok(());
+    }
+    for (suffix, columns) in INDEXES {
+        conn.execute(&format!("CREATE INDEX IF NOT EXISTS idx_{table}_{suffix} ON {table}({columns})"), [])?;
+    }
+    Ok(())
+}
+
+fn rebuild_view(conn: &Connection) -> SqliteResult<()> {
+    rebuild_view_without(conn, None)
+}
+
+/// Point the `metrics` view at every partition except `excluded`. The view is
+/// nested through group views once there are too many partitions for one
+/// compound `SELECT`, which would otherwise fail every write starting a new day.
+fn rebuild_view_without(conn: &Connection, excluded: Option<NaiveDate>) -> SqliteResult<()> {
+    let tables: Vec<String> = days(conn)?
+        .into_iter()
+        .filter(|day| Some(*day) != excluded)
+        .map(table_name)
+        .collect();
+
+    conn.execute("DROP VIEW IF EXISTS metrics", [])?;
+    let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type = 'view' AND name GLOB ?1")?;
+    let groups: Vec<String> = stmt
+        .query_map([format!("{}*", GROUP_VIEW_PREFIX)], |row| row.get(0))?
+        .collect::<SqliteResult<_>>()?;
+    drop(stmt);
+    for group in groups {
+        conn.execute(&format!("DROP VIEW {}", group), [])?;
+    }
+
+    let sources = if tables.is_empty() {
+        vec![TEMPLATE_TABLE.to_string()]
+    } else if tables.len() <= MAX_VIEW_TERMS {
+        tables
+    } else {
+        let mut groups = Vec::new();
+        for (i, chunk) in tables.chunks(MAX_VIEW_TERMS).enumerate() {
+            let group = format!("{}{}", GROUP_VIEW_PREFIX, i);
+            conn.execute(&format!("CREATE VIEW {} AS {}", group, union_all(chunk)), [])?;
+            groups.push(group);
+        }
+        groups
+    };
+    conn.execute(&format!("CREATE VIEW metrics AS {}", union_all(&sources)), [])?;
+    Ok(())
+}
+
+/// `SELECT` of every metrics column from each of `sources`, joined by `UNION ALL`
+fn union_all(sources: &[String]) -> String {
+    sources
+        .iter()
+        .map(|source| format!("SELECT {} FROM {}", COLUMNS, source))
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ")
+}
@@ -1,24 +1,333 @@
-use super::{CollectionRound, Storage, StorageStats};
-use crate::collectors::Metric;
-use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection, Result as SqliteResult, OptionalExtension};
+use super::partition;
+use super::{AggregationRegistry, AggregationResult, CollectionRound, ColumnBatch, HealthReport, ImportSummary, Storage, StorageStats};
+use crate::collectors::names::{self, IDLE_TICKS, TOTAL_TICKS};
+use crate::collectors::{Metric, MetricKind, MetricValue};
+use crate::ThrudError;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, Result as SqliteResult, OptionalExtension};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
 use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::ops::Deref;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+/// Number of rows in a line chart
+const LINE_CHART_HEIGHT: usize = 5;
+
+/// Characters drawing the 8 bar levels of a bar chart row, lowest first; an
+/// empty level is a space
+pub const DEFAULT_BAR_RAMP: &str = "▁▂▃▄▅▆▇█";
+
+/// Bar levels for terminals or fonts without the Unicode block characters
+pub const ASCII_BAR_RAMP: &str = ".:-=+*#@";
+
+/// Imported metrics more than this many milliseconds apart go into separate
+/// collection rounds
+pub const IMPORT_ROUND_GAP_MS: i64 = 50;
+
+/// Number of imported collection rounds written per transaction
+const IMPORT_BATCH_ROUNDS: usize = 500;
+
+/// Metrics have one column per value type and epoch-millisecond timestamps so
+/// window queries can compare them numerically. Histogram buckets are stored as
+/// a JSON array of `[value, count]` pairs in `value_json`
+pub(super) const CREATE_METRICS_TABLE: &str = "CREATE TABLE IF NOT EXISTS metrics (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    collection_round_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value_int INTEGER,
+    value_float REAL,
+    value_text TEXT,
+    value_bool INTEGER,
+    value_json TEXT,
+    metadata TEXT NOT NULL DEFAULT '{}',
+    timestamp INTEGER NOT NULL,
+    unit TEXT,
+    kind TEXT NOT NULL DEFAULT 'gauge',
+    FOREIGN KEY(collection_round_id) REFERENCES collection_rounds(id)
+)";
+
+/// Layout of the `collection_rounds` table, with `{table}` standing for its name.
+/// `seq` numbers rounds in the order they were written and, being AUTOINCREMENT,
+/// is never reused or renumbered, even by VACUUM or after every round is pruned
+const CREATE_COLLECTION_ROUNDS_TABLE: &str = "CREATE TABLE IF NOT EXISTS {table} (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    id TEXT NOT NULL UNIQUE,
+    timestamp TEXT NOT NULL,
+    metrics_count INTEGER NOT NULL,
+    timestamp_ms INTEGER NOT NULL DEFAULT 0,
+    hostname TEXT,
+    thrud_version TEXT
+)";
+
+/// A schema change applied to databases created before it existed
+struct Migration {
+    version: u32,
+    description: &'static str,
+    apply: fn(&Connection) -> Result<(), Box<dyn Error>>,
+}
+
+/// Schema changes in the order they were made; append new ones with the next
+/// version. Databases created before versioning have no recorded version, so
+/// each migration checks whether its change is already present.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "typed value columns and epoch-millisecond timestamps",
+        apply: SqliteStorage::migrate_legacy_metrics,
+    },
+    Migration {
+        version: 2,
+        description: "metric unit and kind columns",
+        apply: SqliteStorage::add_metric_kind_columns,
+    },
+    Migration {
+        version: 3,
+        description: "value_json column for histogram values",
+        apply: SqliteStorage::add_value_json_column,
+    },
+    Migration {
+        version: 4,
+        description: "compressed flag column for gzipped chart data",
+        apply: SqliteStorage::add_chart_compressed_column,
+    },
+    Migration {
+        version: 5,
+        description: "epoch-millisecond timestamps for collection rounds",
+        apply: SqliteStorage::add_round_timestamp_ms_column,
+    },
+    Migration {
+        version: 6,
+        description: "hostname and thrud_version columns for collection rounds",
+        apply: SqliteStorage::add_round_origin_columns,
+    },
+    Migration {
+        version: 7,
+        description: "never-reused seq column ordering collection rounds",
+        apply: SqliteStorage::add_round_seq_column,
+    },
+];
+
+/// Schema version of databases created or migrated by this build
+pub const SCHEMA_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// Tables created by `create_tables`, checked by `health_check`
+const EXPECTED_TABLES: &[&str] = &["collection_rounds", "metrics", "charts", "schema_version"];
+
+/// Indexes created by `create_tables`, checked by `health_check`
+const EXPECTED_INDEXES: &[&str] = &[
+    "idx_collection_rounds_timestamp_ms",
+    "idx_metrics_collection_round",
+    "idx_metrics_name",
+    "idx_metrics_timestamp",
+    "idx_metrics_core_type",
+    "idx_charts_collection_round",
+    "idx_charts_metric_type",
+];
+
 pub struct SqliteStorage {
     db_path: String,
+    /// Single connection shared by all calls, opened on first use
+    conn: Mutex<Option<Connection>>,
+    /// Number of times a connection has been opened
+    open_count: AtomicUsize,
+    /// Rounds accepted by `store_metrics_buffered` but not yet written
+    write_buffer: Mutex<WriteBuffer>,
+    /// Flush once this many rounds are buffered
+    batch_max_rounds: usize,
+    /// Flush once the oldest buffered round is this old
+    batch_max_delay: Duration,
+    /// Times a write transaction is retried after a busy/locked error
+    busy_retries: u32,
+    /// Delay before the first retry; doubled for each later one
+    busy_backoff: Duration,
+    /// Gzip `chart_data` of newly stored charts
+    compress_charts: bool,
+    /// End bar and braille charts with the `..NN%|` average suffix
+    chart_suffix: bool,
+    /// Bar chart levels: a space for empty, then the 8 ramp characters
+    bar_chars: Vec<char>,
+    /// Bar chart character for intervals where the cores had no tick activity
+    /// at all; `None` charts them as 0% busy
+    parked_gap: Option<char>,
+    /// Skip storing a round identical to the previous one
+    dedup_rounds: bool,
+    /// Store rounds without metrics instead of rejecting them
+    heartbeat_rounds: bool,
+    /// Convert the metrics table to day partitions when initializing
+    day_partitions: bool,
+    /// Host recorded with collection rounds; imported rounds are left untagged
+    hostname: Option<String>,
+    /// Dedup key of the last round stored or buffered
+    last_round_key: Mutex<Option<u64>>,
+    /// Open the database with `SQLITE_OPEN_READONLY` and never create it or its tables
+    read_only: bool,
+}
+
+/// Default number of retries for a write that finds the database busy
+const DEFAULT_BUSY_RETRIES: u32 = 3;
+/// Default delay before the first busy retry
+const DEFAULT_BUSY_BACKOFF: Duration = Duration::from_millis(100);
+
+impl Drop for SqliteStorage {
+    fn drop(&mut self) {
+        // Best effort: don't lose buffered rounds when the storage goes away
+        let _ = self.flush();
+    }
+}
+
+#[derive(Default)]
+struct WriteBuffer {
+    rounds: Vec<(DateTime<Utc>, Vec<Metric>)>,
+    oldest: Option<Instant>,
+}
+
+/// Exclusive access to the storage's connection for the duration of a call
+pub struct ConnectionHandle<'a>(MutexGuard<'a, Option<Connection>>);
+
+impl Deref for ConnectionHandle<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.0.as_ref().expect("connection is opened before a handle is created")
+    }
+}
+
+/// Non-empty value of an environment variable
+fn env_path(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Version of thrud recorded with each collection round it stores
+pub const THRUD_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg(unix)]
+extern "C" {
+    fn gethostname(name: *mut std::os::raw::c_char, len: usize) -> std::os::raw::c_int;
+}
+
+/// Name of this machine, recorded with each collection round so rounds from
+/// several machines can share a database. Falls back to `$HOSTNAME`.
+pub fn local_hostname() -> Option<String> {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        // SAFETY: the buffer outlives the call and its length is passed along
+        if unsafe { gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } == 0 {
+            let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+            let name = String::from_utf8_lossy(&buffer[..end]).into_owned();
+            if !name.is_empty() {
+                return Some(name);
+            }
+        }
+    }
+    env_path("HOSTNAME")
+}
+
+/// Database location used when no path is given explicitly.
+///
+/// Resolved in order from `$THRUD_DB`, `$XDG_DATA_HOME/thrud/thrud.db` and
+/// `$HOME/.thrud/thrud.db`. Empty variables are treated as unset, and a relative
+/// `XDG_DATA_HOME` is ignored as the XDG spec requires.
+pub fn default_db_path() -> Result<String, Box<dyn Error>> {
+    db_path_from(env_path)
+}
+
+/// [`default_db_path`] with the non-empty environment variables looked up by `var`
+fn db_path_from(var: impl Fn(&str) -> Option<String>) -> Result<String, Box<dyn Error>> {
+    if let Some(path) = var("THRUD_DB") {
+        return Ok(path);
+    }
+    if let Some(data_home) = var("XDG_DATA_HOME").filter(|dir| Path::new(dir).is_absolute()) {
+        return Ok(format!("{}/thrud/thrud.db", data_home));
+    }
+    if let Some(home_dir) = var("HOME") {
+        return Ok(format!("{}/.thrud/thrud.db", home_dir));
+    }
+    Err("Cannot determine database location: set THRUD_DB, XDG_DATA_HOME or HOME, or pass an explicit database path".into())
 }
 
 impl SqliteStorage {
-    pub fn new(db_path: Option<String>) -> Self {
-        let path = db_path.unwrap_or_else(|| {
-            let home_dir = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-            format!("{}/.thrud/thrud.db", home_dir)
-        });
-        
-        Self { db_path: path }
+    /// Storage at `db_path`, or at [`default_db_path`] when none is given
+    pub fn new(db_path: Option<String>) -> Result<Self, Box<dyn Error>> {
+        let path = match db_path {
+            Some(path) => path,
+            None => default_db_path()?,
+        };
+
+        Ok(Self {
+            db_path: path,
+            conn: Mutex::new(None),
+            open_count: AtomicUsize::new(0),
+            write_buffer: Mutex::new(WriteBuffer::default()),
+            batch_max_rounds: 1,
+            batch_max_delay: Duration::ZERO,
+            busy_retries: DEFAULT_BUSY_RETRIES,
+            busy_backoff: DEFAULT_BUSY_BACKOFF,
+            compress_charts: false,
+            chart_suffix: true,
+            bar_chars: bar_chars(DEFAULT_BAR_RAMP),
+            parked_gap: None,
+            dedup_rounds: false,
+            heartbeat_rounds: false,
+            day_partitions: false,
+            hostname: local_hostname(),
+            last_round_key: Mutex::new(None),
+            read_only: false,
+        })
+    }
+
+    /// Read-only storage at `db_path`, or at [`default_db_path`] when none is
+    /// given, for tools that only read such as `thrud-chart-query`. Unlike
+    /// [`SqliteStorage::new`] the database must already exist: it is opened with
+    /// `SQLITE_OPEN_READONLY`, so a reader never creates an empty database, and
+    /// `initialize` leaves the schema alone. Writes fail with an SQLite error.
+    pub fn open_read_only(db_path: Option<String>) -> Result<Self, Box<dyn Error>> {
+        let mut storage = Self::new(db_path)?;
+        if !Path::new(&storage.db_path).exists() {
+            return Err(format!("Database not found: {}", storage.db_path).into());
+        }
+        storage.read_only = true;
+        // Open now so an unreadable file is reported here rather than on first use
+        drop(storage.get_connection()?);
+        Ok(storage)
+    }
+
+    pub fn db_path(&self) -> &str {
+        &self.db_path
+    }
+
+    /// Create an initialized storage backed by a private in-memory database
+    pub fn new_in_memory() -> Result<Self, Box<dyn Error>> {
+        let storage = Self {
+            db_path: ":memory:".to_string(),
+            conn: Mutex::new(Some(Connection::open_in_memory()?)),
+            open_count: AtomicUsize::new(1),
+            write_buffer: Mutex::new(WriteBuffer::default()),
+            batch_max_rounds: 1,
+            batch_max_delay: Duration::ZERO,
+            busy_retries: DEFAULT_BUSY_RETRIES,
+            busy_backoff: DEFAULT_BUSY_BACKOFF,
+            compress_charts: false,
+            chart_suffix: true,
+            bar_chars: bar_chars(DEFAULT_BAR_RAMP),
+            parked_gap: None,
+            dedup_rounds: false,
+            heartbeat_rounds: false,
+            day_partitions: false,
+            hostname: local_hostname(),
+            last_round_key: Mutex::new(None),
+            read_only: false,
+        };
+        storage.create_tables()?;
+        Ok(storage)
     }
 
     fn ensure_db_directory(&self) -> Result<(), Box<dyn Error>> {
@@ -29,54 +338,90 @@ impl SqliteStorage {
         Ok(())
     }
 
-    fn get_connection(&self) -> SqliteResult<Connection> {
-        Connection::open(&self.db_path)
+    /// Get the shared connection, opening it on first use. The handle holds the
+    /// connection lock, so drop it before calling other storage methods.
+    pub fn get_connection(&self) -> SqliteResult<ConnectionHandle<'_>> {
+        // A panic while holding the lock doesn't leave the connection unusable
+        let mut guard = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        if guard.is_none() {
+            *guard = Some(if self.read_only {
+                Connection::open_with_flags(&self.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI)?
+            } else {
+                Connection::open(&self.db_path)?
+            });
+            self.open_count.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(ConnectionHandle(guard))
+    }
+
+    /// Number of times the database connection has been opened
+    pub fn connections_opened(&self) -> usize {
+        self.open_count.load(Ordering::Relaxed)
+    }
+
+    /// Run a registered aggregation against this database
+    pub fn run_aggregation(&self, registry: &AggregationRegistry, name: &str, params: &HashMap<String, String>) -> Result<AggregationResult, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        registry.execute(name, &conn, params)
     }
 
     fn create_tables(&self) -> Result<(), Box<dyn Error>> {
         let conn = self.get_connection()?;
         
-        // Create collection_rounds table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS collection_rounds (
-                id TEXT PRIMARY KEY,
-                timestamp TEXT NOT NULL,
-                metrics_count INTEGER NOT NULL
-            )",
-            [],
-        )?;
+        // Create collection_rounds table. `timestamp` is RFC3339 for display;
+        // ordering and time windows use `timestamp_ms`
+        conn.execute(&CREATE_COLLECTION_ROUNDS_TABLE.replace("{table}", "collection_rounds"), [])?;
 
-        // Create metrics table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS metrics (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                collection_round_id TEXT NOT NULL,
-                name TEXT NOT NULL,
-                value TEXT NOT NULL,
-                timestamp TEXT NOT NULL,
-                FOREIGN KEY(collection_round_id) REFERENCES collection_rounds(id)
-            )",
+        let has_metrics: bool = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type IN ('table', 'view') AND name = 'metrics'",
             [],
-        )?;
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+        // Partitions are created at the latest layout, so a partitioned database
+        // has no metrics migrations left to apply
+        let partitioned = partition::is_partitioned(&conn)?;
+
+        // Create metrics table, already in the latest layout for a new database
+        if !partitioned {
+            conn.execute(CREATE_METRICS_TABLE, [])?;
+        }
+        Self::run_migrations(&conn, has_metrics)?;
 
         // Create indexes for better query performance
         conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_metrics_collection_round 
-             ON metrics(collection_round_id)",
+            "CREATE INDEX IF NOT EXISTS idx_collection_rounds_timestamp_ms 
+             ON collection_rounds(timestamp_ms)",
             [],
         )?;
 
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_metrics_name 
-             ON metrics(name)",
-            [],
-        )?;
+        // Partitions carry their own copies of the metrics indexes
+        if !partitioned {
+            conn.execute(
+                "CREATE INDEX IF NOT EXISTS idx_metrics_collection_round 
+                 ON metrics(collection_round_id)",
+                [],
+            )?;
 
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_metrics_timestamp 
-             ON metrics(timestamp)",
-            [],
-        )?;
+            conn.execute(
+                "CREATE INDEX IF NOT EXISTS idx_metrics_name 
+                 ON metrics(name)",
+                [],
+            )?;
+
+            conn.execute(
+                "CREATE INDEX IF NOT EXISTS idx_metrics_timestamp 
+                 ON metrics(timestamp)",
+                [],
+            )?;
+
+            // Only used by queries spelling out this exact expression, see
+            // `query_metrics_by_metadata`
+            conn.execute(
+                "CREATE INDEX IF NOT EXISTS idx_metrics_core_type 
+                 ON metrics(json_extract(metadata, '$.core_type'))",
+                [],
+            )?;
+        }
 
         // Create charts table for pre-computed visualizations
         conn.execute(
@@ -88,6 +433,7 @@ impl SqliteStorage {
                 chart_data TEXT NOT NULL,
                 data_points INTEGER NOT NULL,
                 timestamp TEXT NOT NULL,
+                compressed INTEGER NOT NULL DEFAULT 0,
                 FOREIGN KEY(collection_round_id) REFERENCES collection_rounds(id)
             )",
             [],
@@ -106,129 +452,1189 @@ impl SqliteStorage {
             [],
         )?;
 
+        if self.day_partitions && !partitioned {
+            let tx = conn.unchecked_transaction()?;
+            partition::convert(&tx)?;
+            tx.commit()?;
+        }
+
+        Ok(())
+    }
+
+    /// Bring an existing database up to `SCHEMA_VERSION`, recording each applied
+    /// migration in `schema_version`. A new database already has the latest
+    /// layout, so its migrations are only recorded.
+    fn run_migrations(conn: &Connection, existing: bool) -> Result<(), Box<dyn Error>> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER PRIMARY KEY,
+                description TEXT NOT NULL,
+                applied_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        let current = Self::read_schema_version(conn)?;
+        if current > SCHEMA_VERSION {
+            return Err(format!(
+                "Database schema version {} is newer than the supported version {}; upgrade thrud",
+                current, SCHEMA_VERSION
+            )
+            .into());
+        }
+
+        for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+            if existing {
+                (migration.apply)(conn)?;
+            }
+            conn.execute(
+                "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+                params![migration.version, migration.description, Utc::now().to_rfc3339()],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn read_schema_version(conn: &Connection) -> SqliteResult<u32> {
+        conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))
+    }
+
+    /// Schema version recorded in the database, 0 if it predates versioning
+    pub fn schema_version(&self) -> Result<u32, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        let has_table: bool = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+        if !has_table {
+            return Ok(0);
+        }
+        Ok(Self::read_schema_version(&conn)?)
+    }
+
+    /// Number of stored samples of each metric name, most frequent first
+    pub fn get_metric_counts(&self) -> Result<Vec<(String, i64)>, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT name, COUNT(*) AS samples FROM metrics GROUP BY name ORDER BY samples DESC, name",
+        )?;
+        let counts = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<SqliteResult<_>>()?;
+        Ok(counts)
+    }
+
+    /// Distinct names of the stored metrics, sorted
+    pub fn metric_names(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare("SELECT DISTINCT name FROM metrics ORDER BY name")?;
+        let names = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<SqliteResult<_>>()?;
+        Ok(names)
+    }
+
+    /// Check the database without creating or migrating anything: run
+    /// `PRAGMA integrity_check`, look for missing tables and indexes, count rows
+    /// and find metrics and charts whose collection round is missing
+    pub fn health_check(&self) -> Result<HealthReport, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+
+        let mut integrity = Vec::new();
+        let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+        for message in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            integrity.push(message?);
+        }
+        drop(stmt);
+        // The remaining checks can't be trusted, and may not even run, on a damaged file
+        if integrity.len() != 1 || integrity[0] != "ok" {
+            return Ok(HealthReport {
+                integrity,
+                schema_version: 0,
+                missing_tables: Vec::new(),
+                missing_indexes: Vec::new(),
+                row_counts: BTreeMap::new(),
+                orphaned_metrics: 0,
+                orphaned_charts: 0,
+            });
+        }
+
+        let mut existing = std::collections::HashSet::new();
+        let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type IN ('table', 'view', 'index')")?;
+        for name in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            existing.insert(name?);
+        }
+        let missing = |expected: &[&str]| -> Vec<String> {
+            expected
+                .iter()
+                .filter(|name| !existing.contains(**name))
+                .map(|name| name.to_string())
+                .collect()
+        };
+        let missing_tables = missing(EXPECTED_TABLES);
+        // A partitioned database has each partition's indexes instead of the
+        // metrics table's
+        let missing_indexes = if partition::is_partitioned(&conn)? {
+            let mut expected: Vec<String> = EXPECTED_INDEXES
+                .iter()
+                .filter(|name| !name.starts_with("idx_metrics_"))
+                .map(|name| name.to_string())
+                .collect();
+            expected.extend(partition::expected_indexes(&conn)?);
+            expected.into_iter().filter(|name| !existing.contains(name)).collect()
+        } else {
+            missing(EXPECTED_INDEXES)
+        };
+
+        let mut row_counts = BTreeMap::new();
+        for table in EXPECTED_TABLES.iter().filter(|table| existing.contains(**table)) {
+            let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?;
+            row_counts.insert(table.to_string(), count);
+        }
+
+        let has_rounds = existing.contains("collection_rounds");
+        let count_orphans = |table: &str| -> SqliteResult<i64> {
+            if !has_rounds || !existing.contains(table) {
+                return Ok(0);
+            }
+            conn.query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM {} t
+                     WHERE NOT EXISTS (SELECT 1 FROM collection_rounds cr WHERE cr.id = t.collection_round_id)",
+                    table
+                ),
+                [],
+                |row| row.get(0),
+            )
+        };
+        let orphaned_metrics = count_orphans("metrics")?;
+        let orphaned_charts = count_orphans("charts")?;
+
+        let schema_version = if existing.contains("schema_version") {
+            Self::read_schema_version(&conn)?
+        } else {
+            0
+        };
+
+        Ok(HealthReport {
+            integrity,
+            schema_version,
+            missing_tables,
+            missing_indexes,
+            row_counts,
+            orphaned_metrics,
+            orphaned_charts,
+        })
+    }
+
+    /// Convert a `metrics` table using the original `value TEXT` column and RFC3339
+    /// timestamps into the typed layout. Values are classified by their text form.
+    fn migrate_legacy_metrics(conn: &Connection) -> Result<(), Box<dyn Error>> {
+        let has_legacy_value: bool = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('metrics') WHERE name = 'value'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+
+        if !has_legacy_value {
+            return Ok(());
+        }
+
+        let tx = conn.unchecked_transaction()?;
+
+        tx.execute("ALTER TABLE metrics RENAME TO metrics_legacy", [])?;
+        tx.execute("DROP INDEX IF EXISTS idx_metrics_collection_round", [])?;
+        tx.execute("DROP INDEX IF EXISTS idx_metrics_name", [])?;
+        tx.execute("DROP INDEX IF EXISTS idx_metrics_timestamp", [])?;
+        tx.execute(CREATE_METRICS_TABLE, [])?;
+        tx.execute(
+            "INSERT INTO metrics (id, collection_round_id, name, value_int, value_float, value_text, value_bool, metadata, timestamp)
+             SELECT
+                id,
+                collection_round_id,
+                name,
+                CASE WHEN kind = 'int' THEN CAST(value AS INTEGER) END,
+                CASE WHEN kind = 'float' THEN CAST(value AS REAL) END,
+                CASE WHEN kind = 'text' THEN value END,
+                CASE WHEN kind = 'bool' THEN value = 'true' END,
+                '{}',
+                CAST(ROUND((julianday(timestamp) - 2440587.5) * 86400000) AS INTEGER)
+             FROM (
+                SELECT *,
+                    CASE
+                        WHEN value IN ('true', 'false') THEN 'bool'
+                        WHEN value GLOB '[0-9]*' OR value GLOB '-[0-9]*' THEN
+                            CASE WHEN value GLOB '*[^0-9-]*' THEN
+                                CASE WHEN value GLOB '*[^0-9.eE+-]*' THEN 'text' ELSE 'float' END
+                            ELSE 'int' END
+                        ELSE 'text'
+                    END AS kind
+                FROM metrics_legacy
+             )",
+            [],
+        )?;
+        tx.execute("DROP TABLE metrics_legacy", [])?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Add the `unit` and `kind` columns to a metrics table created before they
+    /// existed. Existing rows read as unitless gauges.
+    fn add_metric_kind_columns(conn: &Connection) -> Result<(), Box<dyn Error>> {
+        let has_kind: bool = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('metrics') WHERE name = 'kind'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+
+        if !has_kind {
+            conn.execute("ALTER TABLE metrics ADD COLUMN unit TEXT", [])?;
+            conn.execute("ALTER TABLE metrics ADD COLUMN kind TEXT NOT NULL DEFAULT 'gauge'", [])?;
+        }
+        Ok(())
+    }
+
+    /// Add the `value_json` column used for histogram values to databases created
+    /// before histograms were supported
+    fn add_value_json_column(conn: &Connection) -> Result<(), Box<dyn Error>> {
+        let has_value_json: bool = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('metrics') WHERE name = 'value_json'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+
+        if !has_value_json {
+            conn.execute("ALTER TABLE metrics ADD COLUMN value_json TEXT", [])?;
+        }
+        Ok(())
+    }
+
+    /// Add the `compressed` flag to `charts`; existing charts are uncompressed.
+    /// A database without a `charts` table gets it, flag included, from
+    /// `create_tables` after migrating.
+    fn add_chart_compressed_column(conn: &Connection) -> Result<(), Box<dyn Error>> {
+        let columns: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_table_info('charts')")?
+            .query_map([], |row| row.get(0))?
+            .collect::<SqliteResult<_>>()?;
+
+        if !columns.is_empty() && !columns.iter().any(|name| name == "compressed") {
+            conn.execute("ALTER TABLE charts ADD COLUMN compressed INTEGER NOT NULL DEFAULT 0", [])?;
+        }
+        Ok(())
+    }
+
+    /// Add `timestamp_ms` to `collection_rounds`, filled in from the RFC3339
+    /// `timestamp` of existing rounds
+    fn add_round_timestamp_ms_column(conn: &Connection) -> Result<(), Box<dyn Error>> {
+        let has_timestamp_ms: bool = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('collection_rounds') WHERE name = 'timestamp_ms'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+
+        if !has_timestamp_ms {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute("ALTER TABLE collection_rounds ADD COLUMN timestamp_ms INTEGER NOT NULL DEFAULT 0", [])?;
+            tx.execute(
+                "UPDATE collection_rounds
+                 SET timestamp_ms = CAST(ROUND((julianday(timestamp) - 2440587.5) * 86400000) AS INTEGER)",
+                [],
+            )?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    fn add_round_origin_columns(conn: &Connection) -> Result<(), Box<dyn Error>> {
+        let has_hostname: bool = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('collection_rounds') WHERE name = 'hostname'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+
+        // Rounds stored before this migration keep NULL: their origin is unknown
+        if !has_hostname {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute("ALTER TABLE collection_rounds ADD COLUMN hostname TEXT", [])?;
+            tx.execute("ALTER TABLE collection_rounds ADD COLUMN thrud_version TEXT", [])?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    fn add_round_seq_column(conn: &Connection) -> Result<(), Box<dyn Error>> {
+        let has_seq: bool = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('collection_rounds') WHERE name = 'seq'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+
+        // SQLite can't add a primary key column, so the table is rebuilt. Existing
+        // rounds keep their rowid as `seq`, so cursors handed out before the
+        // migration still point at the same round. Metrics and charts reference
+        // rounds, so foreign keys are off while the old table is dropped (the
+        // pragma is ignored inside a transaction)
+        if !has_seq {
+            let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+            conn.pragma_update(None, "foreign_keys", false)?;
+            let rebuilt = Self::rebuild_collection_rounds(conn);
+            conn.pragma_update(None, "foreign_keys", foreign_keys)?;
+            rebuilt?;
+        }
+        Ok(())
+    }
+
+    /// Copy `collection_rounds` into a table with a `seq` primary key taken from
+    /// each round's rowid, and swap it in
+    fn rebuild_collection_rounds(conn: &Connection) -> Result<(), Box<dyn Error>> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(&CREATE_COLLECTION_ROUNDS_TABLE.replace("{table}", "collection_rounds_seq"), [])?;
+        tx.execute(
+            "INSERT INTO collection_rounds_seq (seq, id, timestamp, metrics_count, timestamp_ms, hostname, thrud_version)
+             SELECT rowid, id, timestamp, metrics_count, timestamp_ms, hostname, thrud_version
+             FROM collection_rounds ORDER BY rowid",
+            [],
+        )?;
+        tx.execute("DROP TABLE collection_rounds", [])?;
+        tx.execute("ALTER TABLE collection_rounds_seq RENAME TO collection_rounds", [])?;
+        tx.commit()?;
         Ok(())
     }
 }
 
 impl Storage for SqliteStorage {
-    fn initialize(&self) -> Result<(), Box<dyn Error>> {
+    fn initialize(&self) -> Result<(), ThrudError> {
+        if self.read_only {
+            return Ok(());
+        }
         self.ensure_db_directory()?;
         self.create_tables()?;
         Ok(())
     }
 
-    fn store_metrics(&self, metrics: Vec<Metric>) -> Result<CollectionRound, Box<dyn Error>> {
-        if metrics.is_empty() {
-            return Err("Cannot store empty metrics collection".into());
+    fn store_metrics(&self, metrics: Vec<Metric>) -> Result<CollectionRound, ThrudError> {
+        self.insert_metrics(&metrics)
+    }
+
+    fn get_stats(&self) -> Result<StorageStats, ThrudError> {
+        let conn = self.get_connection()?;
+
+        // Get total metrics count
+        let total_metrics: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM metrics",
+            [],
+            |row| row.get(0),
+        )?;
+
+        // Get total collection rounds count
+        let total_collection_rounds: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM collection_rounds",
+            [],
+            |row| row.get(0),
+        )?;
+
+        // Get latest collection round
+        let latest_collection = conn.query_row(
+            "SELECT id, timestamp_ms, metrics_count, hostname, thrud_version FROM collection_rounds 
+             ORDER BY timestamp_ms DESC LIMIT 1",
+            [],
+            |row| {
+                let id: String = row.get(0)?;
+                let timestamp_ms: i64 = row.get(1)?;
+                let metrics_count: usize = row.get::<_, i64>(2)? as usize;
+                
+                let timestamp = DateTime::from_timestamp_millis(timestamp_ms)
+                    .ok_or(rusqlite::Error::IntegralValueOutOfRange(1, timestamp_ms))?;
+
+                Ok(CollectionRound {
+                    id,
+                    timestamp,
+                    metrics_count,
+                    hostname: row.get(3)?,
+                    thrud_version: row.get(4)?,
+                })
+            },
+        ).optional()?;
+
+        // Get database file size
+        let database_size_bytes = std::fs::metadata(&self.db_path)
+            .map(|metadata| metadata.len())
+            .ok();
+
+        Ok(StorageStats {
+            total_metrics,
+            total_collection_rounds,
+            latest_collection,
+            database_size_bytes,
+        })
+    }
+
+}
+
+impl SqliteStorage {
+    /// Store a slice of metrics as a new collection round
+    pub fn insert_metrics(&self, metrics: &[Metric]) -> Result<CollectionRound, ThrudError> {
+        if metrics.is_empty() && !self.heartbeat_rounds {
+            return Err(ThrudError::EmptyMetrics);
         }
 
+        let key = self.dedup_key(metrics)?;
+        self.check_duplicate(key)?;
+        let mut rounds = self.insert_rounds(&[(Utc::now(), metrics)], true)?;
+        self.remember_round(key);
+        Ok(rounds.remove(0))
+    }
+
+    /// Buffer writes so `store_metrics_buffered` flushes once `max_rounds` rounds
+    /// are pending or the oldest pending round is `max_delay` old, whichever
+    /// comes first. The default (1 round) writes every round immediately.
+    pub fn with_write_batch(mut self, max_rounds: usize, max_delay: Duration) -> Self {
+        self.batch_max_rounds = max_rounds.max(1);
+        self.batch_max_delay = max_delay;
+        self
+    }
+
+    /// Retry write transactions that fail because another connection holds the
+    /// database lock up to `retries` times, sleeping `backoff`, `2 * backoff`, ...
+    /// between attempts. Other errors are returned immediately. Defaults to 3
+    /// retries starting at 100ms; 0 disables retrying.
+    pub fn with_busy_retry(mut self, retries: u32, backoff: Duration) -> Self {
+        self.busy_retries = retries;
+        self.busy_backoff = backoff;
+        self
+    }
+
+    /// Gzip the `chart_data` of charts stored from now on, keeping it as a BLOB
+    /// flagged by the `compressed` column. Reads decompress transparently and
+    /// charts stored uncompressed stay readable. Off by default.
+    pub fn with_chart_compression(mut self, enabled: bool) -> Self {
+        self.compress_charts = enabled;
+        self
+    }
+
+    /// Store bar and braille charts without the `..NN%|` average suffix, leaving
+    /// just the sparkline, e.g. for embedding in a status bar. Line charts have no
+    /// suffix. On by default.
+    pub fn with_chart_suffix(mut self, enabled: bool) -> Self {
+        self.chart_suffix = enabled;
+        self
+    }
+
+    /// Store a collection round with no metrics as an empty "heartbeat" round
+    /// (`metrics_count` 0) instead of failing with [`ThrudError::EmptyMetrics`],
+    /// so idle periods can be told apart from downtime. Heartbeat rounds are never
+    /// deduplicated and don't count towards chart windows. Off by default.
+    pub fn with_heartbeat_rounds(mut self, enabled: bool) -> Self {
+        self.heartbeat_rounds = enabled;
+        self
+    }
+
+    /// Draw bar charts stored from now on with `ramp`, 8 characters from the lowest
+    /// to the full level such as [`ASCII_BAR_RAMP`]. A ramp of any other length
+    /// falls back to [`DEFAULT_BAR_RAMP`]; check it with [`is_valid_bar_ramp`] first
+    /// to report the mistake.
+    pub fn with_bar_ramp(mut self, ramp: &str) -> Self {
+        self.bar_chars = bar_chars(if is_valid_bar_ramp(ramp) { ramp } else { DEFAULT_BAR_RAMP });
+        self
+    }
+
+    /// Draw intervals in which a core (or every core of a cluster) was parked,
+    /// with no tick activity at all, as `gap` in bar charts stored from now on,
+    /// so they can be told apart from a core that ran but was 0% busy. Parked
+    /// intervals don't count towards the `..NN%|` average. Braille and line
+    /// charts still plot them as 0. `None`, the default, charts parked intervals
+    /// as 0% busy everywhere.
+    pub fn with_parked_gap(mut self, gap: Option<char>) -> Self {
+        self.parked_gap = gap;
+        self
+    }
+
+    /// Record `hostname` with the collection rounds stored from now on instead of
+    /// [`local_hostname`], e.g. to keep a machine's name stable across renames;
+    /// `None` leaves rounds untagged
+    pub fn with_hostname(mut self, hostname: Option<String>) -> Self {
+        self.hostname = hostname;
+        self
+    }
+
+    /// Keep metrics in one table per UTC day behind a `metrics` view (see
+    /// [`partition`](super::partition)), so retention pruning drops whole days
+    /// instead of deleting rows. `initialize` converts an existing database,
+    /// moving its metrics into partitions; the conversion is one-way and a
+    /// partitioned database stays partitioned without this option. Off by default.
+    pub fn with_day_partitions(mut self, enabled: bool) -> Self {
+        self.day_partitions = enabled;
+        self
+    }
+
+    /// Skip storing a collection round whose metrics are identical to the previous
+    /// round's, e.g. from a stale bridge replaying its last reading. Names, values,
+    /// metadata, units and kinds are compared, but not sample timestamps or
+    /// collector timings (`collector.duration_ms`). Skipped rounds fail with
+    /// [`ThrudError::DuplicateRound`]. Off by default.
+    pub fn with_round_dedup(mut self, enabled: bool) -> Self {
+        self.dedup_rounds = enabled;
+        self
+    }
+
+    /// Key identifying a round's metrics regardless of when they were sampled, or
+    /// `None` when deduplication is off or the round is a heartbeat
+    fn dedup_key(&self, metrics: &[Metric]) -> Result<Option<u64>, ThrudError> {
+        if !self.dedup_rounds || metrics.is_empty() {
+            return Ok(None);
+        }
+        let mut hasher = DefaultHasher::new();
+        for metric in metrics.iter().filter(|m| m.name != names::COLLECTOR_DURATION_MS) {
+            metric.name.hash(&mut hasher);
+            serde_json::to_string(&metric.value)?.hash(&mut hasher);
+            metric.metadata.iter().collect::<BTreeMap<_, _>>().hash(&mut hasher);
+            metric.unit.hash(&mut hasher);
+            metric.kind.as_str().hash(&mut hasher);
+        }
+        Ok(Some(hasher.finish()))
+    }
+
+    /// Fail with [`ThrudError::DuplicateRound`] if `key` matches the previous round's
+    fn check_duplicate(&self, key: Option<u64>) -> Result<(), ThrudError> {
+        let last = self.last_round_key.lock().map_err(|_| "Dedup key lock poisoned")?;
+        match key {
+            Some(key) if *last == Some(key) => Err(ThrudError::DuplicateRound),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record `key` as the previous round's, once the round is stored or buffered
+    fn remember_round(&self, key: Option<u64>) {
+        if key.is_some() {
+            if let Ok(mut last) = self.last_round_key.lock() {
+                *last = key;
+            }
+        }
+    }
+
+    /// Run `write`, retrying it with exponential backoff while it fails with a
+    /// busy/locked error
+    fn retry_busy<T>(&self, mut write: impl FnMut() -> Result<T, ThrudError>) -> Result<T, ThrudError> {
+        let mut backoff = self.busy_backoff;
+        let mut attempt = 0;
+        loop {
+            match write() {
+                Err(e) if e.is_busy() && attempt < self.busy_retries => {
+                    attempt += 1;
+                    std::thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Queue a collection round, writing all pending rounds in one transaction
+    /// when the batch limits are reached. Returns the rounds written, which is
+    /// empty while the round is only buffered. Call [`flush`](Self::flush) before
+    /// shutting down so buffered rounds aren't lost.
+    pub fn store_metrics_buffered(&self, metrics: Vec<Metric>) -> Result<Vec<CollectionRound>, ThrudError> {
+        if metrics.is_empty() && !self.heartbeat_rounds {
+            return Err(ThrudError::EmptyMetrics);
+        }
+
+        let key = self.dedup_key(&metrics)?;
+        self.check_duplicate(key)?;
+        let should_flush = {
+            let mut buffer = self.write_buffer.lock().map_err(|_| "Write buffer lock poisoned")?;
+            buffer.rounds.push((Utc::now(), metrics));
+            self.remember_round(key);
+            let oldest = *buffer.oldest.get_or_insert_with(Instant::now);
+            buffer.rounds.len() >= self.batch_max_rounds || oldest.elapsed() >= self.batch_max_delay
+        };
+
+        if should_flush {
+            self.flush()
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Write all buffered rounds in a single transaction. On failure the rounds
+    /// stay buffered for the next attempt.
+    pub fn flush(&self) -> Result<Vec<CollectionRound>, ThrudError> {
+        let mut buffer = self.write_buffer.lock().map_err(|_| "Write buffer lock poisoned")?;
+        if buffer.rounds.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let rounds: Vec<(DateTime<Utc>, &[Metric])> = buffer
+            .rounds
+            .iter()
+            .map(|(timestamp, metrics)| (*timestamp, metrics.as_slice()))
+            .collect();
+        let stored = self.insert_rounds(&rounds, true)?;
+
+        buffer.rounds.clear();
+        buffer.oldest = None;
+        Ok(stored)
+    }
+
+    /// Number of rounds buffered but not yet written
+    pub fn pending_rounds(&self) -> usize {
+        self.write_buffer.lock().map(|b| b.rounds.len()).unwrap_or(0)
+    }
+
+    /// Insert collection rounds and their metrics in one transaction, retrying
+    /// it while the database is busy. `tagged` rounds record this host and thrud
+    /// version.
+    fn insert_rounds(&self, rounds: &[(DateTime<Utc>, &[Metric])], tagged: bool) -> Result<Vec<CollectionRound>, ThrudError> {
+        self.retry_busy(|| self.write_rounds(rounds, tagged))
+    }
+
+    fn write_rounds(&self, rounds: &[(DateTime<Utc>, &[Metric])], tagged: bool) -> Result<Vec<CollectionRound>, ThrudError> {
+        let (hostname, thrud_version) = if tagged { (self.hostname.clone(), Some(THRUD_VERSION.to_string())) } else { (None, None) };
+
         let conn = self.get_connection()?;
-        let collection_id = Uuid::new_v4().to_string();
-        let collection_timestamp = Utc::now();
-        let metrics_count = metrics.len();
 
         // Start transaction
         let tx = conn.unchecked_transaction()?;
+        let mut stored = Vec::with_capacity(rounds.len());
+        let partitioned = partition::is_partitioned(&tx)?;
+        let mut partitions: HashMap<NaiveDate, String> = HashMap::new();
+
+        for (collection_timestamp, metrics) in rounds {
+            let collection_id = Uuid::new_v4().to_string();
+            let metrics_count = metrics.len();
+
+            // Insert collection round
+            tx.prepare_cached(
+                "INSERT INTO collection_rounds (id, timestamp, metrics_count, timestamp_ms, hostname, thrud_version)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?
+            .execute(params![
+                collection_id,
+                collection_timestamp.to_rfc3339(),
+                metrics_count,
+                collection_timestamp.timestamp_millis(),
+                hostname,
+                thrud_version
+            ])?;
+
+            // Insert all metrics, each into its day's partition when partitioned
+            for metric in metrics.iter() {
+                let timestamp_ms = metric.timestamp.timestamp_millis();
+                let table = if partitioned {
+                    let day = partition::day_of(timestamp_ms);
+                    match partitions.get(&day) {
+                        Some(table) => table.as_str(),
+                        None => partitions.entry(day).or_insert(partition::ensure_partition(&tx, day)?).as_str(),
+                    }
+                } else {
+                    "metrics"
+                };
+                let mut insert_metric = tx.prepare_cached(&format!(
+                    "INSERT INTO {} (collection_round_id, name, value_int, value_float, value_text, value_bool, value_json, metadata, timestamp, unit, kind) 
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                    table
+                ))?;
+                let (value_int, value_float, value_text, value_bool, value_json) = value_columns(&metric.value)?;
+                insert_metric.execute(
+                    params![
+                        collection_id,
+                        metric.name,
+                        value_int,
+                        value_float,
+                        value_text,
+                        value_bool,
+                        value_json,
+                        serde_json::to_string(&metric.metadata)?,
+                        timestamp_ms,
+                        metric.unit,
+                        metric.kind.as_str()
+                    ],
+                )?;
+            }
+
+            stored.push(CollectionRound {
+                id: collection_id,
+                timestamp: *collection_timestamp,
+                metrics_count,
+                hostname: hostname.clone(),
+                thrud_version: thrud_version.clone(),
+            });
+        }
+
+        // Commit transaction
+        tx.commit()?;
+
+        Ok(stored)
+    }
+
+    /// Delete metrics, charts and collection rounds older than `cutoff`.
+    ///
+    /// Rows timestamped exactly at the cutoff are kept. Collection rounds are only
+    /// removed once none of their metrics remain, and charts referencing a removed
+    /// round are deleted with it. A day-partitioned database drops the partitions
+    /// of whole days before the cutoff. Returns the number of metric rows removed.
+    pub fn prune_older_than(&self, cutoff: DateTime<Utc>) -> Result<usize, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let metrics_removed = if partition::is_partitioned(&tx)? {
+            partition::prune_older_than(&tx, cutoff.timestamp_millis())?
+        } else {
+            tx.execute(
+                "DELETE FROM metrics WHERE timestamp < ?1",
+                params![cutoff.timestamp_millis()],
+            )?
+        };
 
-        // Insert collection round
         tx.execute(
-            "INSERT INTO collection_rounds (id, timestamp, metrics_count) VALUES (?1, ?2, ?3)",
-            params![collection_id, collection_timestamp.to_rfc3339(), metrics_count],
+            "DELETE FROM charts WHERE julianday(timestamp) < julianday(?1)",
+            params![cutoff.to_rfc3339()],
         )?;
 
-        // Insert all metrics
-        for metric in &metrics {
-            tx.execute(
-                "INSERT INTO metrics (collection_round_id, name, value, timestamp) 
-                 VALUES (?1, ?2, ?3, ?4)",
-                params![
-                    collection_id,
-                    metric.name,
-                    metric.value,
-                    metric.timestamp.to_rfc3339()
-                ],
+        // Charts of the rounds removed below can be newer than the cutoff; they go
+        // first so the rounds they reference can be deleted
+        let orphaned_rounds = "SELECT id FROM collection_rounds
+             WHERE timestamp_ms < ?1
+               AND id NOT IN (SELECT DISTINCT collection_round_id FROM metrics)";
+        tx.execute(
+            &format!("DELETE FROM charts WHERE collection_round_id IN ({})", orphaned_rounds),
+            params![cutoff.timestamp_millis()],
+        )?;
+        tx.execute(
+            &format!("DELETE FROM collection_rounds WHERE id IN ({})", orphaned_rounds),
+            params![cutoff.timestamp_millis()],
+        )?;
+
+        tx.execute(
+            "DELETE FROM charts WHERE collection_round_id NOT IN (SELECT id FROM collection_rounds)",
+            [],
+        )?;
+
+        tx.commit()?;
+
+        Ok(metrics_removed)
+    }
+
+    /// Days with a metrics partition, oldest first; empty for an unpartitioned
+    /// database (see [`Self::with_day_partitions`])
+    pub fn metric_partitions(&self) -> Result<Vec<NaiveDate>, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        if !partition::is_partitioned(&conn)? {
+            return Ok(Vec::new());
+        }
+        Ok(partition::days(&conn)?)
+    }
+
+    /// Drop the metrics partition of a UTC `day`, together with that day's
+    /// collection rounds and their charts. Returns the number of metric rows
+    /// removed, 0 if the day has no partition.
+    pub fn drop_partition(&self, day: NaiveDate) -> Result<usize, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        if !partition::is_partitioned(&conn)? {
+            return Err("Database is not partitioned by day".into());
+        }
+        let tx = conn.unchecked_transaction()?;
+
+        let metrics_removed = partition::drop_partition(&tx, day)?;
+        let start = day.and_hms_opt(0, 0, 0).map(|t| t.and_utc().timestamp_millis()).unwrap_or_default();
+        let day_rounds = "SELECT id FROM collection_rounds
+             WHERE timestamp_ms >= ?1 AND timestamp_ms < ?2
+               AND id NOT IN (SELECT DISTINCT collection_round_id FROM metrics)";
+        tx.execute(
+            &format!("DELETE FROM charts WHERE collection_round_id IN ({})", day_rounds),
+            params![start, start + 86_400_000],
+        )?;
+        tx.execute(
+            &format!("DELETE FROM collection_rounds WHERE id IN ({})", day_rounds),
+            params![start, start + 86_400_000],
+        )?;
+
+        tx.commit()?;
+        Ok(metrics_removed)
+    }
+
+    /// Bytes of the database holding data: its pages minus the free pages left
+    /// behind by deletions, which new writes reuse and [`Self::compact`] releases
+    pub fn live_size_bytes(&self) -> Result<u64, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        let pragma = |name: &str| conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get::<_, i64>(0));
+        let pages = pragma("page_count")? - pragma("freelist_count")?;
+        Ok(pages.max(0) as u64 * pragma("page_size")?.max(0) as u64)
+    }
+
+    /// Delete the oldest collection rounds, with their metrics and charts, until
+    /// [`Self::live_size_bytes`] is at most `max_bytes` or no rounds are left.
+    /// The file itself only shrinks once compacted, but new rounds reuse the freed
+    /// space. Returns the number of rounds removed.
+    pub fn prune_to_size(&self, max_bytes: u64) -> Result<usize, Box<dyn Error>> {
+        let mut removed = 0;
+        loop {
+            let live = self.live_size_bytes()?;
+            if live <= max_bytes {
+                return Ok(removed);
+            }
+
+            let conn = self.get_connection()?;
+            let rounds: i64 = conn.query_row("SELECT COUNT(*) FROM collection_rounds", [], |row| row.get(0))?;
+            if rounds == 0 {
+                return Ok(removed);
+            }
+            // Assume rounds take similar space and remove the share over the cap
+            let excess = (live - max_bytes) as f64 / live as f64;
+            let batch = ((rounds as f64 * excess).ceil() as i64).clamp(1, rounds);
+
+            let tx = conn.unchecked_transaction()?;
+            let oldest = "SELECT id FROM collection_rounds ORDER BY timestamp_ms, seq LIMIT ?1";
+            for table in partition::metric_tables(&tx)? {
+                tx.execute(&format!("DELETE FROM {} WHERE collection_round_id IN ({})", table, oldest), [batch])?;
+            }
+            tx.execute(&format!("DELETE FROM charts WHERE collection_round_id IN ({})", oldest), [batch])?;
+            removed += tx.execute(&format!("DELETE FROM collection_rounds WHERE id IN ({})", oldest), [batch])?;
+            if partition::is_partitioned(&tx)? {
+                partition::drop_empty(&tx)?;
+            }
+            tx.commit()?;
+        }
+    }
+
+    /// Rebuild the database file with `VACUUM` to release space freed by pruning.
+    ///
+    /// `VACUUM` cannot run inside a transaction, so this must be called between
+    /// collection rounds rather than while a write is in progress. It rewrites the
+    /// whole file and can take a while on large databases. Returns the number of
+    /// bytes reclaimed (0 for in-memory databases).
+    pub fn compact(&self) -> Result<u64, Box<dyn Error>> {
+        let size_before = self.get_stats()?.database_size_bytes.unwrap_or(0);
+
+        {
+            let conn = self.get_connection()?;
+            if !conn.is_autocommit() {
+                return Err("Cannot compact while a transaction is active".into());
+            }
+            conn.execute_batch("VACUUM")?;
+        }
+
+        let size_after = self.get_stats()?.database_size_bytes.unwrap_or(0);
+        Ok(size_before.saturating_sub(size_after))
+    }
+
+    /// Stream metrics as CSV (`timestamp,name,value,metadata`) in timestamp order,
+    /// optionally starting at `since`. Rows are written as they are read rather than
+    /// buffered. Returns the number of data rows written.
+    pub fn export_csv<W: Write>(&self, mut writer: W, since: Option<DateTime<Utc>>) -> Result<usize, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, name, value_int, value_float, value_text, value_bool, value_json, metadata
+             FROM metrics
+             WHERE timestamp >= ?1
+             ORDER BY timestamp, id",
+        )?;
+        let since_ms = since.map(|t| t.timestamp_millis()).unwrap_or(i64::MIN);
+
+        writeln!(writer, "timestamp,name,value,metadata")?;
+
+        let mut rows = stmt.query(params![since_ms])?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            let timestamp = DateTime::from_timestamp_millis(row.get(0)?)
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default();
+            let name: String = row.get(1)?;
+            let value = value_from_columns(row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?)
+                .map(|v| v.to_string())
+                .unwrap_or_default();
+            let metadata: String = row.get(7)?;
+
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                csv_field(&timestamp),
+                csv_field(&name),
+                csv_field(&value),
+                csv_field(&metadata)
             )?;
+            count += 1;
+        }
+
+        writer.flush()?;
+        Ok(count)
+    }
+
+    /// Samples of metric `name` between `from` and `to` (both inclusive, unbounded
+    /// when `None`) as columns: timestamp, value and one column per metadata key.
+    /// Metadata keys named `timestamp` or `value` become `metadata.timestamp` and
+    /// `metadata.value` so they don't clash with the fixed columns.
+    pub fn export_columns(&self, name: &str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Result<ColumnBatch, Box<dyn Error>> {
+        let mut metrics = self.query_metrics(name, from)?;
+        metrics.retain(|metric| to.is_none_or(|to| metric.timestamp <= to));
+
+        let keys: BTreeSet<&String> = metrics.iter().flat_map(|metric| metric.metadata.keys()).collect();
+        let mut batch = ColumnBatch::default();
+        for key in keys {
+            let column = if key == "timestamp" || key == "value" { format!("metadata.{}", key) } else { key.clone() };
+            batch.metadata.insert(column, metrics.iter().map(|metric| metric.metadata.get(key).cloned()).collect());
+        }
+        for metric in &metrics {
+            batch.timestamp.push(metric.timestamp.timestamp_millis());
+            batch.value.push(match metric.value {
+                MetricValue::Integer(v) => Some(v as f64),
+                MetricValue::Float(v) => Some(v),
+                MetricValue::Boolean(v) => Some(if v { 1.0 } else { 0.0 }),
+                MetricValue::String(_) | MetricValue::Histogram { .. } => None,
+            });
+        }
+        Ok(batch)
+    }
+
+    /// Samples of metric `name` between `from` and `to` as an Arrow `RecordBatch`,
+    /// with the columns of [`Self::export_columns`] (see
+    /// [`ColumnBatch::to_record_batch`] for their types)
+    #[cfg(feature = "arrow")]
+    pub fn export_arrow(&self, name: &str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Result<arrow::record_batch::RecordBatch, Box<dyn Error>> {
+        Ok(self.export_columns(name, from, to)?.to_record_batch()?)
+    }
+
+    /// Metrics of up to `max_rounds` collection rounds stored after `cursor`, in
+    /// timestamp order, and the cursor to pass next time. Cursors are the rounds'
+    /// `seq`, which follows the order rounds were written in and is never reused,
+    /// so polling with the returned cursor yields every round exactly once; start
+    /// from 0 to read from the oldest round. The cursor comes back unchanged when
+    /// no round was stored since.
+    pub fn metrics_after_round(&self, cursor: i64, max_rounds: usize) -> Result<(Vec<Metric>, i64), Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        let next: Option<i64> = conn.query_row(
+            "SELECT MAX(seq) FROM (SELECT seq FROM collection_rounds WHERE seq > ?1 ORDER BY seq LIMIT ?2)",
+            params![cursor, max_rounds as i64],
+            |row| row.get(0),
+        )?;
+        let Some(next) = next else {
+            return Ok((Vec::new(), cursor));
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM metrics
+             WHERE collection_round_id IN (SELECT id FROM collection_rounds WHERE seq > ?1 AND seq <= ?2)
+             ORDER BY timestamp, id",
+            METRIC_COLUMNS
+        ))?;
+        let mut metrics = Vec::new();
+        for metric in stmt.query_map(params![cursor, next], metric_from_row)? {
+            metrics.extend(metric?);
+        }
+        Ok((metrics, next))
+    }
+
+    /// Store metrics read back from an export, such as the collector's JSON lines
+    /// output, keeping their original timestamps.
+    ///
+    /// Exports don't record collection rounds, so consecutive metrics are grouped
+    /// into synthetic ones: a new round starts when a metric is more than
+    /// [`IMPORT_ROUND_GAP_MS`] apart from the one before it or repeats a series
+    /// (name and metadata) already in the current round. Each round takes its first
+    /// metric's timestamp. Charts are not generated for imported rounds.
+    pub fn import_metrics<I: IntoIterator<Item = Metric>>(&self, metrics: I) -> Result<ImportSummary, ThrudError> {
+        let mut summary = ImportSummary::default();
+        let mut batch: Vec<Vec<Metric>> = Vec::new();
+        let mut series: HashSet<(String, BTreeMap<String, String>)> = HashSet::new();
+
+        for metric in metrics {
+            let key = (metric.name.clone(), metric.metadata.clone().into_iter().collect());
+            let starts_round = match batch.last().and_then(|round| round.last()) {
+                Some(previous) => {
+                    (metric.timestamp - previous.timestamp).num_milliseconds().abs() > IMPORT_ROUND_GAP_MS
+                        || series.contains(&key)
+                }
+                None => true,
+            };
+
+            if starts_round {
+                if batch.len() >= IMPORT_BATCH_ROUNDS {
+                    self.import_rounds(&mut batch, &mut summary)?;
+                }
+                batch.push(Vec::new());
+                series.clear();
+            }
+            series.insert(key);
+            if let Some(round) = batch.last_mut() {
+                round.push(metric);
+            }
+        }
+
+        self.import_rounds(&mut batch, &mut summary)?;
+        Ok(summary)
+    }
+
+    fn import_rounds(&self, batch: &mut Vec<Vec<Metric>>, summary: &mut ImportSummary) -> Result<(), ThrudError> {
+        let rounds: Vec<(DateTime<Utc>, &[Metric])> = batch
+            .iter()
+            .filter(|round| !round.is_empty())
+            .map(|round| (round[0].timestamp, round.as_slice()))
+            .collect();
+        if rounds.is_empty() {
+            return Ok(());
+        }
+
+        // Imported rounds may come from any machine or version, so they stay untagged
+        for round in self.insert_rounds(&rounds, false)? {
+            summary.rounds += 1;
+            summary.metrics += round.metrics_count;
+        }
+        batch.clear();
+        Ok(())
+    }
+
+    /// Metrics whose metadata has `key` set to `value`, e.g. `core_type` =
+    /// `efficiency`, in timestamp order. Lookups by `core_type` use an index.
+    pub fn query_metrics_by_metadata(&self, key: &str, value: &str) -> Result<Vec<Metric>, Box<dyn Error>> {
+        // The key is spliced into the JSON path so the expression matches the
+        // index definition, which a bound parameter would not
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("Invalid metadata key '{}': use letters, digits and underscores", key).into());
+        }
+
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM metrics
+             WHERE json_extract(metadata, '$.{}') = ?1
+             ORDER BY timestamp, id",
+            METRIC_COLUMNS, key
+        ))?;
+
+        let mut metrics = Vec::new();
+        for metric in stmt.query_map(params![value], metric_from_row)? {
+            metrics.extend(metric?);
         }
+        Ok(metrics)
+    }
 
-        // Commit transaction
-        tx.commit()?;
+    /// Stored samples of the metric `name` in timestamp order, optionally starting
+    /// at `since`, with each value read back as the variant it was stored as.
+    /// Aliases such as `cpu_performance_idle_ticks` resolve to the canonical name
+    pub fn query_metrics(&self, name: &str, since: Option<DateTime<Utc>>) -> Result<Vec<Metric>, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM metrics
+             WHERE name = ?1 AND timestamp >= ?2
+             ORDER BY timestamp, id",
+            METRIC_COLUMNS
+        ))?;
+        let since_ms = since.map(|t| t.timestamp_millis()).unwrap_or(i64::MIN);
 
-        Ok(CollectionRound {
-            id: collection_id,
-            timestamp: collection_timestamp,
-            metrics_count,
-        })
+        let mut metrics = Vec::new();
+        for metric in stmt.query_map(params![names::canonical(name), since_ms], metric_from_row)? {
+            metrics.extend(metric?);
+        }
+        Ok(metrics)
     }
 
-    fn get_stats(&self) -> Result<StorageStats, Box<dyn Error>> {
-        let conn = self.get_connection()?;
+    /// Per-second rate of the counter metric `name` over the last
+    /// `window_seconds`, one `(timestamp, rate)` point per collection round after
+    /// the first in the window.
+    ///
+    /// Each round's samples are summed (so a counter reported per core is rated
+    /// as a whole) and paired with the previous round's using `LAG`, as the CPU
+    /// and GPU aggregations do with tick counters. An interval where the counter
+    /// went backwards was a reset (e.g. a reboot) and is dropped. Aliases resolve
+    /// to the canonical name.
+    pub fn query_rate(&self, name: &str, window_seconds: i64) -> Result<Vec<RatePoint>, Box<dyn Error>> {
+        if window_seconds <= 0 {
+            return Err(format!("Rate window must be positive, got {} seconds", window_seconds).into());
+        }
 
-        // Get total metrics count
-        let total_metrics: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM metrics",
-            [],
-            |row| row.get(0),
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "WITH samples AS (
+                 SELECT MAX(timestamp) AS ts, SUM(COALESCE(value_int, value_float)) AS value
+                 FROM metrics
+                 WHERE name = ?1 AND timestamp >= ?2
+                 GROUP BY collection_round_id
+             ),
+             deltas AS (
+                 SELECT
+                     ts,
+                     value - LAG(value) OVER w AS delta_value,
+                     ts - LAG(ts) OVER w AS delta_ms
+                 FROM samples
+                 WINDOW w AS (ORDER BY ts)
+             )
+             SELECT ts, delta_value * 1000.0 / delta_ms
+             FROM deltas
+             WHERE delta_value >= 0 AND delta_ms > 0
+             ORDER BY ts",
         )?;
+        let cutoff_ms = Utc::now().timestamp_millis() - window_seconds.saturating_mul(1000);
 
-        // Get total collection rounds count
-        let total_collection_rounds: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM collection_rounds",
-            [],
-            |row| row.get(0),
-        )?;
+        let mut rates = Vec::new();
+        for row in stmt.query_map(params![names::canonical(name), cutoff_ms], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?)))? {
+            let (timestamp_ms, rate) = row?;
+            if let Some(timestamp) = DateTime::from_timestamp_millis(timestamp_ms) {
+                rates.push((timestamp, rate));
+            }
+        }
+        Ok(rates)
+    }
 
-        // Get latest collection round
-        let latest_collection = conn.query_row(
-            "SELECT id, timestamp, metrics_count FROM collection_rounds 
-             ORDER BY timestamp DESC LIMIT 1",
-            [],
-            |row| {
-                let id: String = row.get(0)?;
-                let timestamp_str: String = row.get(1)?;
-                let metrics_count: usize = row.get::<_, i64>(2)? as usize;
-                
-                let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                    .map_err(|_e| rusqlite::Error::InvalidColumnType(0, "timestamp".to_string(), rusqlite::types::Type::Text))?
-                    .with_timezone(&Utc);
+    /// The newest stored sample of each metric in `names`, keyed by the name as
+    /// requested. Aliases resolve to the canonical name; names never stored are
+    /// left out. For a name sampled with several metadata sets in one round (e.g.
+    /// `proc.cpu_percent`), the sample stored last is returned.
+    pub fn get_latest_values(&self, names: &[&str]) -> Result<HashMap<String, Metric>, Box<dyn Error>> {
+        if names.is_empty() {
+            return Ok(HashMap::new());
+        }
 
-                Ok(CollectionRound {
-                    id,
-                    timestamp,
-                    metrics_count,
-                })
-            },
-        ).optional()?;
+        let canonical: Vec<&str> = names.iter().map(|name| names::canonical(name)).collect();
+        let placeholders = vec!["?"; canonical.len()].join(", ");
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM (
+                 SELECT *, ROW_NUMBER() OVER (PARTITION BY name ORDER BY timestamp DESC, id DESC) AS recency
+                 FROM metrics
+                 WHERE name IN ({})
+             )
+             WHERE recency = 1",
+            METRIC_COLUMNS, placeholders
+        ))?;
 
-        // Get database file size
-        let database_size_bytes = std::fs::metadata(&self.db_path)
-            .map(|metadata| metadata.len())
-            .ok();
+        let mut latest: HashMap<String, Metric> = HashMap::new();
+        for metric in stmt.query_map(params_from_iter(&canonical), metric_from_row)? {
+            if let Some(metric) = metric? {
+                latest.insert(metric.name.clone(), metric);
+            }
+        }
 
-        Ok(StorageStats {
-            total_metrics,
-            total_collection_rounds,
-            latest_collection,
-            database_size_bytes,
-        })
+        Ok(names
+            .iter()
+            .zip(&canonical)
+            .filter_map(|(name, canonical)| latest.get(*canonical).map(|metric| (name.to_string(), metric.clone())))
+            .collect())
     }
 
-}
-
-impl SqliteStorage {
     /// Store pre-computed chart data
     pub fn store_chart(&self, chart: &super::Chart) -> Result<(), Box<dyn Error>> {
         let conn = self.get_connection()?;
         
+        let chart_data = if self.compress_charts {
+            rusqlite::types::Value::Blob(super::gzip::compress(chart.chart_data.as_bytes()))
+        } else {
+            rusqlite::types::Value::Text(chart.chart_data.clone())
+        };
         conn.execute(
-            "INSERT INTO charts (collection_round_id, metric_name, chart_type, chart_data, data_points, timestamp)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO charts (collection_round_id, metric_name, chart_type, chart_data, data_points, timestamp, compressed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
                 chart.collection_round_id,
                 chart.metric_name,
                 chart.chart_type.as_str(),
-                chart.chart_data,
+                chart_data,
                 chart.data_points as i64,
-                chart.timestamp.to_rfc3339()
+                chart.timestamp.to_rfc3339(),
+                self.compress_charts,
             ],
         )?;
         
@@ -241,7 +1647,7 @@ impl SqliteStorage {
         
         let metric_placeholders = metric_names.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         let query = format!(
-            "SELECT collection_round_id, metric_name, chart_type, chart_data, data_points, timestamp 
+            "SELECT collection_round_id, metric_name, chart_type, chart_data, data_points, timestamp, compressed 
              FROM charts 
              WHERE metric_name IN ({}) AND chart_type = ?
              ORDER BY timestamp DESC 
@@ -258,34 +1664,7 @@ impl SqliteStorage {
         let limit_str = limit.to_string();
         params.push(&limit_str);
         
-        let chart_iter = stmt.query_map(
-            rusqlite::params_from_iter(params),
-            |row| {
-                let collection_round_id: String = row.get(0)?;
-                let metric_name: String = row.get(1)?;
-                let chart_type_str: String = row.get(2)?;
-                let chart_data: String = row.get(3)?;
-                let data_points: i64 = row.get(4)?;
-                let timestamp_str: String = row.get(5)?;
-                
-                let chart_type = super::ChartType::from_str(&chart_type_str)
-                    .ok_or_else(|| rusqlite::Error::InvalidColumnType(2, "chart_type".to_string(), rusqlite::types::Type::Text))?;
-                
-                let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                    .map_err(|_| rusqlite::Error::InvalidColumnType(5, "timestamp".to_string(), rusqlite::types::Type::Text))?
-                    .with_timezone(&Utc);
-                
-                Ok(super::Chart {
-                    id: None,
-                    collection_round_id,
-                    metric_name,
-                    chart_type,
-                    chart_data,
-                    data_points: data_points as usize,
-                    timestamp,
-                })
-            },
-        )?;
+        let chart_iter = stmt.query_map(rusqlite::params_from_iter(params), chart_from_row)?;
         
         let mut charts = Vec::new();
         for chart in chart_iter {
@@ -295,26 +1674,189 @@ impl SqliteStorage {
         Ok(charts)
     }
 
-    /// Generate utilization charts for the most recent data
-    pub fn generate_and_store_charts(&self, collection_round_id: &str, data_points: usize) -> Result<(), Box<dyn Error>> {
+    /// Names of the per-GPU utilization charts stored so far, ordered by GPU index
+    pub fn gpu_chart_metrics(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        self.indexed_chart_metrics("gpu_utilization")
+    }
+
+    /// Names of the per-core utilization charts stored so far, ordered by core id
+    pub fn core_chart_metrics(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        self.indexed_chart_metrics(super::PER_CORE_CHARTS)
+    }
+
+    /// Stored chart names of the form `<base>.<index>`, ordered by index
+    fn indexed_chart_metrics(&self, base: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare("SELECT DISTINCT metric_name FROM charts WHERE metric_name GLOB ?1 || '.*'")?;
+        let mut names = Vec::new();
+        for name in stmt.query_map([base], |row| row.get::<_, String>(0))? {
+            names.push(name?);
+        }
+        names.retain(|name| chart_index(name, base).is_some());
+        names.sort_by_key(|name| chart_index(name, base));
+        Ok(names)
+    }
+
+    /// Charts of one type for `metric_names` generated within `[since, until]`,
+    /// oldest first. Either bound may be omitted to leave that side open.
+    pub fn get_charts_in_range(
+        &self,
+        metric_names: &[&str],
+        chart_type: &super::ChartType,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<super::Chart>, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+
+        // Chart timestamps are RFC3339 text with varying fractional digits, so
+        // compare them as Julian days rather than as strings
+        let metric_placeholders = metric_names.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let mut query = format!(
+            "SELECT collection_round_id, metric_name, chart_type, chart_data, data_points, timestamp, compressed 
+             FROM charts 
+             WHERE metric_name IN ({}) AND chart_type = ?",
+            metric_placeholders
+        );
+        let mut params: Vec<String> = metric_names.iter().map(|name| name.to_string()).collect();
+        params.push(chart_type.as_str().to_string());
+        if let Some(since) = since {
+            query.push_str(" AND julianday(timestamp) >= julianday(?)");
+            params.push(since.to_rfc3339());
+        }
+        if let Some(until) = until {
+            query.push_str(" AND julianday(timestamp) <= julianday(?)");
+            params.push(until.to_rfc3339());
+        }
+        query.push_str(" ORDER BY julianday(timestamp), id");
+
+        let mut stmt = conn.prepare(&query)?;
+        let chart_iter = stmt.query_map(rusqlite::params_from_iter(params), chart_from_row)?;
+
+        let mut charts = Vec::new();
+        for chart in chart_iter {
+            charts.push(chart?);
+        }
+
+        Ok(charts)
+    }
+
+    /// Generate charts for the most recent data of each metric in `metric_names`
+    ///
+    /// The names in [`super::DEFAULT_CHART_METRICS`] are derived utilizations; any
+    /// other name is read directly from the metrics table (see
+    /// `extract_metric_values` for how its values are charted). `bar_rows` and
+    /// `braille_rows` set how many stacked rows each bar and braille chart spans; 1
+    /// gives the original single-row charts.
+    pub fn generate_and_store_charts(&self, collection_round_id: &str, metric_names: &[&str], data_points: usize, bar_rows: usize, braille_rows: usize) -> Result<(), Box<dyn Error>> {
+        self.store_round_charts(collection_round_id, Utc::now(), metric_names, data_points, bar_rows, braille_rows)
+    }
+
+    /// Delete every stored chart, returning how many were removed
+    pub fn clear_charts(&self) -> Result<usize, Box<dyn Error>> {
+        let conn = self.get_connection()?;
+        Ok(conn.execute("DELETE FROM charts", [])?)
+    }
+
+    /// Replace all stored charts with ones generated by replaying every collection
+    /// round except heartbeats, oldest first, through the chart generator, as if each had just been
+    /// collected. Charts are timestamped with their round's time. Useful after
+    /// changing how charts are rendered. Either every chart is replaced or, on
+    /// error, none are. Returns the number of rounds replayed.
+    pub fn regenerate_charts(&self, metric_names: &[&str], data_points: usize, bar_rows: usize, braille_rows: usize) -> Result<usize, Box<dyn Error>> {
+        let rounds: Vec<(String, i64)> = {
+            let conn = self.get_connection()?;
+            let mut stmt = conn.prepare(
+                "SELECT id, timestamp_ms FROM collection_rounds WHERE metrics_count > 0 ORDER BY timestamp_ms, seq",
+            )?;
+            let rounds = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<SqliteResult<_>>()?;
+            rounds
+        };
+
+        // Each call below takes the connection in turn, so they all run inside
+        // this transaction
+        self.get_connection()?.execute_batch("BEGIN IMMEDIATE")?;
+        let replay = || -> Result<(), Box<dyn Error>> {
+            self.clear_charts()?;
+            for (round_id, timestamp_ms) in &rounds {
+                let timestamp = DateTime::from_timestamp_millis(*timestamp_ms)
+                    .ok_or_else(|| format!("Invalid timestamp of collection round {}: {}", round_id, timestamp_ms))?;
+                self.store_round_charts(round_id, timestamp, metric_names, data_points, bar_rows, braille_rows)?;
+            }
+            Ok(())
+        };
+        match replay() {
+            Ok(()) => self.get_connection()?.execute_batch("COMMIT")?,
+            Err(e) => {
+                let _ = self.get_connection()?.execute_batch("ROLLBACK");
+                return Err(e);
+            }
+        }
+        Ok(rounds.len())
+    }
+
+    /// Generate the charts of `collection_round_id` from it and the rounds before
+    /// it, and store them with `timestamp`
+    fn store_round_charts(
+        &self,
+        collection_round_id: &str,
+        timestamp: DateTime<Utc>,
+        metric_names: &[&str],
+        data_points: usize,
+        bar_rows: usize,
+        braille_rows: usize,
+    ) -> Result<(), Box<dyn Error>> {
         // Get recent utilization data for chart generation
-        let utilization_data = self.get_recent_utilization_data(data_points + 1)?;
+        let per_core = metric_names.contains(&super::PER_CORE_CHARTS);
+        let custom_metrics: Vec<&str> = metric_names
+            .iter()
+            .copied()
+            .filter(|name| {
+                !super::DEFAULT_CHART_METRICS.contains(name)
+                    && *name != super::PER_CORE_CHARTS
+                    && gpu_chart_index(name).is_none()
+                    && core_chart_index(name).is_none()
+            })
+            .collect();
+        // Braille characters hold two points each, so an odd count is rounded up for them
+        let braille_values = data_points.next_multiple_of(2);
+        let utilization_data = self.get_recent_utilization_data(braille_values + 1, collection_round_id, &custom_metrics, per_core)?;
         
         if utilization_data.len() < 2 {
             return Ok(());  // Need at least 2 data points for delta calculation
         }
+
+        // With more than one GPU, `gpu_utilization` charts their average and each
+        // GPU also gets its own `gpu_utilization.<index>` chart
+        let gpu_indices: std::collections::BTreeSet<i64> = utilization_data
+            .iter()
+            .flat_map(|data| data.gpu_util.keys().copied())
+            .collect();
+        let mut metric_names: Vec<String> = metric_names.iter().map(|name| name.to_string()).collect();
+        if gpu_indices.len() > 1 && metric_names.iter().any(|name| name == "gpu_utilization") {
+            metric_names.extend(gpu_indices.iter().map(|index| gpu_chart_metric(*index)));
+        }
+
+        // `PER_CORE_CHARTS` stands for one `core_utilization.<core_id>` chart per core
+        if per_core {
+            let core_ids: std::collections::BTreeSet<i64> = utilization_data
+                .iter()
+                .flat_map(|data| data.core_ticks.keys().copied())
+                .collect();
+            metric_names.retain(|name| name != super::PER_CORE_CHARTS);
+            metric_names.extend(core_ids.iter().map(|core_id| core_chart_metric(*core_id)));
+        }
         
         // Generate charts for each metric type
-        let metrics = ["performance_cores_utilization", "efficiency_cores_utilization", "gpu_utilization"];
-        let timestamp = Utc::now();
-        
-        for metric_name in &metrics {
+        for metric_name in &metric_names {
             // Extract values for this metric
             let values = self.extract_metric_values(&utilization_data, metric_name)?;
+            let filled: Vec<f64> = values.iter().map(|value| value.unwrap_or(0.0)).collect();
             
             if values.len() >= data_points {
                 // Generate bar chart
-                let bar_chart = self.generate_bar_chart(&values[..data_points], metric_name)?;
+                let bar_chart = self.generate_bar_chart(&values[..data_points], metric_name, bar_rows, self.chart_suffix)?;
                 let bar_chart_obj = super::Chart {
                     id: None,
                     collection_round_id: collection_round_id.to_string(),
@@ -327,9 +1869,9 @@ impl SqliteStorage {
                 self.store_chart(&bar_chart_obj)?;
                 
                 // Generate braille chart (half the data points since each char represents 2 points)
-                let braille_points = (data_points + 1) / 2;
-                if values.len() >= braille_points * 2 {
-                    let braille_chart = self.generate_braille_chart(&values[..braille_points * 2], metric_name)?;
+                let braille_points = braille_values / 2;
+                if values.len() >= braille_values {
+                    let braille_chart = self.generate_braille_chart(&filled[..braille_values], metric_name, braille_rows, self.chart_suffix)?;
                     let braille_chart_obj = super::Chart {
                         id: None,
                         collection_round_id: collection_round_id.to_string(),
@@ -341,74 +1883,150 @@ impl SqliteStorage {
                     };
                     self.store_chart(&braille_chart_obj)?;
                 }
+
+                // Generate multi-row line chart
+                let line_chart = self.generate_line_chart(&filled[..data_points], metric_name)?;
+                let line_chart_obj = super::Chart {
+                    id: None,
+                    collection_round_id: collection_round_id.to_string(),
+                    metric_name: metric_name.to_string(),
+                    chart_type: super::ChartType::Line,
+                    chart_data: line_chart,
+                    data_points,
+                    timestamp,
+                };
+                self.store_chart(&line_chart_obj)?;
             }
         }
         
         Ok(())
     }
 
-    /// Get recent utilization data (similar to shell script logic)
-    fn get_recent_utilization_data(&self, rounds: usize) -> Result<Vec<UtilizationData>, Box<dyn Error>> {
+    /// Get utilization data of the `rounds` rounds up to and including
+    /// `until_round` (similar to shell script logic), plus the per-round values of
+    /// any `custom_metrics` and, if `per_core` is set, each core's tick counters
+    fn get_recent_utilization_data(&self, rounds: usize, until_round: &str, custom_metrics: &[&str], per_core: bool) -> Result<Vec<UtilizationData>, Box<dyn Error>> {
         let conn = self.get_connection()?;
         
-        let query = "
+        // ?1 is the round count and ?2 the last round, then every exact name, then
+        // the GLOB patterns
+        let mut exact_names = vec![
+            names::CPU_PERFORMANCE_TOTAL_TICKS,
+            names::CPU_PERFORMANCE_IDLE_TICKS,
+            names::CPU_EFFICIENCY_TOTAL_TICKS,
+            names::CPU_EFFICIENCY_IDLE_TICKS,
+        ];
+        exact_names.extend(custom_metrics.iter().map(|name| names::canonical(name)));
+        let mut patterns = vec![names::GPU_UTILIZATION_GLOB];
+        if per_core {
+            patterns.push(names::CPU_CORE_TICKS_GLOB);
+        }
+        let name_placeholders = (0..exact_names.len())
+            .map(|i| format!("?{}", i + 3))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let pattern_conditions: String = (0..patterns.len())
+            .map(|i| format!("\n                OR m.name GLOB ?{}", i + 3 + exact_names.len()))
+            .collect();
+        let query = format!("
             SELECT 
                 cr.id as round_id,
-                cr.timestamp,
+                cr.timestamp_ms,
                 m.name,
-                m.value
+                COALESCE(m.value_int, CAST(m.value_float AS INTEGER)),
+                m.value_float
             FROM collection_rounds cr
             JOIN metrics m ON cr.id = m.collection_round_id
-            WHERE m.name IN (
-                'cpu.performance.total_ticks', 'cpu.performance.idle_ticks',
-                'cpu.efficiency.total_ticks', 'cpu.efficiency.idle_ticks',
-                'gpu.utilization'
+            WHERE cr.id IN (
+                SELECT id FROM collection_rounds
+                WHERE timestamp_ms <= (SELECT timestamp_ms FROM collection_rounds WHERE id = ?2)
+                  AND metrics_count > 0
+                ORDER BY timestamp_ms DESC LIMIT ?1
             )
-            ORDER BY cr.timestamp DESC
-            LIMIT ?";
+            AND (
+                m.name IN ({}){}
+            )
+            ORDER BY cr.timestamp_ms DESC",
+            name_placeholders,
+            pattern_conditions,
+        );
             
-        let mut stmt = conn.prepare(query)?;
-        let rows = stmt.query_map([rounds * 5], |row| {
+        let mut stmt = conn.prepare(&query)?;
+        let query_params = [rounds.to_string(), until_round.to_string()]
+            .into_iter()
+            .chain(exact_names.iter().chain(&patterns).map(|name| name.to_string()));
+        let rows = stmt.query_map(params_from_iter(query_params), |row| {
             Ok((
                 row.get::<_, String>(0)?,  // round_id
-                row.get::<_, String>(1)?,  // timestamp
+                row.get::<_, i64>(1)?,  // timestamp_ms
                 row.get::<_, String>(2)?,  // name
-                row.get::<_, String>(3)?   // value
+                row.get::<_, Option<i64>>(3)?.unwrap_or(0),  // integer value
+                row.get::<_, Option<f64>>(4)?  // float value
             ))
         })?;
         
         let mut data: std::collections::HashMap<String, UtilizationData> = std::collections::HashMap::new();
         
         for row in rows {
-            let (round_id, timestamp, name, value) = row?;
+            let (round_id, timestamp_ms, name, val, float_val) = row?;
             let entry = data.entry(round_id.clone()).or_insert(UtilizationData {
-                timestamp,
+                timestamp_ms,
                 perf_total: 0,
                 perf_idle: 0,
                 eff_total: 0,
                 eff_idle: 0,
-                gpu_util: 0.0,
+                gpu_util: BTreeMap::new(),
+                core_ticks: BTreeMap::new(),
+                custom: HashMap::new(),
             });
             
-            let val: i64 = value.parse().unwrap_or(0);
             match name.as_str() {
-                "cpu.performance.total_ticks" => entry.perf_total = val,
-                "cpu.performance.idle_ticks" => entry.perf_idle = val,
-                "cpu.efficiency.total_ticks" => entry.eff_total = val,
-                "cpu.efficiency.idle_ticks" => entry.eff_idle = val,
-                "gpu.utilization" => entry.gpu_util = val as f64,
-                _ => {}
+                names::CPU_PERFORMANCE_TOTAL_TICKS => entry.perf_total = val,
+                names::CPU_PERFORMANCE_IDLE_TICKS => entry.perf_idle = val,
+                names::CPU_EFFICIENCY_TOTAL_TICKS => entry.eff_total = val,
+                names::CPU_EFFICIENCY_IDLE_TICKS => entry.eff_idle = val,
+                _ => {
+                    // Per-GPU utilization ratios are charted as percentages
+                    if let Some(gpu_index) = names::parse_gpu_utilization(&name) {
+                        entry.gpu_util.insert(gpu_index, float_val.unwrap_or(val as f64) * 100.0);
+                    }
+
+                    if let Some((core_id, counter)) = names::parse_cpu_core_ticks(&name) {
+                        let ticks = entry.core_ticks.entry(core_id).or_insert((0, 0));
+                        match counter {
+                            TOTAL_TICKS => ticks.0 = val,
+                            IDLE_TICKS => ticks.1 = val,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+
+            // Custom metrics may be requested by an alias of the stored name
+            if let Some(requested) = custom_metrics.iter().find(|metric| names::canonical(metric) == name) {
+                let sample = entry.custom.entry(requested.to_string()).or_insert(CustomSample { sum: 0.0, count: 0, counter: true });
+                sample.sum += float_val.unwrap_or(val as f64);
+                sample.count += 1;
+                sample.counter &= float_val.is_none();
             }
         }
         
         let mut result: Vec<UtilizationData> = data.into_values().collect();
-        result.sort_by(|a, b| b.timestamp.cmp(&a.timestamp)); // Most recent first
+        result.sort_by_key(|d| std::cmp::Reverse(d.timestamp_ms)); // Most recent first
         
         Ok(result)
     }
 
     /// Extract metric values with delta calculations
-    fn extract_metric_values(&self, data: &[UtilizationData], metric: &str) -> Result<Vec<f64>, Box<dyn Error>> {
+    ///
+    /// Metrics outside the built-in utilizations are charted generically: integer
+    /// metrics are treated as cumulative counters (summed across rows in a round)
+    /// and charted as the per-round delta, while float metrics are averaged per
+    /// round and charted as-is. Rounds missing the metric chart as 0.
+    ///
+    /// CPU utilizations are `None` for intervals without any tick activity, where
+    /// the cores were parked rather than idle.
+    fn extract_metric_values(&self, data: &[UtilizationData], metric: &str) -> Result<Vec<Option<f64>>, Box<dyn Error>> {
         if data.len() < 2 {
             return Ok(Vec::new());
         }
@@ -420,22 +2038,28 @@ impl SqliteStorage {
             let prev = &data[i];    // Older
             
             let utilization = match metric {
-                "performance_cores_utilization" => {
-                    let delta_total = curr.perf_total - prev.perf_total;
-                    let delta_idle = curr.perf_idle - prev.perf_idle;
-                    if delta_total > 0 {
-                        ((delta_total - delta_idle) as f64 / delta_total as f64) * 100.0
-                    } else { 0.0 }
-                },
-                "efficiency_cores_utilization" => {
-                    let delta_total = curr.eff_total - prev.eff_total;
-                    let delta_idle = curr.eff_idle - prev.eff_idle;
-                    if delta_total > 0 {
-                        ((delta_total - delta_idle) as f64 / delta_total as f64) * 100.0
-                    } else { 0.0 }
+                "performance_cores_utilization" => tick_utilization(
+                    curr.perf_total - prev.perf_total,
+                    curr.perf_idle - prev.perf_idle,
+                ),
+                "efficiency_cores_utilization" => tick_utilization(
+                    curr.eff_total - prev.eff_total,
+                    curr.eff_idle - prev.eff_idle,
+                ),
+                "gpu_utilization" if curr.gpu_util.is_empty() => Some(0.0),
+                "gpu_utilization" => Some(curr.gpu_util.values().sum::<f64>() / curr.gpu_util.len() as f64),
+                _ => match (gpu_chart_index(metric), core_chart_index(metric)) {
+                    (Some(index), _) => Some(curr.gpu_util.get(&index).copied().unwrap_or(0.0)),
+                    (_, Some(core_id)) => match (curr.core_ticks.get(&core_id), prev.core_ticks.get(&core_id)) {
+                        (Some(c), Some(p)) => tick_utilization(c.0 - p.0, c.1 - p.1),
+                        _ => Some(0.0),
+                    },
+                    _ => match (curr.custom.get(metric), prev.custom.get(metric)) {
+                        (Some(c), Some(p)) if c.counter && p.counter => Some((c.sum - p.sum).max(0.0)),
+                        (Some(c), _) if !c.counter => Some(c.sum / c.count as f64),
+                        _ => Some(0.0),
+                    },
                 },
-                "gpu_utilization" => curr.gpu_util,
-                _ => 0.0,
             };
             
             values.push(utilization);
@@ -444,80 +2068,548 @@ impl SqliteStorage {
         Ok(values)
     }
 
-    /// Generate bar chart string (like the shell script)
-    fn generate_bar_chart(&self, values: &[f64], _metric: &str) -> Result<String, Box<dyn Error>> {
-        let bar_chars = [" ", "▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+    /// Generate bar chart string (like the shell script), followed by the
+    /// `..NN%|` average if `suffix` is set. `None` values are parked intervals,
+    /// drawn with the parked gap character if one is set and as 0 otherwise.
+    fn generate_bar_chart(&self, values: &[Option<f64>], _metric: &str, rows: usize, suffix: bool) -> Result<String, Box<dyn Error>> {
+        let rows = rows.max(1);
+        let levels = rows * 8;
         
-        let mut chart = String::new();
-        for &value in values {
-            let clamped = value.max(0.0).min(100.0);
-            let index = if clamped == 0.0 { 0 } else { 
-                ((clamped / 100.0 * 8.0).floor() as usize + 1).min(8) 
-            };
-            chart.push_str(bar_chars[index]);
+        let values: Vec<Option<f64>> = match self.parked_gap {
+            Some(_) => values.to_vec(),
+            None => values.iter().map(|value| Some(value.unwrap_or(0.0))).collect(),
+        };
+        let heights: Vec<Option<usize>> = values.iter().map(|value| value.map(|v| bar_height(v, levels))).collect();
+        
+        // Build rows top to bottom; each row shows the part of a bar within its 8
+        // levels, and a parked interval is a gap character on the bottom row
+        let mut lines = Vec::with_capacity(rows);
+        for row in (0..rows).rev() {
+            let mut line = String::new();
+            for height in &heights {
+                match (height, self.parked_gap) {
+                    (Some(height), _) => line.push(self.bar_chars[height.saturating_sub(row * 8).min(8)]),
+                    (None, Some(gap)) if row == 0 => line.push(gap),
+                    (None, _) => line.push(' '),
+                }
+            }
+            lines.push(line);
         }
         
-        // Add percentage
-        let avg_util = values.iter().sum::<f64>() / values.len() as f64;
+        if !suffix {
+            return Ok(lines.join("\n"));
+        }
+
+        // Add percentage of the non-parked intervals to the bottom row, counting
+        // NaN values as 0
+        let active: Vec<f64> = values.iter().flatten().map(|v| if v.is_nan() { 0.0 } else { *v }).collect();
+        let avg_util = active.iter().sum::<f64>() / active.len().max(1) as f64;
         let percentage = format!("..{:>2.0}%", avg_util);
         
-        Ok(format!("{}{}|", chart, percentage))
+        Ok(format!("{}{}|", lines.join("\n"), percentage))
     }
 
-    /// Generate braille chart string
-    fn generate_braille_chart(&self, values: &[f64], _metric: &str) -> Result<String, Box<dyn Error>> {
-        let mut chart = String::new();
-        
-        // Process values in pairs
-        for chunk in values.chunks(2) {
-            let left = chunk[0];
-            let right = chunk.get(1).copied().unwrap_or(0.0);
-            
-            if left == 0.0 && right == 0.0 {
-                chart.push(' ');
-            } else {
-                let left_level = self.percentage_to_braille_level(left);
-                let right_level = self.percentage_to_braille_level(right);
-                chart.push(self.get_braille_char(left_level, right_level));
+    /// Generate braille chart string, two values per character.
+    ///
+    /// A braille cell has 4 dot rows per column, so a single-row chart can only
+    /// show 5 heights (0-4 dots). Stacking `rows` rows gives `rows * 4` levels;
+    /// rows are joined with newlines and the percentage follows the bottom row
+    /// if `suffix` is set.
+    fn generate_braille_chart(&self, values: &[f64], _metric: &str, rows: usize, suffix: bool) -> Result<String, Box<dyn Error>> {
+        let rows = rows.max(1);
+        let levels: Vec<(usize, usize)> = values
+            .chunks(2)
+            .map(|chunk| {
+                let left = chunk[0];
+                let right = chunk.get(1).copied().unwrap_or(0.0);
+                (self.percentage_to_braille_level(left, rows), self.percentage_to_braille_level(right, rows))
+            })
+            .collect();
+
+        // Build rows top to bottom; each row shows the dots of a column within its 4 levels
+        let mut lines = Vec::with_capacity(rows);
+        for row in (0..rows).rev() {
+            let mut line = String::new();
+            for &(left, right) in &levels {
+                let left_dots = left.saturating_sub(row * 4).min(4);
+                let mut right_dots = right.saturating_sub(row * 4).min(4);
+                // The bottom row always draws the right baseline dot under a
+                // non-empty left column, as the original single-row chart did
+                if row == 0 && left_dots > 0 {
+                    right_dots = right_dots.max(1);
+                }
+                line.push(self.get_braille_char(left_dots, right_dots));
             }
+            lines.push(line);
         }
-        
-        // Add percentage
+
+        if !suffix {
+            return Ok(lines.join("\n"));
+        }
+
+        // Add percentage to the bottom row
         let avg_util = values.iter().sum::<f64>() / values.len() as f64;
         let percentage = format!("..{:>2.0}%", avg_util);
-        
-        Ok(format!("{}{}|", chart, percentage))
+
+        Ok(format!("{}{}|", lines.join("\n"), percentage))
+    }
+
+    /// Generate a multi-row ASCII line chart, one column per value.
+    ///
+    /// The y-axis is scaled to the observed min/max; the top and bottom rows are
+    /// labelled with those values. A flat series is drawn on the middle row.
+    fn generate_line_chart(&self, values: &[f64], _metric: &str) -> Result<String, Box<dyn Error>> {
+        if values.is_empty() {
+            return Err("Cannot generate line chart from empty series".into());
+        }
+
+        let height = LINE_CHART_HEIGHT;
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let range = max - min;
+
+        // Row 0 is the bottom of the chart
+        let levels: Vec<usize> = values
+            .iter()
+            .map(|&v| {
+                if range > 0.0 {
+                    (((v - min) / range) * (height - 1) as f64).round() as usize
+                } else {
+                    height / 2
+                }
+            })
+            .collect();
+
+        let mut grid = vec![vec![' '; values.len()]; height];
+        for (col, &level) in levels.iter().enumerate() {
+            match levels.get(col + 1) {
+                Some(&next) if next > level => {
+                    grid[level][col] = '/';
+                    for row in grid.iter_mut().take(next).skip(level + 1) {
+                        row[col] = '|';
+                    }
+                }
+                Some(&next) if next < level => {
+                    grid[level][col] = '\\';
+                    for row in grid.iter_mut().take(level).skip(next + 1) {
+                        row[col] = '|';
+                    }
+                }
+                _ => grid[level][col] = if level == 0 { '_' } else { '-' },
+            }
+        }
+
+        let rows: Vec<String> = (0..height)
+            .rev()
+            .map(|row| {
+                let label = if row == height - 1 {
+                    format!("{:>3.0}", max)
+                } else if row == 0 {
+                    format!("{:>3.0}", min)
+                } else {
+                    "   ".to_string()
+                };
+                let line: String = grid[row].iter().collect();
+                format!("{}|{}", label, line.trim_end())
+            })
+            .collect();
+
+        Ok(rows.join("\n"))
     }
 
-    fn percentage_to_braille_level(&self, percentage: f64) -> u8 {
-        let clamped = percentage.max(0.0).min(100.0);
-        if clamped == 0.0 { 0 } 
-        else if clamped <= 25.0 { 1 }
-        else if clamped <= 50.0 { 2 }
-        else if clamped <= 75.0 { 3 }
-        else { 4 }
+    /// Number of filled dots (0 to `rows * 4`) for a percentage; any value above
+    /// zero shows at least one dot. With one row this is 0 for 0%, then one level
+    /// per 25%.
+    fn percentage_to_braille_level(&self, percentage: f64, rows: usize) -> usize {
+        let levels = rows * 4;
+        let clamped = percentage.clamp(0.0, 100.0);
+        if clamped > 0.0 {
+            ((clamped / 100.0 * levels as f64).ceil() as usize).clamp(1, levels)
+        } else {
+            0
+        }
     }
 
-    fn get_braille_char(&self, left: u8, right: u8) -> char {
-        // Braille pattern mapping (same as shell script)
-        match (left, right) {
-            (0, 0) => ' ',
-            (0, 1) => '⢀', (0, 2) => '⢠', (0, 3) => '⢰', (0, 4) => '⢸',
-            (1, 0) => '⣀', (1, 1) => '⣀', (1, 2) => '⣠', (1, 3) => '⣰', (1, 4) => '⣸',
-            (2, 0) => '⣄', (2, 1) => '⣄', (2, 2) => '⣤', (2, 3) => '⣴', (2, 4) => '⣼',
-            (3, 0) => '⣆', (3, 1) => '⣆', (3, 2) => '⣦', (3, 3) => '⣶', (3, 4) => '⣾',
-            (4, 0) => '⣇', (4, 1) => '⣇', (4, 2) => '⣧', (4, 3) => '⣷', (4, 4) => '⣿',
-            _ => '⣿',
+    /// Braille character with `left` and `right` dots (0-4 each) filled from the bottom
+    fn get_braille_char(&self, left: usize, right: usize) -> char {
+        // Dot bits bottom to top: left column dots 7, 3, 2, 1; right column dots 8, 6, 5, 4
+        const LEFT_DOTS: [u32; 4] = [0x40, 0x04, 0x02, 0x01];
+        const RIGHT_DOTS: [u32; 4] = [0x80, 0x20, 0x10, 0x08];
+
+        let bits: u32 = LEFT_DOTS[..left.min(4)].iter().chain(&RIGHT_DOTS[..right.min(4)]).sum();
+        if bits == 0 {
+            ' '
+        } else {
+            char::from_u32(0x2800 + bits).unwrap_or(' ')
         }
     }
 }
 
 #[derive(Debug, Clone)]
 struct UtilizationData {
-    timestamp: String,
+    timestamp_ms: i64,
     perf_total: i64,
     perf_idle: i64,
     eff_total: i64,
     eff_idle: i64,
-    gpu_util: f64,
-}
\ No newline at end of file
+    /// Utilization percentage keyed by GPU index
+    gpu_util: BTreeMap<i64, f64>,
+    /// (total, idle) tick counters keyed by core id, only read for per-core charts
+    core_ticks: BTreeMap<i64, (i64, i64)>,
+    custom: HashMap<String, CustomSample>,
+}
+
+/// Values of a caller-requested metric within one collection round
+#[derive(Debug, Clone)]
+struct CustomSample {
+    sum: f64,
+    count: usize,
+    /// True when every value was an integer, i.e. a cumulative counter
+    counter: bool,
+}
+
+/// Whether `ramp` has one character for each of the 8 bar levels
+pub fn is_valid_bar_ramp(ramp: &str) -> bool {
+    ramp.chars().count() == 8
+}
+
+/// Bar characters indexed by level: a space, then the ramp
+fn bar_chars(ramp: &str) -> Vec<char> {
+    std::iter::once(' ').chain(ramp.chars()).collect()
+}
+
+/// Height in bar levels (0 to `levels`) for a percentage.
+///
+/// Zero, negative and NaN values are empty. Anything above zero gets at least one
+/// level and each `100 / levels` percent adds another, so with 8 levels 0.01% is
+/// `▁`, 12.5% is `▂` and both 99.9% and 100% are the full block.
+fn bar_height(percentage: f64, levels: usize) -> usize {
+    if percentage > 0.0 {
+        ((percentage.min(100.0) / 100.0 * levels as f64).floor() as usize + 1).min(levels)
+    } else {
+        0
+    }
+}
+
+/// Busy percentage for a tick-counter interval. A negative delta (or more idle
+/// than total ticks) means the counters were reset, e.g. by a reboot, so the
+/// interval is reported as 0 rather than a nonsensical rate. No ticks at all
+/// means the cores were parked, which is `None` rather than 0% busy.
+fn tick_utilization(delta_total: i64, delta_idle: i64) -> Option<f64> {
+    if delta_total == 0 {
+        None
+    } else if delta_total > 0 && delta_idle >= 0 && delta_idle <= delta_total {
+        Some(((delta_total - delta_idle) as f64 / delta_total as f64) * 100.0)
+    } else {
+        Some(0.0)
+    }
+}
+
+/// Columns read by `metric_from_row`, in order
+const METRIC_COLUMNS: &str = "name, value_int, value_float, value_text, value_bool, value_json, metadata, timestamp, unit, kind";
+
+/// Build a metric from a `METRIC_COLUMNS` row, or `None` if no value column is
+/// set (SQLite stores a NaN float as NULL)
+fn metric_from_row(row: &rusqlite::Row) -> SqliteResult<Option<Metric>> {
+    let Some(value) = value_from_columns(row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?) else {
+        return Ok(None);
+    };
+    let metadata: String = row.get(6)?;
+    let metadata = serde_json::from_str(&metadata)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e)))?;
+    let kind: String = row.get(9)?;
+
+    Ok(Some(Metric {
+        name: row.get(0)?,
+        value,
+        metadata,
+        timestamp: DateTime::from_timestamp_millis(row.get(7)?).unwrap_or_default(),
+        unit: row.get(8)?,
+        kind: MetricKind::from_str(&kind).unwrap_or_default(),
+    }))
+}
+
+/// Build a chart from a `collection_round_id, metric_name, chart_type,
+/// chart_data, data_points, timestamp, compressed` row, gunzipping
+/// `chart_data` when `compressed` is set
+fn chart_from_row(row: &rusqlite::Row) -> SqliteResult<super::Chart> {
+    let collection_round_id: String = row.get(0)?;
+    let metric_name: String = row.get(1)?;
+    let chart_type_str: String = row.get(2)?;
+    let compressed = row.get::<_, Option<bool>>(6)?.unwrap_or(false);
+    let chart_data = if compressed {
+        let bytes = super::gzip::decompress(&row.get::<_, Vec<u8>>(3)?)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Blob, e.to_string().into()))?;
+        String::from_utf8(bytes)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Blob, Box::new(e)))?
+    } else {
+        row.get(3)?
+    };
+    let data_points: i64 = row.get(4)?;
+    let timestamp_str: String = row.get(5)?;
+
+    let chart_type = super::ChartType::from_str(&chart_type_str)
+        .ok_or_else(|| rusqlite::Error::InvalidColumnType(2, "chart_type".to_string(), rusqlite::types::Type::Text))?;
+
+    let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
+        .map_err(|_| rusqlite::Error::InvalidColumnType(5, "timestamp".to_string(), rusqlite::types::Type::Text))?
+        .with_timezone(&Utc);
+
+    Ok(super::Chart {
+        id: None,
+        collection_round_id,
+        metric_name,
+        chart_type,
+        chart_data,
+        data_points: data_points as usize,
+        timestamp,
+    })
+}
+
+/// Name of the chart tracking a single GPU's utilization
+pub fn gpu_chart_metric(gpu_index: i64) -> String {
+    format!("gpu_utilization.{}", gpu_index)
+}
+
+/// GPU index of a per-GPU chart name such as `gpu_utilization.1`
+pub fn gpu_chart_index(metric_name: &str) -> Option<i64> {
+    chart_index(metric_name, "gpu_utilization")
+}
+
+/// Name of the chart tracking a single CPU core's utilization
+pub fn core_chart_metric(core_id: i64) -> String {
+    format!("{}.{}", super::PER_CORE_CHARTS, core_id)
+}
+
+/// Core id of a per-core chart name such as `core_utilization.3`
+pub fn core_chart_index(metric_name: &str) -> Option<i64> {
+    chart_index(metric_name, super::PER_CORE_CHARTS)
+}
+
+/// Index of a chart name of the form `<base>.<index>`
+fn chart_index(metric_name: &str, base: &str) -> Option<i64> {
+    metric_name.strip_prefix(base)?.strip_prefix('.')?.parse().ok()
+}
+
+/// Rebuild a metric value from whichever typed column is set.
+///
+/// Each variant has its own column, so the variant never depends on the value
+/// itself: an integral float such as `2.0` stays `Float` and the text `"true"`
+/// stays `String`.
+fn value_from_columns(
+    value_int: Option<i64>,
+    value_float: Option<f64>,
+    value_text: Option<String>,
+    value_bool: Option<bool>,
+    value_json: Option<String>,
+) -> Option<MetricValue> {
+    value_int
+        .map(MetricValue::Integer)
+        .or(value_float.map(MetricValue::Float))
+        .or(value_text.map(MetricValue::String))
+        .or(value_bool.map(MetricValue::Boolean))
+        .or_else(|| {
+            value_json
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .map(|buckets| MetricValue::Histogram { buckets })
+        })
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Rate of a counter at a point in time: (timestamp, per-second rate)
+type RatePoint = (DateTime<Utc>, f64);
+
+/// Columns of the metrics table holding a value: (int, float, text, bool, json)
+type ValueColumns<'a> = (Option<i64>, Option<f64>, Option<&'a str>, Option<bool>, Option<String>);
+
+/// Split a metric value into the typed value columns of the metrics table
+fn value_columns(value: &MetricValue) -> Result<ValueColumns<'_>, serde_json::Error> {
+    Ok(match value {
+        MetricValue::Integer(v) => (Some(*v), None, None, None, None),
+        MetricValue::Float(v) => (None, Some(*v), None, None, None),
+        MetricValue::String(v) => (None, None, Some(v.as_str()), None, None),
+        MetricValue::Boolean(v) => (None, None, None, Some(*v), None),
+        MetricValue::Histogram { buckets } => (None, None, None, None, Some(serde_json::to_string(buckets)?)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn storage() -> SqliteStorage {
+        SqliteStorage::new_in_memory().unwrap()
+    }
+
+    #[test]
+    fn line_chart_draws_a_flat_series_on_the_middle_row() {
+        let chart = storage().generate_line_chart(&[50.0, 50.0, 50.0], "test").unwrap();
+        assert_eq!(chart, [" 50|", "   |", "   |---", "   |", " 50|"].join("\n"));
+    }
+
+    #[test]
+    fn line_chart_scales_a_rising_ramp_to_its_range() {
+        let chart = storage().generate_line_chart(&[0.0, 25.0, 50.0, 75.0, 100.0], "test").unwrap();
+        assert_eq!(chart, ["100|    -", "   |   /", "   |  /", "   | /", "  0|/"].join("\n"));
+
+        let chart = storage().generate_line_chart(&[10.0, 90.0, 10.0], "test").unwrap();
+        assert_eq!(chart, [" 90| \\", "   |||", "   |||", "   |||", " 10|/ _"].join("\n"));
+    }
+
+    #[test]
+    fn line_chart_of_a_single_point() {
+        let chart = storage().generate_line_chart(&[42.0], "test").unwrap();
+        assert_eq!(chart, [" 42|", "   |", "   |-", "   |", " 42|"].join("\n"));
+
+        assert!(storage().generate_line_chart(&[], "test").is_err());
+    }
+
+    #[test]
+    fn two_row_bar_chart_stacks_full_and_partial_blocks() {
+        let storage = storage();
+
+        // 75% of 16 levels: a full bottom block and 5 of the top row's 8 levels
+        let chart = storage.generate_bar_chart(&[Some(75.0), Some(75.0)], "test", 2, true).unwrap();
+        assert_eq!(chart, "▅▅\n██..75%|");
+
+        let chart = storage.generate_bar_chart(&[Some(25.0), Some(100.0)], "test", 2, false).unwrap();
+        assert_eq!(chart, " █\n▅█");
+    }
+
+    #[test]
+    fn one_row_bar_chart_is_unchanged() {
+        let chart = storage().generate_bar_chart(&[Some(75.0), Some(75.0)], "test", 1, true).unwrap();
+        assert_eq!(chart, "▇▇..75%|");
+    }
+
+    #[test]
+    fn bar_height_pins_the_level_of_each_edge_case() {
+        let cases = [(0.0, 0), (0.01, 1), (12.5, 2), (99.9, 8), (100.0, 8), (f64::NAN, 0), (-5.0, 0)];
+        for (percentage, level) in cases {
+            assert_eq!(bar_height(percentage, 8), level, "{}%", percentage);
+        }
+        // Above 100% is the full block, never past it
+        assert_eq!(bar_height(250.0, 8), 8);
+        assert_eq!(bar_height(f64::INFINITY, 16), 16);
+    }
+
+    #[test]
+    fn bar_chart_draws_the_block_of_each_edge_case() {
+        let values = [0.0, 0.01, 12.5, 99.9, 100.0, f64::NAN, -5.0].map(Some);
+        let chart = storage().generate_bar_chart(&values, "test", 1, false).unwrap();
+        assert_eq!(chart, " ▁▂██  ");
+    }
+
+    #[test]
+    fn braille_levels_scale_with_the_number_of_rows() {
+        let storage = storage();
+
+        // One row has 4 levels, so 30% and 45% both round up to the second one
+        assert_eq!(storage.percentage_to_braille_level(30.0, 1), 2);
+        assert_eq!(storage.percentage_to_braille_level(45.0, 1), 2);
+        // Two rows have 8 levels and tell them apart
+        assert_eq!(storage.percentage_to_braille_level(30.0, 2), 3);
+        assert_eq!(storage.percentage_to_braille_level(45.0, 2), 4);
+
+        assert_eq!(storage.percentage_to_braille_level(0.0, 2), 0);
+        assert_eq!(storage.percentage_to_braille_level(0.1, 2), 1);
+        assert_eq!(storage.percentage_to_braille_level(150.0, 2), 8);
+    }
+
+    #[test]
+    fn multi_row_braille_draws_30_and_45_percent_differently() {
+        let storage = storage();
+        let chart = |value: f64, rows: usize| storage.generate_braille_chart(&[value, value], "test", rows, false).unwrap();
+
+        assert_eq!(chart(30.0, 1), chart(45.0, 1));
+        assert_ne!(chart(30.0, 2), chart(45.0, 2));
+        // 3 and 4 of the 8 levels, both within the bottom row
+        assert_eq!(chart(30.0, 2), " \n⣶");
+        assert_eq!(chart(45.0, 2), " \n⣿");
+    }
+
+    /// [`db_path_from`] with only the given variables set
+    fn db_path_with(vars: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+        let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+        db_path_from(|name| vars.get(name).map(|value| value.to_string()).filter(|value| !value.is_empty()))
+    }
+
+    #[test]
+    fn thrud_db_takes_precedence() {
+        let path = db_path_with(&[("THRUD_DB", "/tmp/t.db"), ("XDG_DATA_HOME", "/data"), ("HOME", "/home/u")]);
+        assert_eq!(path.unwrap(), "/tmp/t.db");
+    }
+
+    #[test]
+    fn xdg_data_home_comes_before_home() {
+        let path = db_path_with(&[("XDG_DATA_HOME", "/data"), ("HOME", "/home/u")]);
+        assert_eq!(path.unwrap(), "/data/thrud/thrud.db");
+    }
+
+    #[test]
+    fn relative_or_empty_xdg_data_home_is_ignored() {
+        for data_home in ["data", ""] {
+            let path = db_path_with(&[("XDG_DATA_HOME", data_home), ("HOME", "/home/u")]);
+            assert_eq!(path.unwrap(), "/home/u/.thrud/thrud.db");
+        }
+        let path = db_path_with(&[("THRUD_DB", ""), ("HOME", "/home/u")]);
+        assert_eq!(path.unwrap(), "/home/u/.thrud/thrud.db");
+    }
+
+    #[test]
+    fn no_location_is_an_error() {
+        let error = db_path_with(&[("XDG_DATA_HOME", "relative")]).unwrap_err();
+        assert!(error.to_string().contains("THRUD_DB"), "{}", error);
+    }
+
+    fn sqlite_error(code: std::os::raw::c_int) -> ThrudError {
+        ThrudError::Sqlite(rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(code), None))
+    }
+
+    #[test]
+    fn busy_errors_are_retried_until_the_limit() {
+        let storage = storage().with_busy_retry(3, Duration::ZERO);
+        let mut attempts = 0;
+
+        let result: Result<(), ThrudError> = storage.retry_busy(|| {
+            attempts += 1;
+            Err(sqlite_error(rusqlite::ffi::SQLITE_BUSY))
+        });
+
+        assert!(result.unwrap_err().is_busy());
+        assert_eq!(attempts, 4);
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        let storage = storage().with_busy_retry(3, Duration::from_secs(10));
+        let mut attempts = 0;
+
+        let result: Result<(), ThrudError> = storage.retry_busy(|| {
+            attempts += 1;
+            Err(sqlite_error(rusqlite::ffi::SQLITE_CONSTRAINT))
+        });
+
+        assert!(matches!(result, Err(ThrudError::Sqlite(_))));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn counter_resets_never_yield_negative_utilization() {
+        assert_eq!(tick_utilization(100, 25), Some(75.0));
+        // Counters went backwards, e.g. after a reboot
+        assert_eq!(tick_utilization(-500, -100), Some(0.0));
+        assert_eq!(tick_utilization(-500, 10), Some(0.0));
+        // More idle than total ticks
+        assert_eq!(tick_utilization(100, 150), Some(0.0));
+        assert_eq!(tick_utilization(100, -1), Some(0.0));
+    }
+}
@@ -0,0 +1,93 @@
+//! Unit conversions for display. Collectors report and storage keeps
+//! temperatures in degrees Celsius; these helpers only change how they're shown.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Unit temperatures are displayed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    /// Convert a temperature in degrees Celsius to this unit
+    pub fn from_celsius(self, celsius: f64) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    /// Display a temperature in degrees Celsius in this unit with one decimal,
+    /// e.g. `113.0°F` for 45°C
+    pub fn format(self, celsius: f64) -> String {
+        format!("{:.1}{}", self.from_celsius(celsius), self.symbol())
+    }
+}
+
+impl fmt::Display for TemperatureUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemperatureUnit::Celsius => write!(f, "celsius"),
+            TemperatureUnit::Fahrenheit => write!(f, "fahrenheit"),
+        }
+    }
+}
+
+impl FromStr for TemperatureUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "celsius" | "c" => Ok(TemperatureUnit::Celsius),
+            "fahrenheit" | "f" => Ok(TemperatureUnit::Fahrenheit),
+            _ => Err(format!("unknown temperature unit '{}': expected celsius or fahrenheit", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn celsius_converts_to_fahrenheit() {
+        assert_eq!(TemperatureUnit::Fahrenheit.from_celsius(0.0), 32.0);
+        assert_eq!(TemperatureUnit::Fahrenheit.from_celsius(100.0), 212.0);
+        assert_eq!(TemperatureUnit::Fahrenheit.from_celsius(-40.0), -40.0);
+        assert_eq!(TemperatureUnit::Celsius.from_celsius(45.5), 45.5);
+    }
+
+    #[test]
+    fn units_parse_by_name_or_initial_in_any_case() {
+        assert_eq!("celsius".parse(), Ok(TemperatureUnit::Celsius));
+        assert_eq!("C".parse(), Ok(TemperatureUnit::Celsius));
+        assert_eq!("Fahrenheit".parse(), Ok(TemperatureUnit::Fahrenheit));
+        assert_eq!("f".parse(), Ok(TemperatureUnit::Fahrenheit));
+        assert!("kelvin".parse::<TemperatureUnit>().unwrap_err().contains("kelvin"));
+    }
+
+    #[test]
+    fn display_round_trips_through_from_str() {
+        for unit in [TemperatureUnit::Celsius, TemperatureUnit::Fahrenheit] {
+            assert_eq!(unit.to_string().parse(), Ok(unit));
+        }
+    }
+
+    #[test]
+    fn temperatures_format_with_one_decimal_and_symbol() {
+        assert_eq!(TemperatureUnit::Fahrenheit.format(45.0), "113.0°F");
+        assert_eq!(TemperatureUnit::Celsius.format(45.04), "45.0°C");
+        assert_eq!(TemperatureUnit::Celsius.format(-3.26), "-3.3°C");
+    }
+}
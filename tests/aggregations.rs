@@ -0,0 +1,496 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use thrud::collectors::{names, Metric, MetricValue};
+use thrud::storage::{AggregationRegistry, SqliteStorage, Storage};
+
+fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+#[test]
+fn overflowing_window_is_rejected_not_panicking() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    let registry = AggregationRegistry::new();
+
+    for (name, _) in registry.list() {
+        let aggregation = registry.get(name).unwrap();
+        if !aggregation.param_schema().iter().any(|spec| spec.name == "window_seconds") {
+            continue;
+        }
+        let mut params = params(&[("window_seconds", "9223372036854775807")]);
+        if name == "percentiles" || name == "spike_detector" {
+            params.insert("metric_name".to_string(), "cpu.utilization".to_string());
+        }
+
+        let error = storage.run_aggregation(&registry, name, &params).unwrap_err();
+        assert!(error.to_string().contains("window_seconds"), "{}: {}", name, error);
+
+        // Calling the aggregation directly skips the registry's validation
+        let conn = storage.get_connection().unwrap();
+        assert!(aggregation.execute(&conn, &params).is_err(), "{} accepted the window", name);
+    }
+}
+
+/// Tick counters of one efficiency core, sampled `seconds_ago` seconds ago
+fn core_ticks(seconds_ago: i64, idle: i64, total: i64) -> Vec<Metric> {
+    core_ticks_at(0, Utc::now() - Duration::seconds(seconds_ago), idle, total)
+}
+
+/// Tick counters of efficiency core `core_id` in cluster 0, sampled at `timestamp`
+fn core_ticks_at(core_id: i64, timestamp: DateTime<Utc>, idle: i64, total: i64) -> Vec<Metric> {
+    [(names::IDLE_TICKS, idle), (names::TOTAL_TICKS, total)]
+        .into_iter()
+        .map(|(counter, ticks)| {
+            let mut metric = Metric::counter(names::cpu_core_ticks(names::EFFICIENCY, core_id, counter), ticks)
+                .with_tag("core_id", core_id.to_string())
+                .with_tag("core_type", names::EFFICIENCY)
+                .with_tag("cluster_id", "0");
+            metric.timestamp = timestamp;
+            metric
+        })
+        .collect()
+}
+
+#[test]
+fn cpu_aggregation_reads_stored_tick_counters() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    storage.store_metrics(core_ticks(2, 100, 200)).unwrap();
+    storage.store_metrics(core_ticks(1, 130, 300)).unwrap();
+
+    let result = storage
+        .run_aggregation(&AggregationRegistry::new(), "apple_silicon_cpu", &params(&[("window_seconds", "60")]))
+        .unwrap();
+
+    let cores = result.data["cores"].as_array().unwrap();
+    assert_eq!(cores.len(), 1);
+    assert_eq!(cores[0]["core_type"], names::EFFICIENCY);
+    assert_eq!(cores[0]["sample_count"], 1);
+    // 70 of 100 elapsed ticks were busy
+    assert_eq!(cores[0]["utilization_percent"], 70.0);
+    assert_eq!(result.data["clusters"][0]["avg_utilization"], 70.0);
+}
+
+#[test]
+fn cpu_aggregation_selects_samples_100ms_apart() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    let now = Utc::now();
+    storage.store_metrics(core_ticks_at(0, now - Duration::milliseconds(200), 100, 200)).unwrap();
+    storage.store_metrics(core_ticks_at(0, now - Duration::milliseconds(100), 130, 300)).unwrap();
+
+    // Timestamps are stored as epoch milliseconds, not rounded to the second
+    {
+        let conn = storage.get_connection().unwrap();
+        let (kind, spread): (String, i64) = conn
+            .query_row("SELECT MIN(typeof(timestamp)), MAX(timestamp) - MIN(timestamp) FROM metrics", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((kind.as_str(), spread), ("integer", 100));
+    }
+
+    let result = storage
+        .run_aggregation(&AggregationRegistry::new(), "apple_silicon_cpu", &params(&[("window_seconds", "1")]))
+        .unwrap();
+
+    let core = &result.data["cores"][0];
+    assert_eq!(core["sample_count"], 1);
+    assert_eq!(core["utilization_percent"], 70.0);
+    // 70 busy ticks in 100ms
+    assert_eq!(core["busy_ticks_per_second"], 700.0);
+}
+
+/// Utilization and temperature of GPU `index`
+fn gpu_sample(index: usize, utilization: f64, temperature: f64) -> Vec<Metric> {
+    vec![
+        Metric::gauge(names::gpu_utilization(index), utilization).with_tag("gpu_index", index.to_string()),
+        Metric::gauge(format!("gpu.{}.temperature", index), temperature).with_tag("gpu_index", index.to_string()),
+    ]
+}
+
+#[test]
+fn gpu_aggregation_reports_utilization_and_temperature() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    storage.store_metrics(gpu_sample(0, 0.25, 40.0)).unwrap();
+    storage.store_metrics(gpu_sample(0, 0.75, 50.0)).unwrap();
+    storage.store_metrics(gpu_sample(1, 1.0, 60.0)).unwrap();
+
+    let result = storage.run_aggregation(&AggregationRegistry::new(), "apple_silicon_gpu", &params(&[])).unwrap();
+
+    assert_eq!(result.data["window_seconds"], 60);
+    assert_eq!(result.data["sample_count"], 3);
+    assert_eq!(result.data["min_utilization"], 25.0);
+    assert_eq!(result.data["peak_utilization"], 100.0);
+    assert_eq!(result.data["avg_temperature_c"], 50.0);
+
+    let gpu0 = &result.data["gpus"][0];
+    assert_eq!(gpu0["gpu_index"], 0);
+    assert_eq!(gpu0["avg_utilization"], 50.0);
+    assert_eq!(gpu0["avg_temperature_c"], 45.0);
+    assert_eq!(gpu0["sample_count"], 2);
+    assert_eq!(result.data["gpus"][1]["peak_utilization"], 100.0);
+}
+
+#[test]
+fn gpu_aggregation_without_samples_reports_nulls() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+
+    let result = storage.run_aggregation(&AggregationRegistry::new(), "apple_silicon_gpu", &params(&[])).unwrap();
+
+    assert_eq!(result.data["sample_count"], 0);
+    assert!(result.data["avg_utilization"].is_null());
+    assert!(result.data["avg_temperature_c"].is_null());
+    assert_eq!(result.data["gpus"], serde_json::json!([]));
+}
+
+/// Busy and total tick counters of GPU 0, sampled at `timestamp`
+fn gpu_ticks_at(timestamp: DateTime<Utc>, busy: i64, total: i64) -> Vec<Metric> {
+    [("gpu.0.busy_ticks", busy), ("gpu.0.total_ticks", total)]
+        .into_iter()
+        .map(|(name, ticks)| {
+            let mut metric = Metric::counter(name, ticks).with_tag("gpu_index", "0");
+            metric.timestamp = timestamp;
+            metric
+        })
+        .collect()
+}
+
+#[test]
+fn gpu_rate_aggregation_reads_the_busy_share_of_elapsed_ticks() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    let now = Utc::now();
+    storage.store_metrics(gpu_ticks_at(now - Duration::seconds(2), 100, 1000)).unwrap();
+    storage.store_metrics(gpu_ticks_at(now - Duration::seconds(1), 160, 1200)).unwrap();
+
+    let result = storage
+        .run_aggregation(&AggregationRegistry::new(), "apple_silicon_gpu_rate", &params(&[("window_seconds", "60")]))
+        .unwrap();
+
+    let gpus = result.data["gpus"].as_array().unwrap();
+    assert_eq!(gpus.len(), 1);
+    assert_eq!(gpus[0]["gpu_index"], 0);
+    assert_eq!(gpus[0]["sample_count"], 1);
+    // 60 of 200 elapsed ticks were busy, over one second
+    assert_eq!(gpus[0]["utilization_percent"], 30.0);
+    assert_eq!(gpus[0]["busy_ticks_per_second"], 60.0);
+}
+
+#[test]
+fn percentiles_of_a_known_distribution() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    // 1..=100 in shuffled order, plus a sample of another metric
+    let mut metrics: Vec<Metric> = (0..100).map(|i| Metric::gauge("test.latency", ((i * 37) % 100 + 1) as f64)).collect();
+    metrics.push(Metric::gauge("test.other", 1000.0));
+    storage.store_metrics(metrics).unwrap();
+
+    let result = storage
+        .run_aggregation(
+            &AggregationRegistry::new(),
+            "percentiles",
+            &params(&[("metric_name", "test.latency"), ("percentiles", "0,50,99,99.5,100")]),
+        )
+        .unwrap();
+
+    assert_eq!(result.data["sample_count"], 100);
+    let percentiles = &result.data["percentiles"];
+    assert_eq!(percentiles["p0"], 1.0);
+    assert_eq!(percentiles["p50"], 50.0);
+    assert_eq!(percentiles["p99"], 99.0);
+    assert_eq!(percentiles["p99.5"], 100.0);
+    assert_eq!(percentiles["p100"], 100.0);
+}
+
+#[test]
+fn percentiles_without_samples_are_null() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    let registry = AggregationRegistry::new();
+
+    let result = storage.run_aggregation(&registry, "percentiles", &params(&[("metric_name", "test.latency")])).unwrap();
+    assert_eq!(result.data["sample_count"], 0);
+    assert!(result.data["percentiles"]["p95"].is_null());
+
+    let out_of_range = params(&[("metric_name", "test.latency"), ("percentiles", "50,101")]);
+    assert!(storage.run_aggregation(&registry, "percentiles", &out_of_range).is_err());
+}
+
+#[test]
+fn cpu_aggregation_skips_counter_resets() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    storage.store_metrics(core_ticks(3, 1_000, 2_000)).unwrap();
+    // Reset, e.g. by a reboot: both counters went backwards
+    storage.store_metrics(core_ticks(2, 10, 20)).unwrap();
+    storage.store_metrics(core_ticks(1, 40, 120)).unwrap();
+
+    let result = storage
+        .run_aggregation(&AggregationRegistry::new(), "apple_silicon_cpu", &params(&[("window_seconds", "60")]))
+        .unwrap();
+
+    let core = &result.data["cores"][0];
+    // Only the interval after the reset counts
+    assert_eq!(core["sample_count"], 1);
+    assert_eq!(core["utilization_percent"], 70.0);
+    assert!(core["busy_ticks_per_second"].as_f64().unwrap() >= 0.0);
+    assert!(core["idle_ticks_per_second"].as_f64().unwrap() >= 0.0);
+}
+
+#[test]
+fn cpu_aggregation_scoped_to_a_round_reports_only_its_interval() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    let registry = AggregationRegistry::new();
+    storage.store_metrics(core_ticks(3, 100, 200)).unwrap();
+    // 70 of 100 ticks busy, then 90 of 100
+    let second = storage.store_metrics(core_ticks(2, 130, 300)).unwrap();
+    let third = storage.store_metrics(core_ticks(1, 140, 400)).unwrap();
+
+    let utilization = |round_id: Option<&str>| {
+        let mut params = params(&[("window_seconds", "60")]);
+        if let Some(round_id) = round_id {
+            params.insert("round_id".to_string(), round_id.to_string());
+        }
+        let result = storage.run_aggregation(&registry, "apple_silicon_cpu", &params).unwrap();
+        assert_eq!(result.data["round_id"].as_str(), round_id);
+        let core = &result.data["cores"][0];
+        (core["utilization_percent"].as_f64().unwrap(), core["sample_count"].as_i64().unwrap())
+    };
+
+    assert_eq!(utilization(None), (80.0, 2));
+    assert_eq!(utilization(Some(&second.id)), (70.0, 1));
+    assert_eq!(utilization(Some(&third.id)), (90.0, 1));
+}
+
+#[test]
+fn cpu_aggregation_scoped_to_a_round_honours_max_samples() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    let registry = AggregationRegistry::new();
+    storage.store_metrics(core_ticks(4, 100, 200)).unwrap();
+    // 70 of 100 ticks busy, then 90 of 100
+    let second = storage.store_metrics(core_ticks(3, 130, 300)).unwrap();
+    let third = storage.store_metrics(core_ticks(2, 140, 400)).unwrap();
+    storage.store_metrics(core_ticks(1, 240, 500)).unwrap();
+
+    let utilization = |round_id: &str, max_samples: &str| {
+        let params = params(&[("window_seconds", "60"), ("round_id", round_id), ("max_samples", max_samples)]);
+        let result = storage.run_aggregation(&registry, "apple_silicon_cpu", &params).unwrap();
+        let core = &result.data["cores"][0];
+        (core["utilization_percent"].as_f64().unwrap(), core["sample_count"].as_i64().unwrap())
+    };
+
+    assert_eq!(utilization(&second.id, "5"), (70.0, 1));
+    assert_eq!(utilization(&third.id, "2"), (90.0, 1));
+    // Only the round itself: no previous sample to pair it with
+    let params = params(&[("round_id", third.id.as_str()), ("max_samples", "1")]);
+    let result = storage.run_aggregation(&registry, "apple_silicon_cpu", &params).unwrap();
+    assert_eq!(result.data["cores"], serde_json::json!([]));
+}
+
+#[test]
+fn cpu_aggregation_scoped_to_a_round_keeps_to_the_window_before_it() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    let registry = AggregationRegistry::new();
+    storage.store_metrics(core_ticks(30, 100, 200)).unwrap();
+    let round = storage.store_metrics(core_ticks(2, 130, 300)).unwrap();
+
+    let scoped = |window_seconds: &str| {
+        let params = params(&[("window_seconds", window_seconds), ("round_id", round.id.as_str())]);
+        storage.run_aggregation(&registry, "apple_silicon_cpu", &params).unwrap().data["cores"].clone()
+    };
+
+    // The previous sample is 28 seconds before the round
+    assert_eq!(scoped("10"), serde_json::json!([]));
+    assert_eq!(scoped("60")[0]["utilization_percent"], 70.0);
+
+    let unknown = params(&[("round_id", "no-such-round")]);
+    let error = storage.run_aggregation(&registry, "apple_silicon_cpu", &unknown).unwrap_err();
+    assert!(error.to_string().contains("no-such-round"), "{}", error);
+}
+
+#[test]
+fn cpu_clusters_report_the_spread_between_cores() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    // Core 0 is 10% busy and core 1 90% busy over the same interval
+    for (seconds_ago, elapsed) in [(2, 0), (1, 100)] {
+        let timestamp = Utc::now() - Duration::seconds(seconds_ago);
+        let mut round = core_ticks_at(0, timestamp, elapsed * 9 / 10, elapsed);
+        round.extend(core_ticks_at(1, timestamp, elapsed / 10, elapsed));
+        storage.store_metrics(round).unwrap();
+    }
+
+    let result = storage.run_aggregation(&AggregationRegistry::new(), "apple_silicon_cpu", &params(&[])).unwrap();
+
+    let cluster = &result.data["clusters"][0];
+    assert_eq!(result.data["clusters"].as_array().unwrap().len(), 1);
+    assert_eq!(cluster["core_count"], 2);
+    assert!((cluster["avg_utilization"].as_f64().unwrap() - 50.0).abs() < 1e-9);
+    assert_eq!(cluster["min_core_utilization"], 10.0);
+    assert_eq!(cluster["max_core_utilization"], 90.0);
+    assert!((cluster["stddev_utilization"].as_f64().unwrap() - 40.0).abs() < 1e-9);
+}
+
+/// Efficiency and performance cluster tick counters, sampled `seconds_ago` seconds ago
+fn cluster_ticks(seconds_ago: i64, efficiency: (i64, i64), performance: (i64, i64)) -> Vec<Metric> {
+    let timestamp = Utc::now() - Duration::seconds(seconds_ago);
+    [
+        (names::CPU_EFFICIENCY_IDLE_TICKS, efficiency.0),
+        (names::CPU_EFFICIENCY_TOTAL_TICKS, efficiency.1),
+        (names::CPU_PERFORMANCE_IDLE_TICKS, performance.0),
+        (names::CPU_PERFORMANCE_TOTAL_TICKS, performance.1),
+    ]
+    .into_iter()
+    .map(|(name, ticks)| {
+        let mut metric = Metric::counter(name, ticks);
+        metric.timestamp = timestamp;
+        metric
+    })
+    .collect()
+}
+
+#[test]
+fn efficiency_balance_is_the_efficiency_share_of_active_ticks() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    storage.store_metrics(cluster_ticks(3, (100, 200), (100, 200))).unwrap();
+    // 70 efficiency and 30 performance ticks busy
+    storage.store_metrics(cluster_ticks(2, (130, 300), (170, 300))).unwrap();
+    // 80 efficiency and 20 performance ticks busy
+    storage.store_metrics(cluster_ticks(1, (150, 400), (250, 400))).unwrap();
+
+    let result = storage
+        .run_aggregation(&AggregationRegistry::new(), "efficiency_balance", &params(&[("window_seconds", "60")]))
+        .unwrap();
+
+    assert_eq!(result.data["sample_count"], 2);
+    assert_eq!(result.data["efficiency_active_ticks"], 150);
+    assert_eq!(result.data["performance_active_ticks"], 50);
+    assert_eq!(result.data["efficiency_fraction"], 0.75);
+}
+
+#[test]
+fn efficiency_balance_without_activity_is_null() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    storage.store_metrics(cluster_ticks(2, (100, 200), (100, 200))).unwrap();
+    storage.store_metrics(cluster_ticks(1, (200, 300), (200, 300))).unwrap();
+
+    let result = storage
+        .run_aggregation(&AggregationRegistry::new(), "efficiency_balance", &params(&[("window_seconds", "60")]))
+        .unwrap();
+
+    assert_eq!(result.data["efficiency_active_ticks"], 0);
+    assert!(result.data["efficiency_fraction"].is_null());
+}
+
+#[test]
+fn cpu_aggregation_max_samples_caps_the_rounds_read() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    // 4 rounds, each with 10 of 100 elapsed ticks busy
+    for step in 0..4 {
+        storage.store_metrics(core_ticks(4 - step, 100 + 90 * step, 200 + 100 * step)).unwrap();
+        // Rounds are ordered by the millisecond they were stored in
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    let registry = AggregationRegistry::new();
+
+    let all = storage.run_aggregation(&registry, "apple_silicon_cpu", &params(&[("window_seconds", "60")])).unwrap();
+    let capped = storage
+        .run_aggregation(&registry, "apple_silicon_cpu", &params(&[("window_seconds", "60"), ("max_samples", "2")]))
+        .unwrap();
+
+    assert_eq!(all.data["cores"][0]["sample_count"], 3);
+    // The 2 newest rounds give a single interval
+    assert_eq!(capped.data["max_samples"], 2);
+    assert_eq!(capped.data["cores"][0]["sample_count"], 1);
+    assert_eq!(capped.data["cores"][0]["utilization_percent"], 10.0);
+}
+
+#[test]
+fn cpu_aggregation_rejects_an_invalid_max_samples() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    let aggregation = AggregationRegistry::new();
+    let aggregation = aggregation.get("apple_silicon_cpu").unwrap();
+    let conn = storage.get_connection().unwrap();
+
+    for value in ["0", "-1", "many"] {
+        let error = aggregation.execute(&conn, &params(&[("max_samples", value)])).unwrap_err();
+        assert!(error.to_string().contains("max_samples"), "{}: {}", value, error);
+    }
+}
+
+#[test]
+fn cpu_aggregation_result_validates_against_its_schema() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    storage.store_metrics(core_ticks(2, 100, 200)).unwrap();
+    storage.store_metrics(core_ticks(1, 130, 300)).unwrap();
+    let registry = AggregationRegistry::new();
+
+    let result = storage.run_aggregation(&registry, "apple_silicon_cpu", &params(&[("window_seconds", "60")])).unwrap();
+    let schema = jsonschema::JSONSchema::compile(&registry.get("apple_silicon_cpu").unwrap().result_schema()).unwrap();
+
+    // Populated so the core and cluster item schemas are exercised too
+    assert_eq!(result.data["cores"].as_array().unwrap().len(), 1);
+    assert_eq!(result.data["clusters"].as_array().unwrap().len(), 1);
+    if let Err(errors) = schema.validate(&result.data) {
+        let errors: Vec<String> = errors.map(|error| format!("{} at {}", error, error.instance_path)).collect();
+        panic!("{:?}", errors);
+    }
+
+    let mut out_of_range = result.data.clone();
+    out_of_range["cores"][0]["utilization_percent"] = serde_json::json!(150.0);
+    assert!(!schema.is_valid(&out_of_range));
+    let mut missing = result.data.clone();
+    missing["clusters"][0].as_object_mut().unwrap().remove("core_count");
+    assert!(!schema.is_valid(&missing));
+}
+
+const GIB: i64 = 1 << 30;
+
+/// A memory sample in bytes, `compressed` only where the platform reports it
+fn memory_sample(total: i64, used: i64, swap_used: i64, compressed: Option<i64>) -> Vec<Metric> {
+    let mut fields = vec![
+        (names::MEMORY_TOTAL_BYTES, total),
+        (names::MEMORY_USED_BYTES, used),
+        (names::MEMORY_SWAP_USED_BYTES, swap_used),
+    ];
+    fields.extend(compressed.map(|bytes| (names::MEMORY_COMPRESSED_BYTES, bytes)));
+    fields
+        .into_iter()
+        .map(|(name, bytes)| Metric::new(name.to_string(), MetricValue::Integer(bytes), HashMap::new()).with_unit("bytes"))
+        .collect()
+}
+
+#[test]
+fn memory_pressure_is_low_without_swap() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    storage.store_metrics(memory_sample(16 * GIB, 4 * GIB, 0, None)).unwrap();
+
+    let result = storage.run_aggregation(&AggregationRegistry::new(), "memory_pressure", &params(&[])).unwrap();
+
+    assert_eq!(result.data["pressure_percent"], 25.0);
+    assert_eq!(result.data["swap_active"], false);
+    assert!(result.data["compressed_bytes"].is_null());
+}
+
+#[test]
+fn memory_pressure_is_high_with_compression_and_swap() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    storage.store_metrics(memory_sample(16 * GIB, 4 * GIB, 0, Some(0))).unwrap();
+    // Only the latest sample counts
+    storage.store_metrics(memory_sample(16 * GIB, 10 * GIB, GIB, Some(4 * GIB))).unwrap();
+
+    let result = storage.run_aggregation(&AggregationRegistry::new(), "memory_pressure", &params(&[])).unwrap();
+
+    assert_eq!(result.data["pressure_percent"], 87.5);
+    assert_eq!(result.data["swap_active"], true);
+    assert_eq!(result.data["swap_used_bytes"], GIB);
+
+    // Used and compressed together past the total is capped
+    storage.store_metrics(memory_sample(16 * GIB, 12 * GIB, GIB, Some(8 * GIB))).unwrap();
+    let result = storage.run_aggregation(&AggregationRegistry::new(), "memory_pressure", &params(&[])).unwrap();
+    assert_eq!(result.data["pressure_percent"], 100.0);
+}
+
+#[test]
+fn memory_pressure_without_samples_is_null() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+
+    let result = storage.run_aggregation(&AggregationRegistry::new(), "memory_pressure", &params(&[])).unwrap();
+
+    assert!(result.data["pressure_percent"].is_null());
+    assert!(result.data["swap_active"].is_null());
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "arrow")]
+
+use arrow::array::{Array, Float64Array, StringArray, TimestampMillisecondArray};
+use arrow::datatypes::{DataType, TimeUnit};
+use thrud::collectors::Metric;
+use thrud::storage::{SqliteStorage, Storage};
+
+#[test]
+fn export_arrow_builds_typed_columns() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+    storage
+        .store_metrics(vec![
+            Metric::gauge("gpu.utilization", 0.25).with_tag("gpu", "0"),
+            Metric::gauge("gpu.utilization", 0.75).with_tag("gpu", "1").with_tag("value", "shadowed"),
+            Metric::gauge("cpu.utilization", 0.5),
+        ])
+        .unwrap();
+
+    let batch = storage.export_arrow("gpu.utilization", None, None).unwrap();
+
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(batch.num_columns(), 4);
+    let schema = batch.schema();
+    let columns: Vec<(&str, &DataType)> = schema.fields().iter().map(|field| (field.name().as_str(), field.data_type())).collect();
+    assert_eq!(
+        columns,
+        [
+            ("timestamp", &DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))),
+            ("value", &DataType::Float64),
+            ("gpu", &DataType::Utf8),
+            ("metadata.value", &DataType::Utf8),
+        ]
+    );
+
+    let timestamps = batch.column(0).as_any().downcast_ref::<TimestampMillisecondArray>().unwrap();
+    assert_eq!(timestamps.null_count(), 0);
+    let values = batch.column(1).as_any().downcast_ref::<Float64Array>().unwrap();
+    let mut values: Vec<f64> = values.iter().flatten().collect();
+    values.sort_by(f64::total_cmp);
+    assert_eq!(values, [0.25, 0.75]);
+    let shadowed = batch.column(3).as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(shadowed.null_count(), 1);
+}
+
+#[test]
+fn export_arrow_of_an_unknown_metric_is_empty() {
+    let storage = SqliteStorage::new_in_memory().unwrap();
+
+    let batch = storage.export_arrow("missing", None, None).unwrap();
+
+    assert_eq!(batch.num_rows(), 0);
+    assert_eq!(batch.num_columns(), 2);
+}
@@ -0,0 +1,74 @@
+// The collector is stopped with SIGTERM
+#![cfg(unix)]
+
+use std::process::{Command, Output, Stdio};
+use std::time::Duration;
+
+/// A fresh database path under the system temp directory
+fn temp_db_path() -> String {
+    std::env::temp_dir()
+        .join(format!("thrud-test-{}.db", uuid::Uuid::new_v4()))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Run the collector storing memory metrics every 100ms for about a second with
+/// `flags`, then stop it with SIGTERM as a service manager would
+fn run_collector(flags: &[&str]) -> Output {
+    let path = temp_db_path();
+    let child = Command::new(env!("CARGO_BIN_EXE_thrud-collector"))
+        .args(["--database", &path, "--collectors", "memory", "--interval", "0.1"])
+        .args(flags)
+        .env("RUST_LOG", "debug")
+        .env_remove("THRUD_DEV_MODE")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    std::thread::sleep(Duration::from_secs(1));
+    let stopped = Command::new("kill").args(["-TERM", &child.id().to_string()]).status().unwrap();
+    assert!(stopped.success());
+    let output = child.wait_with_output().unwrap();
+
+    let _ = std::fs::remove_file(path);
+    output
+}
+
+#[test]
+fn quiet_collector_prints_nothing_for_normal_rounds() {
+    let output = run_collector(&["--quiet"]);
+
+    assert!(output.status.success(), "{:?}", output.status);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn collector_logs_to_stderr_without_quiet() {
+    let output = run_collector(&[]);
+
+    assert!(output.status.success(), "{:?}", output.status);
+    // Logs never go to stdout, which is kept for metrics
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Thrud System Metrics Collector"));
+}
+
+#[test]
+fn sigterm_exits_cleanly_after_printing_final_stats() {
+    let output = run_collector(&[]);
+
+    assert!(output.status.success(), "{:?}", output.status);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let shutdown = stderr.find("Shutting down after").expect("shutdown message");
+    let final_stats = &stderr[shutdown..];
+    assert!(final_stats.contains("Database Statistics:"), "{}", final_stats);
+    let rounds: u64 = final_stats
+        .lines()
+        .find_map(|line| line.split("Collection rounds: ").nth(1))
+        .expect("round count")
+        .trim()
+        .parse()
+        .unwrap();
+    assert!(rounds > 0, "{}", final_stats);
+}
@@ -0,0 +1,1236 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use thrud::collectors::{Metric, MetricKind, MetricValue};
+use thrud::storage::{Chart, ChartType, SqliteStorage, Storage};
+use thrud::ThrudError;
+
+fn storage() -> SqliteStorage {
+    SqliteStorage::new_in_memory().expect("in-memory storage")
+}
+
+#[test]
+fn empty_round_is_rejected_without_heartbeats() {
+    let storage = storage();
+
+    assert!(matches!(storage.store_metrics(Vec::new()), Err(ThrudError::EmptyMetrics)));
+    assert_eq!(storage.get_stats().unwrap().total_collection_rounds, 0);
+}
+
+#[test]
+fn heartbeat_round_is_stored_with_zero_metrics() {
+    let storage = storage().with_heartbeat_rounds(true);
+
+    let round = storage.store_metrics(Vec::new()).unwrap();
+    assert_eq!(round.metrics_count, 0);
+
+    let stats = storage.get_stats().unwrap();
+    assert_eq!(stats.total_collection_rounds, 1);
+    assert_eq!(stats.total_metrics, 0);
+    assert_eq!(stats.latest_collection.map(|latest| latest.metrics_count), Some(0));
+}
+
+#[test]
+fn heartbeat_rounds_still_store_metrics() {
+    let storage = storage().with_heartbeat_rounds(true);
+
+    let round = storage.store_metrics(vec![Metric::gauge("cpu.utilization", 0.5)]).unwrap();
+    assert_eq!(round.metrics_count, 1);
+}
+
+/// Store a round holding a single `cpu.utilization` sample of `value`
+fn store_round(storage: &SqliteStorage, value: f64) {
+    storage.store_metrics(vec![Metric::gauge("cpu.utilization", value)]).unwrap();
+}
+
+fn values(metrics: &[Metric]) -> Vec<String> {
+    metrics.iter().map(|metric| metric.value.to_string()).collect()
+}
+
+#[test]
+fn follow_cursor_yields_each_round_once() {
+    let storage = storage();
+    for value in [1.0, 2.0, 3.0] {
+        store_round(&storage, value);
+    }
+
+    let (metrics, cursor) = storage.metrics_after_round(0, 2).unwrap();
+    assert_eq!(values(&metrics), ["1", "2"]);
+
+    let (metrics, cursor) = storage.metrics_after_round(cursor, 2).unwrap();
+    assert_eq!(values(&metrics), ["3"]);
+
+    let (metrics, unchanged) = storage.metrics_after_round(cursor, 2).unwrap();
+    assert!(metrics.is_empty());
+    assert_eq!(unchanged, cursor);
+}
+
+#[test]
+fn follow_cursor_survives_pruning_every_round() {
+    let storage = storage();
+    for value in [1.0, 2.0] {
+        store_round(&storage, value);
+    }
+    let (_, cursor) = storage.metrics_after_round(0, 10).unwrap();
+
+    storage.prune_older_than(chrono::Utc::now() + chrono::Duration::hours(1)).unwrap();
+    assert_eq!(storage.get_stats().unwrap().total_collection_rounds, 0);
+    store_round(&storage, 3.0);
+
+    let (metrics, next) = storage.metrics_after_round(cursor, 10).unwrap();
+    assert_eq!(values(&metrics), ["3"]);
+    assert!(next > cursor);
+}
+
+#[test]
+fn follow_cursor_survives_compaction() {
+    let path = temp_db_path();
+    let storage = SqliteStorage::new(Some(path.clone())).unwrap();
+    storage.initialize().unwrap();
+    for value in [1.0, 2.0, 3.0] {
+        store_round(&storage, value);
+    }
+    let (_, cursor) = storage.metrics_after_round(0, 2).unwrap();
+
+    // Delete the oldest round so VACUUM has a gap to close
+    storage.prune_to_size(0).unwrap();
+    storage.compact().unwrap();
+    store_round(&storage, 4.0);
+
+    let (metrics, _) = storage.metrics_after_round(cursor, 10).unwrap();
+    assert!(!values(&metrics).contains(&"1".to_string()));
+    assert!(!values(&metrics).contains(&"2".to_string()));
+    assert!(values(&metrics).contains(&"4".to_string()));
+
+    drop(storage);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn seq_migration_keeps_rowids_as_cursors() {
+    let path = temp_db_path();
+    {
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE collection_rounds (
+                id TEXT PRIMARY KEY,
+                timestamp TEXT NOT NULL,
+                metrics_count INTEGER NOT NULL,
+                timestamp_ms INTEGER NOT NULL DEFAULT 0,
+                hostname TEXT,
+                thrud_version TEXT
+            );
+            CREATE TABLE metrics (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                collection_round_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                value_int INTEGER,
+                value_float REAL,
+                value_text TEXT,
+                value_bool INTEGER,
+                value_json TEXT,
+                metadata TEXT NOT NULL DEFAULT '{}',
+                timestamp INTEGER NOT NULL,
+                unit TEXT,
+                kind TEXT NOT NULL DEFAULT 'gauge',
+                FOREIGN KEY(collection_round_id) REFERENCES collection_rounds(id)
+            );
+            CREATE TABLE schema_version (version INTEGER PRIMARY KEY, description TEXT NOT NULL, applied_at TEXT NOT NULL);
+            INSERT INTO schema_version VALUES (1, '', ''), (2, '', ''), (3, '', ''), (4, '', ''), (5, '', ''), (6, '', '');
+            INSERT INTO collection_rounds (rowid, id, timestamp, metrics_count, timestamp_ms) VALUES
+                (5, 'a', '2024-01-01T00:00:00Z', 1, 1704067200000),
+                (9, 'b', '2024-01-01T00:00:01Z', 0, 1704067201000);
+            -- Referencing a round used to make dropping the old table fail
+            INSERT INTO metrics (collection_round_id, name, value_int, timestamp) VALUES
+                ('a', 'test.gauge', 7, 1704067200000);",
+        )
+        .unwrap();
+    }
+
+    let storage = SqliteStorage::new(Some(path.clone())).unwrap();
+    storage.initialize().unwrap();
+    assert_eq!(storage.schema_version().unwrap(), thrud::storage::SCHEMA_VERSION);
+    {
+        let conn = storage.get_connection().unwrap();
+        let seqs: Vec<(i64, String)> = conn
+            .prepare("SELECT seq, id FROM collection_rounds ORDER BY seq")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(seqs, [(5, "a".to_string()), (9, "b".to_string())]);
+        let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0)).unwrap();
+        assert!(foreign_keys);
+    }
+    let (metrics, _) = storage.metrics_after_round(0, 10).unwrap();
+    assert_eq!(values(&metrics), ["7"]);
+
+    store_round(&storage, 1.0);
+    let (metrics, cursor) = storage.metrics_after_round(9, 10).unwrap();
+    assert_eq!(values(&metrics), ["1"]);
+    assert_eq!(cursor, 10);
+
+    drop(storage);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn legacy_text_values_migrate_to_typed_columns() {
+    let path = temp_db_path();
+    {
+        // The original schema, before schema_version existed
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE collection_rounds (
+                id TEXT PRIMARY KEY,
+                timestamp TEXT NOT NULL,
+                metrics_count INTEGER NOT NULL
+            );
+            CREATE TABLE metrics (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                collection_round_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                value TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                FOREIGN KEY(collection_round_id) REFERENCES collection_rounds(id)
+            );
+            CREATE INDEX idx_metrics_collection_round ON metrics(collection_round_id);
+            CREATE INDEX idx_metrics_name ON metrics(name);
+            CREATE INDEX idx_metrics_timestamp ON metrics(timestamp);
+            CREATE TABLE charts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                collection_round_id TEXT NOT NULL,
+                metric_name TEXT NOT NULL,
+                chart_type TEXT NOT NULL,
+                chart_data TEXT NOT NULL,
+                data_points INTEGER NOT NULL,
+                timestamp TEXT NOT NULL,
+                FOREIGN KEY(collection_round_id) REFERENCES collection_rounds(id)
+            );
+            INSERT INTO collection_rounds VALUES ('a', '2024-01-01T00:00:00+00:00', 5);
+            INSERT INTO metrics (collection_round_id, name, value, timestamp) VALUES
+                ('a', 'legacy.int', '42', '2024-01-01T00:00:00+00:00'),
+                ('a', 'legacy.negative', '-3', '2024-01-01T00:00:00+00:00'),
+                ('a', 'legacy.float', '0.5', '2024-01-01T00:00:00.250+00:00'),
+                ('a', 'legacy.bool', 'true', '2024-01-01T00:00:00+00:00'),
+                ('a', 'legacy.text', 'idle', '2024-01-01T00:00:00+00:00');",
+        )
+        .unwrap();
+    }
+
+    let storage = SqliteStorage::new(Some(path.clone())).unwrap();
+    storage.initialize().unwrap();
+    assert_eq!(storage.schema_version().unwrap(), thrud::storage::SCHEMA_VERSION);
+    {
+        let conn = storage.get_connection().unwrap();
+        let columns: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_table_info('metrics')")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(!columns.contains(&"value".to_string()), "{:?}", columns);
+        for column in ["value_int", "value_float", "value_text", "value_bool", "value_json", "unit", "kind"] {
+            assert!(columns.contains(&column.to_string()), "{} missing from {:?}", column, columns);
+        }
+    }
+
+    let value = |name: &str| {
+        let metrics = storage.query_metrics(name, None).unwrap();
+        assert_eq!(metrics.len(), 1, "{}", name);
+        metrics[0].value.clone()
+    };
+    assert_eq!(value("legacy.int"), MetricValue::Integer(42));
+    assert_eq!(value("legacy.negative"), MetricValue::Integer(-3));
+    assert_eq!(value("legacy.float"), MetricValue::Float(0.5));
+    assert_eq!(value("legacy.bool"), MetricValue::Boolean(true));
+    assert_eq!(value("legacy.text"), MetricValue::String("idle".to_string()));
+
+    let float = &storage.query_metrics("legacy.float", None).unwrap()[0];
+    assert_eq!(float.timestamp.timestamp_millis(), 1704067200250);
+
+    drop(storage);
+    let _ = std::fs::remove_file(path);
+}
+
+/// A fresh database path under the system temp directory
+fn temp_db_path() -> String {
+    std::env::temp_dir()
+        .join(format!("thrud-test-{}.db", uuid::Uuid::new_v4()))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Store `rounds` rounds of a `test.gauge` sample and chart the last one with
+/// `data_points` points, returning its (bar, braille) charts
+fn chart_rounds(rounds: usize, data_points: usize) -> (Vec<thrud::storage::Chart>, Vec<thrud::storage::Chart>) {
+    let storage = storage();
+    let mut last = None;
+    for i in 0..rounds {
+        last = Some(storage.store_metrics(vec![Metric::gauge("test.gauge", i as f64)]).unwrap());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    storage.generate_and_store_charts(&last.unwrap().id, &["test.gauge"], data_points, 1, 1).unwrap();
+
+    let bar = storage.get_latest_charts(&["test.gauge"], &thrud::storage::ChartType::Bar, 10).unwrap();
+    let braille = storage.get_latest_charts(&["test.gauge"], &thrud::storage::ChartType::Braille, 10).unwrap();
+    (bar, braille)
+}
+
+#[test]
+fn odd_chart_points_still_store_braille_charts() {
+    let (bar, braille) = chart_rounds(7, 5);
+
+    assert_eq!(bar.len(), 1);
+    assert_eq!(bar[0].data_points, 5);
+    assert_eq!(braille.len(), 1);
+    assert_eq!(braille[0].data_points, 3);
+}
+
+#[test]
+fn braille_chart_waits_for_an_even_number_of_points() {
+    let (bar, braille) = chart_rounds(6, 5);
+
+    assert_eq!(bar.len(), 1);
+    assert!(braille.is_empty());
+}
+
+#[test]
+fn even_chart_points_store_half_as_many_braille_points() {
+    let (bar, braille) = chart_rounds(11, 10);
+
+    assert_eq!(bar[0].data_points, 10);
+    assert_eq!(braille[0].data_points, 5);
+}
+
+#[test]
+fn every_scalar_value_variant_round_trips() {
+    let storage = storage();
+    let metrics = vec![
+        Metric::new("test.integer".to_string(), MetricValue::Integer(i64::MAX), HashMap::new()),
+        Metric::new("test.float".to_string(), MetricValue::Float(0.125), HashMap::new()),
+        Metric::new("test.string".to_string(), MetricValue::String("M3 Max".to_string()), HashMap::new()),
+        Metric::new("test.boolean".to_string(), MetricValue::Boolean(true), HashMap::new()).with_tag("gpu", "0"),
+        // An integral float stays a float, and text that looks like a bool or a
+        // number stays text
+        Metric::new("test.integral_float".to_string(), MetricValue::Float(2.0), HashMap::new()),
+        Metric::new("test.true_text".to_string(), MetricValue::String("true".to_string()), HashMap::new()),
+        Metric::new("test.numeric_text".to_string(), MetricValue::String("42".to_string()), HashMap::new()),
+    ];
+    storage.store_metrics(metrics.clone()).unwrap();
+
+    for metric in metrics {
+        let stored = storage.query_metrics(&metric.name, None).unwrap();
+        assert_eq!(stored.len(), 1, "{}", metric.name);
+        assert_eq!(stored[0].value, metric.value);
+        assert_eq!(stored[0].metadata, metric.metadata);
+        assert_eq!(stored[0].timestamp.timestamp_millis(), metric.timestamp.timestamp_millis());
+    }
+}
+
+#[test]
+fn unit_and_kind_are_stored_and_read_back() {
+    let storage = storage();
+    storage
+        .store_metrics(vec![
+            Metric::counter("net.rx_bytes", 1000).with_unit("bytes"),
+            Metric::gauge("cpu.utilization", 0.5),
+        ])
+        .unwrap();
+
+    let counter = &storage.query_metrics("net.rx_bytes", None).unwrap()[0];
+    assert_eq!(counter.unit.as_deref(), Some("bytes"));
+    assert_eq!(counter.kind, MetricKind::Counter);
+
+    let gauge = &storage.query_metrics("cpu.utilization", None).unwrap()[0];
+    assert_eq!(gauge.unit, None);
+    assert_eq!(gauge.kind, MetricKind::Gauge);
+}
+
+#[test]
+fn histogram_value_round_trips() {
+    let storage = storage();
+    let histogram = MetricValue::Histogram { buckets: vec![(600.0, 3), (1296.0, 0), (3228.5, 12)] };
+    storage
+        .store_metrics(vec![Metric::new("test.histogram".to_string(), histogram.clone(), HashMap::new())])
+        .unwrap();
+
+    let stored = storage.query_metrics("test.histogram", None).unwrap();
+
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].value, histogram);
+}
+
+#[test]
+fn ten_thousand_inserts_reuse_one_connection() {
+    let path = temp_db_path();
+    let storage = SqliteStorage::new(Some(path.clone())).unwrap();
+    storage.initialize().unwrap();
+
+    for round in 0..100 {
+        let metrics = (0..100).map(|core| Metric::counter(format!("test.core.{}.ticks", core), round)).collect();
+        storage.store_metrics(metrics).unwrap();
+        storage.get_stats().unwrap();
+    }
+
+    assert_eq!(storage.get_stats().unwrap().total_metrics, 10_000);
+    assert_eq!(storage.connections_opened(), 1);
+
+    drop(storage);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn in_memory_metrics_survive_across_inserts() {
+    let storage = storage();
+    storage.insert_metrics(&[Metric::gauge("cpu.utilization", 0.25)]).unwrap();
+    storage.insert_metrics(&[Metric::gauge("cpu.utilization", 0.5), Metric::counter("gpu.0.busy_ticks", 7)]).unwrap();
+
+    assert_eq!(values(&storage.query_metrics("cpu.utilization", None).unwrap()), ["0.25", "0.5"]);
+    assert_eq!(values(&storage.query_metrics("gpu.0.busy_ticks", None).unwrap()), ["7"]);
+
+    let stats = storage.get_stats().unwrap();
+    assert_eq!(stats.total_collection_rounds, 2);
+    assert_eq!(stats.total_metrics, 3);
+    // The one connection stays open; reopening :memory: would start empty
+    assert_eq!(storage.connections_opened(), 1);
+
+    let count: i64 = storage.get_connection().unwrap().query_row("SELECT COUNT(*) FROM metrics", [], |row| row.get(0)).unwrap();
+    assert_eq!(count, 3);
+}
+
+/// A `test.gauge` sample of `value` taken at `timestamp`
+fn sample_at(timestamp: DateTime<Utc>, value: f64) -> Metric {
+    let mut metric = Metric::gauge("test.gauge", value);
+    metric.timestamp = timestamp;
+    metric
+}
+
+fn count(storage: &SqliteStorage, table: &str) -> i64 {
+    let conn = storage.get_connection().unwrap();
+    conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn prune_keeps_rows_exactly_at_the_cutoff() {
+    let storage = storage();
+    let cutoff = Utc::now() - Duration::hours(1);
+    storage.store_metrics(vec![sample_at(cutoff - Duration::milliseconds(1), 1.0)]).unwrap();
+    storage.store_metrics(vec![sample_at(cutoff, 2.0)]).unwrap();
+    storage.store_metrics(vec![sample_at(cutoff + Duration::milliseconds(1), 3.0)]).unwrap();
+
+    assert_eq!(storage.prune_older_than(cutoff).unwrap(), 1);
+    assert_eq!(values(&storage.query_metrics("test.gauge", None).unwrap()), ["2", "3"]);
+}
+
+#[test]
+fn prune_removes_orphaned_rounds_and_their_charts() {
+    let storage = storage();
+    // Rounds are timestamped now, so prune up to an hour from now
+    let cutoff = Utc::now() + Duration::hours(1);
+    let old = storage.store_metrics(vec![sample_at(cutoff - Duration::hours(2), 1.0)]).unwrap();
+    let kept = storage.store_metrics(vec![sample_at(cutoff, 2.0)]).unwrap();
+    for round in [&old, &kept] {
+        storage
+            .store_chart(&Chart {
+                id: None,
+                collection_round_id: round.id.clone(),
+                metric_name: "test.gauge".to_string(),
+                chart_type: ChartType::Bar,
+                chart_data: "█".to_string(),
+                data_points: 1,
+                // Too new for the age cutoff; only the orphan cleanup removes it
+                timestamp: cutoff,
+            })
+            .unwrap();
+    }
+
+    assert_eq!(storage.prune_older_than(cutoff).unwrap(), 1);
+
+    assert_eq!(count(&storage, "metrics"), 1);
+    assert_eq!(count(&storage, "collection_rounds"), 1);
+    let charts = storage.get_latest_charts(&["test.gauge"], &ChartType::Bar, 10).unwrap();
+    assert_eq!(charts.len(), 1);
+    assert_eq!(charts[0].collection_round_id, kept.id);
+}
+
+#[test]
+fn compact_shrinks_the_file_after_pruning() {
+    let path = temp_db_path();
+    let storage = SqliteStorage::new(Some(path.clone())).unwrap();
+    storage.initialize().unwrap();
+    let old = Utc::now() - Duration::days(1);
+    for round in 0..20 {
+        let metrics = (0..500)
+            .map(|i| sample_at(old, (round * 500 + i) as f64).with_tag("core_id", i.to_string()))
+            .collect();
+        storage.store_metrics(metrics).unwrap();
+    }
+    let size = || std::fs::metadata(&path).unwrap().len();
+    let before = size();
+
+    assert_eq!(storage.prune_older_than(Utc::now()).unwrap(), 10_000);
+    // Deleting rows only frees pages inside the file
+    assert_eq!(size(), before);
+
+    let reclaimed = storage.compact().unwrap();
+    assert!(reclaimed > 0);
+    assert!(size() < before, "{} >= {}", size(), before);
+    assert_eq!(storage.get_stats().unwrap().total_metrics, 0);
+
+    drop(storage);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn compact_is_a_no_op_in_memory() {
+    let storage = storage();
+    store_round(&storage, 1.0);
+
+    assert_eq!(storage.compact().unwrap(), 0);
+    assert_eq!(values(&storage.query_metrics("cpu.utilization", None).unwrap()), ["1"]);
+}
+
+/// Split CSV `text` into records of fields, undoing RFC 4180 quoting
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    records
+}
+
+#[test]
+fn csv_export_parses_back_into_rows() {
+    let storage = storage();
+    let start = Utc::now() - Duration::minutes(10);
+    storage.store_metrics(vec![sample_at(start - Duration::minutes(1), 1.0)]).unwrap();
+    storage
+        .store_metrics(vec![
+            sample_at(start, 2.5).with_tag("gpu", "0"),
+            Metric::counter("gpu.0.busy_ticks", 42),
+        ])
+        .unwrap();
+
+    let mut out = Vec::new();
+    assert_eq!(storage.export_csv(&mut out, Some(start)).unwrap(), 2);
+
+    let records = parse_csv(&String::from_utf8(out).unwrap());
+    assert_eq!(records[0], ["timestamp", "name", "value", "metadata"]);
+    assert_eq!(records.len(), 3);
+    assert_eq!(DateTime::parse_from_rfc3339(&records[1][0]).unwrap().timestamp_millis(), start.timestamp_millis());
+    assert_eq!(records[1][1..], ["test.gauge", "2.5", r#"{"gpu":"0"}"#]);
+    assert_eq!(records[2][1..], ["gpu.0.busy_ticks", "42", "{}"]);
+
+    let mut all = Vec::new();
+    assert_eq!(storage.export_csv(&mut all, None).unwrap(), 3);
+}
+
+#[test]
+fn gpu_name_with_a_comma_and_a_quote_round_trips() {
+    let gpu_name = r#"Apple M2 "Max", 38-core"#;
+    let storage = storage();
+    storage.store_metrics(vec![Metric::gauge("gpu.0.utilization", 0.5).with_tag("gpu_name", gpu_name)]).unwrap();
+
+    // Stored as JSON, so the name reads back intact
+    let stored = storage.query_metrics("gpu.0.utilization", None).unwrap();
+    assert_eq!(stored[0].metadata["gpu_name"], gpu_name);
+
+    let mut out = Vec::new();
+    storage.export_csv(&mut out, None).unwrap();
+    let records = parse_csv(&String::from_utf8(out).unwrap());
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1].len(), 4, "{:?}", records[1]);
+    assert_eq!(records[1][1], "gpu.0.utilization");
+    let metadata: HashMap<String, String> = serde_json::from_str(&records[1][3]).unwrap();
+    assert_eq!(metadata["gpu_name"], gpu_name);
+}
+
+#[test]
+fn custom_metrics_get_charts() {
+    let storage = storage();
+    let mut last = None;
+    for round in 0..3 {
+        last = Some(
+            storage
+                .store_metrics(vec![
+                    Metric::gauge("custom.queue_depth", 50.0),
+                    Metric::counter("custom.requests", round * 10),
+                    Metric::gauge("custom.unlisted", 50.0),
+                ])
+                .unwrap(),
+        );
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    let metrics = ["custom.queue_depth", "custom.requests", "custom.unlisted"];
+    storage.generate_and_store_charts(&last.unwrap().id, &metrics[..2], 2, 1, 1).unwrap();
+
+    let charts = storage.get_latest_charts(&metrics, &ChartType::Bar, 10).unwrap();
+    let chart = |name: &str| charts.iter().find(|chart| chart.metric_name == name).map(|chart| chart.chart_data.as_str());
+    // Gauges chart their value, counters the delta between rounds
+    assert_eq!(chart("custom.queue_depth"), Some("▅▅..50%|"));
+    assert_eq!(chart("custom.requests"), Some("▁▁..10%|"));
+    assert_eq!(chart("custom.unlisted"), None);
+}
+
+fn partitioned_storage() -> SqliteStorage {
+    let storage = storage().with_day_partitions(true);
+    storage.initialize().unwrap();
+    storage
+}
+
+fn noon(date: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(&format!("{}T12:00:00Z", date)).unwrap().with_timezone(&Utc)
+}
+
+#[test]
+fn dropping_a_day_partition_keeps_the_other_days() {
+    let storage = partitioned_storage();
+    let (first, second) = (noon("2026-03-01"), noon("2026-03-02"));
+    storage.import_metrics(vec![sample_at(first, 1.0), sample_at(second, 2.0)]).unwrap();
+    assert_eq!(storage.metric_partitions().unwrap(), [first.date_naive(), second.date_naive()]);
+
+    assert_eq!(storage.drop_partition(first.date_naive()).unwrap(), 1);
+
+    assert_eq!(storage.metric_partitions().unwrap(), [second.date_naive()]);
+    assert_eq!(values(&storage.query_metrics("test.gauge", None).unwrap()), ["2"]);
+    assert_eq!(count(&storage, "collection_rounds"), 1);
+    assert_eq!(storage.drop_partition(first.date_naive()).unwrap(), 0);
+}
+
+#[test]
+fn more_days_than_one_compound_select_holds_still_accept_writes() {
+    let storage = partitioned_storage();
+    let first = noon("2020-01-01");
+    storage
+        .import_metrics((0..501).map(|day| sample_at(first + Duration::days(day), day as f64)))
+        .unwrap();
+    assert_eq!(storage.metric_partitions().unwrap().len(), 501);
+
+    // Today starts yet another partition
+    store_round(&storage, 1.0);
+
+    assert_eq!(storage.get_stats().unwrap().total_metrics, 502);
+    assert_eq!(storage.drop_partition(first.date_naive()).unwrap(), 1);
+    assert_eq!(storage.get_stats().unwrap().total_metrics, 501);
+}
+
+#[test]
+fn locked_database_is_reported_as_a_busy_sqlite_error() {
+    let path = temp_db_path();
+    let storage = SqliteStorage::new(Some(path.clone())).unwrap().with_busy_retry(0, std::time::Duration::ZERO);
+    storage.initialize().unwrap();
+    // Fail at once instead of waiting out SQLite's own busy timeout
+    storage.get_connection().unwrap().busy_timeout(std::time::Duration::ZERO).unwrap();
+    let locker = rusqlite::Connection::open(&path).unwrap();
+    locker.execute_batch("BEGIN EXCLUSIVE").unwrap();
+
+    let error = storage.store_metrics(vec![Metric::gauge("cpu.utilization", 0.5)]).unwrap_err();
+
+    assert!(matches!(error, ThrudError::Sqlite(_)), "{:?}", error);
+    assert!(error.is_busy());
+    assert!(!ThrudError::EmptyMetrics.is_busy());
+
+    locker.execute_batch("ROLLBACK").unwrap();
+    store_round(&storage, 0.5);
+    drop(storage);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn busy_write_succeeds_once_the_lock_is_released_while_retrying() {
+    let path = temp_db_path();
+    let storage = SqliteStorage::new(Some(path.clone())).unwrap().with_busy_retry(10, std::time::Duration::from_millis(20));
+    storage.initialize().unwrap();
+    storage.get_connection().unwrap().busy_timeout(std::time::Duration::ZERO).unwrap();
+
+    let (locked_tx, locked) = std::sync::mpsc::channel();
+    let locker_path = path.clone();
+    let locker = std::thread::spawn(move || {
+        let conn = rusqlite::Connection::open(locker_path).unwrap();
+        conn.execute_batch("BEGIN EXCLUSIVE").unwrap();
+        locked_tx.send(()).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(150));
+        conn.execute_batch("COMMIT").unwrap();
+    });
+    locked.recv().unwrap();
+
+    let started = std::time::Instant::now();
+    storage.store_metrics(vec![Metric::gauge("cpu.utilization", 0.5)]).unwrap();
+
+    // The first attempt found the database locked, so the write waited for it
+    assert!(started.elapsed() >= std::time::Duration::from_millis(100), "{:?}", started.elapsed());
+    locker.join().unwrap();
+    assert_eq!(storage.get_stats().unwrap().total_metrics, 1);
+
+    drop(storage);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn five_buffered_rounds_are_written_together() {
+    let storage = storage().with_write_batch(5, std::time::Duration::from_secs(3600));
+
+    for round in 0..4 {
+        let written = storage.store_metrics_buffered(vec![Metric::gauge("cpu.utilization", round as f64)]).unwrap();
+        assert!(written.is_empty());
+        assert_eq!(storage.pending_rounds(), round + 1);
+    }
+    assert_eq!(storage.get_stats().unwrap().total_collection_rounds, 0);
+
+    let written = storage.store_metrics_buffered(vec![Metric::gauge("cpu.utilization", 4.0)]).unwrap();
+    assert_eq!(written.len(), 5);
+    assert_eq!(storage.pending_rounds(), 0);
+    let stats = storage.get_stats().unwrap();
+    assert_eq!(stats.total_collection_rounds, 5);
+    assert_eq!(stats.total_metrics, 5);
+}
+
+#[test]
+fn flush_writes_a_partial_batch() {
+    let storage = storage().with_write_batch(5, std::time::Duration::from_secs(3600));
+    storage.store_metrics_buffered(vec![Metric::gauge("cpu.utilization", 0.5)]).unwrap();
+    storage.store_metrics_buffered(vec![Metric::gauge("cpu.utilization", 0.6)]).unwrap();
+
+    assert_eq!(storage.flush().unwrap().len(), 2);
+    assert_eq!(storage.pending_rounds(), 0);
+    assert_eq!(storage.get_stats().unwrap().total_collection_rounds, 2);
+    assert!(storage.flush().unwrap().is_empty());
+}
+
+/// Store a bar chart of `test.gauge` timestamped `timestamp` in a round of its own
+fn store_chart_at(storage: &SqliteStorage, timestamp: DateTime<Utc>, chart_data: &str) {
+    let round = storage.store_metrics(vec![sample_at(timestamp, 1.0)]).unwrap();
+    storage
+        .store_chart(&Chart {
+            id: None,
+            collection_round_id: round.id,
+            metric_name: "test.gauge".to_string(),
+            chart_type: ChartType::Bar,
+            chart_data: chart_data.to_string(),
+            data_points: 1,
+            timestamp,
+        })
+        .unwrap();
+}
+
+#[test]
+fn charts_in_range_keep_to_since_and_until() {
+    let storage = storage();
+    let start = noon("2024-01-01");
+    for (hour, chart) in ["▁", "▄", "█"].iter().enumerate() {
+        store_chart_at(&storage, start + Duration::hours(hour as i64), chart);
+    }
+    let in_range = |since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>| -> Vec<String> {
+        storage
+            .get_charts_in_range(&["test.gauge"], &ChartType::Bar, since, until)
+            .unwrap()
+            .into_iter()
+            .map(|chart| chart.chart_data)
+            .collect()
+    };
+
+    let half_past = start + Duration::minutes(30);
+    assert_eq!(in_range(Some(half_past), Some(half_past + Duration::hours(1))), ["▄"]);
+    assert_eq!(in_range(Some(half_past), None), ["▄", "█"]);
+    assert_eq!(in_range(None, Some(half_past)), ["▁"]);
+    // Both ends are inclusive
+    assert_eq!(in_range(Some(start), Some(start + Duration::hours(1))), ["▁", "▄"]);
+    assert!(in_range(Some(start + Duration::hours(3)), None).is_empty());
+}
+
+#[test]
+fn column_export_keeps_to_since_and_until() {
+    let storage = storage();
+    let start = noon("2024-01-01");
+    for hour in 0..3 {
+        storage.store_metrics(vec![sample_at(start + Duration::hours(hour), hour as f64)]).unwrap();
+    }
+
+    let batch = storage
+        .export_columns("test.gauge", Some(start + Duration::minutes(30)), Some(start + Duration::hours(2)))
+        .unwrap();
+    assert_eq!(batch.value, [Some(1.0), Some(2.0)]);
+    assert_eq!(batch.timestamp, [(start + Duration::hours(1)).timestamp_millis(), (start + Duration::hours(2)).timestamp_millis()]);
+
+    let batch = storage.export_columns("test.gauge", None, Some(start)).unwrap();
+    assert_eq!(batch.value, [Some(0.0)]);
+}
+
+/// Store three rounds of the given per-GPU utilization ratios and chart the last one
+fn chart_gpus(ratios: &[f64]) -> SqliteStorage {
+    let storage = storage();
+    let mut last = None;
+    for _ in 0..3 {
+        let metrics = ratios.iter().enumerate().map(|(index, ratio)| Metric::gauge(format!("gpu.{}.utilization", index), *ratio)).collect();
+        last = Some(storage.store_metrics(metrics).unwrap());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    storage.generate_and_store_charts(&last.unwrap().id, &["gpu_utilization"], 2, 1, 1).unwrap();
+    storage
+}
+
+#[test]
+fn two_gpus_get_an_average_chart_and_one_chart_each() {
+    let storage = chart_gpus(&[0.25, 0.75]);
+
+    assert_eq!(storage.gpu_chart_metrics().unwrap(), ["gpu_utilization.0", "gpu_utilization.1"]);
+    let names = ["gpu_utilization", "gpu_utilization.0", "gpu_utilization.1"];
+    let charts = storage.get_latest_charts(&names, &ChartType::Bar, 10).unwrap();
+    let chart = |name: &str| charts.iter().find(|chart| chart.metric_name == name).map(|chart| chart.chart_data.as_str());
+    assert_eq!(chart("gpu_utilization"), Some("▅▅..50%|"));
+    assert_eq!(chart("gpu_utilization.0"), Some("▃▃..25%|"));
+    assert_eq!(chart("gpu_utilization.1"), Some("▇▇..75%|"));
+}
+
+#[test]
+fn single_gpu_gets_no_per_gpu_charts() {
+    let storage = chart_gpus(&[0.25]);
+
+    assert!(storage.gpu_chart_metrics().unwrap().is_empty());
+    let charts = storage.get_latest_charts(&["gpu_utilization"], &ChartType::Bar, 10).unwrap();
+    assert_eq!(charts.len(), 1);
+    assert_eq!(charts[0].chart_data, "▃▃..25%|");
+}
+
+#[test]
+fn each_core_gets_its_own_chart() {
+    use thrud::collectors::names::{cpu_core_ticks, IDLE_TICKS, TOTAL_TICKS};
+    use thrud::storage::PER_CORE_CHARTS;
+
+    let storage = storage();
+    // Core 0 is a quarter busy and core 1 three quarters busy in every round
+    let mut last = None;
+    for round in 1..=3 {
+        let mut metrics = Vec::new();
+        for (core_id, idle_per_round) in [(0, 75), (1, 25)] {
+            metrics.push(Metric::counter(cpu_core_ticks("performance", core_id, TOTAL_TICKS), round * 100));
+            metrics.push(Metric::counter(cpu_core_ticks("performance", core_id, IDLE_TICKS), round * idle_per_round));
+        }
+        last = Some(storage.store_metrics(metrics).unwrap());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    storage.generate_and_store_charts(&last.unwrap().id, &[PER_CORE_CHARTS], 2, 1, 1).unwrap();
+
+    assert_eq!(storage.core_chart_metrics().unwrap(), ["core_utilization.0", "core_utilization.1"]);
+    let names = ["core_utilization.0", "core_utilization.1"];
+    let charts = storage.get_latest_charts(&names, &ChartType::Bar, 10).unwrap();
+    assert_eq!(charts.len(), 2);
+    let chart = |name: &str| charts.iter().find(|chart| chart.metric_name == name).map(|chart| chart.chart_data.as_str());
+    assert_eq!(chart("core_utilization.0"), Some("▃▃..25%|"));
+    assert_eq!(chart("core_utilization.1"), Some("▇▇..75%|"));
+}
+
+#[test]
+fn metadata_query_selects_one_core_type() {
+    let storage = storage();
+    let start = noon("2024-01-01");
+    for (second, core_type) in ["efficiency", "performance", "efficiency"].iter().enumerate() {
+        let metric = sample_at(start + Duration::seconds(second as i64), second as f64).with_tag("core_type", *core_type);
+        storage.store_metrics(vec![metric, Metric::gauge("gpu_utilization", 0.5)]).unwrap();
+    }
+
+    let efficiency = storage.query_metrics_by_metadata("core_type", "efficiency").unwrap();
+    assert_eq!(values(&efficiency), ["0", "2"]);
+    assert!(efficiency.iter().all(|metric| metric.metadata["core_type"] == "efficiency"));
+    assert_eq!(storage.query_metrics_by_metadata("core_type", "performance").unwrap().len(), 1);
+    assert!(storage.query_metrics_by_metadata("core_type", "unknown").unwrap().is_empty());
+}
+
+#[test]
+fn metadata_query_by_core_type_uses_its_index() {
+    let storage = storage();
+    let conn = storage.get_connection().unwrap();
+    let plan: Vec<String> = conn
+        .prepare("EXPLAIN QUERY PLAN SELECT id FROM metrics WHERE json_extract(metadata, '$.core_type') = ?1")
+        .unwrap()
+        .query_map(["efficiency"], |row| row.get::<_, String>(3))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert!(plan.iter().any(|step| step.contains("idx_metrics_core_type")), "{:?}", plan);
+}
+
+#[test]
+fn metadata_query_rejects_keys_that_are_not_identifiers() {
+    let storage = storage();
+    for key in ["", "core type", "x') OR 1=1 --"] {
+        assert!(storage.query_metrics_by_metadata(key, "efficiency").is_err(), "{:?}", key);
+    }
+}
+
+#[test]
+fn health_check_reports_orphaned_rows() {
+    let path = temp_db_path();
+    let storage = SqliteStorage::new(Some(path.clone())).unwrap();
+    storage.initialize().unwrap();
+    store_round(&storage, 0.5);
+
+    let report = storage.health_check().unwrap();
+    assert!(report.is_healthy(), "{:?}", report);
+
+    // Orphans come from writers that didn't enforce the foreign keys
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    conn.pragma_update(None, "foreign_keys", false).unwrap();
+    conn.execute(
+        "INSERT INTO metrics (collection_round_id, name, value_float, timestamp) VALUES ('missing-round', 'cpu.utilization', 0.5, 0)",
+        [],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO charts (collection_round_id, metric_name, chart_type, chart_data, data_points, timestamp)
+         VALUES ('missing-round', 'gpu_utilization', 'bar', '▅▅', 2, '2024-01-01T00:00:00Z')",
+        [],
+    )
+    .unwrap();
+    drop(conn);
+
+    let report = storage.health_check().unwrap();
+    assert!(report.integrity_ok());
+    assert_eq!(report.orphaned_metrics, 1);
+    assert_eq!(report.orphaned_charts, 1);
+    assert!(!report.is_healthy());
+
+    drop(storage);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn metric_counts_break_samples_down_by_name() {
+    let storage = storage();
+    for round in 0..3 {
+        let mut metrics = vec![Metric::gauge("cpu.utilization", 0.5)];
+        if round > 0 {
+            metrics.push(Metric::gauge("gpu.0.utilization", 0.25));
+        }
+        if round == 2 {
+            metrics.push(Metric::gauge("ane.power", 1.0));
+        }
+        storage.store_metrics(metrics).unwrap();
+    }
+
+    let counts = storage.get_metric_counts().unwrap();
+    assert_eq!(
+        counts,
+        [("cpu.utilization".to_string(), 3), ("gpu.0.utilization".to_string(), 2), ("ane.power".to_string(), 1)]
+    );
+}
+
+#[test]
+fn regenerating_replaces_charts_with_one_set_per_round() {
+    let storage = storage().with_heartbeat_rounds(true);
+    let mut rounds = Vec::new();
+    for value in 0..4 {
+        rounds.push(storage.store_metrics(vec![Metric::gauge("test.gauge", value as f64)]).unwrap());
+        if value == 1 {
+            // Heartbeats have nothing to chart and are skipped
+            storage.store_metrics(Vec::new()).unwrap();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    let stale = Chart {
+        id: None,
+        collection_round_id: rounds[0].id.clone(),
+        metric_name: "test.gauge".to_string(),
+        chart_type: ChartType::Bar,
+        chart_data: "stale".to_string(),
+        data_points: 2,
+        timestamp: rounds[0].timestamp,
+    };
+    storage.store_chart(&stale).unwrap();
+
+    assert_eq!(storage.regenerate_charts(&["test.gauge"], 2, 1, 1).unwrap(), 4);
+
+    // Two points are the changes over three rounds, so the first two rounds have
+    // too few and the last two each get a bar, a braille and a line chart
+    assert_eq!(count(&storage, "charts"), 6);
+    let bars = storage.get_charts_in_range(&["test.gauge"], &ChartType::Bar, None, None).unwrap();
+    let bar_rounds: Vec<&str> = bars.iter().map(|chart| chart.collection_round_id.as_str()).collect();
+    assert_eq!(bar_rounds, [&rounds[2].id, &rounds[3].id]);
+    assert!(bars.iter().all(|chart| chart.chart_data != "stale"));
+}
+
+/// The same reading sampled now, with a collector timing that differs per call
+fn repeated_reading(duration_ms: f64) -> Vec<Metric> {
+    vec![
+        Metric::gauge("gpu.0.utilization", 0.5).with_tag("gpu_index", "0"),
+        Metric::gauge(thrud::collectors::names::COLLECTOR_DURATION_MS, duration_ms).with_tag("collector", "gpu"),
+    ]
+}
+
+#[test]
+fn dedup_skips_a_round_identical_to_the_previous_one() {
+    let storage = storage().with_round_dedup(true);
+
+    storage.store_metrics(repeated_reading(3.0)).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    assert!(matches!(storage.store_metrics(repeated_reading(4.0)), Err(ThrudError::DuplicateRound)));
+    assert_eq!(storage.get_stats().unwrap().total_collection_rounds, 1);
+
+    // A changed reading is stored, and the reading before it is no longer a duplicate
+    let mut changed = repeated_reading(3.0);
+    changed[0].value = MetricValue::Float(0.75);
+    storage.store_metrics(changed).unwrap();
+    storage.store_metrics(repeated_reading(3.0)).unwrap();
+    assert_eq!(storage.get_stats().unwrap().total_collection_rounds, 3);
+}
+
+#[test]
+fn identical_rounds_are_stored_without_dedup() {
+    let storage = storage();
+
+    storage.store_metrics(repeated_reading(3.0)).unwrap();
+    storage.store_metrics(repeated_reading(3.0)).unwrap();
+
+    assert_eq!(storage.get_stats().unwrap().total_collection_rounds, 2);
+}
+
+/// Bar and braille charts of three rounds at 50% GPU utilization
+fn half_busy_gpu_charts(suffix: bool) -> (String, String) {
+    let storage = storage().with_chart_suffix(suffix);
+    let mut last = None;
+    for _ in 0..3 {
+        last = Some(storage.store_metrics(vec![Metric::gauge("gpu.0.utilization", 0.5)]).unwrap());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    storage.generate_and_store_charts(&last.unwrap().id, &["gpu_utilization"], 2, 1, 1).unwrap();
+
+    let chart = |chart_type| storage.get_latest_charts(&["gpu_utilization"], &chart_type, 1).unwrap().remove(0).chart_data;
+    (chart(ChartType::Bar), chart(ChartType::Braille))
+}
+
+#[test]
+fn charts_without_suffix_are_bare_sparklines() {
+    let (bar, braille) = half_busy_gpu_charts(true);
+    let (bare_bar, bare_braille) = half_busy_gpu_charts(false);
+
+    assert_eq!(bar, "▅▅..50%|");
+    assert_eq!(bare_bar, "▅▅");
+    assert_eq!(braille, format!("{}..50%|", bare_braille));
+    assert_eq!(bare_braille.chars().count(), 1);
+}
+
+#[test]
+fn latest_values_hold_the_newest_sample_of_each_name() {
+    use thrud::collectors::names::CPU_PERFORMANCE_IDLE_TICKS;
+
+    let storage = storage();
+    let start = noon("2024-01-01");
+    for round in 0..3 {
+        let mut gpu = Metric::gauge("gpu.0.utilization", round as f64 / 10.0);
+        gpu.timestamp = start + Duration::seconds(round);
+        let mut ticks = Metric::counter(CPU_PERFORMANCE_IDLE_TICKS, 100 * (round + 1));
+        ticks.timestamp = start + Duration::seconds(round);
+        let mut metrics = vec![gpu, ticks];
+        if round == 0 {
+            metrics.push(sample_at(start, 7.0));
+        }
+        storage.store_metrics(metrics).unwrap();
+    }
+
+    let latest = storage
+        .get_latest_values(&["gpu.0.utilization", "cpu_performance_idle_ticks", "test.gauge", "never.stored"])
+        .unwrap();
+
+    assert_eq!(latest.len(), 3);
+    assert_eq!(latest["gpu.0.utilization"].value, MetricValue::Float(0.2));
+    assert_eq!(latest["gpu.0.utilization"].timestamp, start + Duration::seconds(2));
+    // Aliases are answered under the requested name
+    assert_eq!(latest["cpu_performance_idle_ticks"].name, CPU_PERFORMANCE_IDLE_TICKS);
+    assert_eq!(latest["cpu_performance_idle_ticks"].value, MetricValue::Integer(300));
+    // A name missing from later rounds keeps its last sample
+    assert_eq!(latest["test.gauge"].value, MetricValue::Float(7.0));
+    assert!(storage.get_latest_values(&[]).unwrap().is_empty());
+}
+
+/// Store `rounds` rounds of 20 tagged samples each, the value of every sample
+/// being the round's index
+fn fill(storage: &SqliteStorage, rounds: usize) {
+    for round in 0..rounds {
+        let metrics = (0..20)
+            .map(|core| Metric::gauge(format!("cpu.performance_core.{}.utilization", core), round as f64).with_tag("core_id", core.to_string()))
+            .collect();
+        storage.store_metrics(metrics).unwrap();
+    }
+}
+
+#[test]
+fn pruning_to_a_size_cap_removes_the_oldest_rounds() {
+    let path = temp_db_path();
+    let storage = SqliteStorage::new(Some(path.clone())).unwrap();
+    storage.initialize().unwrap();
+    fill(&storage, 200);
+    let full = storage.live_size_bytes().unwrap();
+    let cap = full / 2;
+
+    let removed = storage.prune_to_size(cap).unwrap();
+
+    assert!(removed > 0 && removed < 200, "removed {} rounds", removed);
+    assert!(storage.live_size_bytes().unwrap() <= cap);
+    assert_eq!(storage.get_stats().unwrap().total_collection_rounds, 200 - removed as i64);
+    // The rounds left are the newest ones, without gaps
+    let remaining = storage.query_metrics("cpu.performance_core.0.utilization", None).unwrap();
+    let mut rounds: Vec<String> = values(&remaining);
+    rounds.sort_by_key(|value| value.parse::<i64>().unwrap());
+    let expected: Vec<String> = (removed..200).map(|round| round.to_string()).collect();
+    assert_eq!(rounds, expected);
+
+    // Compacting releases the freed pages, so the file itself ends under the cap
+    storage.compact().unwrap();
+    assert!(storage.get_stats().unwrap().database_size_bytes.unwrap() <= cap);
+
+    drop(storage);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn rounds_persist_their_hostname_and_version() {
+    let path = temp_db_path();
+    {
+        let storage = SqliteStorage::new(Some(path.clone())).unwrap().with_hostname(Some("build-01".to_string()));
+        storage.initialize().unwrap();
+        let round = storage.store_metrics(vec![Metric::gauge("cpu.utilization", 0.5)]).unwrap();
+        assert_eq!(round.hostname.as_deref(), Some("build-01"));
+        assert_eq!(round.thrud_version.as_deref(), Some(thrud::storage::THRUD_VERSION));
+    }
+
+    // Read back by a fresh connection to the file
+    let storage = SqliteStorage::new(Some(path.clone())).unwrap();
+    let latest = storage.get_stats().unwrap().latest_collection.unwrap();
+    assert_eq!(latest.hostname.as_deref(), Some("build-01"));
+    assert_eq!(latest.thrud_version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+
+    drop(storage);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn rounds_default_to_this_machines_hostname() {
+    let storage = storage();
+
+    let round = storage.store_metrics(vec![Metric::gauge("cpu.utilization", 0.5)]).unwrap();
+
+    assert!(round.hostname.is_some());
+    assert_eq!(round.hostname, thrud::storage::local_hostname());
+}
+
+/// Bar chart of a core a quarter busy over three intervals but parked, with no
+/// ticks at all, in the middle one
+fn parked_core_chart(gap: Option<char>) -> String {
+    use thrud::collectors::names::{cpu_core_ticks, IDLE_TICKS, TOTAL_TICKS};
+    use thrud::storage::PER_CORE_CHARTS;
+
+    let storage = storage().with_parked_gap(gap);
+    let mut last = None;
+    for total in [100, 200, 200, 300] {
+        last = Some(
+            storage
+                .store_metrics(vec![
+                    Metric::counter(cpu_core_ticks("efficiency", 0, TOTAL_TICKS), total),
+                    Metric::counter(cpu_core_ticks("efficiency", 0, IDLE_TICKS), total * 3 / 4),
+                ])
+                .unwrap(),
+        );
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+    storage.generate_and_store_charts(&last.unwrap().id, &[PER_CORE_CHARTS], 3, 1, 1).unwrap();
+
+    storage.get_latest_charts(&["core_utilization.0"], &ChartType::Bar, 1).unwrap().remove(0).chart_data
+}
+
+#[test]
+fn parked_core_renders_a_gap() {
+    // The parked interval doesn't count towards the average either
+    assert_eq!(parked_core_chart(Some('·')), "▃·▃..25%|");
+}
+
+#[test]
+fn parked_core_is_charted_as_idle_without_a_gap() {
+    assert_eq!(parked_core_chart(None), "▃ ▃..17%|");
+}
+
+/// A `net.rx_bytes` counter sample taken `seconds` seconds after `start`
+fn rx_bytes(start: DateTime<Utc>, seconds: i64, total: i64) -> Metric {
+    let mut metric = Metric::counter("net.rx_bytes", total);
+    metric.timestamp = start + Duration::seconds(seconds);
+    metric
+}
+
+#[test]
+fn constant_rate_counter_gives_a_constant_rate() {
+    let storage = storage();
+    let start = Utc::now() - Duration::seconds(10);
+    // 500 bytes every 2 seconds
+    for step in 0..4 {
+        storage.store_metrics(vec![rx_bytes(start, 2 * step, 1000 + 500 * step)]).unwrap();
+    }
+
+    let rates = storage.query_rate("net.rx_bytes", 60).unwrap();
+
+    let timestamps: Vec<DateTime<Utc>> = rates.iter().map(|(timestamp, _)| *timestamp).collect();
+    let expected: Vec<DateTime<Utc>> = (1..4).map(|step| rx_bytes(start, 2 * step, 0).timestamp).collect();
+    assert_eq!(timestamps.len(), 3);
+    assert!(timestamps.iter().zip(&expected).all(|(t, e)| t.timestamp_millis() == e.timestamp_millis()));
+    assert!(rates.iter().all(|(_, rate)| (rate - 250.0).abs() < 1e-9), "{:?}", rates);
+}
+
+#[test]
+fn rate_drops_counter_resets_and_samples_outside_the_window() {
+    let storage = storage();
+    let start = Utc::now() - Duration::seconds(10);
+    storage.store_metrics(vec![rx_bytes(start, -110, 0)]).unwrap();
+    storage.store_metrics(vec![rx_bytes(start, 4, 5000)]).unwrap();
+    // Reset: the counter went backwards
+    storage.store_metrics(vec![rx_bytes(start, 6, 100)]).unwrap();
+    storage.store_metrics(vec![rx_bytes(start, 8, 600)]).unwrap();
+
+    let rates = storage.query_rate("net.rx_bytes", 60).unwrap();
+
+    assert_eq!(rates.len(), 1, "{:?}", rates);
+    assert!((rates[0].1 - 250.0).abs() < 1e-9, "{:?}", rates);
+    assert!(storage.query_rate("net.rx_bytes", 0).is_err());
+}
+
+#[test]
+fn read_only_open_fails_on_a_missing_database_without_creating_it() {
+    let path = temp_db_path();
+
+    let error = SqliteStorage::open_read_only(Some(path.clone())).err().expect("missing database");
+
+    assert!(error.to_string().contains(&path), "{}", error);
+    assert!(!std::path::Path::new(&path).exists());
+}
+
+#[test]
+fn read_only_open_reads_an_existing_database() {
+    let path = temp_db_path();
+    let writer = SqliteStorage::new(Some(path.clone())).unwrap();
+    writer.initialize().unwrap();
+    store_round(&writer, 0.5);
+    drop(writer);
+
+    let reader = SqliteStorage::open_read_only(Some(path.clone())).unwrap();
+
+    assert_eq!(reader.get_latest_values(&["cpu.utilization"]).unwrap().len(), 1);
+    assert!(reader.store_metrics(vec![Metric::gauge("cpu.utilization", 0.7)]).is_err());
+
+    drop(reader);
+    let _ = std::fs::remove_file(path);
+}